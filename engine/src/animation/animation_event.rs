@@ -0,0 +1,10 @@
+use bevy_ecs::prelude::*;
+
+/// Triggered on an entity when a non-looping layer of its `AnimationPlayer` reaches the
+/// end of its clip. Looping layers never finish.
+#[derive(EntityEvent, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnimationFinished {
+    pub entity: Entity,
+    /// Index into `RenderBody::animations` of the clip that finished.
+    pub clip: usize,
+}
@@ -0,0 +1,267 @@
+use bevy_ecs::prelude::*;
+use glam::Mat4;
+
+/// Plays animation clips of the entity's `RenderBodyComponent` model.
+///
+/// Clips are indices into `RenderBody::animations`; look them up by name with
+/// `RenderBody::animation_index`. Several clips can play at once as layers, and their
+/// poses are blended by weight each frame by `AnimationSystem::update`.
+#[derive(Component, Debug, Clone, Default)]
+#[require(AnimatedPose)]
+pub struct AnimationPlayer {
+    layers: Vec<AnimationLayer>,
+}
+
+/// One clip playing on an `AnimationPlayer`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationLayer {
+    pub clip: usize,
+    /// Playback position in seconds.
+    pub time: f32,
+    /// Playback rate; negative values play backwards.
+    pub speed: f32,
+    /// How much this layer contributes to the blended pose.
+    pub weight: f32,
+    pub looping: bool,
+    /// Multiplies `weight` while fading in or out, from 0 to 1.
+    fade: f32,
+    /// Change of `fade` per second; negative while fading out.
+    fade_rate: f32,
+    finished: bool,
+}
+
+impl AnimationLayer {
+    fn new(clip: usize) -> Self {
+        Self {
+            clip,
+            time: 0.0,
+            speed: 1.0,
+            weight: 1.0,
+            looping: true,
+            fade: 1.0,
+            fade_rate: 0.0,
+            finished: false,
+        }
+    }
+
+    /// `weight` scaled by the current fade.
+    pub fn effective_weight(&self) -> f32 {
+        self.weight * self.fade
+    }
+
+    /// True once a non-looping clip has reached its end. The layer keeps holding the last
+    /// pose until it is stopped.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn is_fading_out(&self) -> bool {
+        self.fade_rate < 0.0
+    }
+}
+
+impl AnimationPlayer {
+    /// Stops every layer and plays `clip` from the start, looping at full weight.
+    pub fn play(&mut self, clip: usize) -> &mut AnimationLayer {
+        self.layers.clear();
+        self.layers.push(AnimationLayer::new(clip));
+        self.layers.last_mut().unwrap()
+    }
+
+    /// Starts `clip` from the start and fades it in over `duration` seconds while every
+    /// other layer fades out and is then removed.
+    pub fn crossfade(&mut self, clip: usize, duration: f32) -> &mut AnimationLayer {
+        if duration <= 0.0 {
+            return self.play(clip);
+        }
+        for layer in &mut self.layers {
+            layer.fade_rate = -1.0 / duration;
+        }
+        let mut layer = AnimationLayer::new(clip);
+        layer.fade = 0.0;
+        layer.fade_rate = 1.0 / duration;
+        self.layers.push(layer);
+        self.layers.last_mut().unwrap()
+    }
+
+    /// Plays `clip` on top of the current layers at `weight`, e.g. a wave over a walk.
+    pub fn blend(&mut self, clip: usize, weight: f32) -> &mut AnimationLayer {
+        let mut layer = AnimationLayer::new(clip);
+        layer.weight = weight;
+        self.layers.push(layer);
+        self.layers.last_mut().unwrap()
+    }
+
+    /// The most recently started layer playing `clip`.
+    pub fn layer(&self, clip: usize) -> Option<&AnimationLayer> {
+        self.layers.iter().rev().find(|layer| layer.clip == clip)
+    }
+
+    pub fn layer_mut(&mut self, clip: usize) -> Option<&mut AnimationLayer> {
+        self.layers
+            .iter_mut()
+            .rev()
+            .find(|layer| layer.clip == clip)
+    }
+
+    pub fn layers(&self) -> &[AnimationLayer] {
+        &self.layers
+    }
+
+    pub fn is_playing(&self, clip: usize) -> bool {
+        self.layer(clip).is_some()
+    }
+
+    /// Removes every layer playing `clip`.
+    pub fn stop(&mut self, clip: usize) {
+        self.layers.retain(|layer| layer.clip != clip);
+    }
+
+    /// Removes every layer; the model returns to its rest pose.
+    pub fn stop_all(&mut self) {
+        self.layers.clear();
+    }
+
+    /// Moves every layer forward by `dt` seconds and drops layers that have faded out or
+    /// whose clip doesn't exist. `duration_of` gives a clip's length. The clips of layers
+    /// that finished during this step are pushed to `finished`.
+    pub fn advance(
+        &mut self,
+        dt: f32,
+        duration_of: impl Fn(usize) -> Option<f32>,
+        finished: &mut Vec<usize>,
+    ) {
+        self.layers.retain_mut(|layer| {
+            let Some(duration) = duration_of(layer.clip) else {
+                log::warn!("Animation clip {} does not exist; stopping it", layer.clip);
+                return false;
+            };
+
+            layer.time += dt * layer.speed;
+            if layer.looping {
+                layer.time = if duration > 0.0 {
+                    layer.time.rem_euclid(duration)
+                } else {
+                    0.0
+                };
+            } else {
+                layer.time = layer.time.clamp(0.0, duration);
+                let at_end = if layer.speed < 0.0 {
+                    layer.time <= 0.0
+                } else {
+                    layer.time >= duration
+                };
+                if at_end && !layer.finished {
+                    finished.push(layer.clip);
+                }
+                layer.finished = at_end;
+            }
+
+            if layer.fade_rate != 0.0 {
+                layer.fade = (layer.fade + layer.fade_rate * dt).clamp(0.0, 1.0);
+                if layer.fade_rate < 0.0 && layer.fade <= 0.0 {
+                    return false;
+                }
+                if layer.fade_rate > 0.0 && layer.fade >= 1.0 {
+                    layer.fade_rate = 0.0;
+                }
+            }
+            true
+        });
+    }
+}
+
+/// Makes a child of an `AnimationPlayer` entity follow the node called by this name in
+/// the parent's model, e.g. a door entity following the garage's "Door" node. Its
+/// `TransformComponent` is overwritten every frame.
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct AnimationTarget(pub String);
+
+/// Model-space matrices of every node of the entity's model in its current pose, written
+/// by `AnimationSystem::update` and used to skin the model's meshes.
+#[derive(Component, Debug, Clone, Default)]
+pub struct AnimatedPose {
+    pub(crate) node_matrices: Vec<Mat4>,
+}
+
+impl AnimatedPose {
+    /// Indexed like `ModelHierarchy::nodes`. Empty until the player has run once.
+    pub fn node_matrices(&self) -> &[Mat4] {
+        &self.node_matrices
+    }
+
+    /// Model-space matrix of one node, e.g. a hand to attach a tool to.
+    pub fn node_matrix(&self, node: usize) -> Option<Mat4> {
+        self.node_matrices.get(node).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_seconds(_: usize) -> Option<f32> {
+        Some(2.0)
+    }
+
+    #[test]
+    fn looping_layers_wrap_and_others_stop_at_the_end() {
+        let mut player = AnimationPlayer::default();
+        player.play(0);
+        player.blend(1, 0.5).looping = false;
+        let mut finished = Vec::new();
+
+        player.advance(2.5, two_seconds, &mut finished);
+
+        assert_eq!(player.layer(0).unwrap().time, 0.5);
+        let once = player.layer(1).unwrap();
+        assert_eq!(once.time, 2.0);
+        assert!(once.is_finished());
+        assert!(!player.layer(0).unwrap().is_finished());
+        assert_eq!(finished, vec![1]);
+
+        // A finished layer is reported once.
+        finished.clear();
+        player.advance(0.5, two_seconds, &mut finished);
+        assert!(finished.is_empty());
+    }
+
+    #[test]
+    fn reversed_layers_wrap_backwards() {
+        let mut player = AnimationPlayer::default();
+        player.play(0).speed = -1.0;
+
+        player.advance(0.5, two_seconds, &mut Vec::new());
+
+        assert_eq!(player.layer(0).unwrap().time, 1.5);
+    }
+
+    #[test]
+    fn crossfades_swap_layers_over_their_duration() {
+        let mut player = AnimationPlayer::default();
+        player.play(0);
+        player.crossfade(1, 1.0);
+
+        player.advance(0.25, two_seconds, &mut Vec::new());
+        assert_eq!(player.layer(0).unwrap().effective_weight(), 0.75);
+        assert_eq!(player.layer(1).unwrap().effective_weight(), 0.25);
+        assert!(player.layer(0).unwrap().is_fading_out());
+
+        player.advance(1.0, two_seconds, &mut Vec::new());
+        assert!(!player.is_playing(0));
+        assert_eq!(player.layers().len(), 1);
+        assert_eq!(player.layer(1).unwrap().effective_weight(), 1.0);
+    }
+
+    #[test]
+    fn missing_clips_are_dropped() {
+        let mut player = AnimationPlayer::default();
+        player.play(0);
+        player.blend(7, 1.0);
+
+        player.advance(0.1, |clip| (clip == 0).then_some(1.0), &mut Vec::new());
+
+        assert!(player.is_playing(0));
+        assert!(!player.is_playing(7));
+    }
+}
@@ -0,0 +1,87 @@
+use bevy_ecs::prelude::*;
+
+use crate::{
+    animation::{
+        animation_event::AnimationFinished,
+        animation_player::{AnimatedPose, AnimationPlayer, AnimationTarget},
+        skeleton::PoseBlender,
+    },
+    components::{
+        hierarchy_component::Parent, render_body_component::RenderBodyComponent,
+        transform_component::TransformComponent,
+    },
+    render::render_body_resource::RenderBodyResource,
+    time_resource::TimeResource,
+};
+
+pub struct AnimationSystem {}
+
+impl AnimationSystem {
+    /// Advances every `AnimationPlayer` by the frame's delta time and writes the blended
+    /// pose of its model into `AnimatedPose`. Triggers `AnimationFinished` for every
+    /// non-looping layer that reached its end this frame.
+    pub fn update(
+        mut query: Query<(
+            Entity,
+            &mut AnimationPlayer,
+            &mut AnimatedPose,
+            &RenderBodyComponent,
+        )>,
+        render_body_resource: Res<RenderBodyResource>,
+        time: Res<TimeResource>,
+        mut commands: Commands,
+        mut blender: Local<PoseBlender>,
+        mut finished: Local<Vec<usize>>,
+    ) {
+        let dt = time.frame_delta_time();
+        let bodies = render_body_resource.read();
+
+        for (entity, mut player, mut pose, render_body) in &mut query {
+            // Still loading, or removed.
+            let Some(body) = bodies.get_render_body(render_body.render_body_id) else {
+                continue;
+            };
+
+            finished.clear();
+            player.advance(
+                dt,
+                |clip| body.animations.get(clip).map(|c| c.duration),
+                &mut finished,
+            );
+            for &clip in finished.iter() {
+                commands.trigger(AnimationFinished { entity, clip });
+            }
+
+            blender.pose(
+                &body.hierarchy,
+                &body.animations,
+                player.layers(),
+                &mut pose.node_matrices,
+            );
+        }
+    }
+
+    /// Moves every `AnimationTarget` to where its node is in its parent's current pose.
+    /// Runs before transforms are propagated, so targets and their children are placed
+    /// the same frame.
+    pub fn update_targets(
+        mut targets: Query<(&AnimationTarget, &Parent, &mut TransformComponent)>,
+        players: Query<(&AnimatedPose, &RenderBodyComponent)>,
+        render_body_resource: Res<RenderBodyResource>,
+    ) {
+        let bodies = render_body_resource.read();
+        for (target, parent, mut transform) in &mut targets {
+            let Ok((pose, render_body)) = players.get(parent.get()) else {
+                continue;
+            };
+            let Some(matrix) = bodies
+                .get_render_body(render_body.render_body_id)
+                .and_then(|body| body.hierarchy.node_index(&target.0))
+                .and_then(|node| pose.node_matrix(node))
+            else {
+                continue;
+            };
+            transform.set_if_neq(TransformComponent::from_mat4(&matrix));
+        }
+    }
+}
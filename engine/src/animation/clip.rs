@@ -0,0 +1,229 @@
+use std::ops::{Add, Mul};
+
+use glam::{Quat, Vec3};
+
+/// How values between two keyframes are found, as in glTF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Holds each keyframe's value until the next one.
+    Step,
+    Linear,
+    /// Hermite spline through the keyframes, with tangents stored alongside each value.
+    CubicSpline,
+}
+
+/// Keyframe values of one animated node property. Cubic spline channels store three
+/// values per keyframe: in-tangent, value and out-tangent.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Keyframes {
+    Translation(Vec<Vec3>),
+    Rotation(Vec<Quat>),
+    Scale(Vec<Vec3>),
+}
+
+/// A channel's value at one point in time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChannelValue {
+    Translation(Vec3),
+    Rotation(Quat),
+    Scale(Vec3),
+}
+
+/// Animates one property of one node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationChannel {
+    /// Index into `ModelHierarchy::nodes` of the animated node.
+    pub node: usize,
+    pub interpolation: Interpolation,
+    /// Keyframe times in seconds, ascending. Never empty.
+    pub times: Vec<f32>,
+    /// One value per time, or three for `CubicSpline`.
+    pub keyframes: Keyframes,
+}
+
+impl AnimationChannel {
+    /// The value at `time`, holding the first and last keyframes outside their range.
+    pub fn sample(&self, time: f32) -> ChannelValue {
+        match &self.keyframes {
+            Keyframes::Translation(values) => {
+                ChannelValue::Translation(self.sample_values(values, time, Vec3::lerp))
+            }
+            Keyframes::Rotation(values) => {
+                ChannelValue::Rotation(self.sample_values(values, time, Quat::slerp).normalize())
+            }
+            Keyframes::Scale(values) => {
+                ChannelValue::Scale(self.sample_values(values, time, Vec3::lerp))
+            }
+        }
+    }
+
+    fn sample_values<T>(&self, values: &[T], time: f32, lerp: fn(T, T, f32) -> T) -> T
+    where
+        T: Copy + Add<Output = T> + Mul<f32, Output = T>,
+    {
+        let value_at = |key: usize| match self.interpolation {
+            Interpolation::CubicSpline => values[key * 3 + 1],
+            _ => values[key],
+        };
+
+        let last = self.times.len() - 1;
+        if time <= self.times[0] {
+            return value_at(0);
+        }
+        if time >= self.times[last] {
+            return value_at(last);
+        }
+
+        let next = self.times.partition_point(|&t| t <= time);
+        let previous = next - 1;
+        let span = self.times[next] - self.times[previous];
+        let t = (time - self.times[previous]) / span;
+
+        match self.interpolation {
+            Interpolation::Step => value_at(previous),
+            Interpolation::Linear => lerp(value_at(previous), value_at(next), t),
+            Interpolation::CubicSpline => {
+                let out_tangent = values[previous * 3 + 2] * span;
+                let in_tangent = values[next * 3] * span;
+                let (t2, t3) = (t * t, t * t * t);
+                value_at(previous) * (2.0 * t3 - 3.0 * t2 + 1.0)
+                    + out_tangent * (t3 - 2.0 * t2 + t)
+                    + value_at(next) * (-2.0 * t3 + 3.0 * t2)
+                    + in_tangent * (t3 - t2)
+            }
+        }
+    }
+}
+
+/// A named set of channels played together, e.g. "walk" or "wave".
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationClip {
+    pub name: Option<String>,
+    /// Time of the last keyframe of any channel, in seconds.
+    pub duration: f32,
+    pub channels: Vec<AnimationChannel>,
+}
+
+impl AnimationClip {
+    pub fn new(name: Option<String>, channels: Vec<AnimationChannel>) -> Self {
+        let duration = channels
+            .iter()
+            .filter_map(|channel| channel.times.last().copied())
+            .fold(0.0, f32::max);
+        Self {
+            name,
+            duration,
+            channels,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translation_channel(interpolation: Interpolation, values: Vec<Vec3>) -> AnimationChannel {
+        AnimationChannel {
+            node: 0,
+            interpolation,
+            times: vec![0.0, 1.0, 3.0],
+            keyframes: Keyframes::Translation(values),
+        }
+    }
+
+    #[test]
+    fn linear_channels_interpolate_between_keyframes() {
+        let channel = translation_channel(
+            Interpolation::Linear,
+            vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 4.0, 0.0)],
+        );
+
+        assert_eq!(
+            channel.sample(0.5),
+            ChannelValue::Translation(Vec3::new(0.5, 0.0, 0.0))
+        );
+        assert_eq!(
+            channel.sample(2.0),
+            ChannelValue::Translation(Vec3::new(1.0, 2.0, 0.0))
+        );
+        // Outside the keyframes the ends are held.
+        assert_eq!(channel.sample(-1.0), ChannelValue::Translation(Vec3::ZERO));
+        assert_eq!(
+            channel.sample(9.0),
+            ChannelValue::Translation(Vec3::new(1.0, 4.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn step_channels_hold_each_keyframe() {
+        let channel = translation_channel(Interpolation::Step, vec![Vec3::ZERO, Vec3::X, Vec3::Y]);
+
+        assert_eq!(channel.sample(0.99), ChannelValue::Translation(Vec3::ZERO));
+        assert_eq!(channel.sample(1.0), ChannelValue::Translation(Vec3::X));
+        assert_eq!(channel.sample(2.9), ChannelValue::Translation(Vec3::X));
+    }
+
+    #[test]
+    fn cubic_spline_channels_pass_through_keyframes_and_follow_tangents() {
+        // Zero tangents give a smoothstep between the keyframes.
+        let channel = AnimationChannel {
+            node: 0,
+            interpolation: Interpolation::CubicSpline,
+            times: vec![0.0, 2.0],
+            keyframes: Keyframes::Translation(vec![
+                Vec3::ZERO,
+                Vec3::ZERO,
+                Vec3::ZERO,
+                Vec3::ZERO,
+                Vec3::splat(2.0),
+                Vec3::ZERO,
+            ]),
+        };
+
+        assert_eq!(
+            channel.sample(2.0),
+            ChannelValue::Translation(Vec3::splat(2.0))
+        );
+        assert_eq!(
+            channel.sample(1.0),
+            ChannelValue::Translation(Vec3::splat(1.0))
+        );
+        let ChannelValue::Translation(early) = channel.sample(0.5) else {
+            unreachable!();
+        };
+        assert!(early.x < 0.5, "eases in, got {early}");
+    }
+
+    #[test]
+    fn rotations_are_slerped() {
+        let channel = AnimationChannel {
+            node: 0,
+            interpolation: Interpolation::Linear,
+            times: vec![0.0, 1.0],
+            keyframes: Keyframes::Rotation(vec![Quat::IDENTITY, Quat::from_rotation_z(1.0)]),
+        };
+
+        let ChannelValue::Rotation(rotation) = channel.sample(0.25) else {
+            unreachable!();
+        };
+        assert!(rotation.abs_diff_eq(Quat::from_rotation_z(0.25), 1e-6));
+    }
+
+    #[test]
+    fn duration_is_the_last_keyframe_of_any_channel() {
+        let clip = AnimationClip::new(
+            Some("walk".to_string()),
+            vec![
+                translation_channel(Interpolation::Linear, vec![Vec3::ZERO; 3]),
+                AnimationChannel {
+                    node: 1,
+                    interpolation: Interpolation::Step,
+                    times: vec![0.0, 1.5],
+                    keyframes: Keyframes::Scale(vec![Vec3::ONE; 2]),
+                },
+            ],
+        );
+
+        assert_eq!(clip.duration, 3.0);
+    }
+}
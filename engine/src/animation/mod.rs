@@ -0,0 +1,5 @@
+pub mod animation_event;
+pub mod animation_player;
+pub mod animation_system;
+pub mod clip;
+pub mod skeleton;
@@ -0,0 +1,365 @@
+use glam::{Mat4, Quat, Vec3, Vec4};
+
+use crate::{
+    animation::{
+        animation_player::AnimationLayer,
+        clip::{AnimationClip, ChannelValue},
+    },
+    components::transform_component::TransformComponent,
+};
+
+/// Most joints a skin can have. Must match `MAX_JOINTS` in `pbr_skinned.vert` and
+/// `shadow_depth_skinned.vert`.
+pub const MAX_JOINTS: usize = 64;
+
+/// One node of a model's hierarchy, as found in the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelNode {
+    pub name: Option<String>,
+    /// Index into `ModelHierarchy::nodes`; always lower than this node's own index.
+    pub parent: Option<usize>,
+    /// Transform relative to the parent when no animation moves the node.
+    pub rest: TransformComponent,
+}
+
+/// Joints that deform a skinned mesh.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skin {
+    /// Indices into `ModelHierarchy::nodes`; a vertex's joint index `i` means `joints[i]`.
+    pub joints: Vec<usize>,
+    /// Per joint, takes a vertex from model space in the bind pose into the joint's space.
+    pub inverse_bind_matrices: Vec<Mat4>,
+}
+
+/// The node tree and skins of a model, which animation clips and skinned parts refer to.
+/// Models without nodes (OBJ files) have an empty hierarchy.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelHierarchy {
+    nodes: Vec<ModelNode>,
+    skins: Vec<Skin>,
+    rest_matrices: Vec<Mat4>,
+}
+
+impl ModelHierarchy {
+    /// `nodes` must list every parent before its children.
+    pub fn new(nodes: Vec<ModelNode>, skins: Vec<Skin>) -> Self {
+        debug_assert!(
+            nodes
+                .iter()
+                .enumerate()
+                .all(|(i, node)| node.parent.is_none_or(|parent| parent < i)),
+            "Model nodes must come after their parents"
+        );
+        let mut hierarchy = Self {
+            nodes,
+            skins,
+            rest_matrices: Vec::new(),
+        };
+        let rest: Vec<TransformComponent> = hierarchy.nodes.iter().map(|node| node.rest).collect();
+        let mut rest_matrices = Vec::with_capacity(rest.len());
+        hierarchy.model_matrices(&rest, &mut rest_matrices);
+        hierarchy.rest_matrices = rest_matrices;
+        hierarchy
+    }
+
+    pub fn nodes(&self) -> &[ModelNode] {
+        &self.nodes
+    }
+
+    pub fn skins(&self) -> &[Skin] {
+        &self.skins
+    }
+
+    /// Index of the first node called `name`.
+    pub fn node_index(&self, name: &str) -> Option<usize> {
+        self.nodes
+            .iter()
+            .position(|node| node.name.as_deref() == Some(name))
+    }
+
+    /// Model-space matrix of every node in its rest pose.
+    pub fn rest_matrices(&self) -> &[Mat4] {
+        &self.rest_matrices
+    }
+
+    /// Model-space matrices from per-node local transforms, one per node.
+    pub fn model_matrices(&self, locals: &[TransformComponent], out: &mut Vec<Mat4>) {
+        out.clear();
+        for (node, local) in self.nodes.iter().zip(locals) {
+            let local = local.to_mat4();
+            let matrix = match node.parent {
+                Some(parent) => out[parent] * local,
+                None => local,
+            };
+            out.push(matrix);
+        }
+    }
+
+    /// Appends the matrices `pbr_skinned.vert` expects for `skin`: each joint's model
+    /// matrix times its inverse bind matrix. Joints past `MAX_JOINTS` are left out.
+    pub fn append_joint_matrices(&self, skin: usize, model_matrices: &[Mat4], out: &mut Vec<Mat4>) {
+        let Some(skin) = self.skins.get(skin) else {
+            return;
+        };
+        out.extend(
+            skin.joints
+                .iter()
+                .zip(&skin.inverse_bind_matrices)
+                .take(MAX_JOINTS)
+                .map(|(&joint, inverse_bind)| model_matrices[joint] * *inverse_bind),
+        );
+    }
+}
+
+/// Samples and blends the layers of an `AnimationPlayer` into a pose. Needs no renderer or
+/// world, so poses can be computed anywhere.
+///
+/// Each node property blends independently: where the weights of the clips animating it
+/// add up to less than 1 the rest pose makes up the difference, and where they add up to
+/// more they are normalized.
+#[derive(Debug, Default)]
+pub struct PoseBlender {
+    translations: Vec<(Vec3, f32)>,
+    rotations: Vec<(Vec4, f32)>,
+    scales: Vec<(Vec3, f32)>,
+    locals: Vec<TransformComponent>,
+}
+
+impl PoseBlender {
+    /// Writes the model-space matrix of every node of `hierarchy` with `layers` of `clips`
+    /// applied. Channels of clips that don't exist or target missing nodes are ignored.
+    pub fn pose(
+        &mut self,
+        hierarchy: &ModelHierarchy,
+        clips: &[AnimationClip],
+        layers: &[AnimationLayer],
+        out: &mut Vec<Mat4>,
+    ) {
+        let nodes = hierarchy.nodes();
+        self.reset(nodes.len());
+        for layer in layers {
+            let weight = layer.effective_weight();
+            let Some(clip) = clips.get(layer.clip) else {
+                continue;
+            };
+            if weight <= 0.0 {
+                continue;
+            }
+            for channel in &clip.channels {
+                if channel.node < nodes.len() {
+                    self.add(channel.node, channel.sample(layer.time), weight);
+                }
+            }
+        }
+
+        let mut locals = std::mem::take(&mut self.locals);
+        self.finish(nodes, &mut locals);
+        hierarchy.model_matrices(&locals, out);
+        self.locals = locals;
+    }
+
+    fn reset(&mut self, node_count: usize) {
+        self.translations.clear();
+        self.translations.resize(node_count, (Vec3::ZERO, 0.0));
+        self.rotations.clear();
+        self.rotations.resize(node_count, (Vec4::ZERO, 0.0));
+        self.scales.clear();
+        self.scales.resize(node_count, (Vec3::ZERO, 0.0));
+    }
+
+    fn add(&mut self, node: usize, value: ChannelValue, weight: f32) {
+        match value {
+            ChannelValue::Translation(translation) => {
+                let (sum, total) = &mut self.translations[node];
+                *sum += translation * weight;
+                *total += weight;
+            }
+            ChannelValue::Rotation(rotation) => {
+                let (sum, total) = &mut self.rotations[node];
+                // q and -q are the same rotation; keep them in one hemisphere so they
+                // don't cancel out.
+                let rotation = Vec4::from(rotation);
+                let sign = if sum.dot(rotation) < 0.0 { -1.0 } else { 1.0 };
+                *sum += rotation * weight * sign;
+                *total += weight;
+            }
+            ChannelValue::Scale(scale) => {
+                let (sum, total) = &mut self.scales[node];
+                *sum += scale * weight;
+                *total += weight;
+            }
+        }
+    }
+
+    /// The blended local transform of every node.
+    fn finish(&self, nodes: &[ModelNode], out: &mut Vec<TransformComponent>) {
+        out.clear();
+        for (i, node) in nodes.iter().enumerate() {
+            let rest = node.rest;
+            let (translation, translation_weight) = self.translations[i];
+            let (rotation, rotation_weight) = self.rotations[i];
+            let (scale, scale_weight) = self.scales[i];
+
+            let rest_rotation = Vec4::from(rest.rotation);
+            let rotation = if rotation_weight < 1.0 {
+                let sign = if rotation.dot(rest_rotation) < 0.0 {
+                    -1.0
+                } else {
+                    1.0
+                };
+                rotation + rest_rotation * (1.0 - rotation_weight) * sign
+            } else {
+                rotation
+            };
+
+            out.push(TransformComponent {
+                position: mix_with_rest(translation, translation_weight, rest.position),
+                rotation: Quat::from_vec4(rotation).normalize(),
+                scale: mix_with_rest(scale, scale_weight, rest.scale),
+            });
+        }
+    }
+}
+
+fn mix_with_rest(sum: Vec3, weight: f32, rest: Vec3) -> Vec3 {
+    if weight < 1.0 {
+        sum + rest * (1.0 - weight)
+    } else {
+        sum / weight
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(parent: Option<usize>, position: Vec3) -> ModelNode {
+        ModelNode {
+            name: None,
+            parent,
+            rest: TransformComponent {
+                position,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn joint_matrices_undo_the_bind_pose() {
+        let nodes = vec![node(None, Vec3::Z), node(Some(0), Vec3::X)];
+        // Bound in the rest pose: the inverse bind matrices are the inverse rest matrices.
+        let inverse_bind_matrices = vec![
+            Mat4::from_translation(-Vec3::Z),
+            Mat4::from_translation(-Vec3::new(1.0, 0.0, 1.0)),
+        ];
+        let hierarchy = ModelHierarchy::new(
+            nodes,
+            vec![Skin {
+                joints: vec![0, 1],
+                inverse_bind_matrices,
+            }],
+        );
+
+        let mut joints = Vec::new();
+        hierarchy.append_joint_matrices(0, hierarchy.rest_matrices(), &mut joints);
+        assert_eq!(joints, vec![Mat4::IDENTITY; 2]);
+
+        // Moving the parent moves both joints.
+        let locals = [
+            TransformComponent {
+                position: Vec3::new(0.0, 2.0, 1.0),
+                ..Default::default()
+            },
+            hierarchy.nodes()[1].rest,
+        ];
+        let mut model = Vec::new();
+        hierarchy.model_matrices(&locals, &mut model);
+        joints.clear();
+        hierarchy.append_joint_matrices(0, &model, &mut joints);
+        assert_eq!(joints, vec![Mat4::from_translation(Vec3::Y * 2.0); 2]);
+    }
+
+    #[test]
+    fn layers_pose_their_nodes_without_a_renderer() {
+        use crate::animation::{
+            animation_player::AnimationPlayer,
+            clip::{AnimationChannel, Interpolation, Keyframes},
+        };
+
+        // A windmill whose blades turn a quarter turn per second.
+        let hierarchy = ModelHierarchy::new(
+            vec![node(None, Vec3::ZERO), node(Some(0), Vec3::Z * 5.0)],
+            Vec::new(),
+        );
+        let spin = AnimationClip::new(
+            Some("spin".to_string()),
+            vec![AnimationChannel {
+                node: 1,
+                interpolation: Interpolation::Linear,
+                times: vec![0.0, 1.0],
+                keyframes: Keyframes::Rotation(vec![
+                    Quat::IDENTITY,
+                    Quat::from_rotation_y(std::f32::consts::FRAC_PI_2),
+                ]),
+            }],
+        );
+        let clips = [spin];
+        let mut player = AnimationPlayer::default();
+        player.play(0).speed = 0.5;
+        player.advance(
+            1.0,
+            |clip| clips.get(clip).map(|c| c.duration),
+            &mut Vec::new(),
+        );
+
+        let mut blender = PoseBlender::default();
+        let mut pose = Vec::new();
+        blender.pose(&hierarchy, &clips, player.layers(), &mut pose);
+
+        assert_eq!(pose[0], Mat4::IDENTITY);
+        let expected = Mat4::from_translation(Vec3::Z * 5.0)
+            * Mat4::from_quat(Quat::from_rotation_y(std::f32::consts::FRAC_PI_4));
+        assert!(pose[1].abs_diff_eq(expected, 1e-6));
+
+        // With nothing playing the model is at rest.
+        blender.pose(&hierarchy, &clips, &[], &mut pose);
+        assert_eq!(pose, hierarchy.rest_matrices());
+    }
+
+    #[test]
+    fn partial_weights_blend_towards_the_rest_pose() {
+        let nodes = vec![node(None, Vec3::ZERO)];
+        let mut blender = PoseBlender::default();
+        blender.reset(1);
+        blender.add(0, ChannelValue::Translation(Vec3::X * 4.0), 0.25);
+
+        let mut pose = Vec::new();
+        blender.finish(&nodes, &mut pose);
+
+        assert_eq!(pose[0].position, Vec3::X);
+        assert_eq!(pose[0].rotation, Quat::IDENTITY);
+        assert_eq!(pose[0].scale, Vec3::ONE);
+    }
+
+    #[test]
+    fn full_weights_are_normalized() {
+        let nodes = vec![node(None, Vec3::ZERO)];
+        let mut blender = PoseBlender::default();
+        blender.reset(1);
+        blender.add(0, ChannelValue::Translation(Vec3::X), 1.0);
+        blender.add(0, ChannelValue::Translation(Vec3::Y), 1.0);
+        blender.add(0, ChannelValue::Rotation(Quat::from_rotation_z(0.5)), 1.0);
+        // The same rotation as above with the opposite sign.
+        blender.add(0, ChannelValue::Rotation(-Quat::from_rotation_z(0.5)), 1.0);
+
+        let mut pose = Vec::new();
+        blender.finish(&nodes, &mut pose);
+
+        assert_eq!(pose[0].position, Vec3::new(0.5, 0.5, 0.0));
+        assert!(
+            pose[0]
+                .rotation
+                .abs_diff_eq(Quat::from_rotation_z(0.5), 1e-6)
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use thiserror::Error;
+
+/// Why an asset could not be loaded. Each variant names the file that failed and wraps
+/// the kind-specific error underneath.
+#[derive(Debug, Error)]
+pub enum AssetError {
+    #[error("Failed to load shader {path}: {source}")]
+    Shader {
+        path: String,
+        #[source]
+        source: ShaderError,
+    },
+
+    #[error("Failed to load texture {path}: {source}")]
+    Texture {
+        path: String,
+        #[source]
+        source: TextureError,
+    },
+
+    #[error("Failed to load model {path}: {source}")]
+    Model {
+        path: String,
+        #[source]
+        source: ModelError,
+    },
+
+    #[error("Failed to load sound {path}: {source}")]
+    Sound {
+        path: String,
+        #[source]
+        source: SoundError,
+    },
+
+    #[error("Unsupported model format: {0}")]
+    UnsupportedFormat(String),
+
+    #[error("{0} has not been loaded")]
+    NotLoaded(String),
+
+    #[error("Asset was not loaded from a file")]
+    NoSourceFile,
+}
+
+#[derive(Debug, Error)]
+pub enum ShaderError {
+    #[error("could not read {path}: {source}")]
+    Read {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("{stage} shader failed to compile: {log}")]
+    Compile { stage: &'static str, log: String },
+
+    #[error("program failed to link: {0}")]
+    Link(String),
+}
+
+#[derive(Debug, Error)]
+pub enum TextureError {
+    #[error(transparent)]
+    Image(#[from] image::ImageError),
+
+    #[error("unsupported glTF image format {0:?}")]
+    UnsupportedFormat(gltf::image::Format),
+}
+
+#[derive(Debug, Error)]
+pub enum ModelError {
+    #[error(transparent)]
+    Obj(#[from] tobj::LoadError),
+
+    #[error(transparent)]
+    Gltf(#[from] gltf::Error),
+
+    #[error("mesh {mesh} has no {attribute}")]
+    MissingAttribute {
+        mesh: usize,
+        attribute: &'static str,
+    },
+
+    #[error("mesh {mesh} has {found} {attribute} for {expected} positions")]
+    AttributeCount {
+        mesh: usize,
+        attribute: &'static str,
+        found: usize,
+        expected: usize,
+    },
+
+    #[error("FBX loading is not implemented yet")]
+    FbxNotImplemented,
+}
+
+#[derive(Debug, Error)]
+pub enum SoundError {
+    #[error(transparent)]
+    Wav(#[from] hound::Error),
+
+    #[error("{0} channels are not supported; use mono or stereo")]
+    UnsupportedChannels(u16),
+}
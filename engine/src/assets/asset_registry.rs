@@ -0,0 +1,174 @@
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
+
+use slotmap::{Key, SecondaryMap};
+
+/// Two-way map between source paths and the handles loaded from them.
+///
+/// Every asset storage owns one, so a path that is already loaded returns its
+/// cached handle and serializers can turn a handle back into a path.
+/// Assets that live inside a file (a model's meshes, materials and embedded
+/// textures) are registered under [`sub_asset_path`], e.g. `models/house.gltf#material0`.
+/// Assets built in code (solid colour textures, procedural meshes) have no path.
+pub struct AssetRegistry<K: Key> {
+    handles: HashMap<String, K>,
+    paths: SecondaryMap<K, String>,
+}
+
+impl<K: Key> Default for AssetRegistry<K> {
+    fn default() -> Self {
+        Self {
+            handles: HashMap::new(),
+            paths: SecondaryMap::new(),
+        }
+    }
+}
+
+impl<K: Key> AssetRegistry<K> {
+    /// Records that `handle` was loaded from `path`. The path is normalized first.
+    pub fn insert(&mut self, path: &str, handle: K) {
+        let path = normalize_asset_path(path);
+        if let Some(old_path) = self.paths.insert(handle, path.clone())
+            && old_path != path
+        {
+            self.handles.remove(&old_path);
+        }
+        self.handles.insert(path, handle);
+    }
+
+    pub fn handle(&self, path: &str) -> Option<K> {
+        self.handles.get(&normalize_asset_path(path)).copied()
+    }
+
+    pub fn path(&self, handle: K) -> Option<&str> {
+        self.paths.get(handle).map(String::as_str)
+    }
+
+    /// Forgets `handle`, returning the path it was registered under.
+    pub fn remove(&mut self, handle: K) -> Option<String> {
+        let path = self.paths.remove(handle)?;
+        self.handles.remove(&path);
+        Some(path)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, K)> {
+        self.handles
+            .iter()
+            .map(|(path, handle)| (path.as_str(), *handle))
+    }
+}
+
+/// Lexically cleans up `path` so different spellings of the same file share a key:
+/// `.` components are dropped, `..` cancels the previous component and separators become `/`.
+/// The filesystem is not touched, so relative paths stay relative.
+pub fn normalize_asset_path(path: &str) -> String {
+    let (file, label) = match path.split_once('#') {
+        Some((file, label)) => (file, Some(label)),
+        None => (path, None),
+    };
+
+    let mut normalized = PathBuf::new();
+    for component in Path::new(file).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) {
+                    normalized.pop();
+                } else {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+
+    let mut normalized = normalized.to_string_lossy().replace('\\', "/");
+    if let Some(label) = label {
+        normalized.push('#');
+        normalized.push_str(label);
+    }
+    normalized
+}
+
+/// The registry path of asset `label` inside the file at `path`.
+pub fn sub_asset_path(path: &str, label: &str) -> String {
+    format!("{}#{}", normalize_asset_path(path), label)
+}
+
+/// The file part of a registry path, without any `#label`.
+pub fn asset_file_path(path: &str) -> &str {
+    path.split_once('#').map_or(path, |(file, _)| file)
+}
+
+#[cfg(test)]
+mod tests {
+    use slotmap::SlotMap;
+
+    use super::*;
+    use crate::assets::handles::MeshHandle;
+
+    #[test]
+    fn normalize_removes_dots_and_parent_components() {
+        assert_eq!(normalize_asset_path("./models/a.gltf"), "models/a.gltf");
+        assert_eq!(
+            normalize_asset_path("models/../models/./a.gltf"),
+            "models/a.gltf"
+        );
+        assert_eq!(
+            normalize_asset_path("../resources/pop.wav"),
+            "../resources/pop.wav"
+        );
+        assert_eq!(normalize_asset_path("/abs/./a.obj"), "/abs/a.obj");
+        assert_eq!(
+            normalize_asset_path("./models/a.gltf#mesh0"),
+            "models/a.gltf#mesh0"
+        );
+    }
+
+    #[test]
+    fn sub_asset_paths_split_back_into_their_file() {
+        let path = sub_asset_path("./models/a.gltf", "material2");
+        assert_eq!(path, "models/a.gltf#material2");
+        assert_eq!(asset_file_path(&path), "models/a.gltf");
+        assert_eq!(asset_file_path("models/a.gltf"), "models/a.gltf");
+    }
+
+    #[test]
+    fn lookups_work_in_both_directions() {
+        let mut slots: SlotMap<MeshHandle, ()> = SlotMap::with_key();
+        let a = slots.insert(());
+        let b = slots.insert(());
+        let mut registry = AssetRegistry::default();
+
+        registry.insert("./a.obj", a);
+        registry.insert("b.obj", b);
+
+        assert_eq!(registry.handle("a.obj"), Some(a));
+        assert_eq!(registry.handle("./b.obj"), Some(b));
+        assert_eq!(registry.path(a), Some("a.obj"));
+        assert_eq!(registry.handle("c.obj"), None);
+
+        assert_eq!(registry.remove(a), Some("a.obj".to_string()));
+        assert_eq!(registry.handle("a.obj"), None);
+        assert_eq!(registry.path(a), None);
+    }
+
+    #[test]
+    fn re_registering_a_handle_drops_its_old_path() {
+        let mut slots: SlotMap<MeshHandle, ()> = SlotMap::with_key();
+        let a = slots.insert(());
+        let mut registry = AssetRegistry::default();
+
+        registry.insert("old.obj", a);
+        registry.insert("new.obj", a);
+
+        assert_eq!(registry.handle("old.obj"), None);
+        assert_eq!(registry.handle("new.obj"), Some(a));
+        assert_eq!(registry.path(a), Some("new.obj"));
+    }
+}
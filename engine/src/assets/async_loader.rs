@@ -0,0 +1,175 @@
+use std::sync::{
+    Arc,
+    mpsc::{self, Receiver, Sender},
+};
+
+use crate::{
+    Engine,
+    assets::{asset_error::AssetError, handles::RenderBodyHandle, model_loader::ImportedModel},
+    render::{render_body::RenderBody, render_body_resource::RenderBodyResource},
+};
+
+/// Where a model started with `Engine::load_model_async` is in its loading.
+#[derive(Debug, Clone)]
+pub enum LoadState {
+    /// Being read on the thread pool. The handle refers to an empty render body meanwhile,
+    /// so entities using it draw nothing.
+    Loading,
+    Loaded,
+    /// The handle stays valid but its render body stays empty. Loading the same path
+    /// again retries into this handle.
+    Failed(Arc<AssetError>),
+}
+
+impl LoadState {
+    pub fn is_loading(&self) -> bool {
+        matches!(self, LoadState::Loading)
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        matches!(self, LoadState::Loaded)
+    }
+
+    pub fn is_failed(&self) -> bool {
+        matches!(self, LoadState::Failed(_))
+    }
+}
+
+struct FinishedLoad {
+    handle: RenderBodyHandle,
+    path: String,
+    result: Result<ImportedModel, AssetError>,
+}
+
+/// Collects models read on the rayon pool until the main thread commits them.
+pub(crate) struct AsyncLoader {
+    sender: Sender<FinishedLoad>,
+    receiver: Receiver<FinishedLoad>,
+    pending: usize,
+}
+
+impl Default for AsyncLoader {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            sender,
+            receiver,
+            pending: 0,
+        }
+    }
+}
+
+impl Engine {
+    /// Starts loading a model in the background and returns its handle immediately.
+    ///
+    /// File IO, parsing, image decoding, tangent generation and BVH building run on the rayon
+    /// thread pool; the result is committed (textures uploaded, meshes and materials registered)
+    /// on the main thread at the start of a later frame. Query progress with `load_state`.
+    /// A path that is already loaded or loading returns its existing handle; one that failed
+    /// to load starts loading again under its existing handle.
+    pub fn load_model_async(&mut self, model_path: &str) -> RenderBodyHandle {
+        let bodies = self
+            .scene
+            .world
+            .get_resource::<RenderBodyResource>()
+            .expect("RenderBodyResource resource not found")
+            .clone();
+        let mut bodies = bodies.write();
+        let handle = match bodies.paths.handle(model_path) {
+            Some(handle) if bodies.load_state(handle).is_some_and(|s| s.is_failed()) => handle,
+            Some(handle) => return handle,
+            None => {
+                let handle = bodies.add_render_body(RenderBody::new(Vec::new()));
+                bodies.paths.insert(model_path, handle);
+                handle
+            }
+        };
+        bodies.load_states.insert(handle, LoadState::Loading);
+
+        let sender = self.async_loader.sender.clone();
+        let path = model_path.to_string();
+        rayon::spawn(move || {
+            let result = ImportedModel::read(&path);
+            // The engine may have been dropped while this was loading.
+            let _ = sender.send(FinishedLoad {
+                handle,
+                path,
+                result,
+            });
+        });
+        self.async_loader.pending += 1;
+        handle
+    }
+
+    /// The load state of a render body, or `None` if the handle is not valid.
+    /// Bodies loaded synchronously are always `Loaded`.
+    pub fn load_state(&self, handle: RenderBodyHandle) -> Option<LoadState> {
+        self.scene
+            .world
+            .get_resource::<RenderBodyResource>()
+            .expect("RenderBodyResource resource not found")
+            .read()
+            .load_state(handle)
+    }
+
+    /// Number of background loads that have not been committed yet.
+    pub fn pending_loads(&self) -> usize {
+        self.async_loader.pending
+    }
+
+    /// Commits every background load that has finished, without waiting for the rest.
+    /// Runs at the start of every frame; returns how many loads were committed.
+    pub fn process_finished_loads(&mut self) -> usize {
+        let mut committed = 0;
+        while let Ok(finished) = self.async_loader.receiver.try_recv() {
+            self.commit_finished_load(finished);
+            committed += 1;
+        }
+        committed
+    }
+
+    /// Blocks until every background load has finished and commits them, e.g. at the end
+    /// of a loading screen.
+    pub fn wait_for_loads(&mut self) {
+        while self.async_loader.pending > 0 {
+            let Ok(finished) = self.async_loader.receiver.recv() else {
+                break;
+            };
+            self.commit_finished_load(finished);
+        }
+    }
+
+    fn commit_finished_load(&mut self, finished: FinishedLoad) {
+        self.async_loader.pending -= 1;
+        let FinishedLoad {
+            handle,
+            path,
+            result,
+        } = finished;
+
+        let bodies = self
+            .scene
+            .world
+            .get_resource::<RenderBodyResource>()
+            .expect("RenderBodyResource resource not found")
+            .clone();
+        // Removed while it was loading.
+        if bodies.read().get_render_body(handle).is_none() {
+            return;
+        }
+
+        match result.and_then(|model| self.commit_model(&path, model, Some(handle))) {
+            Ok(_) => {
+                bodies.write().load_states.remove(handle);
+            }
+            Err(e) => {
+                log::error!("{}", e);
+                // The path keeps its handle, so a later load of it retries into the same one.
+                bodies
+                    .write()
+                    .load_states
+                    .insert(handle, LoadState::Failed(Arc::new(e)));
+            }
+        }
+    }
+}
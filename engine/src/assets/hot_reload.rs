@@ -0,0 +1,530 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use glow::HasContext;
+use slotmap::{Key, SlotMap};
+
+use crate::{
+    Engine, EngineRenderer,
+    assets::{
+        asset_error::AssetError,
+        asset_registry::{AssetRegistry, sub_asset_path},
+        handles::{RenderBodyHandle, ShaderHandle, TextureHandle},
+        material_resource::MaterialResource,
+        mesh_resource::MeshResource,
+        shader::UniformValue,
+        shader_resource::ShaderResource,
+        texture_resource::TextureResource,
+    },
+    render::render_body_resource::RenderBodyResource,
+};
+
+/// How often `advance_frame` checks watched files.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A change is only reported once the file has not been touched for this long,
+/// so editors that write in several steps don't trigger a reload of a half-written file.
+const SETTLE_TIME: Duration = Duration::from_millis(100);
+
+/// Watches asset files by polling their modification times.
+///
+/// The first time a path is seen its current time is recorded; later polls report it
+/// once the time has changed and settled.
+pub(crate) struct HotReloader {
+    poll_interval: Duration,
+    last_poll: Option<Instant>,
+    files: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+impl HotReloader {
+    pub(crate) fn new(poll_interval: Duration) -> Self {
+        Self {
+            poll_interval,
+            last_poll: None,
+            files: HashMap::new(),
+        }
+    }
+
+    /// Whether `poll_interval` has passed since the last poll that returned true.
+    pub(crate) fn poll_due(&mut self, now: Instant) -> bool {
+        if self
+            .last_poll
+            .is_some_and(|last| now.duration_since(last) < self.poll_interval)
+        {
+            return false;
+        }
+        self.last_poll = Some(now);
+        true
+    }
+
+    /// Returns the paths whose modification time changed since they were last reported.
+    /// Paths that are no longer passed in stop being tracked.
+    pub(crate) fn changed_files(
+        &mut self,
+        paths: impl IntoIterator<Item = PathBuf>,
+        now: SystemTime,
+    ) -> Vec<PathBuf> {
+        let mut files = HashMap::with_capacity(self.files.len());
+        let mut changed = Vec::new();
+
+        for path in paths {
+            let modified = std::fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+            let known = match self.files.remove(&path) {
+                None => modified,
+                Some(known) if known == modified => known,
+                Some(known) => {
+                    let settled = modified.is_some_and(|modified| {
+                        now.duration_since(modified)
+                            .is_ok_and(|age| age >= SETTLE_TIME)
+                    });
+                    if settled {
+                        changed.push(path.clone());
+                        modified
+                    } else {
+                        known
+                    }
+                }
+            };
+            files.insert(path, known);
+        }
+
+        self.files = files;
+        changed
+    }
+}
+
+impl Default for HotReloader {
+    fn default() -> Self {
+        Self::new(DEFAULT_POLL_INTERVAL)
+    }
+}
+
+enum WatchedAsset {
+    Shader(ShaderHandle),
+    Texture(TextureHandle),
+    Model(String),
+}
+
+impl Engine {
+    /// Starts watching every loaded shader, texture and model file.
+    /// From then on each frame polls the files, at most every 250 ms, and reloads changed assets
+    /// in place: handles stay the same, so entities pick up the new data without being touched.
+    ///
+    /// Shaders that fail to compile keep their previous program and log the compiler output.
+    /// Textures embedded in a model are reloaded with the model.
+    pub fn enable_hot_reload(&mut self) {
+        if self.hot_reloader.is_none() {
+            let mut reloader = HotReloader::default();
+            reloader.changed_files(
+                Self::watched_asset_files(&self.scene.world).into_keys(),
+                SystemTime::now(),
+            );
+            self.hot_reloader = Some(reloader);
+        }
+    }
+
+    pub fn disable_hot_reload(&mut self) {
+        self.hot_reloader = None;
+    }
+
+    /// Checks the watched files now, ignoring the poll interval, and reloads the assets of any
+    /// that changed. Returns how many assets were reloaded.
+    /// Does nothing unless `enable_hot_reload` has been called.
+    pub fn reload_changed_assets(&mut self) -> usize {
+        let Some(reloader) = self.hot_reloader.as_mut() else {
+            return 0;
+        };
+        let mut watched = Self::watched_asset_files(&self.scene.world);
+        let changed = reloader.changed_files(watched.keys().cloned(), SystemTime::now());
+
+        let mut reloaded = 0;
+        for path in changed {
+            for asset in watched.remove(&path).unwrap_or_default() {
+                if self.reload_asset(&path, asset) {
+                    reloaded += 1;
+                }
+            }
+        }
+        reloaded
+    }
+
+    pub(crate) fn poll_hot_reload(&mut self) {
+        if self
+            .hot_reloader
+            .as_mut()
+            .is_some_and(|reloader| reloader.poll_due(Instant::now()))
+        {
+            self.reload_changed_assets();
+        }
+    }
+
+    /// Re-imports a loaded model and moves the result into the handles it was first loaded into:
+    /// the render body and the meshes, materials and embedded textures registered under the
+    /// model's path. Sub-assets the new version no longer has are freed along with their
+    /// paths. If the import fails the previous version stays in place.
+    pub fn reload_model(&mut self, model_path: &str) -> Result<RenderBodyHandle, AssetError> {
+        let world = &self.scene.world;
+        let body_resource = world
+            .get_resource::<RenderBodyResource>()
+            .expect("RenderBodyResource resource not found")
+            .clone();
+        let mesh_resource = world
+            .get_resource::<MeshResource>()
+            .expect("MeshResource resource not found")
+            .clone();
+        let material_resource = world
+            .get_resource::<MaterialResource>()
+            .expect("MaterialResource resource not found")
+            .clone();
+        let texture_resource = world
+            .get_resource::<TextureResource>()
+            .expect("TextureResource resource not found")
+            .clone();
+
+        let body_handle = body_resource
+            .read()
+            .paths
+            .handle(model_path)
+            .filter(|handle| body_resource.read().render_bodies.contains_key(*handle))
+            .ok_or_else(|| AssetError::NotLoaded(model_path.to_string()))?;
+        let prefix = sub_asset_path(model_path, "");
+        let old_meshes = take_sub_assets(&mut mesh_resource.write().paths, &prefix);
+        let old_materials = take_sub_assets(&mut material_resource.write().paths, &prefix);
+        let old_textures = take_sub_assets(&mut texture_resource.write().paths, &prefix);
+
+        let new_body_handle = match self.import_model(model_path) {
+            Ok(handle) => handle,
+            Err(e) => {
+                // Forget whatever the failed import registered and put the old sub-assets back.
+                take_sub_assets(&mut mesh_resource.write().paths, &prefix);
+                take_sub_assets(&mut material_resource.write().paths, &prefix);
+                take_sub_assets(&mut texture_resource.write().paths, &prefix);
+                restore_sub_assets(&mut mesh_resource.write().paths, old_meshes);
+                restore_sub_assets(&mut material_resource.write().paths, old_materials);
+                restore_sub_assets(&mut texture_resource.write().paths, old_textures);
+                return Err(e);
+            }
+        };
+
+        let moved_meshes = {
+            let mut storage = mesh_resource.write();
+            let storage = &mut *storage;
+            move_into_old_handles(&mut storage.meshes, &mut storage.paths, old_meshes)
+        };
+        let mesh_remap = moved_meshes.remap;
+        let dropped = moved_meshes.dropped.iter().map(|(mesh, _)| mesh);
+        for mesh in mesh_remap.values().chain(dropped) {
+            match &mut self.renderer {
+                EngineRenderer::Gl(renderer) => renderer.invalidate_mesh(*mesh),
+                EngineRenderer::Null(renderer) => renderer.invalidate_mesh(*mesh),
+            }
+        }
+
+        let (texture_remap, material_remap) = {
+            let mut textures = texture_resource.write();
+            let textures = &mut *textures;
+            let moved_textures =
+                move_into_old_handles(&mut textures.textures, &mut textures.paths, old_textures);
+            let mut freed = moved_textures.replaced;
+            freed.extend(
+                moved_textures
+                    .dropped
+                    .into_iter()
+                    .map(|(_, texture)| texture),
+            );
+
+            let mut materials = material_resource.write();
+            let materials = &mut *materials;
+            let moved_materials = move_into_old_handles(
+                &mut materials.materials,
+                &mut materials.paths,
+                old_materials,
+            );
+            let dropped_materials = moved_materials.dropped.iter().map(|(_, material)| material);
+
+            // Colour-only textures are created per material and have no path; the
+            // replaced and dropped materials were their only users.
+            for material in moved_materials.replaced.iter().chain(dropped_materials) {
+                for (_, value) in &material.desc.params {
+                    if let UniformValue::Texture { handle, .. } = value
+                        && textures.paths.path(*handle).is_none()
+                        && !textures.is_missing_texture(*handle)
+                        && let Some(texture) = textures.textures.remove(*handle)
+                    {
+                        freed.push(texture);
+                    }
+                }
+            }
+            if let Some(gl) = self.gl.as_deref() {
+                for gl_tex in freed.into_iter().filter_map(|texture| texture.gl_tex) {
+                    unsafe { gl.delete_texture(gl_tex) };
+                }
+            }
+            (moved_textures.remap, moved_materials.remap)
+        };
+
+        {
+            let mut materials = material_resource.write();
+            let materials = &mut *materials;
+            for (_, material_handle) in materials
+                .paths
+                .iter()
+                .filter(|(path, _)| path.starts_with(&prefix))
+            {
+                let Some(material) = materials.materials.get_mut(material_handle) else {
+                    continue;
+                };
+                for (_, value) in &mut material.desc.params {
+                    if let UniformValue::Texture { handle, .. } = value
+                        && let Some(old) = texture_remap.get(handle)
+                    {
+                        *handle = *old;
+                    }
+                }
+            }
+        }
+
+        let mut bodies = body_resource.write();
+        let mut body = bodies
+            .render_bodies
+            .remove(new_body_handle)
+            .expect("imported render body missing");
+        for part in &mut body.parts {
+            if let Some(old) = mesh_remap.get(&part.mesh_id) {
+                part.mesh_id = *old;
+            }
+            if let Some(old) = material_remap.get(&part.material_id) {
+                part.material_id = *old;
+            }
+        }
+        *bodies
+            .get_render_body_mut(body_handle)
+            .expect("reloaded render body missing") = body;
+        Ok(body_handle)
+    }
+
+    fn reload_asset(&mut self, path: &Path, asset: WatchedAsset) -> bool {
+        let result = match asset {
+            WatchedAsset::Shader(handle) => {
+                // Headless engines have no programs to recompile.
+                let Some(gl) = self.gl.clone() else {
+                    return false;
+                };
+                let result = self
+                    .scene
+                    .world
+                    .get_resource::<ShaderResource>()
+                    .expect("ShaderResource resource not found")
+                    .write()
+                    .reload(&gl, handle);
+                if result.is_ok()
+                    && let EngineRenderer::Gl(renderer) = &mut self.renderer
+                {
+                    renderer.invalidate_shader(handle);
+                }
+                result
+            }
+            WatchedAsset::Texture(handle) => self
+                .scene
+                .world
+                .get_resource::<TextureResource>()
+                .expect("TextureResource resource not found")
+                .write()
+                .reload(self.gl.as_deref(), handle),
+            WatchedAsset::Model(model_path) => self.reload_model(&model_path).map(|_| ()),
+        };
+
+        match result {
+            Ok(()) => {
+                log::info!("Hot reloaded {}", path.display());
+                true
+            }
+            Err(e) => {
+                log::error!(
+                    "Hot reload of {} failed, keeping the old asset: {}",
+                    path.display(),
+                    e
+                );
+                false
+            }
+        }
+    }
+
+    fn watched_asset_files(world: &bevy_ecs::world::World) -> HashMap<PathBuf, Vec<WatchedAsset>> {
+        let mut watched: HashMap<PathBuf, Vec<WatchedAsset>> = HashMap::new();
+
+        let shaders = world
+            .get_resource::<ShaderResource>()
+            .expect("ShaderResource resource not found")
+            .read();
+        for (handle, vertex_path, fragment_path) in shaders.sources() {
+            for path in [vertex_path, fragment_path] {
+                watched
+                    .entry(PathBuf::from(path))
+                    .or_default()
+                    .push(WatchedAsset::Shader(handle));
+            }
+        }
+
+        let textures = world
+            .get_resource::<TextureResource>()
+            .expect("TextureResource resource not found")
+            .read();
+        for (path, handle) in textures.paths.iter() {
+            if !path.contains('#') {
+                watched
+                    .entry(PathBuf::from(path))
+                    .or_default()
+                    .push(WatchedAsset::Texture(handle));
+            }
+        }
+
+        let bodies = world
+            .get_resource::<RenderBodyResource>()
+            .expect("RenderBodyResource resource not found")
+            .read();
+        for (path, _) in bodies.paths.iter() {
+            watched
+                .entry(PathBuf::from(path))
+                .or_default()
+                .push(WatchedAsset::Model(path.to_string()));
+        }
+
+        watched
+    }
+}
+
+/// Unregisters every asset whose path starts with `prefix`, returning them by path.
+fn take_sub_assets<K: Key>(registry: &mut AssetRegistry<K>, prefix: &str) -> HashMap<String, K> {
+    let taken: HashMap<String, K> = registry
+        .iter()
+        .filter(|(path, _)| path.starts_with(prefix))
+        .map(|(path, handle)| (path.to_string(), handle))
+        .collect();
+    for handle in taken.values() {
+        registry.remove(*handle);
+    }
+    taken
+}
+
+fn restore_sub_assets<K: Key>(registry: &mut AssetRegistry<K>, old: HashMap<String, K>) {
+    for (path, handle) in old {
+        registry.insert(&path, handle);
+    }
+}
+
+/// What `move_into_old_handles` took out of the storage.
+struct MovedSubAssets<K: Key, V> {
+    /// New handle to the old handle its value moved into.
+    remap: HashMap<K, K>,
+    /// Old values overwritten by the fresh import.
+    replaced: Vec<V>,
+    /// Old sub-assets the fresh import no longer has, removed with their handles.
+    dropped: Vec<(K, V)>,
+}
+
+/// For every path in `old` that was registered again by a fresh import, moves the new value
+/// into the old handle and frees the new slot. Old paths the import didn't register again
+/// are removed. The caller releases the GPU resources of whatever was replaced or dropped.
+fn move_into_old_handles<K: Key, V>(
+    slots: &mut SlotMap<K, V>,
+    registry: &mut AssetRegistry<K>,
+    old: HashMap<String, K>,
+) -> MovedSubAssets<K, V> {
+    let mut remap = HashMap::with_capacity(old.len());
+    let mut replaced = Vec::with_capacity(old.len());
+    let mut dropped = Vec::new();
+    for (path, old_handle) in old {
+        let Some(new_handle) = registry.handle(&path) else {
+            if let Some(value) = slots.remove(old_handle) {
+                dropped.push((old_handle, value));
+            }
+            continue;
+        };
+        if !slots.contains_key(old_handle) {
+            continue;
+        }
+        let value = slots.remove(new_handle).expect("registered asset missing");
+        replaced.push(std::mem::replace(&mut slots[old_handle], value));
+        registry.remove(new_handle);
+        registry.insert(&path, old_handle);
+        remap.insert(new_handle, old_handle);
+    }
+    MovedSubAssets {
+        remap,
+        replaced,
+        dropped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use super::*;
+
+    fn touch(path: &Path, modified: SystemTime) {
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[test]
+    fn reports_a_file_once_its_modification_time_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.frag");
+        std::fs::write(&path, "void main() {}").unwrap();
+        let start = SystemTime::now() - Duration::from_secs(60);
+        touch(&path, start);
+
+        let mut reloader = HotReloader::default();
+        let now = SystemTime::now();
+        assert!(reloader.changed_files([path.clone()], now).is_empty());
+        assert!(reloader.changed_files([path.clone()], now).is_empty());
+
+        touch(&path, start + Duration::from_secs(10));
+        assert_eq!(
+            reloader.changed_files([path.clone()], now),
+            vec![path.clone()]
+        );
+        assert!(reloader.changed_files([path.clone()], now).is_empty());
+    }
+
+    #[test]
+    fn waits_for_a_change_to_settle() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.png");
+        std::fs::write(&path, [0u8]).unwrap();
+        let start = SystemTime::now() - Duration::from_secs(60);
+        touch(&path, start);
+
+        let mut reloader = HotReloader::default();
+        reloader.changed_files([path.clone()], start + Duration::from_secs(1));
+
+        let written = start + Duration::from_secs(10);
+        touch(&path, written);
+        assert!(reloader.changed_files([path.clone()], written).is_empty());
+        assert_eq!(
+            reloader.changed_files([path.clone()], written + SETTLE_TIME),
+            vec![path]
+        );
+    }
+
+    #[test]
+    fn polls_no_more_often_than_the_interval() {
+        let mut reloader = HotReloader::new(Duration::from_millis(100));
+        let start = Instant::now();
+
+        assert!(reloader.poll_due(start));
+        assert!(!reloader.poll_due(start + Duration::from_millis(50)));
+        assert!(reloader.poll_due(start + Duration::from_millis(100)));
+    }
+}
@@ -1,15 +1,38 @@
 use crate::assets::{handles::*, shader::UniformValue};
 
+/// How a material's surface covers what is behind it, from the alpha of its albedo.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BlendMode {
+    /// Alpha is ignored.
+    #[default]
+    Opaque,
+    /// Fragments with alpha below `cutoff` are discarded, the rest are opaque.
+    Mask { cutoff: f32 },
+    /// Blended over what was drawn before it, after every opaque instance and sorted
+    /// back to front. Blended instances don't write depth or cast shadows.
+    Blend,
+}
+
 pub struct MaterialDesc {
     pub shader: ShaderHandle,
     pub params: MaterialParams,
+    pub blend_mode: BlendMode,
 }
 
 type MaterialParams = Vec<(String, UniformValue)>;
 
 impl MaterialDesc {
     pub fn new(shader: ShaderHandle, params: MaterialParams) -> Self {
-        Self { shader, params }
+        Self {
+            shader,
+            params,
+            blend_mode: BlendMode::Opaque,
+        }
+    }
+
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
     }
 }
 
@@ -3,11 +3,14 @@ use std::sync::{Arc, RwLock};
 use bevy_ecs::prelude::*;
 use slotmap::SlotMap;
 
-use crate::assets::{handles::MaterialHandle, material::Material};
+use crate::assets::{asset_registry::AssetRegistry, handles::MaterialHandle, material::Material};
 
 #[derive(Default)]
 pub struct MaterialStorage {
     pub materials: SlotMap<MaterialHandle, Material>,
+    pub paths: AssetRegistry<MaterialHandle>,
+    /// Created the first time `Engine::error_material` is called.
+    pub(crate) error_material: Option<MaterialHandle>,
 }
 
 #[derive(Resource, Default, Clone)]
@@ -46,5 +49,6 @@ impl MaterialStorage {
     #[allow(dead_code)]
     pub fn remove_material(&mut self, material_id: MaterialHandle) {
         self.materials.remove(material_id);
+        self.paths.remove(material_id);
     }
 }
@@ -2,7 +2,7 @@
 // See accompanying file LICENSE or https://www.gnu.org/licenses/agpl-3.0.html for details.
 use approx::relative_eq;
 use bytemuck::{Pod, Zeroable};
-use glam::Vec3;
+use glam::{Mat4, Vec3};
 use std::hash::{Hash, Hasher};
 
 use crate::components::collider_component::{BVHNode, Triangle};
@@ -16,6 +16,11 @@ pub struct Vertex {
     pub uv_albedo: [f32; 2],
     pub uv_normal: [f32; 2],
     pub tangent: [f32; 4],
+    /// Indices into the skin's joint list, stored as floats for the vertex attribute.
+    /// All zero on meshes without a skin.
+    pub joints: [f32; 4],
+    /// Influence of each of `joints`, summing to 1 on skinned meshes.
+    pub weights: [f32; 4],
 }
 
 impl PartialEq for Vertex {
@@ -35,6 +40,8 @@ unsafe impl Zeroable for Vertex {
             uv_albedo: [0.0, 0.0],
             uv_normal: [0.0, 0.0],
             tangent: [0.0, 0.0, 0.0, 0.0],
+            joints: [0.0, 0.0, 0.0, 0.0],
+            weights: [0.0, 0.0, 0.0, 0.0],
         }
     }
 }
@@ -213,6 +220,45 @@ impl Mesh {
         tris
     }
 
+    /// Gives the corners of every triangle different `barycentric` unit vectors, so edges
+    /// can be found from the interpolated value. A vertex shared by triangles that need it
+    /// at different corners is duplicated; the rest stay shared.
+    pub fn assign_barycentrics(&mut self) {
+        let mut corners: Vec<Option<usize>> = vec![None; self.vertices.len()];
+        for triangle in self.indices.chunks_exact_mut(3) {
+            // Vertices keep the corner they already have unless another vertex of the
+            // triangle took it first.
+            let mut taken = [false; 3];
+            let mut kept = [false; 3];
+            for (i, &index) in triangle.iter().enumerate() {
+                if let Some(corner) = corners[index as usize]
+                    && !taken[corner]
+                {
+                    taken[corner] = true;
+                    kept[i] = true;
+                }
+            }
+            for (i, index) in triangle.iter_mut().enumerate() {
+                if kept[i] {
+                    continue;
+                }
+                let corner = (0..3).find(|&corner| !taken[corner]).unwrap();
+                taken[corner] = true;
+                if corners[*index as usize].is_none() {
+                    corners[*index as usize] = Some(corner);
+                } else {
+                    self.vertices.push(self.vertices[*index as usize]);
+                    corners.push(Some(corner));
+                    *index = (self.vertices.len() - 1) as u32;
+                }
+            }
+        }
+        for (vertex, corner) in self.vertices.iter_mut().zip(corners) {
+            vertex.barycentric = [0.0; 3];
+            vertex.barycentric[corner.unwrap_or(0)] = 1.0;
+        }
+    }
+
     pub fn compute_bounding_sphere(&mut self) {
         // Center = AABB center
         self.sphere_center = (self.aabb.min + self.aabb.max) * 0.5;
@@ -220,6 +266,21 @@ impl Mesh {
         self.sphere_radius = (self.aabb.max - self.sphere_center).length();
     }
 
+    /// Center and radius of the bounding sphere once the mesh is placed by `transform`.
+    /// Non-uniform scale grows the sphere by the largest axis.
+    pub fn world_bounding_sphere(&self, transform: &Mat4) -> (Vec3, f32) {
+        let scale = transform
+            .x_axis
+            .truncate()
+            .length()
+            .max(transform.y_axis.truncate().length())
+            .max(transform.z_axis.truncate().length());
+        (
+            transform.transform_point3(self.sphere_center),
+            self.sphere_radius * scale,
+        )
+    }
+
     pub(crate) fn compute_tangents(
         positions: &[[f32; 3]],
         normals: &[[f32; 3]],
@@ -334,6 +395,28 @@ mod tests {
         assert_eq!(aabb.max, Vec3::new(7.0, 8.0, 9.0));
     }
 
+    #[test]
+    fn triangle_corners_get_distinct_barycentrics() {
+        // A quad shares two vertices without conflict. The third triangle uses vertices 1
+        // and 3, which both sit at the second corner, so vertex 3 is duplicated.
+        let mut mesh = Mesh {
+            vertices: vec![Vertex::zeroed(); 5],
+            indices: vec![0, 1, 2, 0, 2, 3, 1, 3, 4],
+            ..Default::default()
+        };
+        mesh.assign_barycentrics();
+
+        assert_eq!(mesh.vertices.len(), 6);
+        for triangle in mesh.indices.chunks(3) {
+            let sum = triangle.iter().fold(Vec3::ZERO, |sum, &index| {
+                sum + Vec3::from(mesh.vertices[index as usize].barycentric)
+            });
+            assert_eq!(sum, Vec3::ONE, "triangle {triangle:?}");
+        }
+        assert_eq!(&mesh.indices[..6], &[0, 1, 2, 0, 2, 3]);
+        assert_eq!(&mesh.indices[6..], &[1, 5, 4]);
+    }
+
     #[test]
     fn compute_bounding_sphere() {
         let mut mesh = Mesh::default();
@@ -5,13 +5,14 @@ use slotmap::SlotMap;
 use bevy_ecs::prelude::*;
 
 use crate::{
-    assets::{handles::MeshHandle, mesh::Mesh},
-    render::renderer::Renderer,
+    assets::{asset_registry::AssetRegistry, handles::MeshHandle, mesh::Mesh},
+    render::{renderer::Renderer, renderer_backends::GraphicsBackend},
 };
 
 #[derive(Default)]
 pub struct MeshStorage {
     pub meshes: SlotMap<MeshHandle, Mesh>,
+    pub paths: AssetRegistry<MeshHandle>,
 }
 
 #[derive(Resource, Default, Clone)]
@@ -52,7 +53,12 @@ impl MeshStorage {
     }
 
     #[allow(dead_code)]
-    pub fn remove_mesh(&mut self, mesh_id: MeshHandle, renderer: &mut Renderer) {
+    pub fn remove_mesh<B: GraphicsBackend>(
+        &mut self,
+        mesh_id: MeshHandle,
+        renderer: &mut Renderer<B>,
+    ) {
+        self.paths.remove(mesh_id);
         if self.meshes.remove(mesh_id).is_some() {
             renderer.delete_mesh_gpu(mesh_id);
         }
@@ -1,4 +1,8 @@
+pub mod asset_error;
+pub mod asset_registry;
+pub mod async_loader;
 pub mod handles;
+pub mod hot_reload;
 pub mod material;
 pub mod material_resource;
 pub mod mesh;
@@ -1,15 +1,23 @@
+use glam::{Mat4, Quat, Vec3};
+use gltf::material::AlphaMode;
 use log::warn;
 use std::{collections::HashMap, ffi::OsStr};
 
 use crate::{
-    Engine,
+    Engine, TransformComponent,
+    animation::{
+        clip::{AnimationChannel, AnimationClip, Interpolation, Keyframes},
+        skeleton::{MAX_JOINTS, ModelHierarchy, ModelNode, Skin},
+    },
     assets::{
+        asset_error::{AssetError, ModelError, TextureError},
+        asset_registry::sub_asset_path,
         handles::{MaterialHandle, RenderBodyHandle, ShaderHandle, TextureHandle},
-        material::{Material, MaterialDesc},
+        material::{BlendMode, Material, MaterialDesc},
         material_resource::{MaterialResource, MaterialStorage},
         mesh::{Aabb, GltfPrimitiveMesh, Mesh, Vertex},
         mesh_resource::MeshResource,
-        shader::UniformValue,
+        shader::{Shader, UniformValue},
         shader_resource::ShaderResource,
         texture_resource::TextureResource,
     },
@@ -20,6 +28,14 @@ use crate::{
 };
 
 const DEFAULT_MATERIAL_CAPACITY: usize = 32;
+
+fn model_error(path: &str, source: impl Into<ModelError>) -> AssetError {
+    AssetError::Model {
+        path: path.to_string(),
+        source: source.into(),
+    }
+}
+
 impl Engine {
     fn rgba_from_rgb(rgb: [f32; 3]) -> [u8; 4] {
         [
@@ -45,6 +61,7 @@ impl Engine {
         albedo_handle: TextureHandle,
         normal_handle: TextureHandle,
         roughness: f32,
+        blend_mode: BlendMode,
     ) -> MaterialHandle {
         let params = vec![
             ("u_roughness".to_string(), UniformValue::Float(roughness)),
@@ -64,424 +81,336 @@ impl Engine {
                 },
             ),
         ];
-        let desc = MaterialDesc::new(shader_handle, params);
+        let desc = MaterialDesc::new(shader_handle, params).with_blend_mode(blend_mode);
         material_resource.add_material(Material::new(desc))
     }
 
+    /// A PBR material textured with the missing-texture checkerboard, for entities whose own
+    /// material failed to load. If the PBR shader itself fails to compile, the material
+    /// uses a shader without a program and draws nothing.
+    pub fn error_material(&mut self) -> MaterialHandle {
+        let gl = self.gl.as_deref();
+        let world = &self.scene.world;
+        let material_resource = world
+            .get_resource::<MaterialResource>()
+            .expect("MaterialResource not found");
+        if let Some(handle) = material_resource.read().error_material
+            && material_resource.read().materials.contains_key(handle)
+        {
+            return handle;
+        }
+
+        let shader_handle = {
+            let mut shaders = world
+                .get_resource::<ShaderResource>()
+                .expect("ShaderResource not found")
+                .write();
+            shaders
+                .get_or_load(
+                    gl,
+                    OsStr::new("resources/shaders/pbr.vert"),
+                    OsStr::new("resources/shaders/pbr.frag"),
+                )
+                .unwrap_or_else(|e| {
+                    log::error!("{}", e);
+                    shaders.insert_shader(Shader::unloaded())
+                })
+        };
+        let (albedo, normal) = {
+            let mut textures = world
+                .get_resource::<TextureResource>()
+                .expect("TextureResource not found")
+                .write();
+            (
+                textures.missing_texture(gl),
+                textures.create_solid_rgba(gl, [128, 128, 255, 255]),
+            )
+        };
+
+        let mut materials = material_resource.write();
+        let handle = Self::create_pbr_material(
+            &mut materials,
+            shader_handle,
+            albedo,
+            normal,
+            1.0,
+            BlendMode::Opaque,
+        );
+        materials.error_material = Some(handle);
+        handle
+    }
+
     /// Loads a model from the specified file path. Supports different model formats based on file extension.
-    /// Returns a `RenderBodyHandle` if the model is successfully loaded, or `None` if the format is unsupported.
     ///
-    /// Currently supported formats: glTF (.gltf)
+    /// Currently supported formats: glTF (.gltf, .glb) and OBJ (.obj)
     ///
-    /// FBX (.fbx) loading is not yet implemented.
-    pub fn load_model(&mut self, model_path: &str) -> Option<RenderBodyHandle> {
-        let extension = std::path::Path::new(model_path)
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("")
-            .to_lowercase();
-
-        match extension.as_str() {
-            "gltf" | "glb" => Some(self.load_gltf(model_path)),
-            "fbx" => Some(self.load_fbx(model_path)),
-            "obj" => Some(self.load_obj(model_path)),
-            _ => {
-                warn!("Unsupported model format: {}", extension);
-                None
+    /// FBX (.fbx) loading is not yet implemented and returns an error. Textures referenced by
+    /// the model that fail to load are replaced with the missing-texture checkerboard and logged
+    /// rather than failing the whole model.
+    ///
+    /// Loading a path that is already loaded returns the existing handle. The model's meshes,
+    /// materials and embedded textures are registered as sub-assets of the path (`path#mesh0`,
+    /// `path#material0`, `path#texture0`), so their paths can be looked up from their handles.
+    /// A path still loading through `load_model_async` also returns its handle straight away,
+    /// while one whose background load failed is loaded again into its handle.
+    pub fn load_model(&mut self, model_path: &str) -> Result<RenderBodyHandle, AssetError> {
+        let bodies = self
+            .scene
+            .world
+            .get_resource::<RenderBodyResource>()
+            .expect("RenderBodyResource resource not found")
+            .clone();
+        let existing = bodies.read().paths.handle(model_path);
+        if let Some(handle) = existing {
+            if !bodies
+                .read()
+                .load_state(handle)
+                .is_some_and(|s| s.is_failed())
+            {
+                return Ok(handle);
             }
+            // A failed `load_model_async` keeps its handle; retry into it.
+            let model = ImportedModel::read(model_path)?;
+            self.commit_model(model_path, model, Some(handle))?;
+            bodies.write().load_states.remove(handle);
+            return Ok(handle);
         }
-    }
 
-    /// Loads an OBJ model from the specified file path and returns a `RenderBodyHandle`.
-    fn load_obj(&mut self, obj_path: &str) -> RenderBodyHandle {
-        let gl = &self.gl;
-        let obj_path = std::path::Path::new(obj_path);
-        let base_dir = obj_path
-            .parent()
-            .unwrap_or_else(|| std::path::Path::new("."));
+        let handle = self.import_model(model_path)?;
+        self.scene
+            .world
+            .get_resource::<RenderBodyResource>()
+            .expect("RenderBodyResource resource not found")
+            .write()
+            .paths
+            .insert(model_path, handle);
+        Ok(handle)
+    }
 
-        let (models, obj_materials) = tobj::load_obj(
-            obj_path,
-            &tobj::LoadOptions {
-                single_index: true,
-                ..Default::default()
-            },
-        )
-        .expect("Failed to load OBJ file");
+    /// Imports a model without consulting or updating the render body registry.
+    /// Sub-assets are still registered under the model's path.
+    pub(crate) fn import_model(
+        &mut self,
+        model_path: &str,
+    ) -> Result<RenderBodyHandle, AssetError> {
+        let model = ImportedModel::read(model_path)?;
+        self.commit_model(model_path, model, None)
+    }
 
-        let shader_handle = {
-            let shader_resource = self
-                .scene
-                .world
-                .get_resource_mut::<ShaderResource>()
-                .expect("ShaderResource not found");
-            shader_resource.write().get_or_load(
+    /// Moves an imported model into the asset storages and uploads its textures.
+    /// This is the only part of loading that needs the main thread.
+    ///
+    /// The render body goes into `body_handle` when given (a handle handed out before the
+    /// model finished loading), otherwise into a new slot.
+    pub(crate) fn commit_model(
+        &mut self,
+        model_path: &str,
+        model: ImportedModel,
+        body_handle: Option<RenderBodyHandle>,
+    ) -> Result<RenderBodyHandle, AssetError> {
+        let gl = self.gl.as_deref();
+        let world = &self.scene.world;
+
+        let shader_handle = world
+            .get_resource::<ShaderResource>()
+            .expect("ShaderResource not found")
+            .write()
+            .get_or_load(
                 gl,
                 OsStr::new("resources/shaders/pbr.vert"),
                 OsStr::new("resources/shaders/pbr.frag"),
-            )
-        };
+            )?;
 
-        let mut material_inputs: Vec<(TextureHandle, TextureHandle, f32)> =
-            Vec::with_capacity(DEFAULT_MATERIAL_CAPACITY);
+        let mut material_inputs: Vec<(TextureHandle, TextureHandle, f32, BlendMode)> =
+            Vec::with_capacity(model.materials.len().max(DEFAULT_MATERIAL_CAPACITY));
         {
-            let texture_resource = self
-                .scene
-                .world
-                .get_resource_mut::<TextureResource>()
-                .expect("TextureResource not found");
-
-            if let Ok(obj_materials) = obj_materials.as_ref() {
-                for material in obj_materials {
-                    let albedo_handle = if let Some(tex) = material.diffuse_texture.as_ref() {
-                        if !tex.is_empty() {
-                            let tex_path = base_dir.join(tex);
-                            texture_resource
-                                .write()
-                                .load_from_file(gl, tex_path.as_os_str())
-                        } else {
-                            let diffuse = material.diffuse.unwrap_or([1.0, 1.0, 1.0]);
-                            let rgba = Self::rgba_from_rgb(diffuse);
-                            texture_resource.write().create_solid_rgba(gl, rgba)
-                        }
-                    } else {
-                        let diffuse = material.diffuse.unwrap_or([1.0, 1.0, 1.0]);
-                        let rgba = Self::rgba_from_rgb(diffuse);
-                        texture_resource.write().create_solid_rgba(gl, rgba)
-                    };
+            let mut textures = world
+                .get_resource::<TextureResource>()
+                .expect("TextureResource not found")
+                .write();
+
+            let mut embedded = Vec::with_capacity(model.textures.len());
+            for (index, image) in model.textures.into_iter().enumerate() {
+                let handle = match image {
+                    Some(image) => {
+                        let handle = textures.create_from_rgba_with_key(
+                            gl,
+                            image.width,
+                            image.height,
+                            &image.rgba,
+                        );
+                        textures.paths.insert(
+                            &sub_asset_path(model_path, &format!("texture{}", index)),
+                            handle,
+                        );
+                        handle
+                    }
+                    None => textures.missing_texture(gl),
+                };
+                embedded.push(handle);
+            }
 
-                    let normal_handle = if let Some(tex) = material.normal_texture.as_ref() {
-                        if !tex.is_empty() {
-                            let tex_path = base_dir.join(tex);
-                            texture_resource
-                                .write()
-                                .load_from_file(gl, tex_path.as_os_str())
-                        } else {
-                            texture_resource
-                                .write()
-                                .create_solid_rgba(gl, [128, 128, 255, 255])
+            let mut resolve = |source: TextureSource| match source {
+                TextureSource::Solid(rgba) => textures.create_solid_rgba(gl, rgba),
+                TextureSource::Embedded(index) => embedded[index],
+                TextureSource::File { path, image } => {
+                    if let Some(handle) = textures.paths.handle(&path) {
+                        return handle;
+                    }
+                    match image {
+                        Ok(image) => {
+                            let handle = textures.create_from_rgba_with_key(
+                                gl,
+                                image.width,
+                                image.height,
+                                &image.rgba,
+                            );
+                            textures.paths.insert(&path, handle);
+                            handle
                         }
-                    } else {
-                        texture_resource
-                            .write()
-                            .create_solid_rgba(gl, [128, 128, 255, 255])
-                    };
-
-                    let roughness = if let Some(shininess) = material.shininess {
-                        if shininess > 0.0 {
-                            (1.0_f32 - (shininess / 1000.0)).clamp(0.0, 1.0)
-                        } else {
-                            1.0
+                        Err(e) => {
+                            log::error!("{}", e);
+                            textures.missing_texture(gl)
                         }
-                    } else {
-                        1.0
-                    };
-
-                    material_inputs.push((albedo_handle, normal_handle, roughness));
+                    }
                 }
-            }
+            };
 
-            if material_inputs.is_empty() {
-                let albedo = texture_resource
-                    .write()
-                    .create_solid_rgba(gl, [255, 255, 255, 255]);
-                let default_normal = texture_resource
-                    .write()
-                    .create_solid_rgba(gl, [128, 128, 255, 255]);
-                material_inputs.push((albedo, default_normal, 1.0));
+            for material in model.materials {
+                let albedo = resolve(material.albedo);
+                let normal = resolve(material.normal);
+                material_inputs.push((albedo, normal, material.roughness, material.blend_mode));
             }
         }
 
         let mut material_handles: Vec<MaterialHandle> = Vec::with_capacity(material_inputs.len());
         {
-            let material_resource = self
-                .scene
-                .world
-                .get_resource_mut::<MaterialResource>()
-                .expect("MaterialResource not found");
-            for (albedo_handle, normal_handle, roughness) in material_inputs {
+            let mut materials = world
+                .get_resource::<MaterialResource>()
+                .expect("MaterialResource not found")
+                .write();
+            for (index, &(albedo_handle, normal_handle, roughness, blend_mode)) in
+                material_inputs.iter().enumerate()
+            {
                 let handle = Self::create_pbr_material(
-                    &mut material_resource.write(),
+                    &mut materials,
                     shader_handle,
                     albedo_handle,
                     normal_handle,
                     roughness,
+                    blend_mode,
+                );
+                materials.paths.insert(
+                    &sub_asset_path(model_path, &format!("material{}", index)),
+                    handle,
                 );
                 material_handles.push(handle);
             }
         }
 
-        let default_material = material_handles[0];
-
-        let mut built_parts: Vec<(Mesh, MaterialHandle)> = Vec::with_capacity(models.len());
-        for model in models.iter() {
-            let mesh = &model.mesh;
-            let vertex_count = mesh.positions.len() / 3;
-
-            let mut positions: Vec<[f32; 3]> = Vec::with_capacity(vertex_count);
-            for i in 0..vertex_count {
-                positions.push([
-                    mesh.positions[i * 3],
-                    mesh.positions[i * 3 + 1],
-                    mesh.positions[i * 3 + 2],
-                ]);
-            }
-
-            let has_normals = !mesh.normals.is_empty();
-            let mut normals: Vec<[f32; 3]> = Vec::with_capacity(vertex_count);
-            if has_normals {
-                for i in 0..vertex_count {
-                    normals.push([
-                        mesh.normals[i * 3],
-                        mesh.normals[i * 3 + 1],
-                        mesh.normals[i * 3 + 2],
-                    ]);
-                }
-            } else {
-                normals.resize(vertex_count, [0.0, 0.0, 1.0]);
-            }
-
-            let has_uvs = !mesh.texcoords.is_empty();
-            let mut uvs: Vec<[f32; 2]> = Vec::with_capacity(vertex_count);
-            if has_uvs {
-                for i in 0..vertex_count {
-                    let u = mesh.texcoords[i * 2];
-                    let v = mesh.texcoords[i * 2 + 1];
-                    uvs.push([u, 1.0 - v]);
-                }
-            } else {
-                uvs.resize(vertex_count, [0.0, 0.0]);
-            }
-
-            let indices: Vec<u32> = mesh.indices.to_vec();
-
-            let tangents = if has_uvs && has_normals {
-                Mesh::compute_tangents(&positions, &normals, &uvs, &indices)
-            } else {
-                vec![[1.0, 0.0, 0.0, 1.0]; vertex_count]
-            };
-
-            let mut built_mesh = Mesh::default();
-            for i in 0..vertex_count {
-                built_mesh.vertices.push(Vertex {
-                    position: positions[i],
-                    normal: normals[i],
-                    barycentric: [0.0, 0.0, 0.0],
-                    uv_albedo: uvs[i],
-                    uv_normal: uvs[i],
-                    tangent: tangents[i],
-                });
-            }
-            built_mesh.indices.extend(indices.iter().copied());
-
-            built_mesh.aabb = Aabb::from_vertices(&built_mesh.vertices);
-            built_mesh.compute_bounding_sphere();
-            built_mesh.build_bvh(8);
-
-            let material_handle = mesh
-                .material_id
-                .and_then(|idx| material_handles.get(idx).copied())
-                .unwrap_or(default_material);
-
-            built_parts.push((built_mesh, material_handle));
-        }
-
-        let mut parts = Vec::with_capacity(built_parts.len());
+        let default_material = 0;
+        let mut mesh_parts = Vec::with_capacity(model.meshes.len());
         {
-            let mesh_resource = self
-                .scene
-                .world
-                .get_resource_mut::<MeshResource>()
-                .expect("MeshResource not found");
-            for (built_mesh, material_handle) in built_parts {
-                let mesh_handle = mesh_resource.write().add_mesh(built_mesh);
-                parts.push(RenderBodyPart {
-                    mesh_id: mesh_handle,
-                    material_id: material_handle,
-                    local_transform: glam::Mat4::IDENTITY,
-                });
-            }
-        }
-
-        let render_body = RenderBody::new(parts);
-        self.scene
-            .world
-            .get_resource_mut::<RenderBodyResource>()
-            .expect("RenderBodyResource not found")
-            .write()
-            .add_render_body(render_body)
-    }
-
-    /// Loads an FBX model from the specified file path and returns a `RenderBodyHandle`.
-    fn load_fbx(&mut self, _fbx_path: &str) -> RenderBodyHandle {
-        unimplemented!("FBX loading is not yet implemented");
-    }
-
-    /// Loads a glTF model from the specified file path and returns a `RenderBodyHandle`.
-    fn load_gltf(&mut self, gltf_path: &str) -> RenderBodyHandle {
-        let os_path = OsStr::new(gltf_path);
-
-        let mesh_primitives = Self::mesh_primatives_from_gltf(os_path).unwrap();
-
-        let material_handles = self
-            .load_materials_from_gltf(
-                os_path,
-                OsStr::new("resources/shaders/pbr.vert"),
-                OsStr::new("resources/shaders/pbr.frag"),
-            )
-            .expect("Failed to load glTF materials");
-
-        let default_material = *material_handles
-            .first()
-            .expect("No materials found in glTF");
-
-        let mut parts = Vec::with_capacity(mesh_primitives.len());
-        {
-            let mesh_resource = self
-                .scene
-                .world
-                .get_resource_mut::<MeshResource>()
-                .expect("MeshResource not found");
-
-            for prim in mesh_primitives {
-                let mesh_handle = mesh_resource.write().add_mesh(prim.mesh);
+            let mut meshes = world
+                .get_resource::<MeshResource>()
+                .expect("MeshResource not found")
+                .write();
+            for (index, prim) in model.meshes.into_iter().enumerate() {
+                let mesh_handle = meshes.add_mesh(prim.mesh);
+                meshes.paths.insert(
+                    &sub_asset_path(model_path, &format!("mesh{}", index)),
+                    mesh_handle,
+                );
 
-                let material_handle = prim
+                let material_index = prim
                     .material_index
-                    .and_then(|idx| material_handles.get(idx).copied())
+                    .filter(|&idx| idx < material_handles.len())
                     .unwrap_or(default_material);
-
-                parts.push(RenderBodyPart {
-                    mesh_id: mesh_handle,
-                    material_id: material_handle,
-                    local_transform: glam::Mat4::IDENTITY,
-                });
+                mesh_parts.push((mesh_handle, material_index));
             }
         }
 
-        let render_body = RenderBody::new(parts);
-        self.scene
-            .world
-            .get_resource_mut::<RenderBodyResource>()
-            .expect("RenderBodyResource not found")
-            .write()
-            .add_render_body(render_body)
-    }
-
-    fn load_materials_from_gltf(
-        &mut self,
-        gltf_path: &OsStr,
-        vertex_shader: &OsStr,
-        fragment_shader: &OsStr,
-    ) -> Result<Vec<MaterialHandle>, Box<dyn std::error::Error>> {
-        let gl = &self.gl;
-        let path_str = gltf_path.to_str().ok_or("Invalid UTF-8 in glTF path")?;
-        let (gltf, _buffers, images) = gltf::import(path_str)?;
-
-        let shader_handle = {
-            let shader_resource = self
-                .scene
-                .world
-                .get_resource_mut::<ShaderResource>()
-                .expect("ShaderResource not found");
-            shader_resource
-                .write()
-                .get_or_load(gl, vertex_shader, fragment_shader)
-        };
-
-        let texture_map = {
-            let mut texture_resource = self
-                .scene
-                .world
-                .get_resource_mut::<TextureResource>()
-                .expect("TextureResource not found");
-            Self::load_textures_from_gltf_data(&mut texture_resource, gl, &gltf, &images)?
+        // Skinned parts need the skinning vertex shader, so each material they use gets a
+        // twin with the same inputs.
+        let skinned_shader = if model.parts.iter().any(|part| part.skin.is_some()) {
+            Some(
+                world
+                    .get_resource::<ShaderResource>()
+                    .expect("ShaderResource not found")
+                    .write()
+                    .get_or_load(
+                        gl,
+                        OsStr::new("resources/shaders/pbr_skinned.vert"),
+                        OsStr::new("resources/shaders/pbr.frag"),
+                    )?,
+            )
+        } else {
+            None
         };
-
-        let mut material_inputs: Vec<(TextureHandle, TextureHandle, f32)> =
-            Vec::with_capacity(DEFAULT_MATERIAL_CAPACITY);
-
-        let texture_resource = self
-            .scene
-            .world
-            .get_resource_mut::<TextureResource>()
-            .expect("TextureResource not found");
-        let default_normal = texture_resource
-            .write()
-            .create_solid_rgba(gl, [128, 128, 255, 255]);
-
-        for material in gltf.materials() {
-            let pbr = material.pbr_metallic_roughness();
-            let roughness = pbr.roughness_factor();
-
-            let albedo_handle = pbr
-                .base_color_texture()
-                .and_then(|info| texture_map.get(&info.texture().index()).copied())
-                .unwrap_or_else(|| {
-                    let base_color = pbr.base_color_factor();
-                    let rgba = Self::rgba_from_rgba_f32(base_color);
-                    texture_resource.write().create_solid_rgba(gl, rgba)
-                });
-
-            let normal_handle = material
-                .normal_texture()
-                .and_then(|info| texture_map.get(&info.texture().index()).copied())
-                .unwrap_or(default_normal);
-
-            material_inputs.push((albedo_handle, normal_handle, roughness));
-        }
-
-        let mut material_handles = Vec::with_capacity(material_inputs.len());
-        let material_resource = self
-            .scene
-            .world
-            .get_resource_mut::<MaterialResource>()
-            .expect("MaterialResource not found");
-
-        for (albedo_handle, normal_handle, roughness) in material_inputs {
-            let handle = Self::create_pbr_material(
-                &mut material_resource.write(),
-                shader_handle,
-                albedo_handle,
-                normal_handle,
-                roughness,
-            );
-            material_handles.push(handle);
+        let mut skinned_materials: HashMap<usize, MaterialHandle> = HashMap::new();
+
+        let mut parts = Vec::with_capacity(model.parts.len());
+        for part in model.parts {
+            let (mesh_id, material_index) = mesh_parts[part.mesh];
+            let material_id = match (part.skin, skinned_shader) {
+                (Some(_), Some(skinned_shader)) => {
+                    *skinned_materials.entry(material_index).or_insert_with(|| {
+                        let mut materials = world
+                            .get_resource::<MaterialResource>()
+                            .expect("MaterialResource not found")
+                            .write();
+                        let (albedo, normal, roughness, blend_mode) =
+                            material_inputs[material_index];
+                        let handle = Self::create_pbr_material(
+                            &mut materials,
+                            skinned_shader,
+                            albedo,
+                            normal,
+                            roughness,
+                            blend_mode,
+                        );
+                        materials.paths.insert(
+                            &sub_asset_path(
+                                model_path,
+                                &format!("skinned_material{}", material_index),
+                            ),
+                            handle,
+                        );
+                        handle
+                    })
+                }
+                _ => material_handles[material_index],
+            };
+            parts.push(RenderBodyPart {
+                mesh_id,
+                material_id,
+                local_transform: part.transform,
+                name: part.name,
+                node: part.node,
+                skin: part.skin,
+                lod: None,
+            });
         }
 
-        Ok(material_handles)
-    }
-
-    fn load_textures_from_gltf_data(
-        texture_resource: &mut TextureResource,
-        gl: &glow::Context,
-        gltf: &gltf::Document,
-        images: &[gltf::image::Data],
-    ) -> Result<HashMap<usize, TextureHandle>, Box<dyn std::error::Error>> {
-        let mut texture_map = HashMap::new();
-
-        for texture in gltf.textures() {
-            let texture_index = texture.index();
-            let image_index = texture.source().index();
-            let image = images
-                .get(image_index)
-                .ok_or("glTF image index out of bounds")?;
-            let rgba = Self::gltf_image_to_rgba(image)
-                .map_err(|message| std::io::Error::new(std::io::ErrorKind::InvalidData, message))?;
-
-            let handle = texture_resource.write().create_from_rgba_with_key(
-                gl,
-                image.width,
-                image.height,
-                &rgba,
-            );
-            texture_map.insert(texture_index, handle);
+        let render_body = RenderBody {
+            parts,
+            hierarchy: model.hierarchy,
+            animations: model.animations,
+        };
+        let mut bodies = world
+            .get_resource::<RenderBodyResource>()
+            .expect("RenderBodyResource not found")
+            .write();
+        match body_handle.and_then(|handle| bodies.get_render_body_mut(handle)) {
+            Some(slot) => {
+                *slot = render_body;
+                Ok(body_handle.unwrap())
+            }
+            None => Ok(bodies.add_render_body(render_body)),
         }
-
-        Ok(texture_map)
     }
 
-    fn gltf_image_to_rgba(image: &gltf::image::Data) -> Result<Vec<u8>, String> {
+    fn gltf_image_to_rgba(image: &gltf::image::Data) -> Result<Vec<u8>, TextureError> {
         use gltf::image::Format;
 
         let rgba = match image.format {
@@ -501,16 +430,16 @@ impl Engine {
                 .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
                 .collect(),
             Format::R8G8B8A8 => image.pixels.clone(),
-            _ => return Err(format!("Unsupported glTF image format: {:?}", image.format)),
+            format => return Err(TextureError::UnsupportedFormat(format)),
         };
 
         Ok(rgba)
     }
 
     fn mesh_primatives_from_gltf(
-        path: &OsStr,
-    ) -> Result<Vec<GltfPrimitiveMesh>, Box<dyn std::error::Error>> {
-        let (gltf, buffers, _) = gltf::import(path.to_str().unwrap())?;
+        gltf: &gltf::Document,
+        buffers: &[gltf::buffer::Data],
+    ) -> Result<Vec<GltfPrimitiveMesh>, ModelError> {
         let mut meshes = Vec::with_capacity(gltf.meshes().len());
 
         for gltf_mesh in gltf.meshes() {
@@ -524,17 +453,26 @@ impl Engine {
                 // Mandatory attributes
                 let positions: Vec<[f32; 3]> = reader
                     .read_positions()
-                    .ok_or("Mesh missing positions")?
+                    .ok_or(ModelError::MissingAttribute {
+                        mesh: gltf_mesh.index(),
+                        attribute: "positions",
+                    })?
                     .collect();
 
                 let normals: Vec<[f32; 3]> = reader
                     .read_normals()
-                    .ok_or("Mesh missing normals")?
+                    .ok_or(ModelError::MissingAttribute {
+                        mesh: gltf_mesh.index(),
+                        attribute: "normals",
+                    })?
                     .collect();
 
                 let uvs: Vec<[f32; 2]> = reader
                     .read_tex_coords(0)
-                    .ok_or("Mesh missing TEXCOORD_0")?
+                    .ok_or(ModelError::MissingAttribute {
+                        mesh: gltf_mesh.index(),
+                        attribute: "TEXCOORD_0",
+                    })?
                     .into_f32()
                     // .map(|[u, v]| [u, 1.0 - v])
                     .collect();
@@ -542,7 +480,10 @@ impl Engine {
                 // Indices (required for tangent generation)
                 let indices: Vec<u32> = reader
                     .read_indices()
-                    .ok_or("Mesh missing indices")?
+                    .ok_or(ModelError::MissingAttribute {
+                        mesh: gltf_mesh.index(),
+                        attribute: "indices",
+                    })?
                     .into_u32()
                     .collect();
 
@@ -556,16 +497,51 @@ impl Engine {
                     Mesh::compute_tangents(&positions, &normals, &uvs, &indices)
                 };
 
+                // Skinning attributes, only on meshes used by a skinned node.
+                let joints: Vec<[u16; 4]> = reader
+                    .read_joints(0)
+                    .map(|joints| joints.into_u16().collect())
+                    .unwrap_or_default();
+                let weights: Vec<[f32; 4]> = reader
+                    .read_weights(0)
+                    .map(|weights| weights.into_f32().collect())
+                    .unwrap_or_default();
+                let skinned = !joints.is_empty() && !weights.is_empty();
+
                 // Sanity check
-                assert_eq!(positions.len(), normals.len());
-                assert_eq!(positions.len(), uvs.len());
-                assert_eq!(positions.len(), tangents.len());
+                let mut counts = vec![
+                    ("normals", normals.len()),
+                    ("TEXCOORD_0", uvs.len()),
+                    ("tangents", tangents.len()),
+                ];
+                if skinned {
+                    counts.push(("JOINTS_0", joints.len()));
+                    counts.push(("WEIGHTS_0", weights.len()));
+                }
+                for (attribute, found) in counts {
+                    if found != positions.len() {
+                        return Err(ModelError::AttributeCount {
+                            mesh: gltf_mesh.index(),
+                            attribute,
+                            found,
+                            expected: positions.len(),
+                        });
+                    }
+                }
 
                 let mut mesh = Mesh::default();
                 mesh.vertices.reserve(positions.len());
 
                 // Build vertices
                 for i in 0..positions.len() {
+                    let (joints, weights) = if skinned {
+                        // Exporters don't always normalize weights.
+                        let sum: f32 = weights[i].iter().sum();
+                        let scale = if sum > 0.0 { 1.0 / sum } else { 0.0 };
+                        (joints[i].map(f32::from), weights[i].map(|w| w * scale))
+                    } else {
+                        ([0.0; 4], [0.0; 4])
+                    };
                     mesh.vertices.push(Vertex {
                         position: positions[i],
                         normal: normals[i],
@@ -578,10 +554,13 @@ impl Engine {
                             tangents[i][2],
                             tangents[i][3],
                         ],
+                        joints,
+                        weights,
                     });
                 }
 
                 mesh.indices.extend(indices);
+                mesh.assign_barycentrics();
                 mesh.aabb = Aabb::from_vertices(&mesh.vertices);
                 mesh.compute_bounding_sphere();
                 mesh.build_bvh(8);
@@ -597,6 +576,569 @@ impl Engine {
     }
 }
 
+/// A model file read into memory on any thread: meshes are built (tangents, bounds and BVH)
+/// and images decoded, but nothing is in an asset storage or on the GPU yet.
+pub(crate) struct ImportedModel {
+    meshes: Vec<GltfPrimitiveMesh>,
+    /// Placements of `meshes`; a mesh used by several nodes appears in several parts.
+    parts: Vec<ImportedPart>,
+    /// Never empty; formats without materials get a default one.
+    materials: Vec<ImportedMaterial>,
+    /// Images embedded in the file by texture index, `None` where decoding failed.
+    textures: Vec<Option<DecodedImage>>,
+    hierarchy: ModelHierarchy,
+    animations: Vec<AnimationClip>,
+}
+
+struct ImportedPart {
+    /// Index into `ImportedModel::meshes`.
+    mesh: usize,
+    transform: Mat4,
+    name: Option<String>,
+    /// Index into `ModelHierarchy::nodes`.
+    node: Option<usize>,
+    /// Index into `ModelHierarchy::skins`.
+    skin: Option<usize>,
+}
+
+struct ImportedMaterial {
+    albedo: TextureSource,
+    normal: TextureSource,
+    roughness: f32,
+    blend_mode: BlendMode,
+}
+
+enum TextureSource {
+    Solid([u8; 4]),
+    /// Index into `ImportedModel::textures`.
+    Embedded(usize),
+    /// An image file next to the model. Decoded during import, but if the path is already
+    /// loaded the existing texture is used instead.
+    File {
+        path: String,
+        image: Result<DecodedImage, AssetError>,
+    },
+}
+
+struct DecodedImage {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+const FLAT_NORMAL: [u8; 4] = [128, 128, 255, 255];
+
+impl ImportedModel {
+    /// Reads and processes the model at `model_path`, picking the format from the extension.
+    /// Does no GL work and touches no storages, so it can run on a worker thread.
+    pub(crate) fn read(model_path: &str) -> Result<Self, AssetError> {
+        let extension = std::path::Path::new(model_path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        match extension.as_str() {
+            "gltf" | "glb" => Self::read_gltf(model_path),
+            "fbx" => Self::read_fbx(model_path),
+            "obj" => Self::read_obj(model_path),
+            _ => Err(AssetError::UnsupportedFormat(extension)),
+        }
+    }
+
+    fn read_obj(obj_path: &str) -> Result<Self, AssetError> {
+        let registry_path = obj_path;
+        let obj_path = std::path::Path::new(obj_path);
+        let base_dir = obj_path
+            .parent()
+            .unwrap_or_else(|| std::path::Path::new("."));
+
+        let (models, obj_materials) = tobj::load_obj(
+            obj_path,
+            &tobj::LoadOptions {
+                single_index: true,
+                ..Default::default()
+            },
+        )
+        .map_err(|e| model_error(registry_path, e))?;
+        if let Err(e) = &obj_materials {
+            warn!(
+                "{}: no materials loaded ({}), using a default",
+                registry_path, e
+            );
+        }
+
+        let file_texture = |tex: &String| {
+            let path = base_dir.join(tex).to_string_lossy().into_owned();
+            let image = decode_image_file(&path);
+            TextureSource::File { path, image }
+        };
+
+        let mut materials = Vec::new();
+        for material in obj_materials.iter().flatten() {
+            let albedo = match material.diffuse_texture.as_ref() {
+                Some(tex) if !tex.is_empty() => file_texture(tex),
+                _ => {
+                    let diffuse = material.diffuse.unwrap_or([1.0, 1.0, 1.0]);
+                    TextureSource::Solid(Engine::rgba_from_rgb(diffuse))
+                }
+            };
+
+            let normal = match material.normal_texture.as_ref() {
+                Some(tex) if !tex.is_empty() => file_texture(tex),
+                _ => TextureSource::Solid(FLAT_NORMAL),
+            };
+
+            let roughness = match material.shininess {
+                Some(shininess) if shininess > 0.0 => {
+                    (1.0_f32 - (shininess / 1000.0)).clamp(0.0, 1.0)
+                }
+                _ => 1.0,
+            };
+
+            materials.push(ImportedMaterial {
+                albedo,
+                normal,
+                roughness,
+                blend_mode: BlendMode::Opaque,
+            });
+        }
+
+        if materials.is_empty() {
+            materials.push(ImportedMaterial {
+                albedo: TextureSource::Solid([255, 255, 255, 255]),
+                normal: TextureSource::Solid(FLAT_NORMAL),
+                roughness: 1.0,
+                blend_mode: BlendMode::Opaque,
+            });
+        }
+
+        let mut meshes = Vec::with_capacity(models.len());
+        for model in models.iter() {
+            let mesh = &model.mesh;
+            let vertex_count = mesh.positions.len() / 3;
+
+            let mut positions: Vec<[f32; 3]> = Vec::with_capacity(vertex_count);
+            for i in 0..vertex_count {
+                positions.push([
+                    mesh.positions[i * 3],
+                    mesh.positions[i * 3 + 1],
+                    mesh.positions[i * 3 + 2],
+                ]);
+            }
+
+            let has_normals = !mesh.normals.is_empty();
+            let mut normals: Vec<[f32; 3]> = Vec::with_capacity(vertex_count);
+            if has_normals {
+                for i in 0..vertex_count {
+                    normals.push([
+                        mesh.normals[i * 3],
+                        mesh.normals[i * 3 + 1],
+                        mesh.normals[i * 3 + 2],
+                    ]);
+                }
+            } else {
+                normals.resize(vertex_count, [0.0, 0.0, 1.0]);
+            }
+
+            let has_uvs = !mesh.texcoords.is_empty();
+            let mut uvs: Vec<[f32; 2]> = Vec::with_capacity(vertex_count);
+            if has_uvs {
+                for i in 0..vertex_count {
+                    let u = mesh.texcoords[i * 2];
+                    let v = mesh.texcoords[i * 2 + 1];
+                    uvs.push([u, 1.0 - v]);
+                }
+            } else {
+                uvs.resize(vertex_count, [0.0, 0.0]);
+            }
+
+            let indices: Vec<u32> = mesh.indices.to_vec();
+
+            let tangents = if has_uvs && has_normals {
+                Mesh::compute_tangents(&positions, &normals, &uvs, &indices)
+            } else {
+                vec![[1.0, 0.0, 0.0, 1.0]; vertex_count]
+            };
+
+            let mut built_mesh = Mesh::default();
+            for i in 0..vertex_count {
+                built_mesh.vertices.push(Vertex {
+                    position: positions[i],
+                    normal: normals[i],
+                    barycentric: [0.0, 0.0, 0.0],
+                    uv_albedo: uvs[i],
+                    uv_normal: uvs[i],
+                    tangent: tangents[i],
+                    joints: [0.0; 4],
+                    weights: [0.0; 4],
+                });
+            }
+            built_mesh.indices.extend(indices.iter().copied());
+            built_mesh.assign_barycentrics();
+
+            built_mesh.aabb = Aabb::from_vertices(&built_mesh.vertices);
+            built_mesh.compute_bounding_sphere();
+            built_mesh.build_bvh(8);
+
+            meshes.push(GltfPrimitiveMesh {
+                mesh: built_mesh,
+                material_index: mesh.material_id,
+            });
+        }
+
+        // OBJ has no hierarchy; each object becomes one part under its own name.
+        let parts = models
+            .iter()
+            .enumerate()
+            .map(|(mesh, model)| ImportedPart {
+                mesh,
+                transform: Mat4::IDENTITY,
+                name: (!model.name.is_empty()).then(|| model.name.clone()),
+                node: None,
+                skin: None,
+            })
+            .collect();
+
+        Ok(Self {
+            meshes,
+            parts,
+            materials,
+            textures: Vec::new(),
+            hierarchy: ModelHierarchy::default(),
+            animations: Vec::new(),
+        })
+    }
+
+    fn read_fbx(fbx_path: &str) -> Result<Self, AssetError> {
+        Err(model_error(fbx_path, ModelError::FbxNotImplemented))
+    }
+
+    fn read_gltf(gltf_path: &str) -> Result<Self, AssetError> {
+        let (gltf, buffers, images) =
+            gltf::import(gltf_path).map_err(|e| model_error(gltf_path, e))?;
+
+        let meshes = Engine::mesh_primatives_from_gltf(&gltf, &buffers)
+            .map_err(|e| model_error(gltf_path, e))?;
+        let (hierarchy, node_map) = gltf_hierarchy(gltf_path, &gltf, &buffers);
+        let parts = gltf_parts(&gltf, &node_map);
+        let animations = gltf_animations(gltf_path, &gltf, &buffers, &node_map);
+
+        // `gltf::import` has already resolved every image, so only unsupported pixel
+        // formats can fail here.
+        let textures = gltf
+            .textures()
+            .map(|texture| {
+                let image = &images[texture.source().index()];
+                match Engine::gltf_image_to_rgba(image) {
+                    Ok(rgba) => Some(DecodedImage {
+                        width: image.width,
+                        height: image.height,
+                        rgba,
+                    }),
+                    Err(e) => {
+                        warn!("{} texture {}: {}", gltf_path, texture.index(), e);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        let mut materials: Vec<ImportedMaterial> = gltf
+            .materials()
+            .map(|material| {
+                let pbr = material.pbr_metallic_roughness();
+                let albedo = match pbr.base_color_texture() {
+                    Some(info) => TextureSource::Embedded(info.texture().index()),
+                    None => {
+                        TextureSource::Solid(Engine::rgba_from_rgba_f32(pbr.base_color_factor()))
+                    }
+                };
+                let normal = match material.normal_texture() {
+                    Some(info) => TextureSource::Embedded(info.texture().index()),
+                    None => TextureSource::Solid(FLAT_NORMAL),
+                };
+                // glTF's default cutoff is 0.5.
+                let blend_mode = match material.alpha_mode() {
+                    AlphaMode::Opaque => BlendMode::Opaque,
+                    AlphaMode::Mask => BlendMode::Mask {
+                        cutoff: material.alpha_cutoff().unwrap_or(0.5),
+                    },
+                    AlphaMode::Blend => BlendMode::Blend,
+                };
+                ImportedMaterial {
+                    albedo,
+                    normal,
+                    roughness: pbr.roughness_factor(),
+                    blend_mode,
+                }
+            })
+            .collect();
+
+        // glTF's default material: white, fully rough.
+        if materials.is_empty() {
+            materials.push(ImportedMaterial {
+                albedo: TextureSource::Solid([255, 255, 255, 255]),
+                normal: TextureSource::Solid(FLAT_NORMAL),
+                roughness: 1.0,
+                blend_mode: BlendMode::Opaque,
+            });
+        }
+
+        Ok(Self {
+            meshes,
+            parts,
+            materials,
+            textures,
+            hierarchy,
+            animations,
+        })
+    }
+}
+
+/// Places every primitive by walking the default scene (or the first scene) and
+/// accumulating node transforms from the root down. Files without a scene get every
+/// primitive once at the origin. `node_map` maps glTF node indices to hierarchy ones.
+fn gltf_parts(gltf: &gltf::Document, node_map: &[usize]) -> Vec<ImportedPart> {
+    // `mesh_primatives_from_gltf` flattens primitives mesh by mesh, so mesh `i`'s primitives
+    // start at the total primitive count of the meshes before it.
+    let mut first_primitive = Vec::with_capacity(gltf.meshes().len());
+    let mut primitive_count = 0;
+    for mesh in gltf.meshes() {
+        first_primitive.push(primitive_count);
+        primitive_count += mesh.primitives().len();
+    }
+
+    let Some(scene) = gltf.default_scene().or_else(|| gltf.scenes().next()) else {
+        return (0..primitive_count)
+            .map(|mesh| ImportedPart {
+                mesh,
+                transform: Mat4::IDENTITY,
+                name: None,
+                node: None,
+                skin: None,
+            })
+            .collect();
+    };
+
+    let mut parts = Vec::new();
+    for node in scene.nodes() {
+        add_gltf_node_parts(
+            &node,
+            Mat4::IDENTITY,
+            &first_primitive,
+            node_map,
+            &mut parts,
+        );
+    }
+    parts
+}
+
+fn add_gltf_node_parts(
+    node: &gltf::Node,
+    parent: Mat4,
+    first_primitive: &[usize],
+    node_map: &[usize],
+    parts: &mut Vec<ImportedPart>,
+) {
+    let transform = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+    if let Some(mesh) = node.mesh() {
+        let first = first_primitive[mesh.index()];
+        let skin = node.skin().map(|skin| skin.index());
+        for primitive in 0..mesh.primitives().len() {
+            parts.push(ImportedPart {
+                mesh: first + primitive,
+                // glTF ignores the transform of a skinned mesh's node; its joints place it.
+                transform: if skin.is_some() {
+                    Mat4::IDENTITY
+                } else {
+                    transform
+                },
+                name: node.name().map(str::to_string),
+                node: Some(node_map[node.index()]),
+                skin,
+            });
+        }
+    }
+    for child in node.children() {
+        add_gltf_node_parts(&child, transform, first_primitive, node_map, parts);
+    }
+}
+
+/// Every node in the file with its skins, parents before children: the scene's trees
+/// first, then any trees outside it. Also returns each glTF node's index in the hierarchy.
+fn gltf_hierarchy(
+    gltf_path: &str,
+    gltf: &gltf::Document,
+    buffers: &[gltf::buffer::Data],
+) -> (ModelHierarchy, Vec<usize>) {
+    let mut has_parent = vec![false; gltf.nodes().len()];
+    for node in gltf.nodes() {
+        for child in node.children() {
+            has_parent[child.index()] = true;
+        }
+    }
+    let scene_roots = gltf
+        .default_scene()
+        .or_else(|| gltf.scenes().next())
+        .into_iter()
+        .flat_map(|scene| scene.nodes());
+    let other_roots = gltf.nodes().filter(|node| !has_parent[node.index()]);
+
+    let mut nodes = Vec::with_capacity(gltf.nodes().len());
+    let mut node_map = vec![None; gltf.nodes().len()];
+    for root in scene_roots.chain(other_roots) {
+        add_gltf_model_nodes(&root, None, &mut nodes, &mut node_map);
+    }
+    // Nodes in a cycle have no root; glTF forbids them, but don't index out of bounds.
+    let node_map: Vec<usize> = node_map.into_iter().map(|i| i.unwrap_or(0)).collect();
+
+    let skins = gltf
+        .skins()
+        .map(|skin| {
+            let joints: Vec<usize> = skin.joints().map(|joint| node_map[joint.index()]).collect();
+            if joints.len() > MAX_JOINTS {
+                warn!(
+                    "{} skin {} has {} joints; only the first {} deform the mesh",
+                    gltf_path,
+                    skin.index(),
+                    joints.len(),
+                    MAX_JOINTS
+                );
+            }
+            let reader = skin.reader(|buffer| Some(&buffers[buffer.index()]));
+            let mut inverse_bind_matrices: Vec<Mat4> = reader
+                .read_inverse_bind_matrices()
+                .map(|matrices| matrices.map(|m| Mat4::from_cols_array_2d(&m)).collect())
+                .unwrap_or_default();
+            inverse_bind_matrices.resize(joints.len(), Mat4::IDENTITY);
+            Skin {
+                joints,
+                inverse_bind_matrices,
+            }
+        })
+        .collect();
+
+    (ModelHierarchy::new(nodes, skins), node_map)
+}
+
+fn add_gltf_model_nodes(
+    node: &gltf::Node,
+    parent: Option<usize>,
+    nodes: &mut Vec<ModelNode>,
+    node_map: &mut [Option<usize>],
+) {
+    if node_map[node.index()].is_some() {
+        return;
+    }
+    let index = nodes.len();
+    node_map[node.index()] = Some(index);
+    let (translation, rotation, scale) = node.transform().decomposed();
+    nodes.push(ModelNode {
+        name: node.name().map(str::to_string),
+        parent,
+        rest: TransformComponent {
+            position: Vec3::from(translation),
+            rotation: Quat::from_array(rotation),
+            scale: Vec3::from(scale),
+        },
+    });
+    for child in node.children() {
+        add_gltf_model_nodes(&child, Some(index), nodes, node_map);
+    }
+}
+
+/// Reads every animation's channels. Morph target weights aren't supported and channels
+/// with malformed samplers are skipped, with a warning.
+fn gltf_animations(
+    gltf_path: &str,
+    gltf: &gltf::Document,
+    buffers: &[gltf::buffer::Data],
+    node_map: &[usize],
+) -> Vec<AnimationClip> {
+    use gltf::animation::util::ReadOutputs;
+
+    gltf.animations()
+        .map(|animation| {
+            let channels = animation
+                .channels()
+                .filter_map(|channel| {
+                    let reader = channel.reader(|buffer| Some(&buffers[buffer.index()]));
+                    let times: Vec<f32> = reader.read_inputs()?.collect();
+                    let (keyframes, count) = match reader.read_outputs()? {
+                        ReadOutputs::Translations(values) => {
+                            let values: Vec<Vec3> = values.map(Vec3::from).collect();
+                            let count = values.len();
+                            (Keyframes::Translation(values), count)
+                        }
+                        ReadOutputs::Rotations(values) => {
+                            let values: Vec<Quat> =
+                                values.into_f32().map(Quat::from_array).collect();
+                            let count = values.len();
+                            (Keyframes::Rotation(values), count)
+                        }
+                        ReadOutputs::Scales(values) => {
+                            let values: Vec<Vec3> = values.map(Vec3::from).collect();
+                            let count = values.len();
+                            (Keyframes::Scale(values), count)
+                        }
+                        ReadOutputs::MorphTargetWeights(_) => {
+                            warn!(
+                                "{} animation {}: morph targets aren't supported",
+                                gltf_path,
+                                animation.index()
+                            );
+                            return None;
+                        }
+                    };
+
+                    let interpolation = match channel.sampler().interpolation() {
+                        gltf::animation::Interpolation::Step => Interpolation::Step,
+                        gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                        gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+                    };
+                    let values_per_key = match interpolation {
+                        Interpolation::CubicSpline => 3,
+                        _ => 1,
+                    };
+                    if times.is_empty() || count != times.len() * values_per_key {
+                        warn!(
+                            "{} animation {}: channel {} has {} values for {} keyframes",
+                            gltf_path,
+                            animation.index(),
+                            channel.index(),
+                            count,
+                            times.len()
+                        );
+                        return None;
+                    }
+
+                    Some(AnimationChannel {
+                        node: node_map[channel.target().node().index()],
+                        interpolation,
+                        times,
+                        keyframes,
+                    })
+                })
+                .collect();
+            AnimationClip::new(animation.name().map(str::to_string), channels)
+        })
+        .collect()
+}
+
+fn decode_image_file(path: &str) -> Result<DecodedImage, AssetError> {
+    let img = image::open(path).map_err(|source| AssetError::Texture {
+        path: path.to_string(),
+        source: TextureError::Image(source),
+    })?;
+    let rgba = img.to_rgba8();
+    Ok(DecodedImage {
+        width: rgba.width(),
+        height: rgba.height(),
+        rgba: rgba.into_raw(),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
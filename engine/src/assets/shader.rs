@@ -1,8 +1,9 @@
 use std::{ffi::OsStr, fs};
 
-use crate::assets::handles::TextureHandle;
+use crate::assets::{asset_error::ShaderError, handles::TextureHandle};
 use glow::HasContext;
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum UniformValue {
     Float(f32),
     Vec3(glam::Vec3),
@@ -37,67 +38,77 @@ pub struct ShaderAttrib {
 }
 
 pub struct Shader {
-    pub program: glow::Program,
+    /// `None` for shaders created without a GL context (headless engines).
+    pub program: Option<glow::Program>,
     pub uniforms: Vec<(String, glow::UniformLocation)>,
     pub attributes: Vec<ShaderAttrib>,
 }
 
 impl Shader {
-    pub fn new(gl: &glow::Context, vertex_src: &OsStr, fragment_src: &OsStr) -> Self {
-        unsafe {
-            let vertex_shader = gl.create_shader(glow::VERTEX_SHADER).unwrap();
-            let vertex_shader_source = fs::read_to_string(vertex_src.to_str().unwrap())
-                .unwrap_or_else(|_| {
-                    panic!(
-                        "Failed to read vertex shader file {}",
-                        vertex_src.to_str().unwrap()
-                    )
-                });
-
-            gl.shader_source(vertex_shader, &vertex_shader_source);
-            gl.compile_shader(vertex_shader);
-            if !gl.get_shader_compile_status(vertex_shader) {
-                panic!(
-                    "Vertex shader compilation failed: {}",
-                    gl.get_shader_info_log(vertex_shader)
-                );
-            }
+    /// Compiles and links a program from two GLSL files.
+    /// On failure nothing is left allocated and the error carries the driver's info log.
+    pub fn new(
+        gl: &glow::Context,
+        vertex_src: &OsStr,
+        fragment_src: &OsStr,
+    ) -> Result<Self, ShaderError> {
+        let read = |path: &OsStr| {
+            fs::read_to_string(path).map_err(|source| ShaderError::Read {
+                path: path.to_string_lossy().into_owned(),
+                source,
+            })
+        };
+        let vertex_shader_source = read(vertex_src)?;
+        let fragment_shader_source = read(fragment_src)?;
 
-            let fragment_shader = gl.create_shader(glow::FRAGMENT_SHADER).unwrap();
-            let fragment_shader_source = fs::read_to_string(fragment_src.to_str().unwrap())
-                .unwrap_or_else(|_| {
-                    panic!(
-                        "Failed to read fragment shader file {}",
-                        fragment_src.to_str().unwrap()
-                    )
-                });
-            gl.shader_source(fragment_shader, &fragment_shader_source);
-            gl.compile_shader(fragment_shader);
-            if !gl.get_shader_compile_status(fragment_shader) {
-                panic!(
-                    "Fragment shader compilation failed: {}",
-                    gl.get_shader_info_log(fragment_shader)
-                );
-            }
+        unsafe {
+            let vertex_shader = Self::compile_stage(gl, glow::VERTEX_SHADER, &vertex_shader_source)
+                .map_err(|log| ShaderError::Compile {
+                    stage: "vertex",
+                    log,
+                })?;
+            let fragment_shader =
+                match Self::compile_stage(gl, glow::FRAGMENT_SHADER, &fragment_shader_source) {
+                    Ok(shader) => shader,
+                    Err(log) => {
+                        gl.delete_shader(vertex_shader);
+                        return Err(ShaderError::Compile {
+                            stage: "fragment",
+                            log,
+                        });
+                    }
+                };
 
             let program = gl.create_program().unwrap();
             gl.attach_shader(program, vertex_shader);
             gl.attach_shader(program, fragment_shader);
             gl.link_program(program);
+            gl.delete_shader(vertex_shader);
+            gl.delete_shader(fragment_shader);
             if !gl.get_program_link_status(program) {
-                panic!(
-                    "Program linking failed: {}",
-                    gl.get_program_info_log(program)
-                );
+                let log = gl.get_program_info_log(program);
+                gl.delete_program(program);
+                return Err(ShaderError::Link(log));
             }
 
             let count = gl.get_program_parameter_i32(program, glow::ACTIVE_UNIFORMS);
             let mut uniforms = Vec::with_capacity(count as usize);
 
             for i in 0..count {
-                if let Some(info) = gl.get_active_uniform(program, i as u32)
-                    && let Some(loc) = gl.get_uniform_location(program, &info.name)
+                let Some(info) = gl.get_active_uniform(program, i as u32) else {
+                    continue;
+                };
+                // Arrays of plain types are reported once, as `name[0]`; register every element.
+                if info.size > 1
+                    && let Some(base) = info.name.strip_suffix("[0]")
                 {
+                    for element in 0..info.size {
+                        let name = format!("{}[{}]", base, element);
+                        if let Some(loc) = gl.get_uniform_location(program, &name) {
+                            uniforms.push((name, loc));
+                        }
+                    }
+                } else if let Some(loc) = gl.get_uniform_location(program, &info.name) {
                     uniforms.push((info.name, loc));
                 }
             }
@@ -158,16 +169,39 @@ impl Shader {
                 }
             }
 
-            let shader = Shader {
-                program,
+            Ok(Shader {
+                program: Some(program),
                 uniforms,
                 attributes,
-            };
+            })
+        }
+    }
 
-            gl.delete_shader(vertex_shader);
-            gl.delete_shader(fragment_shader);
+    unsafe fn compile_stage(
+        gl: &glow::Context,
+        stage: u32,
+        source: &str,
+    ) -> Result<glow::Shader, String> {
+        unsafe {
+            let shader = gl.create_shader(stage).unwrap();
+            gl.shader_source(shader, source);
+            gl.compile_shader(shader);
+            if !gl.get_shader_compile_status(shader) {
+                let log = gl.get_shader_info_log(shader);
+                gl.delete_shader(shader);
+                return Err(log);
+            }
+            Ok(shader)
+        }
+    }
 
-            shader
+    /// A shader with no GPU program, uniforms or attributes.
+    /// Materials can still reference it when the engine has no GL context.
+    pub fn unloaded() -> Self {
+        Self {
+            program: None,
+            uniforms: Vec::new(),
+            attributes: Vec::new(),
         }
     }
 
@@ -4,10 +4,10 @@ use std::sync::Arc;
 use std::{collections::HashMap, sync::RwLock};
 
 use bevy_ecs::resource::Resource;
-use glow::Context;
+use glow::{Context, HasContext};
 use slotmap::SlotMap;
 
-use crate::assets::{handles::ShaderHandle, shader::Shader};
+use crate::assets::{asset_error::AssetError, handles::ShaderHandle, shader::Shader};
 
 #[derive(Default)]
 pub struct ShaderStorage {
@@ -45,31 +45,94 @@ struct ShaderKey {
     fragment_path: String,
 }
 
+impl ShaderKey {
+    fn display(&self) -> String {
+        format!("{} + {}", self.vertex_path, self.fragment_path)
+    }
+}
+
 impl ShaderStorage {
+    /// Compiles the program for a vertex/fragment pair, or returns the cached handle.
+    /// Without a GL context the shader is registered without a program.
+    /// A program that fails to compile is not cached, so the next call tries again.
     pub fn get_or_load(
         &mut self,
-        gl: &Context,
+        gl: Option<&Context>,
         vertex_src: &OsStr,
         fragment_src: &OsStr,
-    ) -> ShaderHandle {
+    ) -> Result<ShaderHandle, AssetError> {
         let key = ShaderKey {
             vertex_path: vertex_src.to_string_lossy().into_owned(),
             fragment_path: fragment_src.to_string_lossy().into_owned(),
         };
 
         if let Some(handle) = self.shader_cache.get(&key) {
-            return *handle;
+            return Ok(*handle);
         }
 
-        let shader = Shader::new(gl, vertex_src, fragment_src);
+        let shader = match gl {
+            Some(gl) => {
+                Shader::new(gl, vertex_src, fragment_src).map_err(|source| AssetError::Shader {
+                    path: key.display(),
+                    source,
+                })?
+            }
+            None => Shader::unloaded(),
+        };
 
-        self.add_shader(shader, key)
+        Ok(self.add_shader(shader, key))
     }
 
     pub fn get_shader(&self, shader_id: ShaderHandle) -> Option<&Shader> {
         self.shaders.get(shader_id)
     }
 
+    /// Every file-backed shader as `(handle, vertex path, fragment path)`.
+    pub fn sources(&self) -> impl Iterator<Item = (ShaderHandle, &str, &str)> {
+        self.shader_cache.iter().map(|(key, handle)| {
+            (
+                *handle,
+                key.vertex_path.as_str(),
+                key.fragment_path.as_str(),
+            )
+        })
+    }
+
+    /// Recompiles `handle` from its source files, keeping the handle.
+    /// If compilation or linking fails the old program stays in use and the error is returned.
+    pub fn reload(&mut self, gl: &Context, handle: ShaderHandle) -> Result<(), AssetError> {
+        let Some(key) = self
+            .shader_cache
+            .iter()
+            .find_map(|(key, cached)| (*cached == handle).then_some(key))
+        else {
+            return Err(AssetError::NoSourceFile);
+        };
+
+        let shader = Shader::new(
+            gl,
+            OsStr::new(&key.vertex_path),
+            OsStr::new(&key.fragment_path),
+        )
+        .map_err(|source| AssetError::Shader {
+            path: key.display(),
+            source,
+        })?;
+        let Some(slot) = self.shaders.get_mut(handle) else {
+            return Err(AssetError::NoSourceFile);
+        };
+        let old = std::mem::replace(slot, shader);
+        if let Some(program) = old.program {
+            unsafe { gl.delete_program(program) };
+        }
+        Ok(())
+    }
+
+    /// Adds a shader that isn't backed by files, e.g. one built by hand in a test.
+    pub(crate) fn insert_shader(&mut self, shader: Shader) -> ShaderHandle {
+        self.shaders.insert(shader)
+    }
+
     fn add_shader(&mut self, shader: Shader, key: ShaderKey) -> ShaderHandle {
         let id = self.shaders.insert(shader);
         self.shader_cache.insert(
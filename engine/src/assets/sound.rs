@@ -1,6 +1,12 @@
 use std::{path::Path, sync::Arc};
 
-use crate::{Engine, SoundHandle, assets::sound_resource::SoundResource};
+use crate::{
+    Engine, SoundHandle,
+    assets::{
+        asset_error::{AssetError, SoundError},
+        sound_resource::SoundResource,
+    },
+};
 
 pub struct Sound {
     pub sample_rate: u32,
@@ -17,34 +23,21 @@ impl Sound {
         }
     }
 
-    pub fn from_wav(path: &str, sample_rate: u32) -> Self {
-        let mut reader = hound::WavReader::open(path).unwrap();
+    /// Decodes a 16-bit mono or stereo WAV file and resamples it to `sample_rate`.
+    pub fn from_wav(path: &str, sample_rate: u32) -> Result<Self, SoundError> {
+        let mut reader = hound::WavReader::open(path)?;
         let spec = reader.spec();
-        if spec.channels == 1 {
-            let samples = reader
-                .samples::<i16>()
-                .map(|f| f.unwrap())
-                .collect::<Vec<i16>>();
-            let data = Self::resample_mono(&samples, spec.sample_rate, sample_rate);
-            Self {
-                sample_rate,
-                channels: spec.channels,
-                data: Arc::from(data),
-            }
-        } else if spec.channels == 2 {
-            let samples = reader
-                .samples::<i16>()
-                .map(|f| f.unwrap())
-                .collect::<Vec<i16>>();
-            let data = Self::resample_stereo(&samples, spec.sample_rate, sample_rate);
-            Self {
-                sample_rate,
-                channels: spec.channels,
-                data: Arc::from(data),
-            }
-        } else {
-            panic!("Unsupported number of channels: {}", spec.channels);
-        }
+        let samples = reader.samples::<i16>().collect::<Result<Vec<i16>, _>>()?;
+        let data = match spec.channels {
+            1 => Self::resample_mono(&samples, spec.sample_rate, sample_rate),
+            2 => Self::resample_stereo(&samples, spec.sample_rate, sample_rate),
+            channels => return Err(SoundError::UnsupportedChannels(channels)),
+        };
+        Ok(Self {
+            sample_rate,
+            channels: spec.channels,
+            data: Arc::from(data),
+        })
     }
 
     fn resample_mono(samples: &[i16], src_rate: u32, dst_rate: u32) -> Vec<f32> {
@@ -100,9 +93,25 @@ impl Sound {
 }
 
 impl Engine {
-    pub fn load_wav(&mut self, path: &str) -> Result<SoundHandle, String> {
+    /// Loads a WAV file, or returns the existing handle if `path` is already loaded.
+    pub fn load_wav(&mut self, path: &str) -> Result<SoundHandle, AssetError> {
+        if let Some(handle) = self
+            .scene
+            .world
+            .get_resource::<SoundResource>()
+            .expect("SoundResource resource not found")
+            .read()
+            .paths
+            .handle(path)
+        {
+            return Ok(handle);
+        }
+
         let sample_rate = self.audio_mixer.sample_rate;
-        let sound = Sound::from_wav(path, sample_rate);
+        let sound = Sound::from_wav(path, sample_rate).map_err(|source| AssetError::Sound {
+            path: path.to_string(),
+            source,
+        })?;
         let binding = self
             .scene
             .world
@@ -115,6 +124,7 @@ impl Engine {
             .unwrap_or("unknown")
             .to_string();
         let handle = sound_resource.add_sound(sound, name);
+        sound_resource.paths.insert(path, handle);
         Ok(handle)
     }
 }
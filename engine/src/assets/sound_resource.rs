@@ -6,12 +6,13 @@ use std::{
 use bevy_ecs::prelude::*;
 use slotmap::SlotMap;
 
-use crate::assets::{handles::SoundHandle, sound::Sound};
+use crate::assets::{asset_registry::AssetRegistry, handles::SoundHandle, sound::Sound};
 
 #[derive(Default)]
 pub struct SoundStorage {
     pub sounds: SlotMap<SoundHandle, Sound>,
     pub name_map: HashMap<String, SoundHandle>,
+    pub paths: AssetRegistry<SoundHandle>,
 }
 
 #[derive(Resource, Default, Clone)]
@@ -55,6 +56,7 @@ impl SoundStorage {
 
     #[allow(dead_code)]
     pub fn remove_sound(&mut self, sound_id: SoundHandle) {
+        self.paths.remove(sound_id);
         if self.sounds.remove(sound_id).is_some() {
             println!("Removed sound with ID: {:?}", sound_id);
         } else {
@@ -1,16 +1,28 @@
 use bevy_ecs::resource::Resource;
-use glow::Context;
+use glow::{Context, HasContext};
 use image::GenericImageView;
 use slotmap::SlotMap;
 use std::ffi::OsStr;
 use std::sync::{Arc, RwLock};
 
-use crate::assets::{handles::TextureHandle, texture::Texture};
-use crate::render::renderer;
+use crate::Engine;
+use crate::assets::{
+    asset_error::{AssetError, TextureError},
+    asset_registry::AssetRegistry,
+    handles::TextureHandle,
+    texture::Texture,
+};
+use crate::render::renderer_backends::GlowBackend;
+
+const MISSING_TEXTURE_SIZE: u32 = 64;
+const MISSING_TEXTURE_CHECK: u32 = 8;
 
 #[derive(Default)]
 pub struct TextureStorage {
     pub textures: SlotMap<TextureHandle, Texture>,
+    pub paths: AssetRegistry<TextureHandle>,
+    /// Created the first time `missing_texture` is called.
+    missing: Option<TextureHandle>,
 }
 
 #[derive(Resource, Default, Clone)]
@@ -42,33 +54,120 @@ impl TextureStorage {
         self.textures.insert(texture)
     }
 
-    pub fn load_from_file(&mut self, gl: &Context, path: &OsStr) -> TextureHandle {
-        // Load image with the `image` crate
-        let img = image::open(path)
-            .unwrap_or_else(|_| panic!("Failed to open texture image: {:?}", path));
+    /// Loads an image file as a texture, or returns the existing handle if `path` is already loaded.
+    /// The texture is only uploaded when a GL context is given; headless engines pass `None`.
+    pub fn load_from_file(
+        &mut self,
+        gl: Option<&Context>,
+        path: &OsStr,
+    ) -> Result<TextureHandle, AssetError> {
+        let path_str = path.to_string_lossy();
+        if let Some(handle) = self.paths.handle(&path_str) {
+            return Ok(handle);
+        }
+
+        let tex = Self::texture_from_file(gl, &path_str)?;
+        let handle = self.add_texture(tex);
+        self.paths.insert(&path_str, handle);
+        Ok(handle)
+    }
+
+    /// Like `load_from_file`, but logs the error and returns `missing_texture` if the file
+    /// can't be loaded.
+    pub fn load_from_file_or_missing(
+        &mut self,
+        gl: Option<&Context>,
+        path: &OsStr,
+    ) -> TextureHandle {
+        self.load_from_file(gl, path).unwrap_or_else(|e| {
+            log::error!("{}", e);
+            self.missing_texture(gl)
+        })
+    }
+
+    /// A magenta and black checkerboard that stands in for textures that failed to load.
+    pub fn missing_texture(&mut self, gl: Option<&Context>) -> TextureHandle {
+        if let Some(handle) = self.missing
+            && self.textures.contains_key(handle)
+        {
+            return handle;
+        }
+
+        let rgba: Vec<u8> = (0..MISSING_TEXTURE_SIZE * MISSING_TEXTURE_SIZE)
+            .flat_map(|i| {
+                let (x, y) = (i % MISSING_TEXTURE_SIZE, i / MISSING_TEXTURE_SIZE);
+                if (x / MISSING_TEXTURE_CHECK + y / MISSING_TEXTURE_CHECK).is_multiple_of(2) {
+                    [255, 0, 255, 255]
+                } else {
+                    [0, 0, 0, 255]
+                }
+            })
+            .collect();
+        let handle =
+            self.create_from_rgba_with_key(gl, MISSING_TEXTURE_SIZE, MISSING_TEXTURE_SIZE, &rgba);
+        self.missing = Some(handle);
+        handle
+    }
+
+    pub fn is_missing_texture(&self, handle: TextureHandle) -> bool {
+        self.missing == Some(handle)
+    }
+
+    /// Re-reads the image file `handle` was loaded from and swaps it into the same slot.
+    /// Textures embedded in a model are reloaded with the model instead.
+    pub fn reload(
+        &mut self,
+        gl: Option<&Context>,
+        handle: TextureHandle,
+    ) -> Result<(), AssetError> {
+        let Some(path) = self.paths.path(handle) else {
+            return Err(AssetError::NoSourceFile);
+        };
+        if path.contains('#') {
+            return Err(AssetError::NoSourceFile);
+        }
+
+        let tex = Self::texture_from_file(gl, path)?;
+        let Some(slot) = self.textures.get_mut(handle) else {
+            return Err(AssetError::NoSourceFile);
+        };
+        let old = std::mem::replace(slot, tex);
+        if let (Some(gl), Some(gl_tex)) = (gl, old.gl_tex) {
+            unsafe { gl.delete_texture(gl_tex) };
+        }
+        Ok(())
+    }
+
+    fn texture_from_file(gl: Option<&Context>, path: &str) -> Result<Texture, AssetError> {
+        let img = image::open(path).map_err(|source| AssetError::Texture {
+            path: path.to_string(),
+            source: TextureError::Image(source),
+        })?;
         let rgba = img.to_rgba8();
         let (width, height) = img.dimensions();
 
         let mut tex = Texture::new(width, height);
-        renderer::Renderer::upload_texture_to_gpu(&mut tex, gl, &rgba);
-        self.add_texture(tex)
+        if let Some(gl) = gl {
+            GlowBackend::upload_texture_to_gpu(&mut tex, gl, &rgba);
+        }
+        Ok(tex)
     }
 
-    pub fn create_solid_rgba(&mut self, gl: &Context, rgba: [u8; 4]) -> TextureHandle {
-        let mut tex = Texture::new(1, 1);
-        renderer::Renderer::upload_texture_to_gpu(&mut tex, gl, &rgba);
-        self.add_texture(tex)
+    pub fn create_solid_rgba(&mut self, gl: Option<&Context>, rgba: [u8; 4]) -> TextureHandle {
+        self.create_from_rgba_with_key(gl, 1, 1, &rgba)
     }
 
     pub(crate) fn create_from_rgba_with_key(
         &mut self,
-        gl: &Context,
+        gl: Option<&Context>,
         width: u32,
         height: u32,
         rgba: &[u8],
     ) -> TextureHandle {
         let mut tex = Texture::new(width, height);
-        renderer::Renderer::upload_texture_to_gpu(&mut tex, gl, rgba);
+        if let Some(gl) = gl {
+            GlowBackend::upload_texture_to_gpu(&mut tex, gl, rgba);
+        }
         self.add_texture(tex)
     }
 
@@ -76,3 +175,17 @@ impl TextureStorage {
         self.textures.get(id)
     }
 }
+
+impl Engine {
+    /// Loads an image file as a texture, or returns the existing handle if `path` is already
+    /// loaded. Headless engines keep the texture on the CPU only.
+    pub fn load_texture(&mut self, path: &str) -> Result<TextureHandle, AssetError> {
+        let gl = self.gl.as_deref();
+        self.scene
+            .world
+            .get_resource::<TextureResource>()
+            .expect("TextureResource resource not found")
+            .write()
+            .load_from_file(gl, OsStr::new(path))
+    }
+}
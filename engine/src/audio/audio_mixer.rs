@@ -106,6 +106,20 @@ impl Default for AudioMixer {
 }
 
 impl AudioMixer {
+    /// Sample rate reported by the null sink, used to resample loaded sounds.
+    const NULL_SINK_SAMPLE_RATE: cpal::SampleRate = 48_000;
+
+    /// Creates a mixer without an output stream. Commands are accepted and dropped,
+    /// so engines on machines without an audio device can still run audio systems.
+    pub fn null() -> Self {
+        let (producer, _consumer) = RingBuffer::<MixerCommand>::new(1);
+        Self {
+            stream: None,
+            sample_rate: Self::NULL_SINK_SAMPLE_RATE,
+            producer,
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn build_stream(
         &mut self,
@@ -269,6 +283,10 @@ impl AudioMixer {
         sound_resource: &SoundStorage,
     ) {
         const MIXER_FULL_ERROR_MESSAGE: &str = "Audio mixer command queue is full! Sorry.";
+        // Null sink: nothing is consuming the queue, so there is nowhere to send commands.
+        if self.stream.is_none() {
+            return;
+        }
         for command in commands.iter() {
             match command {
                 AudioCommand::SpawnSpatialEmitter {
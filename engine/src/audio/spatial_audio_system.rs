@@ -1,7 +1,7 @@
 use bevy_ecs::prelude::*;
 
 use crate::{
-    TransformComponent,
+    GlobalTransform,
     audio::audio_control::AudioControl,
     components::{
         audio_source_component::AudioSourceComponent,
@@ -17,8 +17,8 @@ impl SpatialAudioSystem {
     /// This currently only supports having a single listener
     pub fn update_listener_position(
         query: Query<
-            (Entity, &TransformComponent, &SingleAudioListenerComponent),
-            Changed<TransformComponent>,
+            (Entity, &GlobalTransform, &SingleAudioListenerComponent),
+            Changed<GlobalTransform>,
         >,
         mut audio_command_queue: ResMut<AudioControl>,
     ) {
@@ -28,19 +28,17 @@ impl SpatialAudioSystem {
             );
         }
         if let Some((_, transform, _)) = query.iter().nth(0) {
+            let transform = transform.to_transform();
             audio_command_queue.update_listener_info(transform.position, transform.rotation);
         }
     }
 
     pub fn update_moved_sources(
-        query: Query<
-            (Entity, &TransformComponent, &AudioSourceComponent),
-            Changed<TransformComponent>,
-        >,
+        query: Query<(Entity, &GlobalTransform, &AudioSourceComponent), Changed<GlobalTransform>>,
         mut audio_control: ResMut<AudioControl>,
     ) {
         for (entity, transform, _) in query.iter() {
-            audio_control.update_source_info(entity, transform.position);
+            audio_control.update_source_info(entity, transform.translation());
         }
     }
 
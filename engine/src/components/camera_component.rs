@@ -3,6 +3,7 @@
 
 use bevy_ecs::prelude::*;
 use glam::Mat4;
+use serde::{Deserialize, Serialize};
 
 use crate::components::transform_component::TransformComponent;
 
@@ -10,7 +11,7 @@ use crate::components::transform_component::TransformComponent;
 ///
 /// Note: This component intentionally does NOT store any transform data.
 /// A camera entity must also have a `TransformComponent` to provide view data.
-#[derive(Component, Debug, Clone, Copy)]
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
 #[require(TransformComponent)]
 pub struct CameraComponent {
     /// Vertical field-of-view in radians.
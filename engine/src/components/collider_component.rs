@@ -1,10 +1,11 @@
 use bevy_ecs::component::Component;
 use glam::{Mat4, Vec3};
+use serde::{Deserialize, Serialize};
 
 use crate::TransformComponent;
 use crate::assets::{handles::RenderBodyHandle, mesh::Aabb};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum CollisionLayer {
     Default,
     Player,
@@ -82,7 +83,7 @@ pub trait Collider {
     fn aabb(&self, transform: &Mat4) -> Aabb;
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum ConvexShape {
     Cuboid {
         length: f32,
@@ -109,7 +110,7 @@ pub enum ConvexShape {
     },
 }
 
-#[derive(Component, Debug, Clone, Copy)]
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
 #[require(TransformComponent)]
 pub struct ConvexCollider {
     pub shape: ConvexShape,
@@ -433,7 +434,7 @@ fn transform_aabb(local: Aabb, transform: &Mat4) -> Aabb {
     }
 }
 
-fn max_scale(transform: &Mat4) -> f32 {
+pub(crate) fn max_scale(transform: &Mat4) -> f32 {
     let x = transform.x_axis.truncate().length();
     let y = transform.y_axis.truncate().length();
     let z = transform.z_axis.truncate().length();
@@ -0,0 +1,38 @@
+// Distributed under the GNU Affero General Public License v3.0 or later.
+// See accompanying file LICENSE or https://www.gnu.org/licenses/agpl-3.0.html for details.
+
+use std::ops::Deref;
+
+use bevy_ecs::prelude::*;
+
+/// Attaches an entity to another one, so its `TransformComponent` is relative to the
+/// parent's world transform and it follows the parent around.
+///
+/// Inserting or removing `Parent` keeps the parent's `Children` up to date. It does not
+/// keep the entity where it was in the world; use `reparent` for that.
+/// Physics bodies are simulated in world space and should not have a parent.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+#[relationship(relationship_target = Children)]
+pub struct Parent(pub Entity);
+
+impl Parent {
+    pub fn get(&self) -> Entity {
+        self.0
+    }
+}
+
+/// The entities whose `Parent` is this one, in the order they were attached.
+///
+/// Maintained by the ECS; add children by inserting `Parent` on them. Despawning an
+/// entity despawns all of its descendants too.
+#[derive(Component, Debug, Default)]
+#[relationship_target(relationship = Parent, linked_spawn)]
+pub struct Children(Vec<Entity>);
+
+impl Deref for Children {
+    type Target = [Entity];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
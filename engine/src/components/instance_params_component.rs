@@ -0,0 +1,79 @@
+use bevy_ecs::prelude::*;
+use glam::Vec4;
+use serde::{Deserialize, Serialize};
+
+/// Values every instance of the entity's render body is drawn with, so entities sharing a
+/// material can still differ, e.g. in colour or while selected. They are packed into the
+/// instance buffer as laid out in `FIELDS`, and shaders read each one through the
+/// attribute `instance_<name>`.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstanceParams {
+    /// Multiplies the albedo. The PBR shader dithers the instance away as alpha drops,
+    /// e.g. for buildings fading in while under construction; blended materials multiply
+    /// their alpha by it instead.
+    pub tint: Vec4,
+    /// Colour added on top of the lit result, scaled by alpha.
+    pub highlight: Vec4,
+    /// Not used by the built-in shaders; free for custom ones.
+    pub custom: Vec4,
+}
+
+/// Where one of the `InstanceParams` sits once packed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstanceField {
+    /// The shader reads the field as `instance_<name>`.
+    pub name: &'static str,
+    /// Bytes from the start of the packed params.
+    pub offset: usize,
+    /// Number of `f32`s.
+    pub components: usize,
+}
+
+impl InstanceParams {
+    /// How `pack` lays out the fields. A new field needs an entry here and in `pack`.
+    pub const FIELDS: &[InstanceField] = &[
+        InstanceField {
+            name: "tint",
+            offset: 0,
+            components: 4,
+        },
+        InstanceField {
+            name: "highlight",
+            offset: 16,
+            components: 4,
+        },
+        InstanceField {
+            name: "custom",
+            offset: 32,
+            components: 4,
+        },
+    ];
+
+    /// Length of the packed params in `f32`s.
+    pub const PACKED_LEN: usize = 12;
+
+    /// The fields as they go into the instance buffer, in the layout of `FIELDS`.
+    pub fn pack(&self) -> [f32; Self::PACKED_LEN] {
+        let mut packed = [0.0; Self::PACKED_LEN];
+        for (field, value) in Self::FIELDS
+            .iter()
+            .zip([self.tint, self.highlight, self.custom])
+        {
+            let start = field.offset / size_of::<f32>();
+            packed[start..start + field.components]
+                .copy_from_slice(&value.to_array()[..field.components]);
+        }
+        packed
+    }
+}
+
+impl Default for InstanceParams {
+    fn default() -> Self {
+        Self {
+            tint: Vec4::ONE,
+            highlight: Vec4::ZERO,
+            custom: Vec4::ZERO,
+        }
+    }
+}
@@ -0,0 +1,82 @@
+// Distributed under the GNU Affero General Public License v3.0 or later.
+// See accompanying file LICENSE or https://www.gnu.org/licenses/agpl-3.0.html for details.
+
+use bevy_ecs::prelude::*;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::components::transform_component::TransformComponent;
+
+/// A light infinitely far away that reaches everything from one direction, like the sun or moon.
+/// It has no position, so it needs no transform.
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DirectionalLight {
+    /// World-space direction the light travels in, e.g. `-Z` for a sun straight overhead.
+    pub direction: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        Self {
+            direction: Vec3::new(0.0, 0.0, -1.0),
+            color: Vec3::ONE,
+            intensity: 1.0,
+        }
+    }
+}
+
+/// A light shining equally in every direction from the entity's position, like a bare bulb.
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+#[require(TransformComponent)]
+pub struct PointLight {
+    pub color: Vec3,
+    pub intensity: f32,
+    /// Distance at which the light has faded to nothing.
+    pub range: f32,
+    /// Exponent of the fade from full intensity at the light to zero at `range`.
+    /// 1 fades linearly, 2 is close to inverse-square within the range.
+    pub falloff: f32,
+}
+
+impl Default for PointLight {
+    fn default() -> Self {
+        Self {
+            color: Vec3::ONE,
+            intensity: 1.0,
+            range: 10.0,
+            falloff: 2.0,
+        }
+    }
+}
+
+/// A cone of light from the entity's position, like a street lamp or headlight.
+///
+/// The cone points along the entity's rotated `WorldBasis::forward`. It is fully lit inside
+/// `inner_cone_radians` and fades to nothing at `outer_cone_radians` (both half-angles).
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+#[require(TransformComponent)]
+pub struct SpotLight {
+    pub color: Vec3,
+    pub intensity: f32,
+    /// Distance at which the light has faded to nothing.
+    pub range: f32,
+    /// Exponent of the fade over `range`, as for `PointLight::falloff`.
+    pub falloff: f32,
+    pub inner_cone_radians: f32,
+    pub outer_cone_radians: f32,
+}
+
+impl Default for SpotLight {
+    fn default() -> Self {
+        Self {
+            color: Vec3::ONE,
+            intensity: 1.0,
+            range: 15.0,
+            falloff: 2.0,
+            inner_cone_radians: 20f32.to_radians(),
+            outer_cone_radians: 30f32.to_radians(),
+        }
+    }
+}
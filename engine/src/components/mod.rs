@@ -1,7 +1,11 @@
 pub mod audio_source_component;
 pub mod camera_component;
 pub mod collider_component;
+pub mod hierarchy_component;
+pub mod instance_params_component;
+pub mod light_component;
 pub mod material_component;
+pub mod occluder_component;
 pub mod physics_component;
 pub mod physics_event_listener_component;
 pub mod render_body_component;
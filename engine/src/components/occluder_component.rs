@@ -0,0 +1,7 @@
+use bevy_ecs::prelude::*;
+
+/// Marks a rendered entity, such as a building, as a large occluder for the renderer's
+/// CPU occlusion culling: instances hidden behind it are not drawn. Its meshes are
+/// rasterized on the CPU every frame, so they should be simple.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct Occluder;
@@ -1,15 +1,16 @@
 use bevy_ecs::component::Component;
+use serde::{Deserialize, Serialize};
 
 use crate::{TransformComponent, VelocityComponent};
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum PhysicsType {
     Static,
     Dynamic,
     Kinematic,
 }
 
-#[derive(Component, Clone, Copy)]
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
 #[require(TransformComponent, VelocityComponent)]
 pub struct PhysicsComponent {
     pub physics_type: PhysicsType,
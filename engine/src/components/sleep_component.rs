@@ -1,6 +1,7 @@
 use bevy_ecs::component::Component;
+use serde::{Deserialize, Serialize};
 
-#[derive(Component, Debug, Clone, Copy)]
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct SleepComponent {
     pub is_sleeping: bool,
     pub sleep_timer: f32,
@@ -1,7 +1,11 @@
 use bevy_ecs::component::Component;
 use glam::{Mat4, Quat, Vec3};
+use serde::{Deserialize, Serialize};
 
-#[derive(Component, Debug, Clone, Copy)]
+/// Position, rotation and scale of an entity relative to its `Parent`, or to the world
+/// if it has none.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[require(GlobalTransform)]
 pub struct TransformComponent {
     pub position: Vec3,
     pub rotation: Quat,
@@ -26,4 +30,45 @@ impl TransformComponent {
 
         translation_matrix * rotation_matrix * scale_matrix
     }
+
+    /// Splits an affine matrix back into position, rotation and scale.
+    /// Shear, which a non-uniformly scaled parent can introduce, is lost.
+    pub fn from_mat4(matrix: &Mat4) -> Self {
+        let (scale, rotation, position) = matrix.to_scale_rotation_translation();
+        Self {
+            position,
+            rotation,
+            scale,
+        }
+    }
+}
+
+/// World-space matrix of an entity: its `TransformComponent` combined with those of all
+/// its ancestors.
+///
+/// Written by `TransformSystem::propagate_transforms` at the start of the frame and
+/// physics schedules; don't set it by hand. Rendering, lights, cameras, audio and
+/// collision read this rather than `TransformComponent`.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct GlobalTransform(pub(crate) Mat4);
+
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        Self(Mat4::IDENTITY)
+    }
+}
+
+impl GlobalTransform {
+    pub fn matrix(&self) -> Mat4 {
+        self.0
+    }
+
+    pub fn translation(&self) -> Vec3 {
+        self.0.w_axis.truncate()
+    }
+
+    /// The world transform as position, rotation and scale.
+    pub fn to_transform(&self) -> TransformComponent {
+        TransformComponent::from_mat4(&self.0)
+    }
 }
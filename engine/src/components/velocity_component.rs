@@ -1,10 +1,11 @@
 use bevy_ecs::component::Component;
 use glam::Vec3;
+use serde::{Deserialize, Serialize};
 use std::ops::{Div, Mul};
 
 use crate::TransformComponent;
 
-#[derive(Component, Default, Clone, Copy)]
+#[derive(Component, Debug, Default, Clone, Copy, Serialize, Deserialize)]
 #[require(TransformComponent)]
 pub struct VelocityComponent {
     pub translational: Vec3,
@@ -0,0 +1,103 @@
+use crate::{
+    Engine,
+    render::{
+        debug_view::RenderDebugSettings, occlusion::OcclusionSettings,
+        post_process::PostProcessSettings, shadows::ShadowSettings,
+    },
+};
+
+/// Configures how an [`Engine`] is created.
+///
+/// `EngineBuilder::new()` creates a windowed engine with an SDL window, an OpenGL
+/// context and a cpal audio stream. `EngineBuilder::headless()` skips all three and
+/// swaps in a renderer over a `NullBackend` and a null audio sink, so the `Scene`, its
+/// schedules, every ECS system and the render path still run on machines without a
+/// display or sound card.
+pub struct EngineBuilder {
+    pub(crate) headless: bool,
+    pub(crate) hidden_window: bool,
+    pub(crate) window_title: String,
+    pub(crate) window_width: u32,
+    pub(crate) window_height: u32,
+    pub(crate) shadow_settings: ShadowSettings,
+    pub(crate) occlusion_settings: OcclusionSettings,
+    pub(crate) post_process_settings: PostProcessSettings,
+    pub(crate) render_debug_settings: RenderDebugSettings,
+}
+
+impl Default for EngineBuilder {
+    fn default() -> Self {
+        Self {
+            headless: false,
+            hidden_window: false,
+            window_title: "Engine".to_string(),
+            window_width: 1024,
+            window_height: 769,
+            shadow_settings: ShadowSettings::default(),
+            occlusion_settings: OcclusionSettings::default(),
+            post_process_settings: PostProcessSettings::default(),
+            render_debug_settings: RenderDebugSettings::default(),
+        }
+    }
+}
+
+impl EngineBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An engine with no window, GL context or audio device.
+    pub fn headless() -> Self {
+        Self {
+            headless: true,
+            ..Self::default()
+        }
+    }
+
+    /// Creates the window and GL context as usual but never shows the window.
+    /// Useful for rendering with `Engine::capture_frame` on build machines.
+    pub fn hidden_window(mut self) -> Self {
+        self.hidden_window = true;
+        self
+    }
+
+    pub fn window_title(mut self, title: &str) -> Self {
+        self.window_title = title.to_string();
+        self
+    }
+
+    /// Also the size a headless engine renders at.
+    pub fn window_size(mut self, width: u32, height: u32) -> Self {
+        self.window_width = width;
+        self.window_height = height;
+        self
+    }
+
+    /// Cascade count and resolution of the directional light's shadow maps.
+    pub fn shadow_settings(mut self, settings: ShadowSettings) -> Self {
+        self.shadow_settings = settings;
+        self
+    }
+
+    /// Whether and at what resolution instances hidden behind `Occluder`s are culled.
+    pub fn occlusion_settings(mut self, settings: OcclusionSettings) -> Self {
+        self.occlusion_settings = settings;
+        self
+    }
+
+    /// Whether the scene is drawn through the HDR post-process stack, and how.
+    pub fn post_process_settings(mut self, settings: PostProcessSettings) -> Self {
+        self.post_process_settings = settings;
+        self
+    }
+
+    /// The debug view and wireframe overlay the renderer starts with.
+    pub fn render_debug_settings(mut self, settings: RenderDebugSettings) -> Self {
+        self.render_debug_settings = settings;
+        self
+    }
+
+    pub fn build(self) -> Engine {
+        Engine::from_builder(self)
+    }
+}
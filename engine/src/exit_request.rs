@@ -0,0 +1,18 @@
+use bevy_ecs::prelude::*;
+
+/// Ends `Engine::run` once the frame that sets it is done. Closing the window does the
+/// same, so a headless engine can only leave `run` through this.
+#[derive(Resource, Debug, Default)]
+pub struct ExitRequest {
+    requested: bool,
+}
+
+impl ExitRequest {
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested
+    }
+}
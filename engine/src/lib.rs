@@ -3,9 +3,12 @@
 
 mod action;
 mod action_manager;
+pub mod animation;
 pub mod assets;
 pub mod audio;
 pub mod components;
+mod engine_builder;
+mod exit_request;
 pub mod input;
 pub mod physics;
 pub mod render;
@@ -14,6 +17,8 @@ mod time_resource;
 mod utils;
 pub mod world_basis;
 use std::{
+    ffi::OsStr,
+    path::Path,
     rc::Rc,
     thread::sleep,
     time::{Duration, Instant},
@@ -22,12 +27,13 @@ use std::{
 use bevy_ecs::prelude::*;
 use glam::{Mat4, Vec3};
 use glow::HasContext;
+use thiserror::Error;
 
 use crate::{
     assets::{
-        material_resource::MaterialResource, mesh_resource::MeshResource,
-        shader_resource::ShaderResource, sound_resource::SoundResource,
-        texture_resource::TextureResource,
+        async_loader::AsyncLoader, hot_reload::HotReloader, material_resource::MaterialResource,
+        mesh_resource::MeshResource, shader_resource::ShaderResource,
+        sound_resource::SoundResource, texture_resource::TextureResource,
     },
     audio::{
         audio_command_queue_system::AudioCommandQueueSystem, audio_control::AudioControl,
@@ -40,32 +46,55 @@ use crate::{
         movement_system::MovementSystem, physics_event_dispatcher, physics_system::PhysicsSystem,
     },
     render::{
+        debug_draw_system::DebugDrawSystem,
+        debug_view::RenderDebugSettings,
+        occlusion::OcclusionSettings,
+        post_process::{self, PostProcessSettings},
         render_body_resource::RenderBodyResource,
+        render_graph::{RenderGraphError, RenderPass},
         render_queue::RenderQueue,
         render_system::RenderSystem,
         renderer::{CameraRenderData, RenderParams, Renderer},
+        renderer_backends::{GlowBackend, GraphicsBackend, NullBackend},
+        shadows::ShadowSettings,
     },
     scene::{
         scene::Scene, scene_changer_resource::SceneChangerResource, scene_services::SceneServices,
+        transform_system,
     },
     utils::scope_timer::ScopeTimer,
 };
 
 pub use physics::collision_system::CollisionSystem;
 pub use physics::gravity_resource::Gravity;
+pub use scene::transform_system::{TransformSystem, reparent};
 
-pub use crate::assets::handles::{MaterialHandle, MeshHandle, RenderBodyHandle, SoundHandle};
+pub use crate::animation::animation_event::AnimationFinished;
+pub use crate::animation::animation_player::{
+    AnimatedPose, AnimationLayer, AnimationPlayer, AnimationTarget,
+};
+pub use crate::animation::animation_system::AnimationSystem;
+pub use crate::assets::handles::{
+    MaterialHandle, MeshHandle, RenderBodyHandle, SoundHandle, TextureHandle,
+};
 pub use crate::assets::mesh::Aabb;
 pub use crate::components::camera_component::{ActiveCamera, CameraComponent};
 pub use crate::components::collider_component::{
     CollisionLayer, ConvexCollider, ConvexShape, MeshCollider,
 };
+pub use crate::components::hierarchy_component::{Children, Parent};
+pub use crate::components::instance_params_component::{InstanceField, InstanceParams};
+pub use crate::components::light_component::{DirectionalLight, PointLight, SpotLight};
 pub use crate::components::material_component::MaterialComponent;
+pub use crate::components::occluder_component::Occluder;
 pub use crate::components::render_body_component::RenderBodyComponent;
 pub use crate::components::sleep_component::SleepComponent;
-pub use crate::components::transform_component::TransformComponent;
+pub use crate::components::transform_component::{GlobalTransform, TransformComponent};
 pub use crate::components::velocity_component::VelocityComponent;
+pub use crate::engine_builder::EngineBuilder;
+pub use crate::exit_request::ExitRequest;
 pub use crate::input::MouseButton;
+pub use crate::render::debug_draw::DebugDraw;
 pub use crate::time_resource::TimeResource;
 pub use crate::world_basis::WorldBasis;
 pub struct Engine {
@@ -74,11 +103,51 @@ pub struct Engine {
     physics_schedule: Schedule,
     frame_schedule: Schedule,
     cleanup_schedule: Schedule,
-    gl: Rc<glow::Context>,
+    /// `None` when the engine runs headless.
+    gl: Option<Rc<glow::Context>>,
+    /// `None` when the engine runs headless.
+    display: Option<Display>,
+    renderer: EngineRenderer,
+    /// The size frames are rendered at when there is no window to take it from.
+    headless_size: (u32, u32),
+    audio_mixer: AudioMixer,
+    /// Unsimulated time carried between frames by the fixed-timestep loop.
+    accumulator: Duration,
+    frame_count: u64,
+    schedules_built: bool,
+    /// `Some` while hot reloading is enabled.
+    hot_reloader: Option<HotReloader>,
+    async_loader: AsyncLoader,
+}
+
+/// Upper bound on fixed ticks per frame, so a slow frame can't spiral.
+const MAX_PHYSICS_STEPS: usize = 6;
+
+/// Longest frame time `run` feeds the accumulator, so debugger pauses, window drags and
+/// the like don't turn into a burst of catch-up ticks.
+const MAX_FRAME_TIME: Duration = Duration::from_millis(250);
+
+/// Why `Engine::capture_frame` or `Engine::save_screenshot` failed.
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    #[error("Capturing a frame needs a GL context; build the engine without headless()")]
+    Headless,
+
+    #[error("Failed to save the captured frame: {0}")]
+    Image(#[from] image::ImageError),
+}
+
+/// Draws through GL into the window, or, on a headless engine, into a `NullBackend`
+/// so culling, batching and the render graph still run with nothing to show.
+enum EngineRenderer {
+    Gl(Renderer<GlowBackend>),
+    Null(Renderer<NullBackend>),
+}
+
+/// The SDL window and everything tied to its lifetime.
+struct Display {
     window: sdl2::video::Window,
     events_loop: sdl2::EventPump,
-    renderer: Renderer,
-    audio_mixer: AudioMixer,
     _gl_context: sdl2::video::GLContext,
 }
 
@@ -90,8 +159,13 @@ impl Engine {
     fn add_frame_schedule(&mut self) {
         self.frame_schedule.add_systems(
             (
-                RenderSystem::build_render_queue,
                 TimeResource::update_time_resource,
+                AnimationSystem::update,
+                AnimationSystem::update_targets,
+                TransformSystem::propagate_transforms,
+                RenderSystem::build_render_queue,
+                RenderSystem::gather_lights,
+                DebugDrawSystem::draw_physics,
                 AudioCommandQueueSystem::build_command_queue,
                 SpatialAudioSystem::update_listener_position,
                 SpatialAudioSystem::update_moved_sources,
@@ -106,6 +180,7 @@ impl Engine {
         self.physics_schedule.add_systems(
             (
                 MovementSystem::update,
+                TransformSystem::propagate_transforms,
                 CollisionSystem::update_world_aabb_cache,
                 CollisionSystem::update_world_dynamic_tree,
                 CollisionSystem::generate_manifolds,
@@ -134,13 +209,54 @@ impl Engine {
         self.add_cleanup_schedule();
     }
 
+    /// Creates a windowed engine. Use [`EngineBuilder`] for other configurations.
     pub fn new() -> Self {
-        env_logger::init();
-        let (gl, window, events_loop, gl_context) = unsafe { Self::create_sdl2_context() };
-        let gl = Rc::new(gl);
+        EngineBuilder::new().build()
+    }
+
+    pub(crate) fn from_builder(builder: EngineBuilder) -> Self {
+        // Several engines may be built in one process (e.g. tests), so don't panic if a logger is already set.
+        let _ = env_logger::try_init();
 
-        let renderer = Renderer::new(gl.clone());
-        let audio_mixer = AudioMixer::default();
+        let (gl, display, mut renderer, audio_mixer) = if builder.headless {
+            let mut renderer = Renderer::new(NullBackend::new());
+            renderer.set_shadow_settings(builder.shadow_settings);
+            renderer.set_occlusion_settings(builder.occlusion_settings);
+            renderer.set_post_process_settings(builder.post_process_settings);
+            renderer.set_debug_settings(builder.render_debug_settings);
+            (
+                None,
+                None,
+                EngineRenderer::Null(renderer),
+                AudioMixer::null(),
+            )
+        } else {
+            let (gl, window, events_loop, gl_context) = unsafe {
+                Self::create_sdl2_context(
+                    &builder.window_title,
+                    builder.window_width,
+                    builder.window_height,
+                    builder.hidden_window,
+                )
+            };
+            let gl = Rc::new(gl);
+            let mut renderer = Renderer::new(GlowBackend::new(gl.clone()));
+            renderer.set_shadow_settings(builder.shadow_settings);
+            renderer.set_occlusion_settings(builder.occlusion_settings);
+            renderer.set_post_process_settings(builder.post_process_settings);
+            renderer.set_debug_settings(builder.render_debug_settings);
+            let display = Display {
+                window,
+                events_loop,
+                _gl_context: gl_context,
+            };
+            (
+                Some(gl),
+                Some(display),
+                EngineRenderer::Gl(renderer),
+                AudioMixer::default(),
+            )
+        };
 
         let scene_services = SceneServices {
             meshes: MeshResource::default(),
@@ -150,6 +266,44 @@ impl Engine {
             bodies: RenderBodyResource::default(),
             materials: MaterialResource::default(),
         };
+        if let (Some(gl), EngineRenderer::Gl(renderer)) = (&gl, &mut renderer) {
+            let shadow_shader = scene_services
+                .shaders
+                .write()
+                .get_or_load(
+                    Some(gl),
+                    OsStr::new("resources/shaders/shadow_depth.vert"),
+                    OsStr::new("resources/shaders/shadow_depth.frag"),
+                )
+                .inspect_err(|e| log::error!("{}; shadows are disabled", e))
+                .ok();
+            renderer.set_shadow_shader(shadow_shader);
+            let skinned_shadow_shader = scene_services
+                .shaders
+                .write()
+                .get_or_load(
+                    Some(gl),
+                    OsStr::new("resources/shaders/shadow_depth_skinned.vert"),
+                    OsStr::new("resources/shaders/shadow_depth.frag"),
+                )
+                .inspect_err(|e| log::error!("{}; skinned meshes cast no shadows", e))
+                .ok();
+            renderer.set_skinned_shadow_shader(skinned_shadow_shader);
+            let debug_line_shader = scene_services
+                .shaders
+                .write()
+                .get_or_load(
+                    Some(gl),
+                    OsStr::new("resources/shaders/debug_line.vert"),
+                    OsStr::new("resources/shaders/debug_line.frag"),
+                )
+                .inspect_err(|e| log::error!("{}; debug shapes are not drawn", e))
+                .ok();
+            renderer.set_debug_line_shader(debug_line_shader);
+            for effect in post_process::builtin_effects(&mut scene_services.shaders.write(), gl) {
+                renderer.add_post_process_effect(effect);
+            }
+        }
         let scene = Scene::new(&scene_services);
         let physics_schedule = Schedule::default();
         let frame_schedule = Schedule::default();
@@ -162,196 +316,465 @@ impl Engine {
             frame_schedule,
             cleanup_schedule,
             gl,
-            window,
-            events_loop,
+            display,
             renderer,
+            headless_size: (builder.window_width, builder.window_height),
             audio_mixer,
-            _gl_context: gl_context,
+            accumulator: Duration::ZERO,
+            frame_count: 0,
+            schedules_built: false,
+            hot_reloader: None,
+            async_loader: AsyncLoader::default(),
+        }
+    }
+
+    /// Returns `true` if the engine was built without a window, GL context or audio device.
+    pub fn is_headless(&self) -> bool {
+        self.display.is_none()
+    }
+
+    /// The renderer's shadow settings.
+    pub fn shadow_settings(&self) -> &ShadowSettings {
+        match &self.renderer {
+            EngineRenderer::Gl(renderer) => renderer.shadow_settings(),
+            EngineRenderer::Null(renderer) => renderer.shadow_settings(),
+        }
+    }
+
+    /// Changes the shadow cascades from the next frame.
+    pub fn set_shadow_settings(&mut self, settings: ShadowSettings) {
+        match &mut self.renderer {
+            EngineRenderer::Gl(renderer) => renderer.set_shadow_settings(settings),
+            EngineRenderer::Null(renderer) => renderer.set_shadow_settings(settings),
+        }
+    }
+
+    /// The renderer's occlusion culling settings.
+    pub fn occlusion_settings(&self) -> &OcclusionSettings {
+        match &self.renderer {
+            EngineRenderer::Gl(renderer) => renderer.occlusion_settings(),
+            EngineRenderer::Null(renderer) => renderer.occlusion_settings(),
+        }
+    }
+
+    /// Turns occlusion culling on or off from the next frame.
+    pub fn set_occlusion_settings(&mut self, settings: OcclusionSettings) {
+        match &mut self.renderer {
+            EngineRenderer::Gl(renderer) => renderer.set_occlusion_settings(settings),
+            EngineRenderer::Null(renderer) => renderer.set_occlusion_settings(settings),
+        }
+    }
+
+    /// The renderer's post-processing settings.
+    pub fn post_process_settings(&self) -> &PostProcessSettings {
+        match &self.renderer {
+            EngineRenderer::Gl(renderer) => renderer.post_process_settings(),
+            EngineRenderer::Null(renderer) => renderer.post_process_settings(),
+        }
+    }
+
+    /// Changes exposure, tone mapping and the post-process effects from the next frame.
+    pub fn set_post_process_settings(&mut self, settings: PostProcessSettings) {
+        match &mut self.renderer {
+            EngineRenderer::Gl(renderer) => renderer.set_post_process_settings(settings),
+            EngineRenderer::Null(renderer) => renderer.set_post_process_settings(settings),
+        }
+    }
+
+    /// The renderer's debug view and wireframe settings.
+    pub fn render_debug_settings(&self) -> &RenderDebugSettings {
+        match &self.renderer {
+            EngineRenderer::Gl(renderer) => renderer.debug_settings(),
+            EngineRenderer::Null(renderer) => renderer.debug_settings(),
+        }
+    }
+
+    /// Switches the debug view and wireframe overlay from the next frame.
+    pub fn set_render_debug_settings(&mut self, settings: RenderDebugSettings) {
+        match &mut self.renderer {
+            EngineRenderer::Gl(renderer) => renderer.set_debug_settings(settings),
+            EngineRenderer::Null(renderer) => renderer.set_debug_settings(settings),
+        }
+    }
+
+    /// Adds a pass to the renderer's render graph, ordered by the targets it reads and
+    /// writes. Passes draw through GL, so a headless engine refuses them with
+    /// `RenderGraphError::Headless`.
+    pub fn add_render_pass(
+        &mut self,
+        pass: Box<dyn RenderPass<GlowBackend>>,
+    ) -> Result<(), RenderGraphError> {
+        match &mut self.renderer {
+            EngineRenderer::Gl(renderer) => renderer.add_render_pass(pass),
+            EngineRenderer::Null(_) => Err(RenderGraphError::Headless(pass.name().to_string())),
+        }
+    }
+
+    /// Removes a pass added with `add_render_pass`.
+    pub fn remove_render_pass(&mut self, name: &str) -> Option<Box<dyn RenderPass<GlowBackend>>> {
+        match &mut self.renderer {
+            EngineRenderer::Gl(renderer) => renderer.remove_render_pass(name),
+            EngineRenderer::Null(_) => None,
         }
     }
 
+    /// Runs frames until the window is closed or a system sets `ExitRequest`. A headless
+    /// engine has no window, so without an `ExitRequest` it runs forever; drive it with
+    /// `step_frame` or `step_simulation` instead where a fixed amount of time is wanted.
     pub fn run(&mut self) {
-        unsafe {
-            let version = self.gl.get_parameter_string(glow::VERSION);
-            let shading_language_version =
-                self.gl.get_parameter_string(glow::SHADING_LANGUAGE_VERSION);
-            let major_version = self.gl.get_parameter_i32(glow::MAJOR_VERSION);
-            let minor_version = self.gl.get_parameter_i32(glow::MINOR_VERSION);
-            println!("OpenGL Version: {}", version);
-            println!("GLSL Version: {}", shading_language_version);
-            println!(
-                "OpenGL Major Version: {}. OpenGL Minor Version: {}",
-                major_version, minor_version
-            );
+        if let Some(gl) = &self.gl {
+            unsafe {
+                let version = gl.get_parameter_string(glow::VERSION);
+                let shading_language_version =
+                    gl.get_parameter_string(glow::SHADING_LANGUAGE_VERSION);
+                let major_version = gl.get_parameter_i32(glow::MAJOR_VERSION);
+                let minor_version = gl.get_parameter_i32(glow::MINOR_VERSION);
+                println!("OpenGL Version: {}", version);
+                println!("GLSL Version: {}", shading_language_version);
+                println!(
+                    "OpenGL Major Version: {}. OpenGL Minor Version: {}",
+                    major_version, minor_version
+                );
+            }
         }
 
-        let max_physics_steps: usize = 6;
         let mut last_frame = Instant::now();
-        let mut accumulator = Duration::ZERO;
-        let mut frame_count: u64 = 0;
-        self.add_schedules();
+        self.ensure_schedules();
 
         'game: loop {
             let frame_start = Instant::now();
-            let time_resource = self
+            let frame_target: Duration = self
                 .scene
                 .world
                 .get_resource::<TimeResource>()
-                .expect("TimeResource resource not found");
-            let fixed_dt: Duration = time_resource.simulation_fixed_dt();
-            let frame_target: Duration = time_resource.target_frame_duration();
+                .expect("TimeResource resource not found")
+                .target_frame_duration();
 
-            {
+            if let Some(display) = self.display.as_mut() {
                 let mut input_state = self
                     .scene
                     .world
                     .get_resource_mut::<InputStateResource>()
                     .expect("InputStateResource resource not found");
 
-                if !Self::handle_input(&mut input_state, &mut self.events_loop) {
+                if !Self::handle_input(&mut input_state, &mut display.events_loop) {
                     break 'game;
                 }
+            }
 
-                // Update things that should run only once per frame
-                self.frame_schedule.run(&mut self.scene.world);
-                self.scene.game_frame_schedule.run(&mut self.scene.world);
-
-                // Render before doing any simulation steps, so that the game feels more responsive.
-                let render_params = RenderParams {
-                    width: self.window.size().0,
-                    height: self.window.size().1,
-                };
-                let camera_data = Self::build_camera_render_data(
-                    &mut self.scene.world,
-                    render_params.width,
-                    render_params.height,
-                );
+            let now = Instant::now();
+            let frame_time = now - last_frame;
+            last_frame = now;
 
-                self.renderer.stage_instances(
-                    &self
-                        .scene
-                        .world
-                        .get_resource::<RenderQueue>()
-                        .expect("RenderQueue resource not found")
-                        .instances,
-                );
-                {
-                    let _timer = ScopeTimer::new("Render");
-                    let mesh_resource = &self
-                        .scene
-                        .world
-                        .get_resource::<MeshResource>()
-                        .expect("MeshResource resource not found")
-                        .read();
-                    let material_resource = &self
-                        .scene
-                        .world
-                        .get_resource::<MaterialResource>()
-                        .expect("MaterialResource resource not found")
-                        .read();
-                    let texture_resource = &self
-                        .scene
-                        .world
-                        .get_resource::<TextureResource>()
-                        .expect("TextureResource resource not found")
-                        .read();
-                    let shader_resource = &self
-                        .scene
-                        .world
-                        .get_resource::<ShaderResource>()
-                        .expect("ShaderResource resource not found")
-                        .read();
-
-                    self.renderer.render(
-                        render_params,
-                        mesh_resource,
-                        material_resource,
-                        texture_resource,
-                        shader_resource,
-                        camera_data,
-                    );
-                }
+            self.advance_frame(frame_time, true);
+            if self.scene.world.resource::<ExitRequest>().is_requested() {
+                break 'game;
+            }
+
+            let frame_time = frame_start.elapsed();
+            if frame_time < frame_target {
+                sleep(frame_target - frame_time);
+            }
+            if let Some(display) = &self.display {
+                display.window.gl_swap_window();
+            }
+        }
+    }
+
+    /// Advances the engine by one frame of length `dt`, exactly as one iteration of `run` does
+    /// minus input polling, frame pacing and buffer swapping.
+    ///
+    /// The frame schedules run once, the frame is rendered, then `dt` is
+    /// added to the fixed-timestep accumulator and as many physics and `game_simulation_schedule`
+    /// ticks run as fit in it. `TimeResource` reports `dt` as the frame delta instead of wall-clock time.
+    ///
+    /// Unlike `run`, a long `dt` is neither clamped nor capped at a number of ticks, so stepping
+    /// is deterministic however large the steps are.
+    pub fn step_frame(&mut self, dt: Duration) {
+        self.ensure_schedules();
+        self.scene
+            .world
+            .get_resource_mut::<TimeResource>()
+            .expect("TimeResource resource not found")
+            .set_frame_dt_override(dt);
+        self.advance_frame(dt, false);
+    }
 
-                let now = Instant::now();
-                let frame_time = now - last_frame;
-                last_frame = now;
+    /// Runs exactly `ticks` fixed simulation ticks, bypassing the frame schedules and the accumulator.
+    ///
+    /// Each tick runs the physics schedule followed by `game_simulation_schedule`, as inside `run`.
+    /// Afterwards the cleanup schedule runs and change trackers are cleared, as at the end of a frame.
+    pub fn step_simulation(&mut self, ticks: usize) {
+        self.ensure_schedules();
+        for _ in 0..ticks {
+            self.run_simulation_tick();
+        }
+        self.end_frame();
+    }
 
-                // Prevent absurd frame times (debugger pauses, window drag, etc.)
-                let frame_time = frame_time.min(Duration::from_millis(250));
+    /// Number of frames advanced by `run` or `step_frame` so far.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
 
-                accumulator += frame_time;
+    fn ensure_schedules(&mut self) {
+        if !self.schedules_built {
+            self.add_schedules();
+            self.schedules_built = true;
+        }
+    }
 
-                let mut steps = 0;
-                while accumulator >= fixed_dt && steps < max_physics_steps {
-                    #[cfg(not(debug_assertions))]
-                    let phys_start = Instant::now();
-                    {
-                        let _timer = ScopeTimer::new("Physics Schedule");
-                        self.physics_schedule.run(&mut self.scene.world);
-                    }
-                    #[cfg(not(debug_assertions))]
-                    {
-                        let phys_time = phys_start.elapsed();
-                        if phys_time > fixed_dt {
-                            log::warn!(
-                                "Physics schedule took {:?}, which is {:.2}% longer than the fixed dt of {:?}.",
-                                phys_time,
-                                phys_time.as_secs_f32() / fixed_dt.as_secs_f32() * 100.0,
-                                fixed_dt
-                            );
-                        }
-                    }
-                    self.scene
-                        .game_simulation_schedule
-                        .run(&mut self.scene.world);
-                    accumulator -= fixed_dt;
-                    steps += 1;
-                }
+    /// `realtime` frames clamp the frame time and cap the ticks they run; the leftover
+    /// time is dropped rather than caught up on.
+    fn advance_frame(&mut self, frame_time: Duration, realtime: bool) {
+        self.process_finished_loads();
+        self.poll_hot_reload();
 
-                if steps == max_physics_steps {
-                    accumulator = accumulator.min(fixed_dt);
-                }
+        let fixed_dt: Duration = self
+            .scene
+            .world
+            .get_resource::<TimeResource>()
+            .expect("TimeResource resource not found")
+            .simulation_fixed_dt();
 
-                self.audio_mixer.make_mixer_commands(
-                    self.scene
-                        .world
-                        .get_resource::<AudioControl>()
-                        .expect("AudioQueue resource not found")
-                        .queue(),
-                    &self
-                        .scene
-                        .world
-                        .get_resource::<SoundResource>()
-                        .expect("SoundResource resource not found")
-                        .read(),
+        // Update things that should run only once per frame
+        self.frame_schedule.run(&mut self.scene.world);
+        self.scene.game_frame_schedule.run(&mut self.scene.world);
+
+        // Render before doing any simulation steps, so that the game feels more responsive.
+        self.render_frame();
+        self.scene
+            .world
+            .get_resource_mut::<DebugDraw>()
+            .expect("DebugDraw resource not found")
+            .clear();
+
+        let frame_time = if realtime {
+            frame_time.min(MAX_FRAME_TIME)
+        } else {
+            frame_time
+        };
+
+        self.accumulator += frame_time;
+
+        let max_steps = if realtime {
+            MAX_PHYSICS_STEPS
+        } else {
+            usize::MAX
+        };
+        let mut steps = 0;
+        while self.accumulator >= fixed_dt && steps < max_steps {
+            self.run_simulation_tick();
+            self.accumulator -= fixed_dt;
+            steps += 1;
+        }
+
+        if steps == max_steps {
+            self.accumulator = self.accumulator.min(fixed_dt);
+        }
+
+        self.audio_mixer.make_mixer_commands(
+            self.scene
+                .world
+                .get_resource::<AudioControl>()
+                .expect("AudioQueue resource not found")
+                .queue(),
+            &self
+                .scene
+                .world
+                .get_resource::<SoundResource>()
+                .expect("SoundResource resource not found")
+                .read(),
+        );
+
+        self.end_frame();
+        log::trace!("Frame count: {}", self.frame_count);
+        self.frame_count += 1;
+    }
+
+    /// Renders the current scene into an offscreen framebuffer the size of the window and
+    /// returns the pixels. Nothing is presented to the window.
+    ///
+    /// The frame schedule runs first, as at the start of a frame, so a freshly built scene is
+    /// captured as it is without stepping a frame. Game schedules and simulation ticks don't run.
+    ///
+    /// This works on software GL implementations such as Mesa llvmpipe
+    /// (`LIBGL_ALWAYS_SOFTWARE=1`), so renders can be diffed against reference images on
+    /// machines without a GPU. Combine with `EngineBuilder::hidden_window` to avoid showing a window.
+    ///
+    /// Fails with `CaptureError::Headless` on a headless engine, which has no GL context to
+    /// render with.
+    pub fn capture_frame(&mut self) -> Result<image::RgbaImage, CaptureError> {
+        if self.gl.is_none() {
+            return Err(CaptureError::Headless);
+        }
+        self.ensure_schedules();
+        self.frame_schedule.run(&mut self.scene.world);
+        let frame = self.render_scene(true).ok_or(CaptureError::Headless);
+        self.scene
+            .world
+            .get_resource_mut::<DebugDraw>()
+            .expect("DebugDraw resource not found")
+            .clear();
+        frame
+    }
+
+    /// Captures a frame with `capture_frame` and writes it to `path`.
+    /// The image format is picked from the file extension.
+    pub fn save_screenshot(&mut self, path: impl AsRef<Path>) -> Result<(), CaptureError> {
+        Ok(self.capture_frame()?.save(path)?)
+    }
+
+    fn render_frame(&mut self) {
+        self.render_scene(false);
+    }
+
+    /// Renders to the window, or to the offscreen target when `capture` is set.
+    /// Returns `None` if nothing was captured.
+    fn render_scene(&mut self, capture: bool) -> Option<image::RgbaImage> {
+        let (width, height) = match &self.display {
+            Some(display) => display.window.size(),
+            None => self.headless_size,
+        };
+        let render_params = RenderParams { width, height };
+        let camera_data = Self::build_camera_render_data(&mut self.scene.world, width, height);
+
+        match &mut self.renderer {
+            EngineRenderer::Gl(renderer) => Self::render_with(
+                renderer,
+                &self.scene.world,
+                render_params,
+                camera_data,
+                capture,
+            ),
+            EngineRenderer::Null(renderer) => {
+                let frame = Self::render_with(
+                    renderer,
+                    &self.scene.world,
+                    render_params,
+                    camera_data,
+                    capture,
                 );
+                // Nothing reads what the null backend recorded.
+                renderer.backend_mut().clear();
+                frame
             }
-            self.cleanup_schedule.run(&mut self.scene.world);
-            // Reset bevy_ecs change detection (Added/Changed/Removed) so the next frame starts with a fresh diff.
-            self.scene.world.clear_trackers();
-            let frame_time = frame_start.elapsed();
-            if frame_time < frame_target {
-                sleep(frame_target - frame_time);
-            }
-            self.window.gl_swap_window();
-            // Scene swapping
-            if let Some(pending_scene) = self
+        }
+    }
+
+    fn render_with<B: GraphicsBackend>(
+        renderer: &mut Renderer<B>,
+        world: &World,
+        render_params: RenderParams,
+        camera_data: Option<CameraRenderData>,
+        capture: bool,
+    ) -> Option<image::RgbaImage> {
+        let render_queue = world
+            .get_resource::<RenderQueue>()
+            .expect("RenderQueue resource not found");
+        renderer.stage_instance_changes(&render_queue.instances, &render_queue.changed_instances);
+        renderer.stage_groups(&render_queue.groups);
+        renderer.stage_joint_matrices(&render_queue.joint_matrices);
+        renderer.stage_lights(&render_queue.lights);
+        renderer.stage_debug_draw(
+            world
+                .get_resource::<DebugDraw>()
+                .expect("DebugDraw resource not found"),
+        );
+
+        let _timer = ScopeTimer::new("Render");
+        let mesh_resource = &world
+            .get_resource::<MeshResource>()
+            .expect("MeshResource resource not found")
+            .read();
+        let material_resource = &world
+            .get_resource::<MaterialResource>()
+            .expect("MaterialResource resource not found")
+            .read();
+        let texture_resource = &world
+            .get_resource::<TextureResource>()
+            .expect("TextureResource resource not found")
+            .read();
+        let shader_resource = &world
+            .get_resource::<ShaderResource>()
+            .expect("ShaderResource resource not found")
+            .read();
+
+        if capture {
+            return Some(renderer.render_to_image(
+                render_params,
+                mesh_resource,
+                material_resource,
+                texture_resource,
+                shader_resource,
+                camera_data,
+            ));
+        }
+
+        renderer.render(
+            render_params,
+            mesh_resource,
+            material_resource,
+            texture_resource,
+            shader_resource,
+            camera_data,
+        );
+        None
+    }
+
+    fn run_simulation_tick(&mut self) {
+        #[cfg(not(debug_assertions))]
+        let phys_start = Instant::now();
+        {
+            let _timer = ScopeTimer::new("Physics Schedule");
+            self.physics_schedule.run(&mut self.scene.world);
+        }
+        #[cfg(not(debug_assertions))]
+        {
+            let fixed_dt = self
                 .scene
                 .world
-                .get_resource_mut::<SceneChangerResource>()
-                .expect("SceneChangerResource resource not found")
-                .take_pending()
-            {
-                self.scene = pending_scene;
-                // Rebuild schedules since bevy_ecs binds systems to the world they were used on
-                self.frame_schedule = Schedule::default();
-                self.physics_schedule = Schedule::default();
-                self.cleanup_schedule = Schedule::default();
-                self.add_schedules();
-                log::info!("Scene switched!");
+                .get_resource::<TimeResource>()
+                .expect("TimeResource resource not found")
+                .simulation_fixed_dt();
+            let phys_time = phys_start.elapsed();
+            if phys_time > fixed_dt {
+                log::warn!(
+                    "Physics schedule took {:?}, which is {:.2}% longer than the fixed dt of {:?}.",
+                    phys_time,
+                    phys_time.as_secs_f32() / fixed_dt.as_secs_f32() * 100.0,
+                    fixed_dt
+                );
+            }
+        }
+        self.scene
+            .game_simulation_schedule
+            .run(&mut self.scene.world);
+    }
+
+    /// Runs the cleanup schedule, resets change detection and applies any pending scene change.
+    fn end_frame(&mut self) {
+        self.cleanup_schedule.run(&mut self.scene.world);
+        // Reset bevy_ecs change detection (Added/Changed/Removed) so the next frame starts with a fresh diff.
+        self.scene.world.clear_trackers();
+        // Scene swapping
+        if let Some(mut pending_scene) = self
+            .scene
+            .world
+            .get_resource_mut::<SceneChangerResource>()
+            .expect("SceneChangerResource resource not found")
+            .take_pending()
+        {
+            if self.scene.world.resource::<ExitRequest>().is_requested() {
+                pending_scene.world.resource_mut::<ExitRequest>().request();
             }
-            log::trace!("Frame count: {}", frame_count);
-            frame_count += 1;
+            self.scene = pending_scene;
+            // Rebuild schedules since bevy_ecs binds systems to the world they were used on
+            self.frame_schedule = Schedule::default();
+            self.physics_schedule = Schedule::default();
+            self.cleanup_schedule = Schedule::default();
+            self.add_schedules();
+            log::info!("Scene switched!");
         }
     }
 
@@ -405,7 +828,12 @@ impl Engine {
         true
     }
 
-    unsafe fn create_sdl2_context() -> (
+    unsafe fn create_sdl2_context(
+        title: &str,
+        width: u32,
+        height: u32,
+        hidden: bool,
+    ) -> (
         glow::Context,
         sdl2::video::Window,
         sdl2::EventPump,
@@ -419,12 +847,12 @@ impl Engine {
             gl_attr.set_context_version(3, 3);
             gl_attr.set_depth_size(24);
             gl_attr.set_context_flags().forward_compatible().set();
-            let window = video
-                .window("Engine", 1024, 769)
-                .opengl()
-                .resizable()
-                .build()
-                .unwrap();
+            let mut window_builder = video.window(title, width, height);
+            window_builder.opengl().resizable();
+            if hidden {
+                window_builder.hidden();
+            }
+            let window = window_builder.build().unwrap();
             let gl_context = window.gl_create_context().unwrap();
             window.gl_make_current(&gl_context).unwrap();
             let gl =
@@ -445,12 +873,12 @@ impl Engine {
         let active = world.get_resource::<ActiveCamera>()?;
         let entity = active.0?;
 
-        let mut query = world.query::<(&TransformComponent, &CameraComponent)>();
-        let Ok((transform, camera)) = query.get(world, entity) else {
-            return None;
-        };
+        let camera = world.get::<CameraComponent>(entity)?;
 
-        let view = transform.to_mat4().try_inverse().unwrap_or(Mat4::IDENTITY);
+        // Composed here rather than read from `GlobalTransform`, which was propagated
+        // before `game_frame_schedule` and would miss camera moves made there this frame.
+        let transform = transform_system::world_matrix(world, entity);
+        let view = transform.try_inverse().unwrap_or(Mat4::IDENTITY);
 
         let fallback_aspect = width as f32 / height as f32;
         let aspect_ratio = if camera.aspect_ratio > 0.0 {
@@ -464,7 +892,9 @@ impl Engine {
 
         Some(CameraRenderData {
             view_proj: projection * view,
-            position: transform.position,
+            position: transform.w_axis.truncate(),
+            near: camera.near,
+            far: camera.far,
         })
     }
 }
@@ -559,3 +989,86 @@ fn union_aabb(a: Aabb, b: Aabb) -> Aabb {
         max: a.max.max(b.max),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn move_camera(mut cameras: Query<&mut TransformComponent, With<CameraComponent>>) {
+        for mut transform in &mut cameras {
+            transform.position = Vec3::new(3.0, 0.0, 0.0);
+        }
+    }
+
+    #[test]
+    fn camera_moves_in_the_game_frame_schedule_reach_the_same_frame() {
+        let mut engine = EngineBuilder::headless().build();
+        engine.scene.game_frame_schedule.add_systems(move_camera);
+        let camera = engine
+            .scene
+            .world
+            .spawn(CameraComponent {
+                fov_y_radians: 1.0,
+                aspect_ratio: 1.0,
+                near: 0.1,
+                far: 100.0,
+            })
+            .id();
+        engine
+            .scene
+            .world
+            .resource_mut::<ActiveCamera>()
+            .set(camera);
+
+        // The schedules ahead of `render_frame` in `advance_frame`.
+        engine.frame_schedule.run(&mut engine.scene.world);
+        engine
+            .scene
+            .game_frame_schedule
+            .run(&mut engine.scene.world);
+        let data = Engine::build_camera_render_data(&mut engine.scene.world, 64, 64).unwrap();
+
+        assert_eq!(data.position, Vec3::new(3.0, 0.0, 0.0));
+        let projection = Mat4::perspective_rh(1.0, 1.0, 0.1, 100.0);
+        let view = Mat4::from_translation(Vec3::new(-3.0, 0.0, 0.0));
+        assert!(data.view_proj.abs_diff_eq(projection * view, 1e-5));
+    }
+
+    #[test]
+    fn headless_engine_renders_each_frame_into_a_null_backend() {
+        let mut engine = EngineBuilder::headless()
+            .occlusion_settings(OcclusionSettings {
+                enabled: false,
+                ..OcclusionSettings::default()
+            })
+            .build();
+        let camera = engine
+            .scene
+            .world
+            .spawn((
+                CameraComponent {
+                    fov_y_radians: 1.0,
+                    aspect_ratio: 1.0,
+                    near: 0.1,
+                    far: 100.0,
+                },
+                TransformComponent::default(),
+            ))
+            .id();
+        engine
+            .scene
+            .world
+            .resource_mut::<ActiveCamera>()
+            .set(camera);
+
+        engine.step_frame(Duration::from_millis(16));
+        engine.step_frame(Duration::from_millis(16));
+
+        let EngineRenderer::Null(renderer) = &engine.renderer else {
+            panic!("a headless engine should render into a NullBackend");
+        };
+        assert_eq!(renderer.frames_rendered(), 2);
+        assert!(renderer.backend().commands.is_empty());
+        assert!(!engine.occlusion_settings().enabled);
+    }
+}
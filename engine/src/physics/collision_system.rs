@@ -7,7 +7,7 @@ use rayon::prelude::*;
 use std::{collections::HashMap, time::Duration};
 
 use crate::{
-    TransformComponent,
+    GlobalTransform, Parent, TransformComponent,
     assets::{
         mesh::Aabb,
         mesh_resource::{MeshResource, MeshStorage},
@@ -40,11 +40,11 @@ impl CollisionSystem {
         query: Query<
             (
                 Entity,
-                &TransformComponent,
+                &GlobalTransform,
                 Option<&ConvexCollider>,
                 Option<&MeshCollider>,
             ),
-            Changed<TransformComponent>,
+            Changed<GlobalTransform>,
         >,
         render_body_resource: Res<RenderBodyResource>,
         mesh_resource: Res<MeshResource>,
@@ -58,12 +58,12 @@ impl CollisionSystem {
                     &render_body_resource,
                     &mesh_resource.read(),
                 ) {
-                    transform_aabb(local_aabb, transform)
+                    transform_aabb_with_mat4(local_aabb, &transform.matrix())
                 } else {
                     continue;
                 }
             } else if let Some(convex_collider) = convex_collider {
-                convex_collider.aabb(&transform.to_mat4())
+                convex_collider.aabb(&transform.matrix())
             } else {
                 continue;
             };
@@ -107,11 +107,11 @@ impl CollisionSystem {
         query: Query<
             (
                 Entity,
-                &TransformComponent,
+                &GlobalTransform,
                 Option<&ConvexCollider>,
                 Option<&MeshCollider>,
             ),
-            Changed<TransformComponent>,
+            Changed<GlobalTransform>,
         >,
         render_body_resource: Res<RenderBodyResource>,
         mesh_resource: Res<MeshResource>,
@@ -125,13 +125,13 @@ impl CollisionSystem {
                     &mesh_resource.read(),
                 )
             {
-                let world_aabb = transform_aabb(local_aabb, transform);
+                let world_aabb = transform_aabb_with_mat4(local_aabb, &transform.matrix());
                 phys.world_aabbs.insert(entity, world_aabb);
                 continue;
             }
 
             if let Some(convex_collider) = convex_collider {
-                let world_aabb = convex_collider.aabb(&transform.to_mat4());
+                let world_aabb = convex_collider.aabb(&transform.matrix());
                 phys.world_aabbs.insert(entity, world_aabb);
             }
         }
@@ -158,11 +158,13 @@ impl CollisionSystem {
                 Option<&ConvexCollider>,
                 Option<&MeshCollider>,
             ),
-            Changed<TransformComponent>,
+            Changed<GlobalTransform>,
         >,
         all_query: Query<(
             Entity,
             &TransformComponent,
+            &GlobalTransform,
+            Option<&Parent>,
             Option<&VelocityComponent>,
             Option<&PhysicsComponent>,
             Option<&ConvexCollider>,
@@ -210,10 +212,12 @@ impl CollisionSystem {
             .candidate_pairs
             .par_iter()
             .filter_map(|(entity_a, entity_b)| {
-                let (.., transform_a, velocity_a, physics_a, convex_a, mesh_a) =
+                let (_, transform_a, global_a, parent_a, velocity_a, physics_a, convex_a, mesh_a) =
                     all_query.get(*entity_a).ok()?;
-                let (.., transform_b, velocity_b, physics_b, convex_b, mesh_b) =
+                let (_, transform_b, global_b, parent_b, velocity_b, physics_b, convex_b, mesh_b) =
                     all_query.get(*entity_b).ok()?;
+                let transform_a = &world_transform(transform_a, global_a, parent_a);
+                let transform_b = &world_transform(transform_b, global_b, parent_b);
 
                 let pair = ordered_pair(*entity_a, *entity_b);
                 let previous_manifold = frame.previous_manifolds.get(pair);
@@ -317,6 +321,20 @@ impl CollisionSystem {
     }
 }
 
+/// The transform narrowphase works with. Roots use their `TransformComponent` as is, so
+/// bodies moved by the solver this tick are seen where they are now; children are placed
+/// by their `GlobalTransform`.
+fn world_transform(
+    transform: &TransformComponent,
+    global: &GlobalTransform,
+    parent: Option<&Parent>,
+) -> TransformComponent {
+    match parent {
+        Some(_) => global.to_transform(),
+        None => *transform,
+    }
+}
+
 fn manifold_merge_distance_pair_map(
     world_aabbs: &HashMap<Entity, Aabb>,
     a: Entity,
@@ -624,11 +642,6 @@ fn merge_contact_manifold(
     }
 }
 
-fn transform_aabb(local: Aabb, transform: &TransformComponent) -> Aabb {
-    let matrix = transform.to_mat4();
-    transform_aabb_with_mat4(local, &matrix)
-}
-
 fn transform_aabb_with_mat4(local: Aabb, transform: &Mat4) -> Aabb {
     let min = local.min;
     let max = local.max;
@@ -3,7 +3,10 @@ use std::num::NonZeroUsize;
 use bevy_ecs::entity::Entity;
 use glam::Vec3;
 
-use crate::assets::mesh::Aabb;
+use crate::{
+    assets::mesh::Aabb,
+    render::frustum::{Containment, Frustum},
+};
 
 // Using NonZeroUsize allows for some nice memory layout optimization
 // since Option<NodeId> can be represented as usize with 0 reserved for None.
@@ -122,6 +125,12 @@ impl DynamicAabbTree {
         self.recycle_node(parent);
     }
 
+    /// Removes `leaf` for good and reuses its node.
+    pub fn destroy_leaf(&mut self, leaf: NodeId) {
+        self.remove(leaf);
+        self.recycle_node(leaf);
+    }
+
     pub fn insert_leaf(&mut self, leaf: NodeId, aabb: Aabb) {
         self.nodes[leaf.get()].aabb = aabb;
         self.nodes[leaf.get()].left = None;
@@ -352,6 +361,61 @@ impl DynamicAabbTree {
         }
     }
 
+    /// Calls `callback` with every leaf whose box touches `frustum`, and whether the box
+    /// is entirely inside it. Subtrees inside the frustum are reported without testing
+    /// their leaves.
+    pub fn query_frustum<F>(&self, frustum: &Frustum, mut callback: F)
+    where
+        F: FnMut(Entity, bool),
+    {
+        if let Some(root) = self.root {
+            self.query_frustum_node(root, frustum, false, &mut callback);
+        }
+    }
+
+    /// Calls `callback` with the box of every node, leaves included, and its depth below
+    /// the root, parents before their children.
+    pub fn for_each_node<F>(&self, mut callback: F)
+    where
+        F: FnMut(&Aabb, usize),
+    {
+        let mut stack: Vec<(NodeId, usize)> = self.root.map(|root| (root, 0)).into_iter().collect();
+        while let Some((node_id, depth)) = stack.pop() {
+            let node = &self.nodes[node_id.get()];
+            callback(&node.aabb, depth);
+            if let (Some(left), Some(right)) = (node.left, node.right) {
+                stack.push((right, depth + 1));
+                stack.push((left, depth + 1));
+            }
+        }
+    }
+
+    fn query_frustum_node<F>(
+        &self,
+        node_id: NodeId,
+        frustum: &Frustum,
+        inside: bool,
+        callback: &mut F,
+    ) where
+        F: FnMut(Entity, bool),
+    {
+        let node = &self.nodes[node_id.get()];
+
+        let inside = inside
+            || match frustum.classify_aabb(&node.aabb) {
+                Containment::Outside => return,
+                Containment::Intersecting => false,
+                Containment::Inside => true,
+            };
+
+        if let Some(entity) = node.entity {
+            callback(entity, inside);
+        } else {
+            self.query_frustum_node(node.left.unwrap(), frustum, inside, callback);
+            self.query_frustum_node(node.right.unwrap(), frustum, inside, callback);
+        }
+    }
+
     fn query_node<F>(&self, node_id: NodeId, aabb: &Aabb, callback: &mut F)
     where
         F: FnMut(Entity),
@@ -802,6 +866,31 @@ mod tests {
         assert!(found_final.is_empty());
     }
 
+    #[test]
+    fn every_node_is_visited_with_its_depth() {
+        let mut tree = DynamicAabbTree::default();
+        let mut visited = 0;
+        tree.for_each_node(|_, _| visited += 1);
+        assert_eq!(visited, 0);
+
+        for i in 0..4 {
+            tree.allocate_leaf(
+                Entity::from_bits(i + 1),
+                make_aabb(Vec3::new(i as f32 * 4.0, 0.0, 0.0), 1.0),
+            );
+        }
+
+        let mut nodes = Vec::new();
+        tree.for_each_node(|aabb, depth| nodes.push((*aabb, depth)));
+        // Four leaves under three internal nodes, the root first.
+        assert_eq!(nodes.len(), 7);
+        let root = tree.root.expect("root should exist");
+        assert_aabb_eq(nodes[0].0, tree.nodes[root.get()].aabb, "root box");
+        assert_eq!(nodes[0].1, 0);
+        let deepest = nodes.iter().map(|&(_, depth)| depth).max().unwrap();
+        assert_eq!(deepest as i32, tree.nodes[root.get()].height);
+    }
+
     #[test]
     fn multiple_updates_sequence() {
         let mut rng = StdRng::seed_from_u64(0xDEAD_BEEF_1234_5678);
@@ -1010,4 +1099,42 @@ mod tests {
             assert_eq!(found, expected);
         }
     }
+
+    #[test]
+    fn frustum_queries_match_testing_every_leaf() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut tree = DynamicAabbTree::default();
+        let mut leaves = Vec::new();
+        for i in 0..500 {
+            let center = Vec3::new(
+                rng.random_range(-100.0..100.0),
+                rng.random_range(-100.0..100.0),
+                rng.random_range(-100.0..100.0),
+            );
+            let aabb = make_aabb(center, rng.random_range(0.5..5.0));
+            let entity = Entity::from_bits(1000 + i);
+            tree.allocate_leaf(entity, aabb);
+            leaves.push((entity, DynamicAabbTree::expand_aabb(aabb, 0.1)));
+        }
+
+        let view = glam::Mat4::look_at_rh(Vec3::ZERO, Vec3::new(1.0, 0.5, -1.0), Vec3::Y);
+        let proj = glam::Mat4::perspective_rh(1.0, 1.5, 0.1, 80.0);
+        let frustum = Frustum::from_view_proj(&(proj * view));
+
+        let mut found = HashSet::new();
+        tree.query_frustum(&frustum, |entity, inside| {
+            found.insert((entity, inside));
+        });
+
+        let expected: HashSet<_> = leaves
+            .iter()
+            .filter_map(|(entity, aabb)| match frustum.classify_aabb(aabb) {
+                Containment::Outside => None,
+                containment => Some((*entity, containment == Containment::Inside)),
+            })
+            .collect();
+        assert!(expected.iter().any(|(_, inside)| *inside));
+        assert!(expected.len() < leaves.len());
+        assert_eq!(found, expected);
+    }
 }
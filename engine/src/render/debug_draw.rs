@@ -0,0 +1,358 @@
+use bevy_ecs::prelude::Resource;
+use glam::{Mat4, Vec2, Vec3};
+
+use crate::assets::mesh::Aabb;
+
+/// Segments of the circles spheres are drawn with.
+const CIRCLE_SEGMENTS: usize = 24;
+
+/// Size of a label character in pixels.
+const GLYPH_WIDTH: f32 = 6.0;
+const GLYPH_HEIGHT: f32 = 10.0;
+const GLYPH_ADVANCE: f32 = 9.0;
+const LINE_HEIGHT: f32 = 14.0;
+
+/// A line segment in world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DebugLine {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub color: Vec3,
+}
+
+/// Text facing the screen at a fixed size, starting at a point in world space.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugLabel {
+    pub position: Vec3,
+    pub text: String,
+    pub color: Vec3,
+}
+
+/// Built-in visualisations of the physics state, drawn by
+/// `DebugDrawSystem::draw_physics`. All are off by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PhysicsDebugDraw {
+    /// The shape of every `ConvexCollider`.
+    pub colliders: bool,
+    /// `PhysicsResource::world_aabbs`.
+    pub world_aabbs: bool,
+    /// Every node of the broadphase tree, coloured by depth.
+    pub broadphase: bool,
+    /// Contact points and normals of the last tick's manifolds.
+    pub contacts: bool,
+    /// BVH nodes of mesh colliders from the root down to this depth, coloured by depth.
+    pub mesh_bvh_depth: Option<u32>,
+}
+
+/// Immediate-mode debug shapes, drawn as lines over the finished frame by the
+/// `debug_lines` render pass.
+///
+/// Any system can push shapes. Everything pushed is drawn by the next rendered frame and
+/// then cleared, so shapes have to be pushed every frame they should stay on screen;
+/// those pushed by simulation ticks show in the frame after them.
+#[derive(Resource, Default)]
+pub struct DebugDraw {
+    pub physics: PhysicsDebugDraw,
+    lines: Vec<DebugLine>,
+    labels: Vec<DebugLabel>,
+}
+
+impl DebugDraw {
+    pub fn line(&mut self, start: Vec3, end: Vec3, color: Vec3) {
+        self.lines.push(DebugLine { start, end, color });
+    }
+
+    /// A line with a head at `end`, a fifth of its length.
+    pub fn arrow(&mut self, start: Vec3, end: Vec3, color: Vec3) {
+        self.line(start, end, color);
+        let direction = end - start;
+        let length = direction.length();
+        if length <= f32::EPSILON {
+            return;
+        }
+        let back = direction * 0.2;
+        let (u, v) = (direction / length).any_orthonormal_pair();
+        let spread = length * 0.08;
+        for side in [u, -u, v, -v] {
+            self.line(end, end - back + side * spread, color);
+        }
+    }
+
+    pub fn aabb(&mut self, aabb: &Aabb, color: Vec3) {
+        let (min, max) = (aabb.min, aabb.max);
+        self.box_edges(
+            std::array::from_fn(|corner| {
+                Vec3::new(
+                    if corner & 1 == 0 { min.x } else { max.x },
+                    if corner & 2 == 0 { min.y } else { max.y },
+                    if corner & 4 == 0 { min.z } else { max.z },
+                )
+            }),
+            color,
+        );
+    }
+
+    /// A box reaching `half_extents` from the origin of `transform` along its axes.
+    pub fn obb(&mut self, transform: &Mat4, half_extents: Vec3, color: Vec3) {
+        self.box_edges(
+            std::array::from_fn(|corner| {
+                transform.transform_point3(Vec3::new(
+                    if corner & 1 == 0 {
+                        -half_extents.x
+                    } else {
+                        half_extents.x
+                    },
+                    if corner & 2 == 0 {
+                        -half_extents.y
+                    } else {
+                        half_extents.y
+                    },
+                    if corner & 4 == 0 {
+                        -half_extents.z
+                    } else {
+                        half_extents.z
+                    },
+                ))
+            }),
+            color,
+        );
+    }
+
+    /// Three circles around `center`, one across each axis.
+    pub fn sphere(&mut self, center: Vec3, radius: f32, color: Vec3) {
+        for normal in [Vec3::X, Vec3::Y, Vec3::Z] {
+            self.circle(center, normal, radius, color);
+        }
+    }
+
+    /// A circle around `center` in the plane facing `normal`.
+    pub fn circle(&mut self, center: Vec3, normal: Vec3, radius: f32, color: Vec3) {
+        let (u, v) = normal.normalize_or(Vec3::Y).any_orthonormal_pair();
+        let point = |segment: usize| {
+            let angle = segment as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            center + (u * angle.cos() + v * angle.sin()) * radius
+        };
+        for segment in 0..CIRCLE_SEGMENTS {
+            self.line(point(segment), point(segment + 1), color);
+        }
+    }
+
+    /// Text starting at `position`. Letters, digits and common punctuation are drawn, with
+    /// lowercase shown as uppercase and anything else as `?`; `\n` starts a new line.
+    pub fn text(&mut self, position: Vec3, text: impl Into<String>, color: Vec3) {
+        self.labels.push(DebugLabel {
+            position,
+            text: text.into(),
+            color,
+        });
+    }
+
+    pub fn lines(&self) -> &[DebugLine] {
+        &self.lines
+    }
+
+    pub fn labels(&self) -> &[DebugLabel] {
+        &self.labels
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty() && self.labels.is_empty()
+    }
+
+    /// Drops every shape pushed so far. The engine calls this after each rendered frame.
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.labels.clear();
+    }
+
+    /// Corners are indexed by bits: 1 for the far x side, 2 for y and 4 for z.
+    fn box_edges(&mut self, corners: [Vec3; 8], color: Vec3) {
+        for (corner, &start) in corners.iter().enumerate() {
+            for axis in [1, 2, 4] {
+                if corner & axis == 0 {
+                    self.line(start, corners[corner | axis], color);
+                }
+            }
+        }
+    }
+}
+
+// Label characters are drawn with the segments of a sixteen-segment display, on a unit
+// cell with y up. Bit `n` of a glyph lights `GLYPH_SEGMENTS[n]`.
+const A1: u16 = 1 << 0;
+const A2: u16 = 1 << 1;
+const B: u16 = 1 << 2;
+const C: u16 = 1 << 3;
+const D2: u16 = 1 << 4;
+const D1: u16 = 1 << 5;
+const E: u16 = 1 << 6;
+const F: u16 = 1 << 7;
+const G1: u16 = 1 << 8;
+const G2: u16 = 1 << 9;
+const H: u16 = 1 << 10;
+const I: u16 = 1 << 11;
+const J: u16 = 1 << 12;
+const K: u16 = 1 << 13;
+const L: u16 = 1 << 14;
+const M: u16 = 1 << 15;
+const TOP: u16 = A1 | A2;
+const BOTTOM: u16 = D1 | D2;
+const MIDDLE: u16 = G1 | G2;
+const FRAME: u16 = TOP | B | C | BOTTOM | E | F;
+
+const GLYPH_SEGMENTS: [[(f32, f32); 2]; 16] = [
+    [(0.0, 1.0), (0.5, 1.0)], // A1
+    [(0.5, 1.0), (1.0, 1.0)], // A2
+    [(1.0, 1.0), (1.0, 0.5)], // B
+    [(1.0, 0.5), (1.0, 0.0)], // C
+    [(1.0, 0.0), (0.5, 0.0)], // D2
+    [(0.5, 0.0), (0.0, 0.0)], // D1
+    [(0.0, 0.0), (0.0, 0.5)], // E
+    [(0.0, 0.5), (0.0, 1.0)], // F
+    [(0.0, 0.5), (0.5, 0.5)], // G1
+    [(0.5, 0.5), (1.0, 0.5)], // G2
+    [(0.0, 1.0), (0.5, 0.5)], // H
+    [(0.5, 1.0), (0.5, 0.5)], // I
+    [(1.0, 1.0), (0.5, 0.5)], // J
+    [(0.5, 0.5), (0.0, 0.0)], // K
+    [(0.5, 0.5), (0.5, 0.0)], // L
+    [(0.5, 0.5), (1.0, 0.0)], // M
+];
+
+fn glyph(character: char) -> u16 {
+    match character.to_ascii_uppercase() {
+        ' ' => 0,
+        '0' => FRAME | J | K,
+        '1' => B | C,
+        '2' => TOP | B | MIDDLE | E | BOTTOM,
+        '3' => TOP | B | G2 | C | BOTTOM,
+        '4' => F | MIDDLE | B | C,
+        '5' => TOP | F | MIDDLE | C | BOTTOM,
+        '6' => TOP | F | E | BOTTOM | C | MIDDLE,
+        '7' => TOP | B | C,
+        '8' => FRAME | MIDDLE,
+        '9' => TOP | B | C | BOTTOM | F | MIDDLE,
+        'A' => TOP | B | C | E | F | MIDDLE,
+        'B' => TOP | B | C | BOTTOM | I | L | G2,
+        'C' => TOP | F | E | BOTTOM,
+        'D' => TOP | B | C | BOTTOM | I | L,
+        'E' => TOP | F | E | BOTTOM | G1,
+        'F' => TOP | F | E | G1,
+        'G' => TOP | F | E | BOTTOM | C | G2,
+        'H' => F | E | B | C | MIDDLE,
+        'I' => TOP | BOTTOM | I | L,
+        'J' => B | C | BOTTOM | E,
+        'K' => F | E | G1 | J | M,
+        'L' => F | E | BOTTOM,
+        'M' => F | E | B | C | H | J,
+        'N' => F | E | B | C | H | M,
+        'O' => FRAME,
+        'P' => TOP | B | F | E | MIDDLE,
+        'Q' => FRAME | M,
+        'R' => TOP | B | F | E | MIDDLE | M,
+        'S' => TOP | F | MIDDLE | C | BOTTOM,
+        'T' => TOP | I | L,
+        'U' => F | E | BOTTOM | B | C,
+        'V' => F | E | K | J,
+        'W' => F | E | B | C | K | M,
+        'X' => H | J | K | M,
+        'Y' => H | J | L,
+        'Z' => TOP | J | K | BOTTOM,
+        '-' => MIDDLE,
+        '+' => MIDDLE | I | L,
+        '=' => MIDDLE | BOTTOM,
+        '*' => H | I | J | K | L | M,
+        '/' => J | K,
+        '|' => I | L,
+        '<' => J | M,
+        '>' => H | K,
+        '(' => J | M,
+        ')' => H | K,
+        '_' => BOTTOM,
+        '.' | ',' => D1,
+        '\'' => I,
+        _ => TOP | B | G2 | L,
+    }
+}
+
+/// The line segments of `text`, in pixels from the label's position with y up.
+pub(crate) fn label_segments(text: &str) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    text.lines().enumerate().flat_map(|(row, line)| {
+        line.chars()
+            .enumerate()
+            .flat_map(move |(column, character)| {
+                let origin = Vec2::new(column as f32 * GLYPH_ADVANCE, -(row as f32) * LINE_HEIGHT);
+                let segments = glyph(character);
+                GLYPH_SEGMENTS
+                    .iter()
+                    .enumerate()
+                    .filter(move |&(bit, _)| segments & (1 << bit) != 0)
+                    .map(move |(_, &[start, end])| {
+                        let scale = Vec2::new(GLYPH_WIDTH, GLYPH_HEIGHT);
+                        (
+                            origin + Vec2::from(start) * scale,
+                            origin + Vec2::from(end) * scale,
+                        )
+                    })
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boxes_and_spheres_are_pushed_as_lines() {
+        let mut debug_draw = DebugDraw::default();
+        debug_draw.aabb(
+            &Aabb {
+                min: Vec3::ZERO,
+                max: Vec3::ONE,
+            },
+            Vec3::X,
+        );
+        assert_eq!(debug_draw.lines().len(), 12);
+        for line in debug_draw.lines() {
+            // Every edge runs along one axis and is as long as the box.
+            assert!(((line.end - line.start).length() - 1.0).abs() < 1e-6);
+        }
+
+        debug_draw.clear();
+        debug_draw.obb(
+            &Mat4::from_rotation_z(std::f32::consts::FRAC_PI_2),
+            Vec3::new(2.0, 1.0, 1.0),
+            Vec3::X,
+        );
+        let extent = debug_draw
+            .lines()
+            .iter()
+            .fold(Vec3::ZERO, |extent, line| extent.max(line.start.abs()));
+        assert!(extent.abs_diff_eq(Vec3::new(1.0, 2.0, 1.0), 1e-5));
+
+        debug_draw.clear();
+        debug_draw.sphere(Vec3::ONE, 2.0, Vec3::X);
+        assert_eq!(debug_draw.lines().len(), 3 * CIRCLE_SEGMENTS);
+        for line in debug_draw.lines() {
+            assert!(((line.start - Vec3::ONE).length() - 2.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn labels_are_laid_out_in_glyph_cells() {
+        let segments: Vec<_> = label_segments("1\n-").collect();
+        // "1" lights the right-hand side of the first row's cell, "-" the middle of the
+        // second row's.
+        assert_eq!(
+            segments,
+            vec![
+                (Vec2::new(6.0, 10.0), Vec2::new(6.0, 5.0)),
+                (Vec2::new(6.0, 5.0), Vec2::new(6.0, 0.0)),
+                (Vec2::new(0.0, -9.0), Vec2::new(3.0, -9.0)),
+                (Vec2::new(3.0, -9.0), Vec2::new(6.0, -9.0)),
+            ]
+        );
+        assert!(label_segments("a").eq(label_segments("A")));
+        assert_eq!(label_segments(" ").count(), 0);
+    }
+}
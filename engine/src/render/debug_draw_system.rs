@@ -0,0 +1,187 @@
+use bevy_ecs::prelude::{Query, Res, ResMut};
+use glam::{Mat4, Vec3};
+
+use crate::{
+    assets::mesh_resource::MeshResource,
+    components::{
+        collider_component::{BVHNode, ConvexCollider, ConvexShape, MeshCollider, max_scale},
+        transform_component::GlobalTransform,
+    },
+    physics::physics_resource::{CollisionFrameData, PhysicsResource},
+    render::{debug_draw::DebugDraw, render_body_resource::RenderBodyResource},
+};
+
+const COLLIDER_COLOR: Vec3 = Vec3::new(0.2, 1.0, 0.3);
+const WORLD_AABB_COLOR: Vec3 = Vec3::new(0.2, 0.6, 1.0);
+const CONTACT_COLOR: Vec3 = Vec3::new(1.0, 1.0, 0.2);
+const NORMAL_COLOR: Vec3 = Vec3::new(1.0, 0.3, 0.2);
+/// Half the size of the cross marking a contact point, and the length of its normal.
+const CONTACT_SIZE: f32 = 0.05;
+const NORMAL_LENGTH: f32 = 0.3;
+
+/// Colours of tree nodes by depth, repeating down the tree.
+const DEPTH_COLORS: [Vec3; 6] = [
+    Vec3::new(1.0, 0.2, 0.2),
+    Vec3::new(1.0, 0.6, 0.1),
+    Vec3::new(0.9, 0.9, 0.2),
+    Vec3::new(0.3, 0.9, 0.3),
+    Vec3::new(0.2, 0.7, 1.0),
+    Vec3::new(0.7, 0.3, 1.0),
+];
+
+fn depth_color(depth: usize) -> Vec3 {
+    DEPTH_COLORS[depth % DEPTH_COLORS.len()]
+}
+
+pub struct DebugDrawSystem {}
+
+impl DebugDrawSystem {
+    /// Pushes the physics visualisations enabled in `DebugDraw::physics`. Runs in the
+    /// frame schedule, so it shows the state the last simulation tick left.
+    pub fn draw_physics(
+        mut debug_draw: ResMut<DebugDraw>,
+        convex_colliders: Query<(&ConvexCollider, &GlobalTransform)>,
+        mesh_colliders: Query<(&MeshCollider, &GlobalTransform)>,
+        physics: Res<PhysicsResource>,
+        collision_frame_data: Res<CollisionFrameData>,
+        render_body_resource: Res<RenderBodyResource>,
+        mesh_resource: Res<MeshResource>,
+    ) {
+        let debug_draw = &mut *debug_draw;
+        let toggles = debug_draw.physics;
+
+        if toggles.colliders {
+            for (collider, transform) in &convex_colliders {
+                draw_convex_shape(debug_draw, &collider.shape, &transform.matrix());
+            }
+        }
+
+        if toggles.world_aabbs {
+            for aabb in physics.world_aabbs.values() {
+                debug_draw.aabb(aabb, WORLD_AABB_COLOR);
+            }
+        }
+
+        if toggles.broadphase {
+            physics
+                .broadphase
+                .for_each_node(|aabb, depth| debug_draw.aabb(aabb, depth_color(depth)));
+        }
+
+        if toggles.contacts {
+            for entry in collision_frame_data.manifolds.iter() {
+                for contact in &entry.manifold.contacts {
+                    let point = contact.contact_point;
+                    for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+                        debug_draw.line(
+                            point - axis * CONTACT_SIZE,
+                            point + axis * CONTACT_SIZE,
+                            CONTACT_COLOR,
+                        );
+                    }
+                    debug_draw.arrow(point, point + contact.normal * NORMAL_LENGTH, NORMAL_COLOR);
+                }
+            }
+        }
+
+        if let Some(max_depth) = toggles.mesh_bvh_depth {
+            let bodies = render_body_resource.read();
+            let meshes = mesh_resource.read();
+            for (collider, transform) in &mesh_colliders {
+                let Some(body) = bodies.get_render_body(collider.render_body_id) else {
+                    continue;
+                };
+                for part in &body.parts {
+                    let Some(bvh) = meshes
+                        .get_mesh(part.mesh_id)
+                        .and_then(|mesh| mesh.bvh.as_ref())
+                    else {
+                        continue;
+                    };
+                    let mesh_world = transform.matrix() * part.local_transform;
+                    draw_bvh(debug_draw, bvh, &mesh_world, 0, max_depth as usize);
+                }
+            }
+        }
+    }
+}
+
+/// The outline of `shape`, placed by `transform` the way collision places it.
+fn draw_convex_shape(debug_draw: &mut DebugDraw, shape: &ConvexShape, transform: &Mat4) {
+    let color = COLLIDER_COLOR;
+    match *shape {
+        ConvexShape::Cuboid {
+            length,
+            width,
+            height,
+        } => {
+            debug_draw.obb(transform, Vec3::new(length, width, height) * 0.5, color);
+        }
+        ConvexShape::Sphere { radius } => {
+            // Spheres are scaled by the largest axis, as in their bounding box.
+            debug_draw.sphere(
+                transform.transform_point3(Vec3::ZERO),
+                radius * max_scale(transform),
+                color,
+            );
+        }
+        ConvexShape::Triangle { v0, v1, v2 } => {
+            let [v0, v1, v2] = [v0, v1, v2].map(|v| transform.transform_point3(v));
+            debug_draw.line(v0, v1, color);
+            debug_draw.line(v1, v2, color);
+            debug_draw.line(v2, v0, color);
+        }
+        ConvexShape::TrianglePrism {
+            v0,
+            v1,
+            v2,
+            half_thickness,
+        } => {
+            let offset = (v1 - v0).cross(v2 - v0).normalize_or(Vec3::Z) * half_thickness;
+            let front = [v0, v1, v2].map(|v| transform.transform_point3(v + offset));
+            let back = [v0, v1, v2].map(|v| transform.transform_point3(v - offset));
+            for i in 0..3 {
+                let next = (i + 1) % 3;
+                debug_draw.line(front[i], front[next], color);
+                debug_draw.line(back[i], back[next], color);
+                debug_draw.line(front[i], back[i], color);
+            }
+        }
+        ConvexShape::Egg { length, radius } => {
+            // A segment along x swept by a disc across it: a cylinder capped by its rims.
+            let half_length = length * 0.5;
+            let center = transform.transform_point3(Vec3::ZERO);
+            let axis = transform.transform_vector3(Vec3::X * half_length);
+            let up = transform.transform_vector3(Vec3::Y * radius);
+            let side = transform.transform_vector3(Vec3::Z * radius);
+            for end in [center - axis, center + axis] {
+                debug_draw.circle(end, axis, up.length().max(side.length()), color);
+            }
+            for offset in [up, -up, side, -side] {
+                debug_draw.line(center - axis + offset, center + axis + offset, color);
+            }
+        }
+    }
+}
+
+fn draw_bvh(
+    debug_draw: &mut DebugDraw,
+    node: &BVHNode,
+    mesh_world: &Mat4,
+    depth: usize,
+    max_depth: usize,
+) {
+    if depth > max_depth {
+        return;
+    }
+    let center = (node.aabb.min + node.aabb.max) * 0.5;
+    let half_extents = (node.aabb.max - node.aabb.min) * 0.5;
+    debug_draw.obb(
+        &(*mesh_world * Mat4::from_translation(center)),
+        half_extents,
+        depth_color(depth),
+    );
+    for child in [&node.left, &node.right].into_iter().flatten() {
+        draw_bvh(debug_draw, child, mesh_world, depth + 1, max_depth);
+    }
+}
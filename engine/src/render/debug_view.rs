@@ -0,0 +1,55 @@
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+/// What the forward passes show in place of lit colour. Materials whose shader has no
+/// `u_debug_view` uniform are drawn lit in every view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DebugView {
+    #[default]
+    Lit,
+    /// The shading normal, normal map included, with each axis mapped from -1..1 to 0..1.
+    WorldNormals,
+    /// Albedo texture coordinates as red and green, wrapped to 0..1.
+    Uv,
+    /// Tinted albedo, unlit.
+    Albedo,
+    Roughness,
+    /// Every surface covering a pixel adds heat to it, hidden ones included, from dark red
+    /// for a single layer through yellow to white.
+    Overdraw,
+}
+
+impl DebugView {
+    /// The value of the `u_debug_view` uniform.
+    pub(crate) fn shader_index(self) -> i32 {
+        match self {
+            DebugView::Lit => 0,
+            DebugView::WorldNormals => 1,
+            DebugView::Uv => 2,
+            DebugView::Albedo => 3,
+            DebugView::Roughness => 4,
+            DebugView::Overdraw => 5,
+        }
+    }
+}
+
+/// Views for inspecting meshes and materials, switchable at runtime. Views other than
+/// `DebugView::Lit` show their values as they are, so post-processing is skipped while
+/// one is on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderDebugSettings {
+    pub view: DebugView,
+    /// Draws triangle edges over the view, found from the meshes' `barycentric` attribute.
+    pub wireframe: bool,
+    pub wireframe_color: Vec3,
+}
+
+impl Default for RenderDebugSettings {
+    fn default() -> Self {
+        Self {
+            view: DebugView::Lit,
+            wireframe: false,
+            wireframe_color: Vec3::new(0.1, 1.0, 0.3),
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use glam::{Mat4, Vec3};
 
+use crate::assets::mesh::Aabb;
+
 #[derive(Debug, Clone, Copy)]
 pub struct FrustumPlane {
     pub normal: Vec3,
@@ -16,6 +18,14 @@ impl FrustumPlane {
         }
     }
 }
+/// Where a volume is relative to a `Frustum`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Containment {
+    Outside,
+    Intersecting,
+    Inside,
+}
+
 pub struct Frustum {
     pub planes: [FrustumPlane; 6],
 }
@@ -48,4 +58,22 @@ impl Frustum {
         }
         true
     }
+
+    /// Classifies `aabb` against every plane; an `Inside` box needs no further tests
+    /// for anything it contains.
+    pub fn classify_aabb(&self, aabb: &Aabb) -> Containment {
+        let mut containment = Containment::Inside;
+        for plane in &self.planes {
+            // The corners furthest along and against the plane's normal.
+            let positive = Vec3::select(plane.normal.cmpge(Vec3::ZERO), aabb.max, aabb.min);
+            let negative = Vec3::select(plane.normal.cmpge(Vec3::ZERO), aabb.min, aabb.max);
+            if plane.normal.dot(positive) + plane.distance < 0.0 {
+                return Containment::Outside;
+            }
+            if plane.normal.dot(negative) + plane.distance < 0.0 {
+                containment = Containment::Intersecting;
+            }
+        }
+        containment
+    }
 }
@@ -0,0 +1,271 @@
+use glam::Vec3;
+
+use crate::{
+    assets::shader::{Shader, UniformValue},
+    render::renderer_backends::GraphicsBackend,
+};
+
+/// Most directional lights a frame can use. Must match `MAX_DIRECTIONAL_LIGHTS` in `pbr.frag`.
+pub const MAX_DIRECTIONAL_LIGHTS: usize = 4;
+/// Most point lights a frame can use; the ones nearest the camera are kept.
+/// Must match `MAX_POINT_LIGHTS` in `pbr.frag`.
+pub const MAX_POINT_LIGHTS: usize = 32;
+/// Most spot lights a frame can use; the ones nearest the camera are kept.
+/// Must match `MAX_SPOT_LIGHTS` in `pbr.frag`.
+pub const MAX_SPOT_LIGHTS: usize = 16;
+
+/// Share of the directional lights' colour added everywhere as ambient light.
+const AMBIENT_FACTOR: f32 = 0.2;
+
+/// A `DirectionalLight` in the form the shaders use; `color` is premultiplied by intensity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DirectionalLightData {
+    pub direction: Vec3,
+    pub color: Vec3,
+}
+
+impl DirectionalLightData {
+    /// Used when a scene has no lights at all: a white sun straight overhead.
+    pub const DEFAULT_SUN: Self = Self {
+        direction: Vec3::new(0.0, 0.0, -1.0),
+        color: Vec3::ONE,
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointLightData {
+    pub position: Vec3,
+    pub color: Vec3,
+    pub range: f32,
+    pub falloff: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpotLightData {
+    pub position: Vec3,
+    pub direction: Vec3,
+    pub color: Vec3,
+    pub range: f32,
+    pub falloff: f32,
+    pub cos_inner: f32,
+    pub cos_outer: f32,
+}
+
+/// Every light in the scene for one frame, gathered by `RenderSystem::gather_lights`.
+#[derive(Debug, Default, Clone)]
+pub struct FrameLights {
+    pub directional: Vec<DirectionalLightData>,
+    pub point: Vec<PointLightData>,
+    pub spot: Vec<SpotLightData>,
+}
+
+impl FrameLights {
+    pub fn clear(&mut self) {
+        self.directional.clear();
+        self.point.clear();
+        self.spot.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.directional.is_empty() && self.point.is_empty() && self.spot.is_empty()
+    }
+
+    pub fn ambient_color(&self) -> Vec3 {
+        self.directional
+            .iter()
+            .map(|light| light.color)
+            .sum::<Vec3>()
+            * AMBIENT_FACTOR
+    }
+
+    /// Drops lights past the shader limits, keeping the point and spot lights nearest to
+    /// `camera_position` and the brightest directional lights. The brightest directional
+    /// light ends up first; it is the one that casts shadows.
+    pub(crate) fn limit_to_nearest(&mut self, camera_position: Vec3) {
+        self.directional.sort_by(|a, b| {
+            b.color
+                .length_squared()
+                .total_cmp(&a.color.length_squared())
+        });
+        self.directional.truncate(MAX_DIRECTIONAL_LIGHTS);
+        if self.point.len() > MAX_POINT_LIGHTS {
+            self.point.sort_unstable_by(|a, b| {
+                let a = a.position.distance_squared(camera_position);
+                let b = b.position.distance_squared(camera_position);
+                a.total_cmp(&b)
+            });
+            self.point.truncate(MAX_POINT_LIGHTS);
+        }
+        if self.spot.len() > MAX_SPOT_LIGHTS {
+            self.spot.sort_unstable_by(|a, b| {
+                let a = a.position.distance_squared(camera_position);
+                let b = b.position.distance_squared(camera_position);
+                a.total_cmp(&b)
+            });
+            self.spot.truncate(MAX_SPOT_LIGHTS);
+        }
+    }
+}
+
+const DIRECTIONAL_FIELDS: [&str; 2] = ["direction", "color"];
+const POINT_FIELDS: [&str; 4] = ["position", "color", "range", "falloff"];
+const SPOT_FIELDS: [&str; 7] = [
+    "position",
+    "direction",
+    "color",
+    "range",
+    "falloff",
+    "cos_inner",
+    "cos_outer",
+];
+
+/// Where one shader's light uniforms live, looked up once per shader since the names
+/// (`u_point_lights[3].range`, ...) are expensive to search for every frame.
+/// Shaders without lighting have no locations and bind nothing.
+pub(crate) struct LightUniformLocations {
+    ambient_color: Option<glow::UniformLocation>,
+    directional_count: Option<glow::UniformLocation>,
+    point_count: Option<glow::UniformLocation>,
+    spot_count: Option<glow::UniformLocation>,
+    directional: Vec<[Option<glow::UniformLocation>; 2]>,
+    point: Vec<[Option<glow::UniformLocation>; 4]>,
+    spot: Vec<[Option<glow::UniformLocation>; 7]>,
+}
+
+impl LightUniformLocations {
+    pub(crate) fn new(shader: &Shader) -> Self {
+        Self {
+            ambient_color: shader.get_uniform("u_ambient_color"),
+            directional_count: shader.get_uniform("u_directional_light_count"),
+            point_count: shader.get_uniform("u_point_light_count"),
+            spot_count: shader.get_uniform("u_spot_light_count"),
+            directional: array_locations(
+                shader,
+                "u_directional_lights",
+                &DIRECTIONAL_FIELDS,
+                MAX_DIRECTIONAL_LIGHTS,
+            ),
+            point: array_locations(shader, "u_point_lights", &POINT_FIELDS, MAX_POINT_LIGHTS),
+            spot: array_locations(shader, "u_spot_lights", &SPOT_FIELDS, MAX_SPOT_LIGHTS),
+        }
+    }
+
+    /// Sets the light uniforms of the program in use. Lights beyond the shader's array
+    /// lengths are left out of the counts.
+    pub(crate) fn bind<B: GraphicsBackend>(&self, backend: &mut B, lights: &FrameLights) {
+        let mut set = |location: &Option<glow::UniformLocation>, value: UniformValue| {
+            if let Some(location) = location {
+                backend.set_uniform(&(*location).into(), &value);
+            }
+        };
+
+        set(
+            &self.ambient_color,
+            UniformValue::Vec3(lights.ambient_color()),
+        );
+
+        let count = lights.directional.len().min(self.directional.len());
+        set(&self.directional_count, UniformValue::Int(count as i32));
+        for (light, [direction, color]) in lights.directional.iter().zip(&self.directional) {
+            set(direction, UniformValue::Vec3(light.direction));
+            set(color, UniformValue::Vec3(light.color));
+        }
+
+        let count = lights.point.len().min(self.point.len());
+        set(&self.point_count, UniformValue::Int(count as i32));
+        for (light, [position, color, range, falloff]) in lights.point.iter().zip(&self.point) {
+            set(position, UniformValue::Vec3(light.position));
+            set(color, UniformValue::Vec3(light.color));
+            set(range, UniformValue::Float(light.range));
+            set(falloff, UniformValue::Float(light.falloff));
+        }
+
+        let count = lights.spot.len().min(self.spot.len());
+        set(&self.spot_count, UniformValue::Int(count as i32));
+        for (light, locations) in lights.spot.iter().zip(&self.spot) {
+            let [
+                position,
+                direction,
+                color,
+                range,
+                falloff,
+                cos_inner,
+                cos_outer,
+            ] = locations;
+            set(position, UniformValue::Vec3(light.position));
+            set(direction, UniformValue::Vec3(light.direction));
+            set(color, UniformValue::Vec3(light.color));
+            set(range, UniformValue::Float(light.range));
+            set(falloff, UniformValue::Float(light.falloff));
+            set(cos_inner, UniformValue::Float(light.cos_inner));
+            set(cos_outer, UniformValue::Float(light.cos_outer));
+        }
+    }
+}
+
+/// Locations of `array[i].field` for each element the shader declares, up to `max`.
+fn array_locations<const N: usize>(
+    shader: &Shader,
+    array: &str,
+    fields: &[&str; N],
+    max: usize,
+) -> Vec<[Option<glow::UniformLocation>; N]> {
+    let mut elements = Vec::new();
+    for index in 0..max {
+        let element = fields.map(|field| shader.get_uniform(&format!("{array}[{index}].{field}")));
+        if element.iter().all(Option::is_none) {
+            break;
+        }
+        elements.push(element);
+    }
+    elements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point_at(x: f32) -> PointLightData {
+        PointLightData {
+            position: Vec3::new(x, 0.0, 0.0),
+            color: Vec3::ONE,
+            range: 10.0,
+            falloff: 2.0,
+        }
+    }
+
+    #[test]
+    fn keeps_the_point_lights_nearest_the_camera() {
+        let mut lights = FrameLights::default();
+        for i in (0..MAX_POINT_LIGHTS + 8).rev() {
+            lights.point.push(point_at(i as f32));
+        }
+
+        lights.limit_to_nearest(Vec3::new(-1.0, 0.0, 0.0));
+
+        assert_eq!(lights.point.len(), MAX_POINT_LIGHTS);
+        let farthest = lights
+            .point
+            .iter()
+            .map(|light| light.position.x)
+            .fold(f32::MIN, f32::max);
+        assert_eq!(farthest, (MAX_POINT_LIGHTS - 1) as f32);
+    }
+
+    #[test]
+    fn ambient_follows_the_directional_lights() {
+        let mut lights = FrameLights::default();
+        assert_eq!(lights.ambient_color(), Vec3::ZERO);
+
+        lights.directional.push(DirectionalLightData::DEFAULT_SUN);
+        lights.directional.push(DirectionalLightData {
+            direction: Vec3::NEG_Z,
+            color: Vec3::new(0.0, 0.0, 1.0),
+        });
+
+        assert_eq!(
+            lights.ambient_color(),
+            Vec3::new(1.0, 1.0, 2.0) * AMBIENT_FACTOR
+        );
+    }
+}
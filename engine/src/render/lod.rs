@@ -0,0 +1,210 @@
+use glam::{Mat4, Vec3, Vec4};
+
+use crate::assets::handles::MeshHandle;
+
+/// Coarser meshes a `RenderBodyPart` switches to as it shrinks on screen, e.g. a building
+/// drawn as a box from across the city.
+///
+/// Sizes are the diameter of the part's bounding sphere in pixels, measured on the mesh
+/// the part was created with. The renderer picks a level per instance while culling.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LodGroup {
+    /// Sorted by decreasing `max_pixels`.
+    levels: Vec<LodLevel>,
+    /// Parts smaller than this are not drawn at all; 0 always draws them.
+    pub cull_below_pixels: f32,
+    /// Width of the band above each threshold, as a fraction of it, over which the two
+    /// neighbouring levels are dithered into each other. 0 switches levels at once.
+    pub cross_fade: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LodLevel {
+    pub mesh_id: MeshHandle,
+    /// The level is used once the part is less than this many pixels across.
+    pub max_pixels: f32,
+}
+
+/// One mesh to draw for a part.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LodDraw {
+    pub mesh_id: MeshHandle,
+    /// See `RenderInstance::lod_fade`.
+    pub fade: f32,
+}
+
+impl LodGroup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a level drawing `mesh_id` below `max_pixels`.
+    pub fn with_level(mut self, mesh_id: MeshHandle, max_pixels: f32) -> Self {
+        let index = self
+            .levels
+            .partition_point(|level| level.max_pixels > max_pixels);
+        self.levels.insert(
+            index,
+            LodLevel {
+                mesh_id,
+                max_pixels,
+            },
+        );
+        self
+    }
+
+    /// Stops drawing the part once it is smaller than `pixels`.
+    pub fn with_cull_below(mut self, pixels: f32) -> Self {
+        self.cull_below_pixels = pixels;
+        self
+    }
+
+    /// Dithers levels into each other over `fraction` of each threshold.
+    pub fn with_cross_fade(mut self, fraction: f32) -> Self {
+        self.cross_fade = fraction.max(0.0);
+        self
+    }
+
+    pub fn levels(&self) -> &[LodLevel] {
+        &self.levels
+    }
+
+    /// The meshes to draw for a part `pixels` across whose full-detail mesh is `base`:
+    /// none once culled, two while cross-fading, one otherwise.
+    pub fn select(&self, base: MeshHandle, pixels: f32) -> [Option<LodDraw>; 2] {
+        let solid = |mesh_id| LodDraw { mesh_id, fade: 1.0 };
+        if pixels < self.cull_below_pixels {
+            return [None, None];
+        }
+
+        // Levels are sorted coarsest last, so the last one the part is small enough for
+        // wins.
+        let level = self
+            .levels
+            .iter()
+            .rposition(|level| pixels < level.max_pixels);
+        let mesh_id = level.map_or(base, |level| self.levels[level].mesh_id);
+
+        // Cross-fade towards the next coarser level, or towards nothing when culling.
+        let next = level.map_or(0, |level| level + 1);
+        let (threshold, coarser) = match self.levels.get(next) {
+            Some(level) => (level.max_pixels, Some(level.mesh_id)),
+            None => (self.cull_below_pixels, None),
+        };
+        let band = threshold * self.cross_fade;
+        if band <= 0.0 || pixels >= threshold + band {
+            return [Some(solid(mesh_id)), None];
+        }
+        let fade = (pixels - threshold) / band;
+        if fade <= 0.0 {
+            return [coarser.map(solid), None];
+        }
+        [
+            Some(LodDraw { mesh_id, fade }),
+            coarser.map(|mesh_id| LodDraw {
+                mesh_id,
+                fade: -fade,
+            }),
+        ]
+    }
+}
+
+/// Measures how large bounding spheres appear on screen through a camera.
+#[derive(Debug, Clone, Copy)]
+pub struct ScreenProjection {
+    /// Row of the view-projection matrix giving clip-space w, the distance in front of a
+    /// perspective camera.
+    w_row: Vec4,
+    /// Pixels a sphere of radius 1 covers at w = 1.
+    pixels_per_unit: f32,
+}
+
+impl ScreenProjection {
+    /// For a camera with `view_proj` drawing into a viewport `height` pixels tall.
+    pub fn new(view_proj: &Mat4, height: u32) -> Self {
+        // The view matrix doesn't scale, so the length of the projection's y row is the
+        // vertical focal length.
+        let focal_length = view_proj.row(1).truncate().length();
+        Self {
+            w_row: view_proj.row(3),
+            pixels_per_unit: focal_length * height as f32,
+        }
+    }
+
+    /// Diameter in pixels of a sphere around `center`; infinite when the camera is inside
+    /// or too close for it to fit on screen.
+    pub fn pixels(&self, center: Vec3, radius: f32) -> f32 {
+        let w = self.w_row.dot(center.extend(1.0));
+        if w <= radius {
+            return f32::INFINITY;
+        }
+        radius * self.pixels_per_unit / w
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use slotmap::SlotMap;
+
+    use super::*;
+
+    fn meshes() -> [MeshHandle; 3] {
+        let mut handles = SlotMap::<MeshHandle, ()>::with_key();
+        [handles.insert(()), handles.insert(()), handles.insert(())]
+    }
+
+    fn drawn(draws: [Option<LodDraw>; 2]) -> Vec<(MeshHandle, f32)> {
+        draws
+            .into_iter()
+            .flatten()
+            .map(|draw| (draw.mesh_id, draw.fade))
+            .collect()
+    }
+
+    #[test]
+    fn smaller_parts_use_coarser_levels_until_culled() {
+        let [full, half, box_] = meshes();
+        let lod = LodGroup::new()
+            .with_level(box_, 40.0)
+            .with_level(half, 200.0)
+            .with_cull_below(10.0);
+
+        assert_eq!(lod.levels()[0].mesh_id, half);
+        assert_eq!(drawn(lod.select(full, f32::INFINITY)), vec![(full, 1.0)]);
+        assert_eq!(drawn(lod.select(full, 200.0)), vec![(full, 1.0)]);
+        assert_eq!(drawn(lod.select(full, 199.0)), vec![(half, 1.0)]);
+        assert_eq!(drawn(lod.select(full, 39.0)), vec![(box_, 1.0)]);
+        assert_eq!(drawn(lod.select(full, 9.0)), vec![]);
+    }
+
+    #[test]
+    fn cross_fades_dither_neighbouring_levels() {
+        let [full, half, _] = meshes();
+        let lod = LodGroup::new()
+            .with_level(half, 100.0)
+            .with_cull_below(10.0)
+            .with_cross_fade(0.5);
+
+        // Halfway through the 100..150 band both are drawn with complementary patterns.
+        assert_eq!(
+            drawn(lod.select(full, 125.0)),
+            vec![(full, 0.5), (half, -0.5)]
+        );
+        assert_eq!(drawn(lod.select(full, 150.0)), vec![(full, 1.0)]);
+        assert_eq!(drawn(lod.select(full, 100.0)), vec![(half, 1.0)]);
+        // The last level fades out into the cull distance on its own.
+        assert_eq!(drawn(lod.select(full, 12.5)), vec![(half, 0.5)]);
+    }
+
+    #[test]
+    fn screen_size_shrinks_with_distance() {
+        let view = Mat4::look_at_rh(Vec3::new(0.0, 0.0, 10.0), Vec3::ZERO, Vec3::Y);
+        // A 90° vertical field of view has a focal length of 1.
+        let proj = Mat4::perspective_rh(90f32.to_radians(), 1.5, 0.1, 100.0);
+        let screen = ScreenProjection::new(&(proj * view), 400);
+
+        assert!((screen.pixels(Vec3::ZERO, 1.0) - 40.0).abs() < 1e-3);
+        assert!((screen.pixels(Vec3::new(3.0, 0.0, -10.0), 1.0) - 20.0).abs() < 1e-3);
+        assert_eq!(screen.pixels(Vec3::new(0.0, 0.0, 9.5), 1.0), f32::INFINITY);
+    }
+}
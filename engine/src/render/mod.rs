@@ -1,8 +1,18 @@
+pub mod debug_draw;
+pub mod debug_draw_system;
+pub mod debug_view;
 pub mod frustum;
+pub mod lights;
+pub mod lod;
+pub mod occlusion;
+pub mod post_process;
 pub mod render_body;
 pub mod render_body_resource;
+pub mod render_graph;
 pub mod render_instance;
 pub mod render_queue;
 pub mod render_system;
 pub mod renderer;
 pub mod renderer_backends;
+pub mod shadows;
+pub mod spatial_index;
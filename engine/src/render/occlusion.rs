@@ -0,0 +1,265 @@
+use glam::{Mat4, Vec3, Vec4};
+
+use crate::assets::mesh::{Aabb, Mesh};
+
+/// CPU occlusion culling: the meshes of `Occluder` entities are rasterized into a small
+/// depth buffer each frame, and instances whose bounding box is behind them at every
+/// pixel it covers are not drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OcclusionSettings {
+    pub enabled: bool,
+    /// Size of the depth buffer in pixels. A coarser buffer is faster to fill but lets
+    /// more hidden instances through.
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for OcclusionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            width: 256,
+            height: 128,
+        }
+    }
+}
+
+/// Depth buffer occluders are rasterized into, holding the NDC depth of the nearest
+/// occluder at each pixel's center. Rows go bottom-up like GL's.
+#[derive(Debug, Clone, Default)]
+pub struct OcclusionBuffer {
+    width: usize,
+    height: usize,
+    depth: Vec<f32>,
+}
+
+impl OcclusionBuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut buffer = Self::default();
+        buffer.resize(width, height);
+        buffer
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width as usize;
+        self.height = height as usize;
+        self.depth.resize(self.width * self.height, 1.0);
+    }
+
+    /// Empties the buffer back to the far plane.
+    pub fn clear(&mut self) {
+        self.depth.fill(1.0);
+    }
+
+    pub fn depth_at(&self, x: usize, y: usize) -> f32 {
+        self.depth[y * self.width + x]
+    }
+
+    /// Draws every triangle of `mesh`, placed by `mvp`, into the buffer. Triangles are
+    /// drawn whichever way they face.
+    pub fn rasterize_mesh(&mut self, mesh: &Mesh, mvp: &Mat4) {
+        for triangle in mesh.indices.chunks_exact(3) {
+            let corner = |index: u32| {
+                let position = Vec3::from(mesh.vertices[index as usize].position);
+                *mvp * position.extend(1.0)
+            };
+            self.rasterize_triangle([
+                corner(triangle[0]),
+                corner(triangle[1]),
+                corner(triangle[2]),
+            ]);
+        }
+    }
+
+    /// Draws a triangle given in clip space, cut off at the near plane.
+    pub fn rasterize_triangle(&mut self, clip: [Vec4; 3]) {
+        // Clipping a triangle by one plane leaves at most four corners.
+        let mut polygon = [Vec4::ZERO; 4];
+        let mut count = 0;
+        for i in 0..3 {
+            let (a, b) = (clip[i], clip[(i + 1) % 3]);
+            let (a_dist, b_dist) = (a.z + a.w, b.z + b.w);
+            if a_dist >= 0.0 {
+                polygon[count] = a;
+                count += 1;
+            }
+            if (a_dist >= 0.0) != (b_dist >= 0.0) {
+                polygon[count] = a + (b - a) * (a_dist / (a_dist - b_dist));
+                count += 1;
+            }
+        }
+        if count < 3 {
+            return;
+        }
+
+        let first = self.to_screen(polygon[0]);
+        for i in 1..count - 1 {
+            let second = self.to_screen(polygon[i]);
+            let third = self.to_screen(polygon[i + 1]);
+            self.fill(first, second, third);
+        }
+    }
+
+    /// Whether any part of `aabb`, placed by `mvp`, may be in front of the occluders.
+    /// Boxes crossing the near plane or off the buffer are always visible.
+    pub fn is_box_visible(&self, aabb: &Aabb, mvp: &Mat4) -> bool {
+        let mut min = Vec3::INFINITY;
+        let mut max = Vec3::NEG_INFINITY;
+        for i in 0..8 {
+            let corner = Vec3::new(
+                if i & 1 == 0 { aabb.min.x } else { aabb.max.x },
+                if i & 2 == 0 { aabb.min.y } else { aabb.max.y },
+                if i & 4 == 0 { aabb.min.z } else { aabb.max.z },
+            );
+            let clip = *mvp * corner.extend(1.0);
+            if clip.w <= f32::EPSILON || clip.z < -clip.w {
+                return true;
+            }
+            let screen = self.to_screen(clip);
+            min = min.min(screen);
+            max = max.max(screen);
+        }
+
+        let x_start = min.x.floor().max(0.0) as usize;
+        let x_end = (max.x.ceil().max(0.0) as usize).min(self.width);
+        let y_start = min.y.floor().max(0.0) as usize;
+        let y_end = (max.y.ceil().max(0.0) as usize).min(self.height);
+        if x_start >= x_end || y_start >= y_end {
+            return true;
+        }
+        (y_start..y_end).any(|y| {
+            self.depth[y * self.width + x_start..y * self.width + x_end]
+                .iter()
+                .any(|&depth| depth >= min.z)
+        })
+    }
+
+    /// Pixel coordinates and NDC depth of a clip-space point in front of the camera.
+    fn to_screen(&self, clip: Vec4) -> Vec3 {
+        let ndc = clip.truncate() / clip.w;
+        Vec3::new(
+            (ndc.x * 0.5 + 0.5) * self.width as f32,
+            (ndc.y * 0.5 + 0.5) * self.height as f32,
+            ndc.z,
+        )
+    }
+
+    /// Keeps the nearest depth at every pixel whose center is inside the triangle.
+    fn fill(&mut self, a: Vec3, b: Vec3, c: Vec3) {
+        let edge = |from: Vec3, to: Vec3, x: f32, y: f32| {
+            (to.x - from.x) * (y - from.y) - (to.y - from.y) * (x - from.x)
+        };
+        let area = edge(a, b, c.x, c.y);
+        if area.abs() <= f32::EPSILON {
+            return;
+        }
+
+        let min = a.min(b).min(c);
+        let max = a.max(b).max(c);
+        let x_start = min.x.floor().max(0.0) as usize;
+        let x_end = (max.x.ceil().max(0.0) as usize).min(self.width);
+        let y_start = min.y.floor().max(0.0) as usize;
+        let y_end = (max.y.ceil().max(0.0) as usize).min(self.height);
+
+        for y in y_start..y_end {
+            let center_y = y as f32 + 0.5;
+            for x in x_start..x_end {
+                let center_x = x as f32 + 0.5;
+                // Barycentric weights, positive inside whichever way the triangle winds.
+                let wa = edge(b, c, center_x, center_y) / area;
+                let wb = edge(c, a, center_x, center_y) / area;
+                let wc = edge(a, b, center_x, center_y) / area;
+                if wa < 0.0 || wb < 0.0 || wc < 0.0 {
+                    continue;
+                }
+                let depth = wa * a.z + wb * b.z + wc * c.z;
+                let stored = &mut self.depth[y * self.width + x];
+                if depth < *stored {
+                    *stored = depth;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::mesh::Vertex;
+
+    fn view_proj() -> Mat4 {
+        let view = Mat4::look_at_rh(Vec3::new(0.0, 0.0, 10.0), Vec3::ZERO, Vec3::Y);
+        let proj = Mat4::perspective_rh(90f32.to_radians(), 2.0, 0.1, 100.0);
+        proj * view
+    }
+
+    /// A square wall facing the camera.
+    fn wall(half_extent: f32) -> Mesh {
+        let vertex = |x: f32, y: f32| Vertex {
+            position: [x * half_extent, y * half_extent, 0.0],
+            ..Default::default()
+        };
+        Mesh {
+            vertices: vec![
+                vertex(-1.0, -1.0),
+                vertex(1.0, -1.0),
+                vertex(1.0, 1.0),
+                vertex(-1.0, 1.0),
+            ],
+            indices: vec![0, 1, 2, 0, 2, 3],
+            ..Default::default()
+        }
+    }
+
+    fn cube(center: Vec3, half_extent: f32) -> Aabb {
+        Aabb {
+            min: center - Vec3::splat(half_extent),
+            max: center + Vec3::splat(half_extent),
+        }
+    }
+
+    #[test]
+    fn walls_hide_boxes_entirely_behind_them() {
+        let mut buffer = OcclusionBuffer::new(64, 32);
+        buffer.rasterize_mesh(&wall(4.0), &view_proj());
+
+        // The wall covers the middle of the buffer and leaves the corners empty.
+        assert!(buffer.depth_at(32, 16) < 1.0);
+        assert_eq!(buffer.depth_at(0, 0), 1.0);
+
+        let behind = Mat4::from_translation(Vec3::new(0.0, 0.0, -10.0));
+        assert!(!buffer.is_box_visible(&cube(Vec3::ZERO, 1.0), &(view_proj() * behind)));
+        // In front of the wall, poking out beside it, or too big to hide.
+        assert!(buffer.is_box_visible(&cube(Vec3::new(0.0, 0.0, 2.0), 1.0), &view_proj()));
+        assert!(buffer.is_box_visible(&cube(Vec3::new(5.0, 0.0, -10.0), 2.0), &view_proj()));
+        assert!(buffer.is_box_visible(&cube(Vec3::new(0.0, 0.0, -10.0), 8.0), &view_proj()));
+    }
+
+    #[test]
+    fn triangles_crossing_the_near_plane_are_clipped() {
+        let mut buffer = OcclusionBuffer::new(64, 32);
+        // A floor running from behind the camera into the distance.
+        let floor = Mesh {
+            vertices: [[-5.0, -2.0, 20.0], [5.0, -2.0, 20.0], [0.0, -2.0, -50.0]]
+                .into_iter()
+                .map(|position| Vertex {
+                    position,
+                    ..Default::default()
+                })
+                .collect(),
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        };
+        buffer.rasterize_mesh(&floor, &view_proj());
+
+        // The floor fills the bottom of the view and nothing above the horizon.
+        assert!(buffer.depth_at(32, 0) < 1.0);
+        assert_eq!(buffer.depth_at(32, 31), 1.0);
+        assert!(buffer.depth_at(32, 0) < buffer.depth_at(32, 14));
+
+        // Boxes reaching behind the camera can't be judged.
+        buffer.clear();
+        buffer.rasterize_mesh(&wall(50.0), &view_proj());
+        assert!(buffer.is_box_visible(&cube(Vec3::new(0.0, 0.0, 10.0), 1.0), &view_proj()));
+    }
+}
@@ -0,0 +1,370 @@
+use std::ffi::OsStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::{
+        handles::{ShaderHandle, TextureHandle},
+        shader::UniformValue,
+        shader_resource::ShaderStorage,
+        texture_resource::TextureStorage,
+    },
+    render::{
+        render_graph::ColorTargetPool, renderer::Renderer, renderer_backends::GraphicsBackend,
+    },
+};
+
+/// Turns HDR colour into displayable colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Tonemapper {
+    /// Narkowicz's fit of the ACES filmic curve.
+    #[default]
+    Aces,
+    Reinhard,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BloomSettings {
+    pub enabled: bool,
+    /// Brightness above which colour bleeds into its surroundings.
+    pub threshold: f32,
+    /// How much of the blurred bright colour is added back.
+    pub intensity: f32,
+    /// Horizontal and vertical blur rounds; more spread the glow further.
+    pub blur_passes: u32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold: 1.0,
+            intensity: 0.1,
+            blur_passes: 3,
+        }
+    }
+}
+
+/// With post-processing enabled the scene is lit into an HDR colour target, and the
+/// enabled effects of the renderer's stack turn it into the frame, each reading the
+/// previous one's output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PostProcessSettings {
+    pub enabled: bool,
+    /// Multiplies the HDR colour before tone mapping.
+    pub exposure: f32,
+    pub tonemapper: Tonemapper,
+    pub bloom: BloomSettings,
+    /// Lookup table tone-mapped colour is graded through: a strip of N slices of N×N
+    /// texels, with red across each slice, green down it and blue from slice to slice.
+    /// `None` leaves colour as it is.
+    pub color_grading_lut: Option<TextureHandle>,
+    pub fxaa: bool,
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            exposure: 1.0,
+            tonemapper: Tonemapper::Aces,
+            bloom: BloomSettings::default(),
+            color_grading_lut: None,
+            fxaa: true,
+        }
+    }
+}
+
+/// One full-screen step of the post-process stack.
+pub trait PostProcessEffect<B: GraphicsBackend> {
+    fn name(&self) -> &str;
+    /// Whether the effect runs this frame; disabled effects are skipped entirely.
+    fn is_enabled(&self, settings: &PostProcessSettings) -> bool;
+    /// Draws the effect from `pass.input()` into `pass.output()`.
+    fn apply(&mut self, pass: &mut PostProcessPass<'_, B>);
+}
+
+/// What a `PostProcessEffect` draws with.
+pub struct PostProcessPass<'a, B: GraphicsBackend> {
+    pub(crate) backend: &'a mut B,
+    pub(crate) shaders: &'a ShaderStorage,
+    pub(crate) textures: &'a TextureStorage,
+    pub(crate) targets: &'a mut ColorTargetPool<B::ColorTarget>,
+    pub(crate) settings: &'a PostProcessSettings,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) input: B::ColorTarget,
+    pub(crate) output: Option<B::ColorTarget>,
+}
+
+impl<B: GraphicsBackend> PostProcessPass<'_, B> {
+    pub fn settings(&self) -> &PostProcessSettings {
+        self.settings
+    }
+
+    /// Size of the frame in pixels.
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The colour the effect reads.
+    pub fn input(&self) -> B::ColorTarget {
+        self.input
+    }
+
+    /// Where the effect writes; `None` is the frame's own target.
+    pub fn output(&self) -> Option<B::ColorTarget> {
+        self.output
+    }
+
+    /// A colour target for the effect's intermediate results, kept from frame to frame
+    /// while its size stays the same. Effects should prefix `name` with their own.
+    pub fn scratch_target(
+        &mut self,
+        name: &'static str,
+        width: u32,
+        height: u32,
+    ) -> B::ColorTarget {
+        self.targets.get(self.backend, name, width, height, false)
+    }
+
+    /// Draws a full-screen triangle with `shader` into `target`. Each input is bound to
+    /// the sampler uniform of its name, on texture units from 0 up, so texture uniforms
+    /// should use units after them.
+    pub fn draw(
+        &mut self,
+        shader: ShaderHandle,
+        target: Option<B::ColorTarget>,
+        inputs: &[(&str, B::ColorTarget)],
+        uniforms: &[(&str, UniformValue)],
+    ) {
+        self.backend.bind_color_target(target);
+        draw_fullscreen(
+            self.backend,
+            self.shaders,
+            self.textures,
+            shader,
+            inputs,
+            uniforms,
+        );
+    }
+}
+
+/// Draws a full-screen triangle with `shader` into the bound target, with each input
+/// bound to the sampler uniform of its name on texture units from 0 up.
+pub(crate) fn draw_fullscreen<B: GraphicsBackend>(
+    backend: &mut B,
+    shaders: &ShaderStorage,
+    textures: &TextureStorage,
+    shader: ShaderHandle,
+    inputs: &[(&str, B::ColorTarget)],
+    uniforms: &[(&str, UniformValue)],
+) {
+    let Some(shader) = shaders.get_shader(shader) else {
+        return;
+    };
+    backend.use_program(shader.program.map(Into::into));
+    for (unit, &(name, input)) in inputs.iter().enumerate() {
+        backend.bind_color_texture(unit as u32, Some(input));
+        if let Some(loc) = shader.get_uniform(name) {
+            backend.set_uniform(&loc.into(), &UniformValue::Int(unit as i32));
+        }
+    }
+    for (name, value) in uniforms {
+        if let Some(loc) = shader.get_uniform(name) {
+            Renderer::<B>::bind_uniform(backend, &loc, value, textures);
+        }
+    }
+
+    backend.draw_fullscreen_triangle();
+
+    for unit in 0..inputs.len() {
+        backend.bind_color_texture(unit as u32, None);
+    }
+    for (_, value) in uniforms {
+        if let UniformValue::Texture { unit, .. } = value {
+            backend.bind_texture(*unit, None);
+        }
+    }
+}
+
+/// Bright colour blurred at half resolution and added back on top.
+pub struct Bloom {
+    pub extract_shader: ShaderHandle,
+    pub blur_shader: ShaderHandle,
+    pub composite_shader: ShaderHandle,
+}
+
+impl<B: GraphicsBackend> PostProcessEffect<B> for Bloom {
+    fn name(&self) -> &str {
+        "bloom"
+    }
+
+    fn is_enabled(&self, settings: &PostProcessSettings) -> bool {
+        settings.bloom.enabled
+    }
+
+    fn apply(&mut self, pass: &mut PostProcessPass<'_, B>) {
+        let settings = pass.settings().bloom;
+        let (width, height) = pass.size();
+        let (half_width, half_height) = ((width / 2).max(1), (height / 2).max(1));
+        let bright = pass.scratch_target("bloom_bright", half_width, half_height);
+        let blurred = pass.scratch_target("bloom_blurred", half_width, half_height);
+        let input = pass.input();
+
+        pass.draw(
+            self.extract_shader,
+            Some(bright),
+            &[("u_source", input)],
+            &[("u_threshold", UniformValue::Float(settings.threshold))],
+        );
+        for _ in 0..settings.blur_passes {
+            pass.draw(
+                self.blur_shader,
+                Some(blurred),
+                &[("u_source", bright)],
+                &[("u_horizontal", UniformValue::Int(1))],
+            );
+            pass.draw(
+                self.blur_shader,
+                Some(bright),
+                &[("u_source", blurred)],
+                &[("u_horizontal", UniformValue::Int(0))],
+            );
+        }
+        pass.draw(
+            self.composite_shader,
+            pass.output(),
+            &[("u_scene", input), ("u_bloom", bright)],
+            &[("u_intensity", UniformValue::Float(settings.intensity))],
+        );
+    }
+}
+
+/// Exposure and the tone curve, from HDR to displayable colour. Always enabled.
+pub struct ToneMapping {
+    pub shader: ShaderHandle,
+}
+
+impl<B: GraphicsBackend> PostProcessEffect<B> for ToneMapping {
+    fn name(&self) -> &str {
+        "tone_mapping"
+    }
+
+    fn is_enabled(&self, _settings: &PostProcessSettings) -> bool {
+        true
+    }
+
+    fn apply(&mut self, pass: &mut PostProcessPass<'_, B>) {
+        let settings = *pass.settings();
+        let tonemapper = match settings.tonemapper {
+            Tonemapper::Aces => 0,
+            Tonemapper::Reinhard => 1,
+        };
+        let input = pass.input();
+        pass.draw(
+            self.shader,
+            pass.output(),
+            &[("u_source", input)],
+            &[
+                ("u_exposure", UniformValue::Float(settings.exposure)),
+                ("u_tonemapper", UniformValue::Int(tonemapper)),
+            ],
+        );
+    }
+}
+
+/// Grades colour through `PostProcessSettings::color_grading_lut`.
+pub struct ColorGrading {
+    pub shader: ShaderHandle,
+}
+
+impl<B: GraphicsBackend> PostProcessEffect<B> for ColorGrading {
+    fn name(&self) -> &str {
+        "color_grading"
+    }
+
+    fn is_enabled(&self, settings: &PostProcessSettings) -> bool {
+        settings.color_grading_lut.is_some()
+    }
+
+    fn apply(&mut self, pass: &mut PostProcessPass<'_, B>) {
+        let Some(lut) = pass.settings().color_grading_lut else {
+            return;
+        };
+        let input = pass.input();
+        pass.draw(
+            self.shader,
+            pass.output(),
+            &[("u_source", input)],
+            &[(
+                "u_lut",
+                UniformValue::Texture {
+                    handle: lut,
+                    unit: 1,
+                },
+            )],
+        );
+    }
+}
+
+/// Fast approximate anti-aliasing, smoothing edges found by their contrast.
+pub struct Fxaa {
+    pub shader: ShaderHandle,
+}
+
+impl<B: GraphicsBackend> PostProcessEffect<B> for Fxaa {
+    fn name(&self) -> &str {
+        "fxaa"
+    }
+
+    fn is_enabled(&self, settings: &PostProcessSettings) -> bool {
+        settings.fxaa
+    }
+
+    fn apply(&mut self, pass: &mut PostProcessPass<'_, B>) {
+        let input = pass.input();
+        pass.draw(self.shader, pass.output(), &[("u_source", input)], &[]);
+    }
+}
+
+/// The built-in stack: bloom, tone mapping, colour grading and FXAA, in that order.
+/// Effects whose shaders fail to load are logged and left out.
+pub fn builtin_effects<B: GraphicsBackend>(
+    shaders: &mut ShaderStorage,
+    gl: &glow::Context,
+) -> Vec<Box<dyn PostProcessEffect<B>>> {
+    let mut load = |fragment: &str| {
+        shaders
+            .get_or_load(
+                Some(gl),
+                OsStr::new("resources/shaders/fullscreen.vert"),
+                OsStr::new(fragment),
+            )
+            .inspect_err(|e| log::error!("{}; the post-process effect is disabled", e))
+            .ok()
+    };
+
+    let mut effects: Vec<Box<dyn PostProcessEffect<B>>> = Vec::new();
+    if let (Some(extract_shader), Some(blur_shader), Some(composite_shader)) = (
+        load("resources/shaders/post_bloom_extract.frag"),
+        load("resources/shaders/post_blur.frag"),
+        load("resources/shaders/post_bloom_composite.frag"),
+    ) {
+        effects.push(Box::new(Bloom {
+            extract_shader,
+            blur_shader,
+            composite_shader,
+        }));
+    }
+    if let Some(shader) = load("resources/shaders/post_tonemap.frag") {
+        effects.push(Box::new(ToneMapping { shader }));
+    }
+    if let Some(shader) = load("resources/shaders/post_color_grading.frag") {
+        effects.push(Box::new(ColorGrading { shader }));
+    }
+    if let Some(shader) = load("resources/shaders/post_fxaa.frag") {
+        effects.push(Box::new(Fxaa { shader }));
+    }
+    effects
+}
@@ -1,21 +1,70 @@
+use std::sync::Arc;
+
 use glam::Mat4;
 
-use crate::assets::handles::{MaterialHandle, MeshHandle};
+use crate::{
+    animation::{clip::AnimationClip, skeleton::ModelHierarchy},
+    assets::handles::{MaterialHandle, MeshHandle},
+    render::lod::LodGroup,
+};
 
 #[derive(Clone)]
 pub struct RenderBodyPart {
     pub mesh_id: MeshHandle,
     pub material_id: MaterialHandle,
     pub local_transform: Mat4,
+    /// The glTF node or OBJ object the part came from, if it had a name.
+    pub name: Option<String>,
+    /// Index into `ModelHierarchy::nodes` of the node placing this part. On an entity
+    /// with an `AnimationPlayer` the node's animated matrix replaces `local_transform`.
+    pub node: Option<usize>,
+    /// Index into `ModelHierarchy::skins` of a skinned part. Its vertices are placed by
+    /// the skin's joints, and `local_transform` is the identity.
+    pub skin: Option<usize>,
+    /// Coarser versions of `mesh_id` to draw when the part is small on screen, shared by
+    /// every instance of the part.
+    pub lod: Option<Arc<LodGroup>>,
 }
 
 #[derive(Clone)]
 pub struct RenderBody {
     pub parts: Vec<RenderBodyPart>,
+    pub hierarchy: ModelHierarchy,
+    /// Clips an `AnimationPlayer` on an entity showing this body can play.
+    pub animations: Vec<AnimationClip>,
 }
 
 impl RenderBody {
     pub fn new(parts: Vec<RenderBodyPart>) -> Self {
-        Self { parts }
+        Self {
+            parts,
+            hierarchy: ModelHierarchy::default(),
+            animations: Vec::new(),
+        }
+    }
+
+    /// The parts placed by the node called `name`; a node whose mesh has several
+    /// primitives yields one part per primitive.
+    pub fn parts_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a RenderBodyPart> {
+        self.parts
+            .iter()
+            .filter(move |part| part.name.as_deref() == Some(name))
+    }
+
+    /// Stops drawing each part once it is smaller on screen than `pixels`, keeping the
+    /// levels of detail the parts already have.
+    pub fn set_cull_below_pixels(&mut self, pixels: f32) {
+        for part in &mut self.parts {
+            let mut lod = part.lod.as_deref().cloned().unwrap_or_default();
+            lod.cull_below_pixels = pixels;
+            part.lod = Some(Arc::new(lod));
+        }
+    }
+
+    /// Index of the first animation called `name`, for `AnimationPlayer::play`.
+    pub fn animation_index(&self, name: &str) -> Option<usize> {
+        self.animations
+            .iter()
+            .position(|clip| clip.name.as_deref() == Some(name))
     }
 }
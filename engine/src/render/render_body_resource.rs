@@ -1,13 +1,22 @@
 use std::sync::{Arc, RwLock};
 
 use bevy_ecs::prelude::*;
-use slotmap::SlotMap;
+use slotmap::{SecondaryMap, SlotMap};
 
-use crate::{RenderBodyHandle, render::render_body::RenderBody};
+use crate::{
+    RenderBodyHandle,
+    assets::{asset_registry::AssetRegistry, async_loader::LoadState},
+    render::render_body::RenderBody,
+};
 
 #[derive(Default)]
 pub struct RenderBodyStorage {
     pub render_bodies: SlotMap<RenderBodyHandle, RenderBody>,
+    pub paths: AssetRegistry<RenderBodyHandle>,
+    /// Bodies from `Engine::load_model_async` that are still loading or failed to load.
+    pub(crate) load_states: SecondaryMap<RenderBodyHandle, LoadState>,
+    /// Bumped whenever a body may change or disappear in place.
+    revision: u64,
 }
 
 #[derive(Resource, Default, Clone)]
@@ -43,16 +52,39 @@ impl RenderBodyStorage {
         self.render_bodies.get(render_body_id)
     }
 
-    #[allow(dead_code)]
+    /// Entities already showing the body are queued again with its changes.
     pub fn get_render_body_mut(
         &mut self,
         render_body_id: RenderBodyHandle,
     ) -> Option<&mut RenderBody> {
+        self.revision += 1;
         self.render_bodies.get_mut(render_body_id)
     }
 
+    /// Changes whenever a body may have been changed through `get_render_body_mut` or
+    /// removed, so the render queue knows to rebuild the instances it kept.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// `None` if the handle is not valid; bodies that were never loading are `Loaded`.
+    pub fn load_state(&self, render_body_id: RenderBodyHandle) -> Option<LoadState> {
+        if !self.render_bodies.contains_key(render_body_id) {
+            return None;
+        }
+        Some(
+            self.load_states
+                .get(render_body_id)
+                .cloned()
+                .unwrap_or(LoadState::Loaded),
+        )
+    }
+
     #[allow(dead_code)]
     pub fn remove_render_body(&mut self, render_body_id: RenderBodyHandle) {
+        self.revision += 1;
         self.render_bodies.remove(render_body_id);
+        self.paths.remove(render_body_id);
+        self.load_states.remove(render_body_id);
     }
 }
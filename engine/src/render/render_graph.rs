@@ -0,0 +1,501 @@
+use thiserror::Error;
+
+use crate::{
+    assets::{
+        handles::ShaderHandle, material_resource::MaterialStorage, mesh_resource::MeshStorage,
+        shader::UniformValue, shader_resource::ShaderStorage, texture_resource::TextureStorage,
+    },
+    render::{
+        post_process::draw_fullscreen, renderer::CameraRenderData,
+        renderer_backends::GraphicsBackend,
+    },
+};
+
+/// Depth of the directional light's shadow cascades, written by the shadow pass.
+pub const SHADOW_MAP_TARGET: &str = "shadow_map";
+/// The lit scene: the HDR target while post-processing, otherwise the frame itself.
+pub const SCENE_COLOR_TARGET: &str = "scene_color";
+/// What ends up in the window, or in the image of `render_to_image`.
+pub const FRAME_TARGET: &str = "frame";
+
+/// A step of the frame in the render graph. Passes name the targets they read and
+/// write, and the graph orders them so that:
+///
+/// - each pass writing a target runs after the passes registered before it that write
+///   it too, so later passes draw over earlier ones;
+/// - a pass reading a target it doesn't write runs after every pass writing it.
+///
+/// Besides the renderer's own targets, any name a pass writes is a colour target of the
+/// frame's size, cleared before the first pass writing it runs. A pass must leave blending
+/// and the bound program as it found them.
+pub trait RenderPass<B: GraphicsBackend> {
+    /// Unique within the graph.
+    fn name(&self) -> &str;
+    fn inputs(&self) -> &[&str];
+    /// The first output is bound for the pass to draw into. The shadow map can only be
+    /// named to order passes; nothing is bound for it.
+    fn outputs(&self) -> &[&str];
+    fn execute(&mut self, context: &mut RenderPassContext<'_, B>);
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum RenderGraphError {
+    #[error("A render pass named {0} is already in the graph")]
+    DuplicatePass(String),
+    #[error("Render passes {0:?} wait on each other's targets")]
+    Cycle(Vec<String>),
+    #[error("Render pass {0} draws through GL, which a headless engine doesn't have")]
+    Headless(String),
+}
+
+/// What a `RenderPass` draws with.
+pub struct RenderPassContext<'a, B: GraphicsBackend> {
+    pub(crate) backend: &'a mut B,
+    pub(crate) meshes: &'a MeshStorage,
+    pub(crate) materials: &'a MaterialStorage,
+    pub(crate) textures: &'a TextureStorage,
+    pub(crate) shaders: &'a ShaderStorage,
+    pub(crate) camera: &'a CameraRenderData,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// The HDR scene target while post-processing.
+    pub(crate) scene_target: Option<B::ColorTarget>,
+    pub(crate) targets: &'a ColorTargetPool<B::ColorTarget>,
+}
+
+impl<B: GraphicsBackend> RenderPassContext<'_, B> {
+    /// The pass's first output is already bound.
+    pub fn backend(&mut self) -> &mut B {
+        self.backend
+    }
+
+    pub fn camera(&self) -> &CameraRenderData {
+        self.camera
+    }
+
+    /// Size of the frame in pixels.
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn meshes(&self) -> &MeshStorage {
+        self.meshes
+    }
+
+    pub fn materials(&self) -> &MaterialStorage {
+        self.materials
+    }
+
+    pub fn textures(&self) -> &TextureStorage {
+        self.textures
+    }
+
+    pub fn shaders(&self) -> &ShaderStorage {
+        self.shaders
+    }
+
+    /// The colour target of `name` to sample. `None` for the frame, the shadow map, the
+    /// scene colour without post-processing and targets no pass has written yet.
+    pub fn target(&self, name: &str) -> Option<B::ColorTarget> {
+        match name {
+            SCENE_COLOR_TARGET => self.scene_target,
+            SHADOW_MAP_TARGET | FRAME_TARGET => None,
+            _ => self.targets.find(name),
+        }
+    }
+
+    /// Draws a full-screen triangle with `shader` into the pass's output. Each input is
+    /// bound to the sampler uniform of its name, on texture units from 0 up, so texture
+    /// uniforms should use units after them.
+    pub fn draw_fullscreen(
+        &mut self,
+        shader: ShaderHandle,
+        inputs: &[(&str, B::ColorTarget)],
+        uniforms: &[(&str, UniformValue)],
+    ) {
+        draw_fullscreen(
+            self.backend,
+            self.shaders,
+            self.textures,
+            shader,
+            inputs,
+            uniforms,
+        );
+    }
+}
+
+/// The renderer's own passes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BuiltinPass {
+    Shadows,
+    Opaque,
+    Translucent,
+    PostProcess,
+    /// `DebugDraw` shapes, drawn over the finished frame.
+    DebugLines,
+}
+
+impl BuiltinPass {
+    fn name(self) -> &'static str {
+        match self {
+            BuiltinPass::Shadows => "shadows",
+            BuiltinPass::Opaque => "forward_opaque",
+            BuiltinPass::Translucent => "forward_translucent",
+            BuiltinPass::PostProcess => "post_process",
+            BuiltinPass::DebugLines => "debug_lines",
+        }
+    }
+
+    fn inputs(self) -> &'static [&'static str] {
+        match self {
+            BuiltinPass::Shadows | BuiltinPass::DebugLines => &[],
+            BuiltinPass::Opaque | BuiltinPass::Translucent => &[SHADOW_MAP_TARGET],
+            BuiltinPass::PostProcess => &[SCENE_COLOR_TARGET],
+        }
+    }
+
+    fn outputs(self) -> &'static [&'static str] {
+        match self {
+            BuiltinPass::Shadows => &[SHADOW_MAP_TARGET],
+            BuiltinPass::Opaque | BuiltinPass::Translucent => &[SCENE_COLOR_TARGET],
+            BuiltinPass::PostProcess | BuiltinPass::DebugLines => &[FRAME_TARGET],
+        }
+    }
+}
+
+pub(crate) enum GraphNode<B: GraphicsBackend> {
+    Builtin(BuiltinPass),
+    Custom(Box<dyn RenderPass<B>>),
+}
+
+impl<B: GraphicsBackend> GraphNode<B> {
+    fn name(&self) -> &str {
+        match self {
+            GraphNode::Builtin(pass) => pass.name(),
+            GraphNode::Custom(pass) => pass.name(),
+        }
+    }
+
+    fn inputs(&self) -> &[&str] {
+        match self {
+            GraphNode::Builtin(pass) => pass.inputs(),
+            GraphNode::Custom(pass) => pass.inputs(),
+        }
+    }
+
+    fn outputs(&self) -> &[&str] {
+        match self {
+            GraphNode::Builtin(pass) => pass.outputs(),
+            GraphNode::Custom(pass) => pass.outputs(),
+        }
+    }
+}
+
+/// The passes of a frame and the order they run in, which is worked out again whenever
+/// a pass is added or removed.
+pub(crate) struct RenderGraph<B: GraphicsBackend> {
+    pub(crate) nodes: Vec<GraphNode<B>>,
+    /// Indices into `nodes`, in the order they run.
+    pub(crate) order: Vec<usize>,
+    /// Per node, whether it is the first in `order` to write its first output, which is
+    /// then cleared before it runs.
+    pub(crate) clears_output: Vec<bool>,
+}
+
+impl<B: GraphicsBackend> Default for RenderGraph<B> {
+    fn default() -> Self {
+        let nodes = [
+            BuiltinPass::Shadows,
+            BuiltinPass::Opaque,
+            BuiltinPass::Translucent,
+            BuiltinPass::PostProcess,
+            BuiltinPass::DebugLines,
+        ]
+        .into_iter()
+        .map(GraphNode::Builtin)
+        .collect();
+        let mut graph = Self {
+            nodes,
+            order: Vec::new(),
+            clears_output: Vec::new(),
+        };
+        graph.sort().expect("the built-in passes are acyclic");
+        graph
+    }
+}
+
+impl<B: GraphicsBackend> RenderGraph<B> {
+    /// Adds a pass, unless its name is taken or its targets would make passes wait on
+    /// each other.
+    pub(crate) fn add(&mut self, pass: Box<dyn RenderPass<B>>) -> Result<(), RenderGraphError> {
+        if self.nodes.iter().any(|node| node.name() == pass.name()) {
+            return Err(RenderGraphError::DuplicatePass(pass.name().to_string()));
+        }
+        self.nodes.push(GraphNode::Custom(pass));
+        if let Err(e) = self.sort() {
+            self.nodes.pop();
+            self.sort().expect("the graph was acyclic before");
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Removes a pass added with `add`; the renderer's own passes stay.
+    pub(crate) fn remove(&mut self, name: &str) -> Option<Box<dyn RenderPass<B>>> {
+        let index = self
+            .nodes
+            .iter()
+            .position(|node| matches!(node, GraphNode::Custom(_)) && node.name() == name)?;
+        let GraphNode::Custom(pass) = self.nodes.remove(index) else {
+            unreachable!();
+        };
+        self.sort()
+            .expect("removing a pass keeps the graph acyclic");
+        Some(pass)
+    }
+
+    pub(crate) fn names_in_order(&self) -> Vec<&str> {
+        self.order
+            .iter()
+            .map(|&index| self.nodes[index].name())
+            .collect()
+    }
+
+    /// Orders the passes with Kahn's algorithm, taking the earliest registered pass of
+    /// those ready each time, so independent passes keep the order they were added in.
+    fn sort(&mut self) -> Result<(), RenderGraphError> {
+        let nodes = &self.nodes;
+        let writes = |index: usize, target: &str| nodes[index].outputs().contains(&target);
+
+        let mut successors = vec![Vec::new(); nodes.len()];
+        let mut blockers = vec![0; nodes.len()];
+        let mut add_edge = |from: usize, to: usize| {
+            if from != to && !successors[from].contains(&to) {
+                successors[from].push(to);
+                blockers[to] += 1;
+            }
+        };
+        for (index, node) in nodes.iter().enumerate() {
+            for &target in node.outputs() {
+                if let Some(previous) = (0..index).rev().find(|&i| writes(i, target)) {
+                    add_edge(previous, index);
+                }
+            }
+            for &target in node.inputs() {
+                if node.outputs().contains(&target) {
+                    continue;
+                }
+                if let Some(last) = (0..nodes.len()).rev().find(|&i| writes(i, target)) {
+                    add_edge(last, index);
+                }
+            }
+        }
+
+        let mut order = Vec::with_capacity(nodes.len());
+        let mut done = vec![false; nodes.len()];
+        while order.len() < nodes.len() {
+            let Some(next) = (0..nodes.len()).find(|&i| !done[i] && blockers[i] == 0) else {
+                let waiting = (0..nodes.len())
+                    .filter(|&i| !done[i])
+                    .map(|i| nodes[i].name().to_string())
+                    .collect();
+                return Err(RenderGraphError::Cycle(waiting));
+            };
+            done[next] = true;
+            order.push(next);
+            for &successor in &successors[next] {
+                blockers[successor] -= 1;
+            }
+        }
+
+        let mut clears_output = vec![false; nodes.len()];
+        let mut written: Vec<&str> = Vec::new();
+        for &index in &order {
+            if let Some(&target) = nodes[index].outputs().first()
+                && !written.contains(&target)
+            {
+                clears_output[index] = true;
+                written.push(target);
+            }
+        }
+
+        self.order = order;
+        self.clears_output = clears_output;
+        Ok(())
+    }
+}
+
+/// Colour targets by name, recreated when their size changes.
+pub(crate) struct ColorTargetPool<T> {
+    targets: Vec<NamedTarget<T>>,
+}
+
+struct NamedTarget<T> {
+    name: String,
+    target: T,
+    width: u32,
+    height: u32,
+}
+
+impl<T> Default for ColorTargetPool<T> {
+    fn default() -> Self {
+        Self {
+            targets: Vec::new(),
+        }
+    }
+}
+
+impl<T: Copy> ColorTargetPool<T> {
+    pub(crate) fn get<B: GraphicsBackend<ColorTarget = T>>(
+        &mut self,
+        backend: &mut B,
+        name: &str,
+        width: u32,
+        height: u32,
+        depth: bool,
+    ) -> T {
+        if let Some(existing) = self.targets.iter_mut().find(|t| t.name == name) {
+            if existing.width != width || existing.height != height {
+                backend.delete_color_target(existing.target);
+                existing.target = backend.create_color_target(width, height, depth);
+                existing.width = width;
+                existing.height = height;
+            }
+            return existing.target;
+        }
+
+        let target = backend.create_color_target(width, height, depth);
+        self.targets.push(NamedTarget {
+            name: name.to_string(),
+            target,
+            width,
+            height,
+        });
+        target
+    }
+
+    pub(crate) fn find(&self, name: &str) -> Option<T> {
+        self.targets
+            .iter()
+            .find(|t| t.name == name)
+            .map(|t| t.target)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    pub(crate) fn delete_all<B: GraphicsBackend<ColorTarget = T>>(&mut self, backend: &mut B) {
+        for named in self.targets.drain(..) {
+            backend.delete_color_target(named.target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::renderer_backends::NullBackend;
+
+    /// A pass that only declares targets.
+    struct Declared {
+        name: &'static str,
+        inputs: &'static [&'static str],
+        outputs: &'static [&'static str],
+    }
+
+    impl RenderPass<NullBackend> for Declared {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn inputs(&self) -> &[&str] {
+            self.inputs
+        }
+
+        fn outputs(&self) -> &[&str] {
+            self.outputs
+        }
+
+        fn execute(&mut self, _context: &mut RenderPassContext<'_, NullBackend>) {}
+    }
+
+    fn declared(
+        name: &'static str,
+        inputs: &'static [&'static str],
+        outputs: &'static [&'static str],
+    ) -> Box<Declared> {
+        Box::new(Declared {
+            name,
+            inputs,
+            outputs,
+        })
+    }
+
+    #[test]
+    fn passes_run_after_the_writers_of_their_inputs() {
+        let mut graph = RenderGraph::<NullBackend>::default();
+        assert_eq!(
+            graph.names_in_order(),
+            [
+                "shadows",
+                "forward_opaque",
+                "forward_translucent",
+                "post_process",
+                "debug_lines"
+            ]
+        );
+
+        // Registered before the pass writing its input, the composite still runs after it.
+        graph
+            .add(declared("composite", &["mask"], &[SCENE_COLOR_TARGET]))
+            .unwrap();
+        graph.add(declared("mask", &[], &["mask"])).unwrap();
+        graph
+            .add(declared("hud", &[FRAME_TARGET], &[FRAME_TARGET]))
+            .unwrap();
+        assert_eq!(
+            graph.names_in_order(),
+            [
+                "shadows",
+                "forward_opaque",
+                "forward_translucent",
+                "mask",
+                "composite",
+                "post_process",
+                "debug_lines",
+                "hud"
+            ]
+        );
+
+        // The first writer of a target clears it; the composite draws over the scene.
+        let clears = |name: &str| {
+            let index = graph.nodes.iter().position(|n| n.name() == name).unwrap();
+            graph.clears_output[index]
+        };
+        assert!(clears("mask"));
+        assert!(clears("forward_opaque"));
+        assert!(!clears("composite"));
+        assert!(!clears("hud"));
+
+        assert!(graph.remove("mask").is_some());
+        assert!(graph.remove("forward_opaque").is_none());
+        assert_eq!(graph.names_in_order().len(), 7);
+    }
+
+    #[test]
+    fn cycles_and_duplicate_names_are_rejected() {
+        let mut graph = RenderGraph::<NullBackend>::default();
+        graph.add(declared("a", &["b"], &["a"])).unwrap();
+        assert_eq!(
+            graph.add(declared("b", &["a"], &["b"])).unwrap_err(),
+            RenderGraphError::Cycle(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(
+            graph.add(declared("a", &[], &["c"])).unwrap_err(),
+            RenderGraphError::DuplicatePass("a".to_string())
+        );
+        assert_eq!(graph.nodes.len(), 6);
+        assert_eq!(graph.names_in_order().last(), Some(&"a"));
+    }
+}
@@ -1,4 +1,11 @@
-use crate::assets::handles::*;
+use std::{ops::Range, sync::Arc};
+
+use crate::{
+    assets::{handles::*, mesh::Aabb},
+    components::instance_params_component::InstanceParams,
+    render::lod::LodGroup,
+};
+use bevy_ecs::entity::Entity;
 use glam::Mat4;
 
 #[derive(Debug, Clone)]
@@ -6,4 +13,28 @@ pub struct RenderInstance {
     pub mesh_id: MeshHandle,
     pub transform: Mat4,
     pub material_id: MaterialHandle,
+    /// For skinned meshes, the range of `RenderQueue::joint_matrices` holding this
+    /// instance's joints.
+    pub joints: Option<Range<usize>>,
+    /// Coarser meshes the renderer swaps `mesh_id` for as the instance shrinks on screen.
+    pub lod: Option<Arc<LodGroup>>,
+    /// Share of pixels drawn while cross-fading between LOD levels, set by the renderer.
+    /// 1 draws every pixel; a positive value draws that share of a dither pattern and a
+    /// negative one the rest of it, so two levels fading together cover every pixel once.
+    pub lod_fade: f32,
+    /// Drawn into the occlusion buffer to hide the instances behind it, see `Occluder`.
+    pub occluder: bool,
+    /// Per-instance shader inputs, see `InstanceParams`.
+    pub params: InstanceParams,
+}
+
+/// The instances one entity queued, culled together by the renderer's spatial index.
+#[derive(Debug, Clone)]
+pub struct RenderGroup {
+    pub entity: Entity,
+    /// Range of `RenderQueue::instances`.
+    pub instances: Range<usize>,
+    /// World-space bounds of every instance in the group. Only given when they may have
+    /// changed since the previous frame; otherwise the renderer keeps the ones it has.
+    pub moved_bounds: Option<Aabb>,
 }
@@ -1,8 +1,47 @@
-use bevy_ecs::resource::Resource;
+use std::{collections::HashMap, ops::Range};
 
-use crate::render::render_instance::RenderInstance;
+use bevy_ecs::{entity::Entity, resource::Resource};
+use glam::Mat4;
 
+use crate::{
+    components::instance_params_component::InstanceParams,
+    render::{
+        lights::FrameLights,
+        render_instance::{RenderGroup, RenderInstance},
+    },
+};
+
+/// What `RenderSystem::build_render_queue` hands the renderer. Instances and groups are
+/// kept from frame to frame and only patched where entities changed.
 #[derive(Resource, Default)]
 pub struct RenderQueue {
     pub instances: Vec<RenderInstance>,
+    /// Which entity queued which instances, see `Renderer::stage_groups`.
+    pub groups: Vec<RenderGroup>,
+    pub lights: FrameLights,
+    /// Joint matrices of every skinned instance this frame, see `RenderInstance::joints`.
+    pub joint_matrices: Vec<Mat4>,
+    /// Ranges of `instances` written this frame; the rest are as they were the frame
+    /// before. See `Renderer::stage_instance_changes`.
+    pub changed_instances: Vec<Range<usize>>,
+    /// Where each queued entity's instances live.
+    pub(crate) entries: HashMap<Entity, QueuedEntity>,
+    /// `RenderBodyStorage::revision` the queue was last rebuilt at.
+    pub(crate) body_revision: u64,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct QueuedEntity {
+    /// Index into `RenderQueue::groups`.
+    pub group: usize,
+    /// Range of `RenderQueue::joint_matrices`.
+    pub joints: Range<usize>,
+    pub values: InstanceValues,
+}
+
+/// What an entity gives each of its instances besides a transform.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) struct InstanceValues {
+    pub occluder: bool,
+    pub params: InstanceParams,
 }
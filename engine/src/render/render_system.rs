@@ -1,39 +1,344 @@
-use bevy_ecs::prelude::{Query, Res, ResMut};
+use bevy_ecs::{
+    change_detection::{DetectChanges, Ref},
+    entity::Entity,
+    prelude::{Has, Query, RemovedComponents, Res, ResMut},
+};
+use glam::Vec3;
 
 use crate::{
+    animation::animation_player::AnimatedPose,
+    assets::{
+        mesh::Aabb,
+        mesh_resource::{MeshResource, MeshStorage},
+    },
     components::{
-        render_body_component::RenderBodyComponent, transform_component::TransformComponent,
+        instance_params_component::InstanceParams,
+        light_component::{DirectionalLight, PointLight, SpotLight},
+        occluder_component::Occluder,
+        render_body_component::RenderBodyComponent,
+        transform_component::GlobalTransform,
     },
     render::{
-        render_body_resource::RenderBodyResource, render_instance::RenderInstance,
-        render_queue::RenderQueue,
+        lights::{DirectionalLightData, PointLightData, SpotLightData},
+        render_body::RenderBody,
+        render_body_resource::RenderBodyResource,
+        render_instance::{RenderGroup, RenderInstance},
+        render_queue::{InstanceValues, QueuedEntity, RenderQueue},
     },
+    world_basis::WorldBasis,
 };
 
 pub struct RenderSystem {}
 
 impl RenderSystem {
+    /// Keeps the queue of every part of every rendered entity up to date. Parts of an
+    /// entity with an `AnimatedPose` are placed by their animated node, and skinned parts
+    /// also get their joint matrices, from the pose or, without one, the model's rest pose.
+    ///
+    /// Instances persist between frames. Entities whose transform, body, pose,
+    /// `Occluder` or `InstanceParams` changed are requeued in place and new ones are appended; the queue is
+    /// only rebuilt from scratch when entities stop rendering or a body changed in place.
+    /// The instances touched are listed in `RenderQueue::changed_instances`.
+    ///
+    /// Each entity's instances form a `RenderGroup`, whose bounds are only given when the
+    /// entity was requeued.
+    #[allow(clippy::type_complexity)]
     pub fn build_render_queue(
-        query: Query<(&TransformComponent, &RenderBodyComponent)>,
+        query: Query<(
+            Entity,
+            Ref<GlobalTransform>,
+            Ref<RenderBodyComponent>,
+            Option<Ref<AnimatedPose>>,
+            Has<Occluder>,
+            Option<&InstanceParams>,
+        )>,
+        mut removed_bodies: RemovedComponents<RenderBodyComponent>,
+        mut removed_transforms: RemovedComponents<GlobalTransform>,
+        mut removed_poses: RemovedComponents<AnimatedPose>,
         render_body_resource: Res<RenderBodyResource>,
+        mesh_resource: Res<MeshResource>,
         mut queue: ResMut<RenderQueue>,
     ) {
-        queue.instances.clear();
+        let queue = &mut *queue;
+        queue.changed_instances.clear();
 
-        for (transform, render_body) in &query {
-            let guard = render_body_resource.read();
-            let body = guard
+        let bodies = render_body_resource.read();
+        let meshes = mesh_resource.read();
+        let body_of = |render_body: &RenderBodyComponent| {
+            bodies
                 .get_render_body(render_body.render_body_id)
-                .expect("RenderBody not found");
-
-            let world_transform = transform.to_mat4();
-            for part in &body.parts {
-                queue.instances.push(RenderInstance {
-                    mesh_id: part.mesh_id,
-                    transform: world_transform * part.local_transform,
-                    material_id: part.material_id,
-                });
+                .expect("RenderBody not found")
+        };
+
+        // Entities that stopped rendering would leave holes, and bodies changed in place
+        // may have a different number of parts, so both rebuild the whole queue.
+        let mut rebuild = bodies.revision() != queue.body_revision;
+        for entity in removed_bodies
+            .read()
+            .chain(removed_transforms.read())
+            .chain(removed_poses.read())
+        {
+            rebuild |= queue.entries.contains_key(&entity);
+        }
+
+        if !rebuild {
+            for (entity, transform, render_body, pose, occluder, params) in &query {
+                let body = body_of(&render_body);
+                let values = InstanceValues {
+                    occluder,
+                    params: params.copied().unwrap_or_default(),
+                };
+                let Some(entry) = queue.entries.get(&entity) else {
+                    Self::queue_entity(
+                        queue,
+                        &meshes,
+                        entity,
+                        &transform,
+                        body,
+                        pose.as_deref(),
+                        values,
+                    );
+                    continue;
+                };
+
+                let changed = transform.is_changed()
+                    || render_body.is_changed()
+                    || pose.as_ref().is_some_and(|pose| pose.is_changed())
+                    || entry.values != values;
+                if !changed {
+                    queue.groups[entry.group].moved_bounds = None;
+                    continue;
+                }
+                if !Self::requeue_entity(
+                    queue,
+                    &meshes,
+                    entity,
+                    &transform,
+                    body,
+                    pose.as_deref(),
+                    values,
+                ) {
+                    rebuild = true;
+                    break;
+                }
             }
         }
+
+        if rebuild {
+            queue.instances.clear();
+            queue.groups.clear();
+            queue.joint_matrices.clear();
+            queue.entries.clear();
+            for (entity, transform, render_body, pose, occluder, params) in &query {
+                let body = body_of(&render_body);
+                let values = InstanceValues {
+                    occluder,
+                    params: params.copied().unwrap_or_default(),
+                };
+                Self::queue_entity(
+                    queue,
+                    &meshes,
+                    entity,
+                    &transform,
+                    body,
+                    pose.as_deref(),
+                    values,
+                );
+            }
+            queue.changed_instances.clear();
+            queue.changed_instances.push(0..queue.instances.len());
+            queue.body_revision = bodies.revision();
+        }
+    }
+
+    /// Appends the instances and group of an entity that isn't queued yet.
+    fn queue_entity(
+        queue: &mut RenderQueue,
+        meshes: &MeshStorage,
+        entity: Entity,
+        transform: &GlobalTransform,
+        body: &RenderBody,
+        pose: Option<&AnimatedPose>,
+        values: InstanceValues,
+    ) {
+        let first_instance = queue.instances.len();
+        let first_joint = queue.joint_matrices.len();
+        Self::append_instances(queue, body, transform, pose, values, first_joint);
+
+        let instances = first_instance..queue.instances.len();
+        queue.entries.insert(
+            entity,
+            QueuedEntity {
+                group: queue.groups.len(),
+                joints: first_joint..queue.joint_matrices.len(),
+                values,
+            },
+        );
+        queue.groups.push(RenderGroup {
+            entity,
+            instances: instances.clone(),
+            moved_bounds: Some(Self::group_bounds(
+                &queue.instances[instances.clone()],
+                meshes,
+                transform,
+            )),
+        });
+        queue.changed_instances.push(instances);
+    }
+
+    /// Rebuilds the instances and joint matrices of a queued entity where they are.
+    /// Returns `false` without finishing if their number changed, which needs a rebuild.
+    fn requeue_entity(
+        queue: &mut RenderQueue,
+        meshes: &MeshStorage,
+        entity: Entity,
+        transform: &GlobalTransform,
+        body: &RenderBody,
+        pose: Option<&AnimatedPose>,
+        values: InstanceValues,
+    ) -> bool {
+        let entry = queue.entries.get_mut(&entity).expect("entity not queued");
+        entry.values = values;
+        let (group, joints) = (entry.group, entry.joints.clone());
+        let slot = queue.groups[group].instances.clone();
+        if body.parts.len() != slot.len() {
+            return false;
+        }
+
+        // Build the new instances at the end, then swap them into the entity's slot.
+        let appended_instances = queue.instances.len();
+        let appended_joints = queue.joint_matrices.len();
+        Self::append_instances(queue, body, transform, pose, values, joints.start);
+        if queue.joint_matrices.len() - appended_joints != joints.len() {
+            return false;
+        }
+        for (offset, index) in slot.clone().enumerate() {
+            queue.instances.swap(index, appended_instances + offset);
+        }
+        queue.instances.truncate(appended_instances);
+        queue
+            .joint_matrices
+            .copy_within(appended_joints.., joints.start);
+        queue.joint_matrices.truncate(appended_joints);
+
+        queue.groups[group].moved_bounds = Some(Self::group_bounds(
+            &queue.instances[slot.clone()],
+            meshes,
+            transform,
+        ));
+        queue.changed_instances.push(slot);
+        true
+    }
+
+    /// Appends an entity's instances and the joint matrices of its skinned parts, whose
+    /// ranges are given as if the matrices started at `joints_at`.
+    fn append_instances(
+        queue: &mut RenderQueue,
+        body: &RenderBody,
+        transform: &GlobalTransform,
+        pose: Option<&AnimatedPose>,
+        values: InstanceValues,
+        joints_at: usize,
+    ) {
+        let pose = pose
+            .map(|pose| &pose.node_matrices[..])
+            .filter(|matrices| matrices.len() == body.hierarchy.nodes().len());
+        let node_matrices = pose.unwrap_or(body.hierarchy.rest_matrices());
+
+        let world_transform = transform.matrix();
+        let first_joint = queue.joint_matrices.len();
+        for part in &body.parts {
+            let joints = part.skin.map(|skin| {
+                let start = queue.joint_matrices.len();
+                body.hierarchy.append_joint_matrices(
+                    skin,
+                    node_matrices,
+                    &mut queue.joint_matrices,
+                );
+                let end = queue.joint_matrices.len();
+                start - first_joint + joints_at..end - first_joint + joints_at
+            });
+            let local_transform = match (pose, part.node, part.skin) {
+                (Some(pose), Some(node), None) => pose[node],
+                _ => part.local_transform,
+            };
+            queue.instances.push(RenderInstance {
+                mesh_id: part.mesh_id,
+                transform: world_transform * local_transform,
+                material_id: part.material_id,
+                joints,
+                lod: part.lod.clone(),
+                lod_fade: 1.0,
+                occluder: values.occluder,
+                params: values.params,
+            });
+        }
+    }
+
+    /// World-space bounds of an entity's instances, or its position when none has a mesh.
+    fn group_bounds(
+        instances: &[RenderInstance],
+        meshes: &MeshStorage,
+        transform: &GlobalTransform,
+    ) -> Aabb {
+        instances
+            .iter()
+            .filter_map(|instance| {
+                let mesh = meshes.get_mesh(instance.mesh_id)?;
+                let (center, radius) = mesh.world_bounding_sphere(&instance.transform);
+                Some(Aabb {
+                    min: center - Vec3::splat(radius),
+                    max: center + Vec3::splat(radius),
+                })
+            })
+            .reduce(|a, b| a.union(&b))
+            .unwrap_or(Aabb {
+                min: transform.translation(),
+                max: transform.translation(),
+            })
+    }
+
+    /// Collects every light component into `RenderQueue::lights`. The renderer trims the
+    /// list to the shader limits once it knows where the camera is.
+    pub fn gather_lights(
+        directional_query: Query<&DirectionalLight>,
+        point_query: Query<(&GlobalTransform, &PointLight)>,
+        spot_query: Query<(&GlobalTransform, &SpotLight)>,
+        world_basis: Res<WorldBasis>,
+        mut queue: ResMut<RenderQueue>,
+    ) {
+        let lights = &mut queue.lights;
+        lights.clear();
+
+        for light in &directional_query {
+            lights.directional.push(DirectionalLightData {
+                direction: light.direction.normalize_or(-world_basis.up()),
+                color: light.color * light.intensity,
+            });
+        }
+
+        for (transform, light) in &point_query {
+            lights.point.push(PointLightData {
+                position: transform.translation(),
+                color: light.color * light.intensity,
+                range: light.range,
+                falloff: light.falloff,
+            });
+        }
+
+        for (transform, light) in &spot_query {
+            lights.spot.push(SpotLightData {
+                position: transform.translation(),
+                direction: transform
+                    .matrix()
+                    .transform_vector3(world_basis.forward())
+                    .normalize(),
+                color: light.color * light.intensity,
+                range: light.range,
+                falloff: light.falloff,
+                cos_inner: light.inner_cone_radians.cos(),
+                cos_outer: light.outer_cone_radians.cos(),
+            });
+        }
     }
 }
@@ -1,64 +1,416 @@
-use std::{collections::HashMap, mem::offset_of, ops::Range, rc::Rc};
+use std::{
+    collections::HashMap,
+    mem::{self, offset_of, size_of},
+    ops::Range,
+};
 
-use glam::{Mat4, Vec3};
-use glow::{Context as GlowContext, HasContext};
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec2, Vec3};
+use image::RgbaImage;
 use slotmap::SecondaryMap;
 
 use crate::{
     assets::{
         handles::{MaterialHandle, MeshHandle, ShaderHandle},
+        material::BlendMode,
         material_resource::MaterialStorage,
         mesh::{Mesh, Vertex},
         mesh_resource::MeshStorage,
         shader::{
             InputRate::{PerInstance, PerVertex},
-            UniformValue, VertexAttribType,
+            Shader, UniformValue, VertexAttribType,
         },
         shader_resource::ShaderStorage,
-        texture,
         texture_resource::TextureStorage,
     },
-    render::{frustum::Frustum, render_instance::RenderInstance},
+    components::instance_params_component::InstanceParams,
+    render::{
+        debug_draw::{DebugDraw, DebugLabel, DebugLine, label_segments},
+        debug_view::{DebugView, RenderDebugSettings},
+        frustum::Frustum,
+        lights::{DirectionalLightData, FrameLights, LightUniformLocations},
+        lod::ScreenProjection,
+        occlusion::{OcclusionBuffer, OcclusionSettings},
+        post_process::{PostProcessEffect, PostProcessPass, PostProcessSettings},
+        render_graph::{
+            BuiltinPass, ColorTargetPool, FRAME_TARGET, GraphNode, RenderGraph, RenderGraphError,
+            RenderPass, RenderPassContext, SCENE_COLOR_TARGET, SHADOW_MAP_TARGET,
+        },
+        render_instance::{RenderGroup, RenderInstance},
+        renderer_backends::{
+            AttribSource, BufferKind, GlowBackend, GraphicsBackend, MeshBuffers,
+            VertexAttribBinding,
+        },
+        shadows::{
+            SHADOW_MAP_TEXTURE_UNIT, ShadowCascade, ShadowSettings, ShadowUniformLocations,
+            compute_cascades,
+        },
+        spatial_index::RenderSpatialIndex,
+    },
 };
 
-pub struct Renderer {
-    gl: Rc<GlowContext>,
+pub struct Renderer<B: GraphicsBackend = GlowBackend> {
+    backend: B,
     frames_rendered: u64,
-    vao_cache: HashMap<VaoKey, glow::VertexArray>,
-    mesh_render_data: SecondaryMap<MeshHandle, MeshRenderData>,
+    /// Instance buffers of the draws of recent frames, kept so unchanged instances
+    /// aren't uploaded again.
+    instance_streams: HashMap<StreamKey, InstanceStream<B::Buffer, B::VertexArray>>,
+    mesh_render_data: SecondaryMap<MeshHandle, MeshRenderData<B::Buffer>>,
+    light_uniforms: SecondaryMap<ShaderHandle, LightUniformLocations>,
+    shadow_uniforms: SecondaryMap<ShaderHandle, ShadowUniformLocations>,
     frame_data: PersistentFrameData,
+    /// Bounds of the staged render groups, kept across frames.
+    spatial_index: RenderSpatialIndex,
+    /// Lazily created by `render_to_image` and recreated when the requested size changes.
+    offscreen_target: Option<OffscreenTarget<B::RenderTarget>>,
+    shadow_settings: ShadowSettings,
+    /// Depth-only shader for the shadow pass. Without one no shadows are drawn.
+    shadow_shader: Option<ShaderHandle>,
+    /// Depth-only shader for skinned meshes. Without one they cast no shadows.
+    skinned_shadow_shader: Option<ShaderHandle>,
+    /// Created on the first shadowed frame and recreated when the settings change its size.
+    shadow_map: Option<ShadowMapTarget<B::ShadowMap>>,
+    occlusion_settings: OcclusionSettings,
+    /// Occluders of the current frame, drawn on the CPU.
+    occlusion_buffer: OcclusionBuffer,
+    post_process_settings: PostProcessSettings,
+    debug_settings: RenderDebugSettings,
+    /// Run in order on the scene's HDR colour while post-processing is enabled.
+    post_process_effects: Vec<Box<dyn PostProcessEffect<B>>>,
+    /// The HDR scene target and those the effects draw through, by name.
+    post_process_targets: ColorTargetPool<B::ColorTarget>,
+    render_graph: RenderGraph<B>,
+    /// Targets written by custom render passes, by name.
+    graph_targets: ColorTargetPool<B::ColorTarget>,
+    /// Shader of the debug line pass. Without one no debug shapes are drawn.
+    debug_line_shader: Option<ShaderHandle>,
+    /// Created on the first frame with debug shapes and refilled every frame after.
+    debug_line_buffers: Option<(MeshBuffers<B::Buffer>, B::VertexArray)>,
+}
+
+struct ShadowMapTarget<T> {
+    shadow_map: T,
+    resolution: u32,
+    layers: u32,
+}
+
+struct OffscreenTarget<T> {
+    target: T,
+    width: u32,
+    height: u32,
 }
 
-pub struct MeshRenderData {
+pub struct MeshRenderData<Buffer> {
     // GPU handles
-    pub vbo: Option<glow::Buffer>,
-    pub ebo: Option<glow::Buffer>,
-    pub instance_vbo: Option<glow::Buffer>,
-    pub instance_count: usize,
+    pub vbo: Option<Buffer>,
+    pub ebo: Option<Buffer>,
+}
+
+/// Instance streams not drawn for this many frames are deleted.
+const UNUSED_STREAM_FRAMES: u64 = 120;
+
+/// The pass an instanced draw belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum DrawPass {
+    Opaque,
+    /// Blended instances, drawn after the opaque ones.
+    Translucent,
+    /// Into the given cascade of the shadow map.
+    Shadow(u32),
+}
+
+/// Identifies one instanced draw from frame to frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct StreamKey {
+    pass: DrawPass,
+    material: MaterialHandle,
+    mesh: MeshHandle,
+    shader: ShaderHandle,
+    /// Numbers draws that share the rest of the key, such as skinned instances.
+    ordinal: u32,
+}
+
+impl StreamKey {
+    /// Numbers the key after the draw before it, if that one differs only by ordinal.
+    fn after(mut self, previous: Option<StreamKey>) -> Self {
+        if let Some(previous) = previous
+            && previous
+                == (StreamKey {
+                    ordinal: previous.ordinal,
+                    ..self
+                })
+        {
+            self.ordinal = previous.ordinal + 1;
+        }
+        self
+    }
+}
+
+/// A persistent instance buffer and the vertex array drawing its mesh from it. Every
+/// instance keeps its slot in the buffer from frame to frame, so only the slots of
+/// instances that were restaged, faded or moved into a slot another one left are
+/// written. Grown buffers are orphaned and written whole.
+struct InstanceStream<Buffer, VertexArray> {
+    buffer: Buffer,
+    vertex_array: Option<VertexArray>,
+    /// Instances the buffer has room for.
+    capacity: usize,
+    /// Staged index of the instance in each slot, in the order they are drawn.
+    occupants: Vec<usize>,
+    /// `lod_fade` each slot was written with. Fades are picked by the renderer, so
+    /// staging doesn't flag them as changed.
+    fades: Vec<f32>,
+    /// Slot of each instance in `occupants`, by staged index.
+    slots: HashMap<usize, usize>,
+    /// Which of the instances being uploaded goes in each slot. Reused between uploads.
+    order: Vec<usize>,
+    /// `frames_rendered` when the stream was last drawn.
+    last_used: u64,
+}
+
+impl<Buffer: Copy, VertexArray> InstanceStream<Buffer, VertexArray> {
+    /// Writes the slots of `instances` the buffer doesn't hold yet. `indices` are their
+    /// staged indices and `changed` flags the staged instances restaged since the last
+    /// frame. With `ordered` the slots follow the order of `instances`; otherwise
+    /// instances stay where they are and the slots of ones that left are filled from the
+    /// end of the buffer.
+    fn upload<B: GraphicsBackend<Buffer = Buffer>>(
+        &mut self,
+        backend: &mut B,
+        instances: &[InstanceData],
+        indices: &[usize],
+        changed: &[bool],
+        ordered: bool,
+        frame: u64,
+    ) {
+        // Changes staged while the stream wasn't drawn were never seen, so start over.
+        if self.last_used + 1 < frame {
+            self.occupants.clear();
+            self.fades.clear();
+            self.slots.clear();
+        }
+        self.last_used = frame;
+
+        let mut order = mem::take(&mut self.order);
+        if ordered {
+            order.clear();
+            order.extend(0..instances.len());
+        } else {
+            self.keep_slots(indices, &mut order);
+        }
+
+        let stride = size_of::<InstanceData>();
+        let grown = instances.len() > self.capacity;
+        if grown {
+            self.capacity = instances.len().next_power_of_two();
+            backend.allocate_buffer(self.buffer, self.capacity * stride);
+        }
+
+        let mut moved = self.occupants.len() != order.len();
+        let mut run_start = 0;
+        let mut run = Vec::new();
+        for (slot, &i) in order.iter().enumerate() {
+            let index = indices[i];
+            let occupied = self.occupants.get(slot) == Some(&index);
+            moved |= !occupied;
+            let stale = grown
+                || !occupied
+                || changed[index]
+                || self.fades.get(slot) != Some(&instances[i].lod_fade);
+            if stale {
+                if run.is_empty() {
+                    run_start = slot;
+                }
+                run.push(instances[i]);
+            } else if !run.is_empty() {
+                backend.update_buffer_range(
+                    self.buffer,
+                    run_start * stride,
+                    bytemuck::cast_slice(&run),
+                );
+                run.clear();
+            }
+        }
+        if !run.is_empty() {
+            backend.update_buffer_range(
+                self.buffer,
+                run_start * stride,
+                bytemuck::cast_slice(&run),
+            );
+        }
+
+        self.occupants.clear();
+        self.occupants.extend(order.iter().map(|&i| indices[i]));
+        self.fades.clear();
+        self.fades
+            .extend(order.iter().map(|&i| instances[i].lod_fade));
+        if moved && !ordered {
+            self.slots.clear();
+            self.slots.extend(
+                self.occupants
+                    .iter()
+                    .enumerate()
+                    .map(|(slot, &index)| (index, slot)),
+            );
+        }
+        self.order = order;
+    }
+
+    /// Fills `order` with the instance of `indices` each slot is to hold: the one already
+    /// in it if it is still drawn, otherwise one from past the new end of the buffer or a
+    /// new one.
+    fn keep_slots(&self, indices: &[usize], order: &mut Vec<usize>) {
+        const FREE: usize = usize::MAX;
+        order.clear();
+        order.resize(self.occupants.len().max(indices.len()), FREE);
+        let mut entering = Vec::new();
+        for (i, index) in indices.iter().enumerate() {
+            match self.slots.get(index) {
+                Some(&slot) if order[slot] == FREE => order[slot] = i,
+                _ => entering.push(i),
+            }
+        }
+
+        let mut movers: Vec<usize> = order[indices.len()..]
+            .iter()
+            .copied()
+            .filter(|&i| i != FREE)
+            .collect();
+        movers.append(&mut entering);
+        order.truncate(indices.len());
+        let mut movers = movers.into_iter();
+        for slot in order.iter_mut().filter(|slot| **slot == FREE) {
+            *slot = movers
+                .next()
+                .expect("every free slot has an instance to fill it");
+        }
+    }
+}
+
+/// One instance as laid out in a mesh's instance buffer.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InstanceData {
+    pub model: [f32; 16],
+    pub lod_fade: f32,
+    /// `InstanceParams` of the instance, packed as `InstanceParams::FIELDS` lays them out.
+    pub params: [f32; InstanceParams::PACKED_LEN],
+}
+
+/// One end of a debug line, already projected.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DebugVertex {
+    pub position: [f32; 4],
+    pub color: [f32; 3],
+}
+
+// SAFETY: `DebugVertex` is `repr(C)` and made only of `f32`s, so it has no padding and
+// any bit pattern is valid.
+unsafe impl Zeroable for DebugVertex {}
+unsafe impl Pod for DebugVertex {}
+
+// SAFETY: `InstanceData` is `repr(C)` and made only of `f32`s, so it has no padding and
+// any bit pattern is valid.
+unsafe impl Zeroable for InstanceData {}
+unsafe impl Pod for InstanceData {}
+
+impl InstanceData {
+    fn new(instance: &RenderInstance) -> Self {
+        Self {
+            model: instance.transform.to_cols_array(),
+            lod_fade: instance.lod_fade,
+            params: instance.params.pack(),
+        }
+    }
+}
+
+/// A staged instance that passed culling.
+#[derive(Debug, Clone)]
+pub struct VisibleInstance {
+    /// Index of the instance among the staged ones.
+    pub index: usize,
+    pub instance: RenderInstance,
 }
 
 struct PersistentFrameData {
     /// Instances copied from the render queue at the start of each frame.
     input_instances: Vec<RenderInstance>,
-    visible_instances: Vec<RenderInstance>,
+    /// Whether each of `input_instances` was restaged since the last rendered frame.
+    changed_instances: Vec<bool>,
+    /// Whether `spatial_index` describes `input_instances`; without groups every instance
+    /// is tested on its own.
+    groups_staged: bool,
+    /// Joint matrices copied from the render queue, indexed by `RenderInstance::joints`.
+    joint_matrices: Vec<[f32; 16]>,
+    visible_instances: Vec<VisibleInstance>,
     frame_uniforms: FrameUniforms,
-    /// Flat storage for all instance matrices in the frame (reused across frames).
-    instance_matrices: Vec<[f32; 16]>,
-    /// Ranges into `instance_matrices` for each mesh within a material batch.
-    mesh_batch_ranges: Vec<MeshBatchRange>,
-    /// Ranges into `mesh_batch_ranges` for each material batch.
-    material_batch_ranges: Vec<MaterialBatchRange>,
+    /// Lights copied from the render queue, trimmed to the nearest ones each frame.
+    lights: FrameLights,
+    /// Shaders whose light uniforms have been set this frame.
+    lit_shaders: Vec<ShaderHandle>,
+    /// Cascades of this frame's shadow map; empty when nothing casts shadows.
+    shadow_cascades: Vec<ShadowCascade>,
+    /// Culling and batching output of the shadow pass, reused cascade to cascade.
+    shadow_instances: Vec<VisibleInstance>,
+    shadow_batches: DrawBatches,
+    opaque_batches: DrawBatches,
+    /// Visible instances with a blended material, taken out of `visible_instances`.
+    translucent_instances: Vec<VisibleInstance>,
+    translucent_batches: DrawBatches,
+    /// Translucent draws so far this frame of each material and mesh.
+    translucent_runs: HashMap<(MaterialHandle, MeshHandle), u32>,
+    /// Debug shapes copied from `DebugDraw`.
+    debug_lines: Vec<DebugLine>,
+    debug_labels: Vec<DebugLabel>,
+    debug_vertices: Vec<DebugVertex>,
 }
 
 impl Default for PersistentFrameData {
     fn default() -> Self {
         Self {
             input_instances: Vec::with_capacity(1024),
+            changed_instances: Vec::with_capacity(1024),
+            groups_staged: false,
+            joint_matrices: Vec::with_capacity(1024),
             visible_instances: Vec::with_capacity(1024),
             frame_uniforms: FrameUniforms::default(),
-            instance_matrices: Vec::with_capacity(1024),
-            mesh_batch_ranges: Vec::with_capacity(256),
-            material_batch_ranges: Vec::with_capacity(256),
+            lights: FrameLights::default(),
+            lit_shaders: Vec::with_capacity(16),
+            shadow_cascades: Vec::with_capacity(4),
+            shadow_instances: Vec::with_capacity(1024),
+            shadow_batches: DrawBatches::with_capacity(1024, 256),
+            opaque_batches: DrawBatches::with_capacity(1024, 256),
+            translucent_instances: Vec::with_capacity(64),
+            translucent_batches: DrawBatches::with_capacity(64, 64),
+            translucent_runs: HashMap::with_capacity(64),
+            debug_lines: Vec::new(),
+            debug_labels: Vec::new(),
+            debug_vertices: Vec::new(),
+        }
+    }
+}
+
+/// Instances grouped into material → mesh batches, in draw order. Reused across frames.
+#[derive(Default)]
+struct DrawBatches {
+    /// Flat storage for the instance data of every batch.
+    instance_data: Vec<InstanceData>,
+    /// Staged index of each entry of `instance_data`.
+    instance_indices: Vec<usize>,
+    /// Ranges into `instance_data` for each mesh within a material batch.
+    mesh_batch_ranges: Vec<MeshBatchRange>,
+    /// Ranges into `mesh_batch_ranges` for each material batch.
+    material_batch_ranges: Vec<MaterialBatchRange>,
+}
+
+impl DrawBatches {
+    fn with_capacity(instances: usize, batches: usize) -> Self {
+        Self {
+            instance_data: Vec::with_capacity(instances),
+            instance_indices: Vec::with_capacity(instances),
+            mesh_batch_ranges: Vec::with_capacity(batches),
+            material_batch_ranges: Vec::with_capacity(batches),
         }
     }
 }
@@ -69,16 +421,18 @@ struct MaterialBatchRange {
 }
 
 struct MeshBatchRange {
+    material_id: MaterialHandle,
     mesh_id: MeshHandle,
-    matrices: Range<usize>,
+    instances: Range<usize>,
+    /// Joint matrices of a skinned instance. Skinned instances are never batched together,
+    /// since each has its own pose.
+    joints: Option<Range<usize>>,
 }
 
 #[derive(Default)]
 struct FrameUniforms {
     view_proj: Mat4,
     camera_position: Vec3,
-    light_direction: Vec3,
-    light_color: Vec3,
 }
 
 pub struct RenderParams {
@@ -86,39 +440,143 @@ pub struct RenderParams {
     pub height: u32,
 }
 
-#[derive(PartialEq, Hash, Eq)]
-pub struct VaoKey {
-    mesh: MeshHandle,
-    shader: ShaderHandle,
-}
-
 /// Precomputed camera data required by the renderer.
 pub struct CameraRenderData {
     pub view_proj: Mat4,
     pub position: Vec3,
+    /// Near and far plane distances, used to split the view into shadow cascades.
+    pub near: f32,
+    pub far: f32,
 }
 
-impl Renderer {
-    pub fn new(gl: Rc<GlowContext>) -> Self {
-        unsafe {
-            gl.enable(glow::DEPTH_TEST);
-            gl.depth_func(glow::LESS);
-
-            Self {
-                gl,
-                frames_rendered: 0,
-                vao_cache: HashMap::with_capacity(256),
-                frame_data: PersistentFrameData::default(),
-                mesh_render_data: SecondaryMap::with_capacity(256),
-            }
+impl<B: GraphicsBackend> Renderer<B> {
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend,
+            frames_rendered: 0,
+            instance_streams: HashMap::with_capacity(256),
+            frame_data: PersistentFrameData::default(),
+            spatial_index: RenderSpatialIndex::default(),
+            mesh_render_data: SecondaryMap::with_capacity(256),
+            light_uniforms: SecondaryMap::new(),
+            shadow_uniforms: SecondaryMap::new(),
+            offscreen_target: None,
+            shadow_settings: ShadowSettings::default(),
+            shadow_shader: None,
+            skinned_shadow_shader: None,
+            shadow_map: None,
+            occlusion_settings: OcclusionSettings::default(),
+            occlusion_buffer: OcclusionBuffer::default(),
+            post_process_settings: PostProcessSettings::default(),
+            debug_settings: RenderDebugSettings::default(),
+            post_process_effects: Vec::new(),
+            post_process_targets: ColorTargetPool::default(),
+            render_graph: RenderGraph::default(),
+            graph_targets: ColorTargetPool::default(),
+            debug_line_shader: None,
+            debug_line_buffers: None,
         }
     }
 
-    fn max_scale(mat: Mat4) -> f32 {
-        let x = mat.x_axis.truncate().length();
-        let y = mat.y_axis.truncate().length();
-        let z = mat.z_axis.truncate().length();
-        x.max(y).max(z)
+    pub fn shadow_settings(&self) -> &ShadowSettings {
+        &self.shadow_settings
+    }
+
+    /// Takes effect from the next frame; the shadow map is recreated if its size changed.
+    pub fn set_shadow_settings(&mut self, settings: ShadowSettings) {
+        self.shadow_settings = settings;
+    }
+
+    pub fn occlusion_settings(&self) -> &OcclusionSettings {
+        &self.occlusion_settings
+    }
+
+    /// Takes effect from the next frame.
+    pub fn set_occlusion_settings(&mut self, settings: OcclusionSettings) {
+        self.occlusion_settings = settings;
+    }
+
+    pub fn post_process_settings(&self) -> &PostProcessSettings {
+        &self.post_process_settings
+    }
+
+    /// Takes effect from the next frame. While disabled, or with no effect enabled, the
+    /// scene is drawn straight into the frame and the post-process targets are deleted.
+    pub fn set_post_process_settings(&mut self, settings: PostProcessSettings) {
+        self.post_process_settings = settings;
+    }
+
+    pub fn debug_settings(&self) -> &RenderDebugSettings {
+        &self.debug_settings
+    }
+
+    /// Takes effect from the next frame.
+    pub fn set_debug_settings(&mut self, settings: RenderDebugSettings) {
+        self.debug_settings = settings;
+    }
+
+    /// Appends an effect to the end of the post-process stack.
+    pub fn add_post_process_effect(&mut self, effect: Box<dyn PostProcessEffect<B>>) {
+        self.post_process_effects.push(effect);
+    }
+
+    /// The post-process stack, to reorder or remove effects, or to insert one before the
+    /// tone mapping so it still sees HDR colour.
+    pub fn post_process_effects_mut(&mut self) -> &mut Vec<Box<dyn PostProcessEffect<B>>> {
+        &mut self.post_process_effects
+    }
+
+    /// Adds a pass to the render graph, to run where the targets it reads and writes put
+    /// it. Fails if the name is taken or the pass would wait on passes waiting on it.
+    pub fn add_render_pass(
+        &mut self,
+        pass: Box<dyn RenderPass<B>>,
+    ) -> Result<(), RenderGraphError> {
+        self.render_graph.add(pass)
+    }
+
+    /// Removes a pass added with `add_render_pass`. The targets of the remaining custom
+    /// passes are recreated on their next frame.
+    pub fn remove_render_pass(&mut self, name: &str) -> Option<Box<dyn RenderPass<B>>> {
+        let pass = self.render_graph.remove(name)?;
+        self.graph_targets.delete_all(&mut self.backend);
+        Some(pass)
+    }
+
+    /// Names of the render graph's passes, in the order they run.
+    pub fn render_pass_order(&self) -> Vec<&str> {
+        self.render_graph.names_in_order()
+    }
+
+    /// Sets the depth-only shader used to render shadow maps. It needs the `position` and
+    /// `instance_model_col*` attributes and a `u_light_view_proj` uniform.
+    pub fn set_shadow_shader(&mut self, shader: Option<ShaderHandle>) {
+        self.shadow_shader = shader;
+    }
+
+    /// Sets the depth-only shader skinned meshes are drawn with in the shadow pass. It
+    /// needs the attributes of the shadow shader plus `joints`, `weights` and a
+    /// `u_joint_matrices` array.
+    pub fn set_skinned_shadow_shader(&mut self, shader: Option<ShaderHandle>) {
+        self.skinned_shadow_shader = shader;
+    }
+
+    /// Sets the shader debug shapes are drawn with. It reads a clip-space `vec4` at
+    /// attribute location 0 and an RGB colour at location 1.
+    pub fn set_debug_line_shader(&mut self, shader: Option<ShaderHandle>) {
+        self.debug_line_shader = shader;
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    pub fn frames_rendered(&self) -> u64 {
+        self.frames_rendered
     }
 
     /// Copies instances into the renderer's internal buffer.
@@ -126,6 +584,67 @@ impl Renderer {
     pub fn stage_instances(&mut self, instances: &[RenderInstance]) {
         self.frame_data.input_instances.clear();
         self.frame_data.input_instances.extend_from_slice(instances);
+        self.frame_data.changed_instances.clear();
+        self.frame_data
+            .changed_instances
+            .resize(instances.len(), true);
+        self.frame_data.groups_staged = false;
+    }
+
+    /// Copies only the `changed` ranges of `instances` into the renderer, keeping the
+    /// rest from the last frame. Everything is copied when the number of instances
+    /// changed. Call this instead of `stage_instances` with a persistent render queue.
+    pub fn stage_instance_changes(
+        &mut self,
+        instances: &[RenderInstance],
+        changed: &[Range<usize>],
+    ) {
+        if self.frame_data.input_instances.len() != instances.len() {
+            self.stage_instances(instances);
+            return;
+        }
+        for range in changed {
+            self.frame_data.input_instances[range.clone()]
+                .clone_from_slice(&instances[range.clone()]);
+            self.frame_data.changed_instances[range.clone()].fill(true);
+        }
+        self.frame_data.groups_staged = false;
+    }
+
+    /// Tells the renderer which entity staged which instances, so they are culled by
+    /// group through a bounding volume tree. Call this after `stage_instances`; groups
+    /// missing from one frame to the next are dropped from the tree.
+    pub fn stage_groups(&mut self, groups: &[RenderGroup]) {
+        self.spatial_index
+            .update(groups, self.frame_data.input_instances.len());
+        self.frame_data.groups_staged = true;
+    }
+
+    /// Copies the frame's joint matrices into the renderer. Call this before `render()`
+    /// whenever staged instances are skinned.
+    pub fn stage_joint_matrices(&mut self, joint_matrices: &[Mat4]) {
+        self.frame_data.joint_matrices.clear();
+        self.frame_data
+            .joint_matrices
+            .extend(joint_matrices.iter().map(Mat4::to_cols_array));
+    }
+
+    /// Copies the frame's lights into the renderer. Call this before `render()`.
+    pub fn stage_lights(&mut self, lights: &FrameLights) {
+        self.frame_data.lights.clone_from(lights);
+    }
+
+    /// Copies the debug shapes drawn over the frame into the renderer. Call this before
+    /// `render()`.
+    pub fn stage_debug_draw(&mut self, debug_draw: &DebugDraw) {
+        self.frame_data.debug_lines.clear();
+        self.frame_data
+            .debug_lines
+            .extend_from_slice(debug_draw.lines());
+        self.frame_data.debug_labels.clear();
+        self.frame_data
+            .debug_labels
+            .extend_from_slice(debug_draw.labels());
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -138,194 +657,235 @@ impl Renderer {
         shader_resource: &ShaderStorage,
         camera: Option<CameraRenderData>,
     ) {
-        let gl = self.gl.clone();
         // let current_time = std::time::Instant::now();
         // let mut draw_calls = 0;
 
-        unsafe {
-            gl.enable(glow::DEPTH_TEST);
-            gl.depth_func(glow::LEQUAL);
-            gl.clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
-            gl.enable(glow::CULL_FACE);
-            gl.cull_face(glow::BACK);
+        let (width, height) = (render_params.width, render_params.height);
+        let settings = &self.post_process_settings;
+        let post_processing = settings.enabled
+            && self.debug_settings.view == DebugView::Lit
+            && self
+                .post_process_effects
+                .iter()
+                .any(|effect| effect.is_enabled(settings));
+        if !post_processing && !self.post_process_targets.is_empty() {
+            self.post_process_targets.delete_all(&mut self.backend);
         }
-
-        let saved_viewport = {
-            let mut vp = [0; 4];
-            unsafe {
-                gl.get_parameter_i32_slice(glow::VIEWPORT, &mut vp);
-            }
-            vp
-        };
-
-        unsafe {
-            gl.viewport(
-                0,
-                0,
-                render_params.width as i32,
-                render_params.height as i32,
-            );
+        // The scene is lit into an HDR target, which the effects turn into the frame.
+        let scene_target = (post_processing && camera.is_some()).then(|| {
+            self.post_process_targets
+                .get(&mut self.backend, "scene", width, height, true)
+        });
+        if scene_target.is_some() {
+            self.backend.bind_color_target(scene_target);
         }
 
+        self.backend.begin_frame(width, height);
+
         let Some(camera) = camera else {
-            unsafe {
-                gl.viewport(
-                    saved_viewport[0],
-                    saved_viewport[1],
-                    saved_viewport[2],
-                    saved_viewport[3],
-                );
-                return;
-            }
+            self.backend.end_frame();
+            return;
         };
 
         let view_proj = camera.view_proj;
 
         // Set common uniforms
-        let default_light_color = Vec3::new(1.0, 1.0, 1.0);
-
         self.frame_data.frame_uniforms.view_proj = view_proj;
         self.frame_data.frame_uniforms.camera_position = camera.position;
-        self.frame_data.frame_uniforms.light_direction = Vec3::new(0.0, 0.0, 1.0);
-        self.frame_data.frame_uniforms.light_color = default_light_color;
 
+        // A scene without any lights still gets a sun so it isn't drawn black.
+        if self.frame_data.lights.is_empty() {
+            self.frame_data
+                .lights
+                .directional
+                .push(DirectionalLightData::DEFAULT_SUN);
+        }
+        self.frame_data.lights.limit_to_nearest(camera.position);
+        self.frame_data.lit_shaders.clear();
+
+        // Levels of detail are picked by size on the camera's screen in every pass, so
+        // shadows come from the meshes that are drawn.
+        let screen = ScreenProjection::new(&view_proj, render_params.height);
+
+        // Culling and batching happen once, before any pass runs.
         Self::frustum_culling(
             &mut self.frame_data.visible_instances,
             &self.frame_data.input_instances,
+            self.frame_data.groups_staged.then_some(&self.spatial_index),
             mesh_resource,
             &view_proj,
+            &screen,
         );
+        if self.occlusion_settings.enabled {
+            let settings = self.occlusion_settings;
+            self.occlusion_buffer
+                .resize(settings.width, settings.height);
+            Self::occlusion_culling(
+                &mut self.frame_data.visible_instances,
+                &mut self.occlusion_buffer,
+                mesh_resource,
+                &view_proj,
+            );
+        }
 
+        // Blended instances are drawn after the rest, farthest first, each over what is
+        // behind it.
+        let translucent = &mut self.frame_data.translucent_instances;
+        translucent.clear();
+        self.frame_data.visible_instances.retain(|inst| {
+            if !Self::is_blended(material_resource, inst.instance.material_id) {
+                return true;
+            }
+            translucent.push(inst.clone());
+            false
+        });
         Self::material_batcher(
             &mut self.frame_data.visible_instances,
-            &mut self.frame_data.material_batch_ranges,
-            &mut self.frame_data.mesh_batch_ranges,
-            &mut self.frame_data.instance_matrices,
+            &mut self.frame_data.opaque_batches,
+        );
+        Self::depth_sorted_batcher(
+            &mut self.frame_data.translucent_instances,
+            &mut self.frame_data.translucent_batches,
+            mesh_resource,
+            camera.position,
         );
 
-        for mat_idx in 0..self.frame_data.material_batch_ranges.len() {
-            let material_id = self.frame_data.material_batch_ranges[mat_idx].material_id;
-            let mesh_range = self.frame_data.material_batch_ranges[mat_idx]
-                .mesh_batches
-                .clone();
-
-            let material = material_resource
-                .get_material(material_id)
-                .expect("Material not found");
-            let shader = shader_resource
-                .get_shader(material.desc.shader)
-                .expect("Shader not found");
-
-            // Bind shader
-            unsafe {
-                gl.use_program(Some(shader.program));
-            }
-
-            // 1. Bind frame uniforms
-            if let Some(loc) = shader.get_uniform("u_view_proj") {
-                Self::bind_uniform(
-                    &gl,
-                    &loc,
-                    &UniformValue::Mat4(self.frame_data.frame_uniforms.view_proj),
-                    texture_resource,
-                );
-            }
-            if let Some(loc) = shader.get_uniform("u_camera_position") {
-                Self::bind_uniform(
-                    &gl,
-                    &loc,
-                    &UniformValue::Vec3(self.frame_data.frame_uniforms.camera_position),
-                    texture_resource,
-                );
-            }
-            if let Some(loc) = shader.get_uniform("u_light_direction") {
-                Self::bind_uniform(
-                    &gl,
-                    &loc,
-                    &UniformValue::Vec3(self.frame_data.frame_uniforms.light_direction),
-                    texture_resource,
-                );
-            }
-            if let Some(loc) = shader.get_uniform("u_light_color") {
-                Self::bind_uniform(
-                    &gl,
-                    &loc,
-                    &UniformValue::Vec3(self.frame_data.frame_uniforms.light_color),
-                    texture_resource,
-                );
-            }
-
-            let mut textures_bound: u32 = 0;
-
-            // 2. Bind material uniforms
-            for (name, value) in &material.desc.params {
-                if let Some(loc) = shader.get_uniform(name) {
-                    textures_bound += Self::bind_uniform(&gl, &loc, value, texture_resource);
-                }
-            }
-
-            // Draw each mesh
-            for mesh_idx in mesh_range {
-                let mesh_id = self.frame_data.mesh_batch_ranges[mesh_idx].mesh_id;
-                let matrices_range = self.frame_data.mesh_batch_ranges[mesh_idx].matrices.clone();
-                let matrices_slice = &self.frame_data.instance_matrices[matrices_range];
-
-                let vao = Self::get_or_create_vao(
-                    &mut self.vao_cache,
-                    &gl,
-                    mesh_id,
-                    &material.desc.shader,
-                    shader_resource,
-                    mesh_resource,
-                    &mut self.mesh_render_data,
-                );
-                Self::update_instance_buffer(
-                    &gl,
-                    mesh_id,
-                    &mut self.mesh_render_data,
-                    matrices_slice,
-                );
-
-                let index_count: i32 = {
-                    mesh_resource
-                        .get_mesh(mesh_id)
-                        .unwrap_or_else(|| panic!("Couldn't find mesh: {:?}", mesh_id))
-                        .indices
-                        .len() as i32
-                };
-
-                unsafe {
-                    gl.bind_vertex_array(Some(vao));
-                    gl.draw_elements_instanced(
-                        glow::TRIANGLES,
-                        index_count,
-                        glow::UNSIGNED_INT,
-                        0,
-                        matrices_slice.len() as i32,
-                    );
+        // Overdraw adds up every surface drawn, so depth neither hides nor sorts them.
+        let overdraw = self.debug_settings.view == DebugView::Overdraw;
+        let mut shadow_map = None;
+        for position in 0..self.render_graph.order.len() {
+            let index = self.render_graph.order[position];
+            let clears_output = self.render_graph.clears_output[index];
+            match &mut self.render_graph.nodes[index] {
+                GraphNode::Builtin(pass) => {
+                    let pass = *pass;
+                    match pass {
+                        BuiltinPass::Shadows => {
+                            shadow_map = self.shadow_pass(
+                                mesh_resource,
+                                material_resource,
+                                shader_resource,
+                                &camera,
+                                &screen,
+                            );
+                        }
+                        BuiltinPass::Opaque => {
+                            let opaque = mem::take(&mut self.frame_data.opaque_batches);
+                            if overdraw {
+                                self.backend.set_additive_blending(true);
+                            }
+                            self.draw_batches(
+                                DrawPass::Opaque,
+                                &opaque,
+                                mesh_resource,
+                                material_resource,
+                                texture_resource,
+                                shader_resource,
+                            );
+                            if overdraw {
+                                self.backend.set_additive_blending(false);
+                            }
+                            self.frame_data.opaque_batches = opaque;
+                        }
+                        BuiltinPass::Translucent => {
+                            let translucent = mem::take(&mut self.frame_data.translucent_batches);
+                            if !translucent.material_batch_ranges.is_empty() {
+                                if overdraw {
+                                    self.backend.set_additive_blending(true);
+                                } else {
+                                    self.backend.set_blending(true);
+                                }
+                                self.draw_batches(
+                                    DrawPass::Translucent,
+                                    &translucent,
+                                    mesh_resource,
+                                    material_resource,
+                                    texture_resource,
+                                    shader_resource,
+                                );
+                                if overdraw {
+                                    self.backend.set_additive_blending(false);
+                                } else {
+                                    self.backend.set_blending(false);
+                                }
+                            }
+                            self.frame_data.translucent_batches = translucent;
+                        }
+                        BuiltinPass::PostProcess => {
+                            if let Some(scene_target) = scene_target {
+                                self.post_process(
+                                    scene_target,
+                                    width,
+                                    height,
+                                    texture_resource,
+                                    shader_resource,
+                                );
+                            }
+                        }
+                        BuiltinPass::DebugLines => {
+                            self.debug_line_pass(
+                                &view_proj,
+                                width,
+                                height,
+                                shader_resource,
+                                scene_target,
+                            );
+                        }
+                    }
                 }
-                // draw_calls += 1;
-            }
-
-            // Now that we are done with the material, unbind any textures it used
-            for unit in 0..textures_bound {
-                unsafe {
-                    gl.active_texture(glow::TEXTURE0 + unit);
-                    gl.bind_texture(glow::TEXTURE_2D, None);
+                GraphNode::Custom(pass) => {
+                    // Custom passes draw into their first output, after which the scene
+                    // target is bound again for the passes that follow.
+                    let output = pass.outputs().first().copied();
+                    let (bound, clear) = match output {
+                        Some(SCENE_COLOR_TARGET) => (Some(scene_target), false),
+                        Some(FRAME_TARGET) => (Some(None), false),
+                        Some(SHADOW_MAP_TARGET) | None => (None, false),
+                        Some(name) => {
+                            let target = self.graph_targets.get(
+                                &mut self.backend,
+                                name,
+                                width,
+                                height,
+                                true,
+                            );
+                            (Some(Some(target)), clears_output)
+                        }
+                    };
+                    if let Some(target) = bound {
+                        self.backend.bind_color_target(target);
+                    }
+                    if clear {
+                        self.backend.clear();
+                    }
+                    pass.execute(&mut RenderPassContext {
+                        backend: &mut self.backend,
+                        meshes: mesh_resource,
+                        materials: material_resource,
+                        textures: texture_resource,
+                        shaders: shader_resource,
+                        camera: &camera,
+                        width,
+                        height,
+                        scene_target,
+                        targets: &self.graph_targets,
+                    });
+                    if bound.is_some() {
+                        self.backend.bind_color_target(scene_target);
+                    }
                 }
             }
         }
 
-        unsafe {
-            gl.bind_vertex_array(None);
-            gl.viewport(
-                saved_viewport[0],
-                saved_viewport[1],
-                saved_viewport[2],
-                saved_viewport[3],
-            );
+        if shadow_map.is_some() {
+            self.backend.bind_shadow_map(SHADOW_MAP_TEXTURE_UNIT, None);
         }
 
+        self.backend.end_frame();
+
+        let frame = self.frames_rendered;
+        self.delete_streams(|_, stream| frame - stream.last_used > UNUSED_STREAM_FRAMES);
+        self.frame_data.changed_instances.fill(false);
         self.frames_rendered += 1;
 
         // if self.frames_rendered % 60 == 0 {
@@ -338,49 +898,637 @@ impl Renderer {
         // }
     }
 
-    /// Groups visible instances into material → mesh batches using a sort
-    /// instead of hash maps. All output is written into caller-owned `Vec`s
-    /// that are `.clear()`-ed here and reused across frames, so after the
-    /// first few frames there are zero allocations.
-    fn material_batcher(
-        instances: &mut [RenderInstance],
-        material_ranges: &mut Vec<MaterialBatchRange>,
-        mesh_ranges: &mut Vec<MeshBatchRange>,
-        matrices: &mut Vec<[f32; 16]>,
-    ) {
-        material_ranges.clear();
-        mesh_ranges.clear();
-        matrices.clear();
-
-        // Sort by (material, mesh) so identical keys are contiguous.
+    /// Renders the staged instances into an offscreen target of `render_params`' size
+    /// instead of the window, then reads the pixels back.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_to_image(
+        &mut self,
+        render_params: RenderParams,
+        mesh_resource: &MeshStorage,
+        material_resource: &MaterialStorage,
+        texture_resource: &TextureStorage,
+        shader_resource: &ShaderStorage,
+        camera: Option<CameraRenderData>,
+    ) -> RgbaImage {
+        let (width, height) = (render_params.width, render_params.height);
+        let target = self.offscreen_target(width, height);
+
+        self.backend.bind_render_target(Some(target));
+        self.render(
+            render_params,
+            mesh_resource,
+            material_resource,
+            texture_resource,
+            shader_resource,
+            camera,
+        );
+        let pixels = self.backend.read_pixels(width, height);
+        self.backend.bind_render_target(None);
+
+        // GL rows start at the bottom, image rows at the top.
+        let row_len = width as usize * 4;
+        let mut flipped = Vec::with_capacity(pixels.len());
+        for row in pixels.chunks_exact(row_len).rev() {
+            flipped.extend_from_slice(row);
+        }
+        RgbaImage::from_raw(width, height, flipped).expect("Pixel buffer has the wrong size")
+    }
+
+    /// Renders the shadow maps and binds them for the passes that follow. Returns the
+    /// shadow map if any cascade was drawn.
+    fn shadow_pass(
+        &mut self,
+        mesh_resource: &MeshStorage,
+        material_resource: &MaterialStorage,
+        shader_resource: &ShaderStorage,
+        camera: &CameraRenderData,
+        screen: &ScreenProjection,
+    ) -> Option<B::ShadowMap> {
+        self.render_shadow_maps(
+            mesh_resource,
+            material_resource,
+            shader_resource,
+            camera,
+            screen,
+        );
+        let shadow_map = self
+            .shadow_map
+            .as_ref()
+            .filter(|_| !self.frame_data.shadow_cascades.is_empty())
+            .map(|target| target.shadow_map);
+        if shadow_map.is_some() {
+            self.backend
+                .bind_shadow_map(SHADOW_MAP_TEXTURE_UNIT, shadow_map);
+        }
+        shadow_map
+    }
+
+    /// Runs the enabled effects over `scene_target`, each reading the output of the one
+    /// before. Outputs alternate between two targets and the last effect draws into the
+    /// frame.
+    fn post_process(
+        &mut self,
+        scene_target: B::ColorTarget,
+        width: u32,
+        height: u32,
+        texture_resource: &TextureStorage,
+        shader_resource: &ShaderStorage,
+    ) {
+        let settings = self.post_process_settings;
+        let last = self
+            .post_process_effects
+            .iter()
+            .rposition(|effect| effect.is_enabled(&settings));
+
+        let mut input = scene_target;
+        let mut ping = true;
+        for (index, effect) in self.post_process_effects.iter_mut().enumerate() {
+            if !effect.is_enabled(&settings) {
+                continue;
+            }
+            let output = (Some(index) != last).then(|| {
+                let name = if ping { "post_ping" } else { "post_pong" };
+                ping = !ping;
+                self.post_process_targets
+                    .get(&mut self.backend, name, width, height, false)
+            });
+            effect.apply(&mut PostProcessPass {
+                backend: &mut self.backend,
+                shaders: shader_resource,
+                textures: texture_resource,
+                targets: &mut self.post_process_targets,
+                settings: &settings,
+                width,
+                height,
+                input,
+                output,
+            });
+            if let Some(output) = output {
+                input = output;
+            }
+        }
+    }
+
+    /// Draws the staged debug shapes over the frame. Lines are projected here, and label
+    /// strokes are placed in pixels around their projected position, so the shader only
+    /// passes clip-space positions through.
+    fn debug_line_pass(
+        &mut self,
+        view_proj: &Mat4,
+        width: u32,
+        height: u32,
+        shader_resource: &ShaderStorage,
+        scene_target: Option<B::ColorTarget>,
+    ) {
+        let frame_data = &mut self.frame_data;
+        if frame_data.debug_lines.is_empty() && frame_data.debug_labels.is_empty() {
+            return;
+        }
+        let Some(shader) = self
+            .debug_line_shader
+            .and_then(|shader| shader_resource.get_shader(shader))
+        else {
+            return;
+        };
+
+        let vertices = &mut frame_data.debug_vertices;
+        vertices.clear();
+        for line in &frame_data.debug_lines {
+            for point in [line.start, line.end] {
+                vertices.push(DebugVertex {
+                    position: (*view_proj * point.extend(1.0)).to_array(),
+                    color: line.color.to_array(),
+                });
+            }
+        }
+        for label in &frame_data.debug_labels {
+            let anchor = *view_proj * label.position.extend(1.0);
+            if anchor.w <= 0.0 {
+                continue;
+            }
+            // Offsets scaled by `w` come out of the perspective divide as whole pixels.
+            let pixel = Vec2::new(2.0 / width as f32, 2.0 / height as f32) * anchor.w;
+            for (start, end) in label_segments(&label.text) {
+                for offset in [start, end] {
+                    vertices.push(DebugVertex {
+                        position: (anchor + (offset * pixel).extend(0.0).extend(0.0)).to_array(),
+                        color: label.color.to_array(),
+                    });
+                }
+            }
+        }
+
+        let backend = &mut self.backend;
+        let (buffers, vertex_array) = *self.debug_line_buffers.get_or_insert_with(|| {
+            let vertex = backend.create_buffer(BufferKind::Vertex, &[]);
+            let buffers = MeshBuffers {
+                vertex,
+                index: backend.create_buffer(BufferKind::Index, &[]),
+                instance: vertex,
+            };
+            let stride = size_of::<DebugVertex>() as i32;
+            let bindings = [
+                VertexAttribBinding {
+                    location: 0,
+                    source: AttribSource::Vertex,
+                    components: 4,
+                    stride,
+                    offset: offset_of!(DebugVertex, position) as i32,
+                    divisor: 0,
+                },
+                VertexAttribBinding {
+                    location: 1,
+                    source: AttribSource::Vertex,
+                    components: 3,
+                    stride,
+                    offset: offset_of!(DebugVertex, color) as i32,
+                    divisor: 0,
+                },
+            ];
+            (buffers, backend.create_vertex_array(buffers, &bindings))
+        });
+        backend.update_buffer(buffers.vertex, bytemuck::cast_slice(vertices));
+
+        // The shapes go over the finished frame, after post-processing.
+        if scene_target.is_some() {
+            backend.bind_color_target(None);
+        }
+        backend.use_program(shader.program.map(Into::into));
+        backend.draw_lines(vertex_array, vertices.len() as i32);
+        if scene_target.is_some() {
+            backend.bind_color_target(scene_target);
+        }
+    }
+
+    /// Renders a depth map per cascade for the brightest directional light, seen from the
+    /// light. Each cascade culls and batches the staged instances on its own, since casters
+    /// outside the camera's view still throw shadows into it. Blended instances cast none.
+    fn render_shadow_maps(
+        &mut self,
+        mesh_resource: &MeshStorage,
+        material_resource: &MaterialStorage,
+        shader_resource: &ShaderStorage,
+        camera: &CameraRenderData,
+        screen: &ScreenProjection,
+    ) {
+        self.frame_data.shadow_cascades.clear();
+        let settings = self.shadow_settings;
+        let (true, Some(shader_handle), Some(sun)) = (
+            settings.enabled,
+            self.shadow_shader,
+            self.frame_data.lights.directional.first(),
+        ) else {
+            return;
+        };
+        let Some(shader) = shader_resource.get_shader(shader_handle) else {
+            return;
+        };
+
+        compute_cascades(
+            camera.view_proj,
+            camera.near,
+            camera.far,
+            sun.direction,
+            &settings,
+            &mut self.frame_data.shadow_cascades,
+        );
+        if self.frame_data.shadow_cascades.is_empty() {
+            return;
+        }
+
+        let skinned_shader = self
+            .skinned_shadow_shader
+            .and_then(|handle| Some((handle, shader_resource.get_shader(handle)?)));
+
+        let shadow_map =
+            self.shadow_map(settings.resolution, settings.clamped_cascade_count() as u32);
+
+        for layer in 0..self.frame_data.shadow_cascades.len() {
+            let cascade = self.frame_data.shadow_cascades[layer];
+            Self::frustum_culling(
+                &mut self.frame_data.shadow_instances,
+                &self.frame_data.input_instances,
+                self.frame_data.groups_staged.then_some(&self.spatial_index),
+                mesh_resource,
+                &cascade.light_view_proj,
+                screen,
+            );
+            self.frame_data
+                .shadow_instances
+                .retain(|inst| !Self::is_blended(material_resource, inst.instance.material_id));
+            Self::material_batcher(
+                &mut self.frame_data.shadow_instances,
+                &mut self.frame_data.shadow_batches,
+            );
+
+            self.backend.begin_shadow_pass(shadow_map, layer as u32);
+
+            // Depth doesn't depend on the material, so every mesh batch is drawn the same
+            // way: rigid ones with the shadow shader, then skinned ones with theirs.
+            let passes = [
+                (Some((shader_handle, shader)), false),
+                (skinned_shader, true),
+            ];
+            for (pass_shader, skinned) in passes {
+                let Some((pass_handle, pass_shader)) = pass_shader else {
+                    continue;
+                };
+                let mut batches = self
+                    .frame_data
+                    .shadow_batches
+                    .mesh_batch_ranges
+                    .iter()
+                    .filter(|batch| batch.joints.is_some() == skinned)
+                    .peekable();
+                if batches.peek().is_none() {
+                    continue;
+                }
+
+                self.backend
+                    .use_program(pass_shader.program.map(Into::into));
+                if let Some(loc) = pass_shader.get_uniform("u_light_view_proj") {
+                    self.backend
+                        .set_uniform(&loc.into(), &UniformValue::Mat4(cascade.light_view_proj));
+                }
+                let mut stream = None;
+                for batch in batches {
+                    if let Some(joints) = &batch.joints {
+                        Self::bind_joint_matrices(
+                            &mut self.backend,
+                            pass_shader,
+                            &self.frame_data.joint_matrices[joints.clone()],
+                        );
+                    }
+                    let batches = &self.frame_data.shadow_batches;
+                    let instances = &batches.instance_data[batch.instances.clone()];
+                    let indices = &batches.instance_indices[batch.instances.clone()];
+                    let key = StreamKey {
+                        pass: DrawPass::Shadow(layer as u32),
+                        material: batch.material_id,
+                        mesh: batch.mesh_id,
+                        shader: pass_handle,
+                        ordinal: 0,
+                    }
+                    .after(stream);
+                    stream = Some(key);
+                    let vao = Self::upload_instances(
+                        &mut self.backend,
+                        &mut self.instance_streams,
+                        key,
+                        instances,
+                        indices,
+                        &self.frame_data.changed_instances,
+                        self.frames_rendered,
+                        shader_resource,
+                        mesh_resource,
+                        &mut self.mesh_render_data,
+                    );
+                    let index_count = mesh_resource
+                        .get_mesh(batch.mesh_id)
+                        .unwrap_or_else(|| panic!("Couldn't find mesh: {:?}", batch.mesh_id))
+                        .indices
+                        .len() as i32;
+                    self.backend
+                        .draw_elements_instanced(vao, index_count, instances.len() as i32);
+                }
+            }
+            self.backend.end_shadow_pass();
+        }
+    }
+
+    /// Draws `batches` material by material into the bound target.
+    fn draw_batches(
+        &mut self,
+        pass: DrawPass,
+        batches: &DrawBatches,
+        mesh_resource: &MeshStorage,
+        material_resource: &MaterialStorage,
+        texture_resource: &TextureStorage,
+        shader_resource: &ShaderStorage,
+    ) {
+        self.frame_data.translucent_runs.clear();
+        for mat_idx in 0..batches.material_batch_ranges.len() {
+            let material_id = batches.material_batch_ranges[mat_idx].material_id;
+            let mesh_range = batches.material_batch_ranges[mat_idx].mesh_batches.clone();
+
+            let material = material_resource
+                .get_material(material_id)
+                .expect("Material not found");
+            let shader = shader_resource
+                .get_shader(material.desc.shader)
+                .expect("Shader not found");
+
+            // Bind shader
+            self.backend.use_program(shader.program.map(Into::into));
+
+            // 1. Bind frame uniforms
+            if let Some(loc) = shader.get_uniform("u_view_proj") {
+                Self::bind_uniform(
+                    &mut self.backend,
+                    &loc,
+                    &UniformValue::Mat4(self.frame_data.frame_uniforms.view_proj),
+                    texture_resource,
+                );
+            }
+            if let Some(loc) = shader.get_uniform("u_camera_position") {
+                Self::bind_uniform(
+                    &mut self.backend,
+                    &loc,
+                    &UniformValue::Vec3(self.frame_data.frame_uniforms.camera_position),
+                    texture_resource,
+                );
+            }
+            let debug = self.debug_settings;
+            for (name, value) in [
+                ("u_debug_view", UniformValue::Int(debug.view.shader_index())),
+                ("u_wireframe", UniformValue::Int(debug.wireframe as i32)),
+                (
+                    "u_wireframe_color",
+                    UniformValue::Vec3(debug.wireframe_color),
+                ),
+            ] {
+                if let Some(loc) = shader.get_uniform(name) {
+                    Self::bind_uniform(&mut self.backend, &loc, &value, texture_resource);
+                }
+            }
+            // Uniform values stay with the program, so the (many) light uniforms are only
+            // set for the first material using each shader.
+            if !self.frame_data.lit_shaders.contains(&material.desc.shader) {
+                self.frame_data.lit_shaders.push(material.desc.shader);
+                self.light_uniforms
+                    .entry(material.desc.shader)
+                    .expect("Shader not found")
+                    .or_insert_with(|| LightUniformLocations::new(shader))
+                    .bind(&mut self.backend, &self.frame_data.lights);
+                self.shadow_uniforms
+                    .entry(material.desc.shader)
+                    .expect("Shader not found")
+                    .or_insert_with(|| ShadowUniformLocations::new(shader))
+                    .bind(&mut self.backend, &self.frame_data.shadow_cascades);
+            }
+
+            let mut textures_bound: u32 = 0;
+
+            // 2. Bind material uniforms
+            for (name, value) in &material.desc.params {
+                if let Some(loc) = shader.get_uniform(name) {
+                    textures_bound +=
+                        Self::bind_uniform(&mut self.backend, &loc, value, texture_resource);
+                }
+            }
+
+            // Uniform values stay with the program, so every material sets its alpha mode.
+            let (alpha_mode, alpha_cutoff) = match material.desc.blend_mode {
+                BlendMode::Opaque => (0, 0.0),
+                BlendMode::Mask { cutoff } => (1, cutoff),
+                BlendMode::Blend => (2, 0.0),
+            };
+            if let Some(loc) = shader.get_uniform("u_alpha_mode") {
+                self.backend
+                    .set_uniform(&loc.into(), &UniformValue::Int(alpha_mode));
+            }
+            if let Some(loc) = shader.get_uniform("u_alpha_cutoff") {
+                self.backend
+                    .set_uniform(&loc.into(), &UniformValue::Float(alpha_cutoff));
+            }
+
+            // Draw each mesh
+            let mut stream = None;
+            for mesh_idx in mesh_range {
+                let mesh_id = batches.mesh_batch_ranges[mesh_idx].mesh_id;
+                let instance_range = batches.mesh_batch_ranges[mesh_idx].instances.clone();
+                let instance_slice = &batches.instance_data[instance_range.clone()];
+                let indices = &batches.instance_indices[instance_range];
+                if let Some(joints) = &batches.mesh_batch_ranges[mesh_idx].joints {
+                    Self::bind_joint_matrices(
+                        &mut self.backend,
+                        shader,
+                        &self.frame_data.joint_matrices[joints.clone()],
+                    );
+                }
+
+                let key = StreamKey {
+                    pass,
+                    material: material_id,
+                    mesh: mesh_id,
+                    shader: material.desc.shader,
+                    ordinal: 0,
+                };
+                // Depth sorting can bring a material and mesh back later in the pass, so
+                // translucent draws are numbered by the draws of theirs before them, which
+                // stays the same as the camera moves unless the runs themselves change.
+                let key = match pass {
+                    DrawPass::Translucent => {
+                        let runs = self
+                            .frame_data
+                            .translucent_runs
+                            .entry((material_id, mesh_id))
+                            .or_insert(0);
+                        *runs += 1;
+                        StreamKey {
+                            ordinal: *runs - 1,
+                            ..key
+                        }
+                    }
+                    _ => key.after(stream),
+                };
+                stream = Some(key);
+                let vao = Self::upload_instances(
+                    &mut self.backend,
+                    &mut self.instance_streams,
+                    key,
+                    instance_slice,
+                    indices,
+                    &self.frame_data.changed_instances,
+                    self.frames_rendered,
+                    shader_resource,
+                    mesh_resource,
+                    &mut self.mesh_render_data,
+                );
+
+                let index_count: i32 = {
+                    mesh_resource
+                        .get_mesh(mesh_id)
+                        .unwrap_or_else(|| panic!("Couldn't find mesh: {:?}", mesh_id))
+                        .indices
+                        .len() as i32
+                };
+
+                self.backend
+                    .draw_elements_instanced(vao, index_count, instance_slice.len() as i32);
+                // draw_calls += 1;
+            }
+
+            // Now that we are done with the material, unbind any textures it used
+            for unit in 0..textures_bound {
+                self.backend.bind_texture(unit, None);
+            }
+        }
+    }
+
+    fn shadow_map(&mut self, resolution: u32, layers: u32) -> B::ShadowMap {
+        if let Some(existing) = &self.shadow_map {
+            if existing.resolution == resolution && existing.layers == layers {
+                return existing.shadow_map;
+            }
+            self.backend.delete_shadow_map(existing.shadow_map);
+        }
+
+        let shadow_map = self.backend.create_shadow_map(resolution, layers);
+        self.shadow_map = Some(ShadowMapTarget {
+            shadow_map,
+            resolution,
+            layers,
+        });
+        shadow_map
+    }
+
+    fn offscreen_target(&mut self, width: u32, height: u32) -> B::RenderTarget {
+        if let Some(existing) = &self.offscreen_target {
+            if existing.width == width && existing.height == height {
+                return existing.target;
+            }
+            self.backend.delete_render_target(existing.target);
+        }
+
+        let target = self.backend.create_render_target(width, height);
+        self.offscreen_target = Some(OffscreenTarget {
+            target,
+            width,
+            height,
+        });
+        target
+    }
+
+    /// Groups visible instances into material → mesh batches using a sort
+    /// instead of hash maps. All output is written into caller-owned `Vec`s
+    /// that are `.clear()`-ed here and reused across frames, so after the
+    /// first few frames there are zero allocations.
+    fn material_batcher(instances: &mut [VisibleInstance], batches: &mut DrawBatches) {
+        // Sort by (material, mesh, skinned) so identical keys are contiguous.
         instances.sort_unstable_by(|a, b| {
+            let (a, b) = (&a.instance, &b.instance);
             a.material_id
                 .cmp(&b.material_id)
                 .then(a.mesh_id.cmp(&b.mesh_id))
+                .then(a.joints.is_some().cmp(&b.joints.is_some()))
         });
+        Self::batch_runs(instances, batches);
+    }
+
+    /// Orders instances farthest from `camera_position` first, then batches the runs of
+    /// neighbours sharing a material and mesh.
+    fn depth_sorted_batcher(
+        instances: &mut [VisibleInstance],
+        batches: &mut DrawBatches,
+        mesh_resource: &MeshStorage,
+        camera_position: Vec3,
+    ) {
+        let distance = |inst: &VisibleInstance| {
+            let (center, _) = mesh_resource
+                .get_mesh(inst.instance.mesh_id)
+                .expect("Mesh not found")
+                .world_bounding_sphere(&inst.instance.transform);
+            center.distance_squared(camera_position)
+        };
+        // A stable sort keeps instances at the same distance from flickering.
+        instances.sort_by(|a, b| distance(b).total_cmp(&distance(a)));
+        Self::batch_runs(instances, batches);
+    }
+
+    /// Batches runs of instances sharing a material, and within those runs of instances
+    /// sharing a mesh, keeping their order.
+    fn batch_runs(instances: &[VisibleInstance], batches: &mut DrawBatches) {
+        let DrawBatches {
+            instance_data,
+            instance_indices,
+            mesh_batch_ranges: mesh_ranges,
+            material_batch_ranges: material_ranges,
+        } = batches;
+        material_ranges.clear();
+        mesh_ranges.clear();
+        instance_data.clear();
+        instance_indices.clear();
 
         let mut i = 0;
         while i < instances.len() {
-            let material_id = instances[i].material_id;
+            let material_id = instances[i].instance.material_id;
             let mesh_batches_start = mesh_ranges.len();
 
             // Walk all instances that share this material.
-            while i < instances.len() && instances[i].material_id == material_id {
-                let mesh_id = instances[i].mesh_id;
-                let matrices_start = matrices.len();
+            while i < instances.len() && instances[i].instance.material_id == material_id {
+                let mesh_id = instances[i].instance.mesh_id;
+                let instances_start = instance_data.len();
+
+                if let Some(joints) = &instances[i].instance.joints {
+                    instance_data.push(InstanceData::new(&instances[i].instance));
+                    instance_indices.push(instances[i].index);
+                    mesh_ranges.push(MeshBatchRange {
+                        material_id,
+                        mesh_id,
+                        instances: instances_start..instance_data.len(),
+                        joints: Some(joints.clone()),
+                    });
+                    i += 1;
+                    continue;
+                }
 
-                // Walk all instances that share this material AND mesh.
+                // Walk all unskinned instances that share this material AND mesh.
                 while i < instances.len()
-                    && instances[i].material_id == material_id
-                    && instances[i].mesh_id == mesh_id
+                    && instances[i].instance.material_id == material_id
+                    && instances[i].instance.mesh_id == mesh_id
+                    && instances[i].instance.joints.is_none()
                 {
-                    matrices.push(instances[i].transform.to_cols_array());
+                    instance_data.push(InstanceData::new(&instances[i].instance));
+                    instance_indices.push(instances[i].index);
                     i += 1;
                 }
 
                 mesh_ranges.push(MeshBatchRange {
+                    material_id,
                     mesh_id,
-                    matrices: matrices_start..matrices.len(),
+                    instances: instances_start..instance_data.len(),
+                    joints: None,
                 });
             }
 
@@ -391,321 +1539,1926 @@ impl Renderer {
         }
     }
 
+    /// Whether instances of `material` are drawn in the translucent pass.
+    fn is_blended(material_resource: &MaterialStorage, material: MaterialHandle) -> bool {
+        material_resource
+            .get_material(material)
+            .is_some_and(|material| material.desc.blend_mode == BlendMode::Blend)
+    }
+
+    /// Collects the instances whose bounding sphere is inside `view_proj`'s frustum,
+    /// skipping groups of `index` outside it. Instances with levels of detail are
+    /// replaced by the meshes picked for their size on `screen`, which may be none or two
+    /// while cross-fading.
     pub fn frustum_culling(
-        visible_instances: &mut Vec<RenderInstance>,
+        visible_instances: &mut Vec<VisibleInstance>,
         instances: &[RenderInstance],
+        index: Option<&RenderSpatialIndex>,
         mesh_resource: &MeshStorage,
         view_proj: &Mat4,
+        screen: &ScreenProjection,
     ) {
         visible_instances.clear();
         let frustum = Frustum::from_view_proj(view_proj);
 
-        for inst in instances {
+        let mut cull = |range: Range<usize>, group_inside: bool| {
+            for (index, inst) in range.clone().zip(&instances[range]) {
+                let mut visible = |instance: RenderInstance| {
+                    visible_instances.push(VisibleInstance { index, instance });
+                };
+                // Nothing to measure for an instance in a group entirely on screen.
+                if group_inside && inst.lod.is_none() {
+                    visible(inst.clone());
+                    continue;
+                }
+
+                let (world_center, world_radius) = mesh_resource
+                    .get_mesh(inst.mesh_id)
+                    .expect("Mesh not found")
+                    .world_bounding_sphere(&inst.transform);
+                if !group_inside && !frustum.intersects_sphere(world_center, world_radius) {
+                    continue;
+                }
+                let Some(lod) = &inst.lod else {
+                    visible(inst.clone());
+                    continue;
+                };
+                let pixels = screen.pixels(world_center, world_radius);
+                for draw in lod.select(inst.mesh_id, pixels).into_iter().flatten() {
+                    visible(RenderInstance {
+                        mesh_id: draw.mesh_id,
+                        lod_fade: draw.fade,
+                        ..inst.clone()
+                    });
+                }
+            }
+        };
+        match index {
+            Some(index) => index.query(&frustum, cull),
+            None => cull(0..instances.len(), false),
+        }
+    }
+
+    /// Draws the visible occluders into `buffer`, then drops every other visible instance
+    /// whose bounding box is behind them. Occluders that are only partly drawn while
+    /// cross-fading don't hide anything, and skinned instances are always kept since
+    /// their bind-pose bounds needn't cover their pose.
+    pub fn occlusion_culling(
+        visible_instances: &mut Vec<VisibleInstance>,
+        buffer: &mut OcclusionBuffer,
+        mesh_resource: &MeshStorage,
+        view_proj: &Mat4,
+    ) {
+        buffer.clear();
+        let mut any_occluder = false;
+        for VisibleInstance { instance: inst, .. } in visible_instances.iter() {
+            if !inst.occluder || inst.lod_fade != 1.0 || inst.joints.is_some() {
+                continue;
+            }
             let mesh = mesh_resource
                 .get_mesh(inst.mesh_id)
                 .expect("Mesh not found");
+            buffer.rasterize_mesh(mesh, &(*view_proj * inst.transform));
+            any_occluder = true;
+        }
+        if !any_occluder {
+            return;
+        }
 
-            let scale = Self::max_scale(inst.transform);
-            let world_center = inst.transform.transform_point3(mesh.sphere_center);
-            let world_radius = mesh.sphere_radius * scale;
-
-            if frustum.intersects_sphere(world_center, world_radius) {
-                visible_instances.push(inst.clone());
+        visible_instances.retain(|VisibleInstance { instance: inst, .. }| {
+            if inst.occluder || inst.joints.is_some() {
+                return true;
             }
-        }
+            let mesh = mesh_resource
+                .get_mesh(inst.mesh_id)
+                .expect("Mesh not found");
+            buffer.is_box_visible(&mesh.aabb, &(*view_proj * inst.transform))
+        });
     }
 
     pub fn upload_mesh_to_gpu(
-        gl: &glow::Context,
+        backend: &mut B,
         mesh: &Mesh,
         handle: MeshHandle,
-        mesh_render_data: &mut SecondaryMap<MeshHandle, MeshRenderData>,
+        mesh_render_data: &mut SecondaryMap<MeshHandle, MeshRenderData<B::Buffer>>,
     ) {
-        unsafe {
-            // Unbind any active VAO so that EBO binding below does not
-            // corrupt a previously-created VAO's element-buffer state.
-            gl.bind_vertex_array(None);
-
-            // --- Create GPU objects ---
-            let vbo = gl.create_buffer().unwrap();
-            let ebo = gl.create_buffer().unwrap();
-            let instance_vbo = gl.create_buffer().unwrap();
-
-            // Upload vertex data
-            gl.bind_buffer(glow::ARRAY_BUFFER, Some(vbo));
-            gl.buffer_data_u8_slice(
-                glow::ARRAY_BUFFER,
-                bytemuck::cast_slice(&mesh.vertices),
-                glow::STATIC_DRAW,
-            );
+        let vbo = backend.create_buffer(BufferKind::Vertex, bytemuck::cast_slice(&mesh.vertices));
+        let ebo = backend.create_buffer(BufferKind::Index, bytemuck::cast_slice(&mesh.indices));
 
-            // Upload index data
-            gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(ebo));
-            gl.buffer_data_u8_slice(
-                glow::ELEMENT_ARRAY_BUFFER,
-                bytemuck::cast_slice(&mesh.indices),
-                glow::STATIC_DRAW,
-            );
+        let mesh_data = MeshRenderData {
+            vbo: Some(vbo),
+            ebo: Some(ebo),
+        };
+        mesh_render_data.insert(handle, mesh_data);
+    }
 
-            // Allocate empty instance buffer (resized on update)
-            gl.bind_buffer(glow::ARRAY_BUFFER, Some(instance_vbo));
-            gl.buffer_data_size(glow::ARRAY_BUFFER, 0, glow::DYNAMIC_DRAW);
-            gl.bind_buffer(glow::ARRAY_BUFFER, None);
-            gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, None);
-
-            let mesh_data = MeshRenderData {
-                vbo: Some(vbo),
-                ebo: Some(ebo),
-                instance_vbo: Some(instance_vbo),
-                instance_count: 0,
-            };
-            mesh_render_data.insert(handle, mesh_data);
+    /// Brings the instance buffer of the draw `key` up to date with `instances`, staged
+    /// at `indices`, creating it on first use, and returns the vertex array to draw it
+    /// with. Translucent draws keep the order of `instances`, which is sorted by depth.
+    #[allow(clippy::too_many_arguments)]
+    fn upload_instances(
+        backend: &mut B,
+        streams: &mut HashMap<StreamKey, InstanceStream<B::Buffer, B::VertexArray>>,
+        key: StreamKey,
+        instances: &[InstanceData],
+        indices: &[usize],
+        changed: &[bool],
+        frame: u64,
+        shader_resource: &ShaderStorage,
+        mesh_resource: &MeshStorage,
+        mesh_render_data: &mut SecondaryMap<MeshHandle, MeshRenderData<B::Buffer>>,
+    ) -> B::VertexArray {
+        let stream = streams.entry(key).or_insert_with(|| InstanceStream {
+            buffer: backend.create_buffer(BufferKind::Instance, &[]),
+            vertex_array: None,
+            capacity: 0,
+            occupants: Vec::new(),
+            fades: Vec::new(),
+            slots: HashMap::new(),
+            order: Vec::new(),
+            last_used: frame,
+        });
+        let ordered = key.pass == DrawPass::Translucent;
+        stream.upload(backend, instances, indices, changed, ordered, frame);
 
-            // Unbind the vertex array to avoid accidental state corruption.
-            gl.bind_vertex_array(None);
+        if let Some(vao) = stream.vertex_array {
+            return vao;
+        }
+        if !mesh_render_data.contains_key(key.mesh) {
+            let mesh_data = mesh_resource.get_mesh(key.mesh).expect("Mesh not found");
+            Self::upload_mesh_to_gpu(backend, mesh_data, key.mesh, mesh_render_data);
         }
+
+        let mesh_data = mesh_render_data.get(key.mesh).unwrap();
+        let buffers = MeshBuffers {
+            vertex: mesh_data.vbo.unwrap(),
+            index: mesh_data.ebo.unwrap(),
+            instance: stream.buffer,
+        };
+        let bindings = attrib_bindings(shader_resource.get_shader(key.shader).unwrap());
+
+        let vao = backend.create_vertex_array(buffers, &bindings);
+        stream.vertex_array = Some(vao);
+        vao
     }
 
-    pub fn update_instance_buffer(
-        gl: &glow::Context,
-        mesh_handle: MeshHandle,
-        mesh_render_data: &mut SecondaryMap<MeshHandle, MeshRenderData>,
-        instance_matrices: &[[f32; 16]],
-    ) {
-        let mesh_data = mesh_render_data
-            .get_mut(mesh_handle)
-            .expect("Mesh not found");
-        if instance_matrices.is_empty() {
-            mesh_data.instance_count = 0;
+    /// Deletes a mesh's GPU resources
+    #[allow(dead_code)]
+    pub fn delete_mesh_gpu(&mut self, mesh_handle: MeshHandle) {
+        let Some(mesh_data) = self.mesh_render_data.remove(mesh_handle) else {
             return;
+        };
+        for buffer in [mesh_data.vbo, mesh_data.ebo].into_iter().flatten() {
+            self.backend.delete_buffer(buffer);
         }
+        self.delete_streams(|key, _| key.mesh == mesh_handle);
+    }
 
-        mesh_data.instance_count = instance_matrices.len();
+    /// Drops everything uploaded for `mesh_handle` so its current data is re-uploaded
+    /// the next time it is drawn. Used after a mesh is reloaded in place.
+    pub fn invalidate_mesh(&mut self, mesh_handle: MeshHandle) {
+        self.delete_mesh_gpu(mesh_handle);
+    }
 
-        let instance_buf = mesh_data.instance_vbo.unwrap();
-        unsafe {
-            gl.bind_buffer(glow::ARRAY_BUFFER, Some(instance_buf));
-            gl.buffer_data_u8_slice(
-                glow::ARRAY_BUFFER,
-                bytemuck::cast_slice(instance_matrices),
-                glow::DYNAMIC_DRAW,
-            );
-            gl.bind_buffer(glow::ARRAY_BUFFER, None);
+    /// Drops the vertex arrays and light and shadow uniform locations found for `shader`, which may
+    /// have changed after it was recompiled.
+    pub fn invalidate_shader(&mut self, shader: ShaderHandle) {
+        self.light_uniforms.remove(shader);
+        self.shadow_uniforms.remove(shader);
+        for (key, stream) in &mut self.instance_streams {
+            if key.shader == shader
+                && let Some(vao) = stream.vertex_array.take()
+            {
+                self.backend.delete_vertex_array(vao);
+            }
         }
     }
 
-    fn get_or_create_vao(
-        vao_cache: &mut HashMap<VaoKey, glow::VertexArray>,
-        gl: &glow::Context,
-        mesh: MeshHandle,
-        shader: &ShaderHandle,
-        shader_resource: &ShaderStorage,
-        mesh_resource: &MeshStorage,
-        mesh_render_data: &mut SecondaryMap<MeshHandle, MeshRenderData>,
-    ) -> glow::VertexArray {
-        let key = VaoKey {
-            mesh,
-            shader: *shader,
-        };
+    /// Deletes the instance buffers and vertex arrays of the streams `matches` picks.
+    fn delete_streams(
+        &mut self,
+        mut matches: impl FnMut(&StreamKey, &InstanceStream<B::Buffer, B::VertexArray>) -> bool,
+    ) {
+        let backend = &mut self.backend;
+        self.instance_streams.retain(|key, stream| {
+            if !matches(key, stream) {
+                return true;
+            }
+            if let Some(vao) = stream.vertex_array {
+                backend.delete_vertex_array(vao);
+            }
+            backend.delete_buffer(stream.buffer);
+            false
+        });
+    }
 
-        if let Some(vao) = vao_cache.get(&key) {
-            return *vao;
+    /// Sets `u_joint_matrices` of the program in use, if the shader has it.
+    fn bind_joint_matrices(backend: &mut B, shader: &Shader, joint_matrices: &[[f32; 16]]) {
+        if let Some(loc) = shader.get_uniform("u_joint_matrices[0]") {
+            backend.set_uniform_mat4_array(&loc.into(), joint_matrices);
         }
+    }
 
-        if !mesh_render_data.contains_key(mesh) {
-            let mesh_data = mesh_resource.get_mesh(mesh).expect("Mesh not found");
-            Self::upload_mesh_to_gpu(gl, mesh_data, mesh, mesh_render_data);
+    /// This returns an int to indicate how many texture units were bound
+    /// (so they can be unbound later). Not sure if this is clever or gross.
+    pub(crate) fn bind_uniform(
+        backend: &mut B,
+        loc: &glow::UniformLocation,
+        value: &UniformValue,
+        texture_resource: &TextureStorage,
+    ) -> u32 {
+        let loc = B::UniformLocation::from(*loc);
+        match value {
+            UniformValue::Texture { handle, unit } => {
+                let tex = texture_resource
+                    .get_texture(*handle)
+                    .expect("Texture missing");
+
+                backend.bind_texture(*unit, tex.gl_tex.map(Into::into));
+                backend.set_uniform(&loc, &UniformValue::Int(*unit as i32));
+                1
+            }
+            _ => {
+                backend.set_uniform(&loc, value);
+                0
+            }
         }
+    }
+}
 
-        let mesh_data = mesh_render_data.get(mesh).unwrap();
-        let vertex_stride = Vertex::stride();
+/// Works out which buffer and offset feeds each of `shader`'s vertex attributes.
+/// Attributes the mesh layout doesn't know about are left unbound.
+fn attrib_bindings(shader: &Shader) -> Vec<VertexAttribBinding> {
+    // (offset, components) of a per-instance attribute in `InstanceData`.
+    let instance_layout_for = |name: &str| -> Option<(i32, i32)> {
+        if let Some(suffix) = name.strip_prefix("instance_model_col")
+            && let Ok(index) = suffix.parse::<i32>()
+            && (0..4).contains(&index)
+        {
+            return Some((offset_of!(InstanceData, model) as i32 + index * 16, 4));
+        }
+        if name == "instance_lod_fade" {
+            return Some((offset_of!(InstanceData, lod_fade) as i32, 1));
+        }
+        let field = name.strip_prefix("instance_")?;
+        let field = InstanceParams::FIELDS.iter().find(|f| f.name == field)?;
+        let offset = offset_of!(InstanceData, params) + field.offset;
+        Some((offset as i32, field.components as i32))
+    };
+
+    let mut bindings = Vec::with_capacity(shader.attributes.len());
+    for attrib in &shader.attributes {
+        match attrib.rate {
+            PerInstance => {
+                if let Some((offset, components)) = instance_layout_for(&attrib.name) {
+                    bindings.push(VertexAttribBinding {
+                        location: attrib.location,
+                        source: AttribSource::Instance,
+                        components,
+                        stride: size_of::<InstanceData>() as i32,
+                        offset,
+                        divisor: 1,
+                    });
+                }
+            }
+            PerVertex => {
+                let offset = match attrib.name.as_str() {
+                    "position" => Some(offset_of!(Vertex, position) as i32),
+                    "normal" => Some(offset_of!(Vertex, normal) as i32),
+                    "barycentric" => Some(offset_of!(Vertex, barycentric) as i32),
+                    "uv_albedo" => Some(offset_of!(Vertex, uv_albedo) as i32),
+                    "uv_normal" => Some(offset_of!(Vertex, uv_normal) as i32),
+                    "tangent" => Some(offset_of!(Vertex, tangent) as i32),
+                    "joints" => Some(offset_of!(Vertex, joints) as i32),
+                    "weights" => Some(offset_of!(Vertex, weights) as i32),
+                    _ => None,
+                };
+                if let Some(offset) = offset {
+                    let components = match attrib.ty {
+                        VertexAttribType::Float32 => 1,
+                        VertexAttribType::Vec2 => 2,
+                        VertexAttribType::Vec3 => 3,
+                        VertexAttribType::Vec4 => 4,
+                    };
+                    bindings.push(VertexAttribBinding {
+                        location: attrib.location,
+                        source: AttribSource::Vertex,
+                        components,
+                        stride: Vertex::stride(),
+                        offset,
+                        divisor: 0,
+                    });
+                }
+            }
+        }
+    }
+    bindings
+}
 
-        unsafe {
-            let vao = gl.create_vertex_array().unwrap();
-            gl.bind_vertex_array(Some(vao));
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, slice, sync::Arc};
+
+    use bevy_ecs::entity::Entity;
+    use bytemuck::Zeroable;
+    use glam::{Vec3, Vec4};
+    use glow::NativeUniformLocation;
+
+    use super::*;
+    use crate::{
+        InstanceParams,
+        assets::{
+            material::{BlendMode, Material, MaterialDesc},
+            mesh::Aabb,
+            shader::{InputRate, ShaderAttrib},
+            texture::Texture,
+        },
+        render::{
+            lights::PointLightData,
+            lod::LodGroup,
+            post_process::{Bloom, ColorGrading, Fxaa, ToneMapping},
+            renderer_backends::{NullBackend, NullCommand},
+        },
+    };
+
+    const VIEW_PROJ_LOC: u32 = 0;
+    const CAMERA_POSITION_LOC: u32 = 1;
+    const ROUGHNESS_LOC: u32 = 5;
+    const ALBEDO_LOC: u32 = 6;
+
+    struct Storages {
+        meshes: MeshStorage,
+        materials: MaterialStorage,
+        textures: TextureStorage,
+        shaders: ShaderStorage,
+    }
 
-            gl.bind_buffer(glow::ARRAY_BUFFER, Some(mesh_data.vbo.unwrap()));
-            gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(mesh_data.ebo.unwrap()));
+    impl Storages {
+        fn new() -> Self {
+            Self {
+                meshes: MeshStorage::default(),
+                materials: MaterialStorage::default(),
+                textures: TextureStorage::default(),
+                shaders: ShaderStorage::default(),
+            }
+        }
 
-            let instance_offset_for = |name: &str| -> Option<i32> {
-                if let Some(suffix) = name.strip_prefix("instance_model_col")
-                    && let Ok(index) = suffix.parse::<i32>()
-                    && (0..4).contains(&index)
-                {
-                    return Some(index * 16);
-                }
-                None
+        fn add_triangle(&mut self) -> MeshHandle {
+            let vertex = |x: f32, y: f32| Vertex {
+                position: [x, y, 0.0],
+                ..Vertex::zeroed()
+            };
+            let mut mesh = Mesh {
+                vertices: vec![vertex(-1.0, -1.0), vertex(1.0, -1.0), vertex(0.0, 1.0)],
+                indices: vec![0, 1, 2],
+                ..Default::default()
             };
+            mesh.compute_bounding_sphere();
+            self.meshes.add_mesh(mesh)
+        }
 
-            for attrib in &shader_resource.get_shader(*shader).unwrap().attributes {
-                let location = attrib.location;
-                let divisor = match attrib.rate {
-                    PerVertex => 0,
-                    PerInstance => 1,
-                };
+        fn add_shader(&mut self) -> ShaderHandle {
+            self.add_shader_with_uniforms(&[])
+        }
 
-                match attrib.rate {
-                    PerInstance => {
-                        if let Some(offset) = instance_offset_for(&attrib.name) {
-                            gl.bind_buffer(
-                                glow::ARRAY_BUFFER,
-                                Some(mesh_data.instance_vbo.unwrap()),
-                            );
-                            gl.enable_vertex_attrib_array(location);
-                            gl.vertex_attrib_pointer_f32(
-                                location,
-                                4,
-                                glow::FLOAT,
-                                false,
-                                64,
-                                offset,
-                            );
-                            gl.vertex_attrib_divisor(location, divisor);
-                        }
-                    }
-                    PerVertex => {
-                        if let Some(offset) = {
-                            let name: &str = &attrib.name;
-                            match name {
-                                "position" => Some(offset_of!(Vertex, position) as i32),
-                                "normal" => Some(offset_of!(Vertex, normal) as i32),
-                                "barycentric" => Some(offset_of!(Vertex, barycentric) as i32),
-                                "uv_albedo" => Some(offset_of!(Vertex, uv_albedo) as i32),
-                                "uv_normal" => Some(offset_of!(Vertex, uv_normal) as i32),
-                                "tangent" => Some(offset_of!(Vertex, tangent) as i32),
-                                _ => None,
-                            }
-                        } {
-                            gl.bind_buffer(glow::ARRAY_BUFFER, Some(mesh_data.vbo.unwrap()));
-                            gl.enable_vertex_attrib_array(location);
-                            let (size, ty) = match attrib.ty {
-                                VertexAttribType::Float32 => (1, glow::FLOAT),
-                                VertexAttribType::Vec2 => (2, glow::FLOAT),
-                                VertexAttribType::Vec3 => (3, glow::FLOAT),
-                                VertexAttribType::Vec4 => (4, glow::FLOAT),
-                            };
-                            gl.vertex_attrib_pointer_f32(
-                                location,
-                                size,
-                                ty,
-                                false,
-                                vertex_stride,
-                                offset,
-                            );
-                            gl.vertex_attrib_divisor(location, divisor);
-                        }
-                    }
-                }
+        fn add_shader_with_uniforms(&mut self, extra_uniforms: &[(&str, u32)]) -> ShaderHandle {
+            self.add_shader_with(extra_uniforms, &[])
+        }
+
+        /// A shader with extra uniforms and extra per-instance `vec4` attributes.
+        fn add_shader_with(
+            &mut self,
+            extra_uniforms: &[(&str, u32)],
+            extra_instance_attributes: &[(&str, u32)],
+        ) -> ShaderHandle {
+            let uniform = |name: &str, loc: u32| (name.to_string(), NativeUniformLocation(loc));
+            let attrib =
+                |name: &str, location: u32, ty: VertexAttribType, rate: InputRate| ShaderAttrib {
+                    name: name.to_string(),
+                    location,
+                    ty,
+                    rate,
+                };
+            let mut attributes = vec![attrib(
+                "position",
+                0,
+                VertexAttribType::Vec3,
+                InputRate::PerVertex,
+            )];
+            for col in 0..4 {
+                attributes.push(attrib(
+                    &format!("instance_model_col{}", col),
+                    6 + col,
+                    VertexAttribType::Vec4,
+                    InputRate::PerInstance,
+                ));
             }
+            for &(name, location) in extra_instance_attributes {
+                attributes.push(attrib(
+                    name,
+                    location,
+                    VertexAttribType::Vec4,
+                    InputRate::PerInstance,
+                ));
+            }
+            let mut uniforms = vec![
+                uniform("u_view_proj", VIEW_PROJ_LOC),
+                uniform("u_camera_position", CAMERA_POSITION_LOC),
+                uniform("u_roughness", ROUGHNESS_LOC),
+                uniform("u_albedo", ALBEDO_LOC),
+            ];
+            uniforms.extend(
+                extra_uniforms
+                    .iter()
+                    .map(|&(name, location)| uniform(name, location)),
+            );
+            self.shaders.insert_shader(Shader {
+                program: None,
+                uniforms,
+                attributes,
+            })
+        }
 
-            gl.bind_vertex_array(None);
-            vao_cache.insert(key, vao);
-            vao
+        fn add_material(
+            &mut self,
+            shader: ShaderHandle,
+            params: Vec<(String, UniformValue)>,
+        ) -> MaterialHandle {
+            self.add_material_with_blend_mode(shader, params, BlendMode::Opaque)
+        }
+
+        fn add_material_with_blend_mode(
+            &mut self,
+            shader: ShaderHandle,
+            params: Vec<(String, UniformValue)>,
+            blend_mode: BlendMode,
+        ) -> MaterialHandle {
+            self.materials.add_material(Material::new(
+                MaterialDesc::new(shader, params).with_blend_mode(blend_mode),
+            ))
+        }
+
+        fn render(&self, renderer: &mut Renderer<NullBackend>, camera: Option<CameraRenderData>) {
+            renderer.render(
+                RenderParams {
+                    width: 640,
+                    height: 480,
+                },
+                &self.meshes,
+                &self.materials,
+                &self.textures,
+                &self.shaders,
+                camera,
+            );
         }
     }
 
-    /// Deletes a mesh's GPU resources
-    #[allow(dead_code)]
-    pub fn delete_mesh_gpu(&mut self, mesh_handle: MeshHandle) {
-        let mesh_data = self
-            .mesh_render_data
-            .get_mut(mesh_handle)
-            .expect("Mesh not found");
-        unsafe {
-            if let Some(vbo) = mesh_data.vbo.take() {
-                self.gl.delete_buffer(vbo);
-            }
-            if let Some(ebo) = mesh_data.ebo.take() {
-                self.gl.delete_buffer(ebo);
-            }
-            if let Some(inst) = mesh_data.instance_vbo.take() {
-                self.gl.delete_buffer(inst);
-            }
-            mesh_data.instance_count = 0;
+    fn camera() -> CameraRenderData {
+        let position = Vec3::new(0.0, 0.0, 10.0);
+        let view = Mat4::look_at_rh(position, Vec3::ZERO, Vec3::Y);
+        let proj = Mat4::perspective_rh(60f32.to_radians(), 4.0 / 3.0, 0.1, 100.0);
+        CameraRenderData {
+            view_proj: proj * view,
+            position,
+            near: 0.1,
+            far: 100.0,
         }
     }
 
-    /// This returns an int to indicate how many texture units were bound
-    /// (so they can be unbound later). Not sure if this is clever or gross.
-    fn bind_uniform(
-        gl: &glow::Context,
-        loc: &glow::UniformLocation,
-        value: &UniformValue,
-        texture_resource: &TextureStorage,
-    ) -> u32 {
-        unsafe {
-            match value {
-                UniformValue::Float(v) => {
-                    gl.uniform_1_f32(Some(loc), *v);
-                    0
-                }
-                UniformValue::Vec3(v) => {
-                    gl.uniform_3_f32(Some(loc), v.x, v.y, v.z);
-                    0
-                }
-                UniformValue::Mat4(m) => {
-                    gl.uniform_matrix_4_f32_slice(Some(loc), false, &m.to_cols_array());
-                    0
-                }
-                UniformValue::Int(i) => {
-                    gl.uniform_1_i32(Some(loc), *i);
-                    0
-                }
-                UniformValue::Texture { handle, unit } => {
-                    let tex = texture_resource
-                        .get_texture(*handle)
-                        .expect("Texture missing");
-
-                    gl.active_texture(glow::TEXTURE0 + *unit);
-                    gl.bind_texture(glow::TEXTURE_2D, tex.gl_tex);
-                    gl.uniform_1_i32(Some(loc), *unit as i32);
-                    1
+    fn instance(mesh_id: MeshHandle, material_id: MaterialHandle, at: Vec3) -> RenderInstance {
+        RenderInstance {
+            mesh_id,
+            transform: Mat4::from_translation(at),
+            material_id,
+            joints: None,
+            lod: None,
+            lod_fade: 1.0,
+            occluder: false,
+            params: InstanceParams::default(),
+        }
+    }
+
+    #[test]
+    fn batches_instances_by_material_then_mesh() {
+        let mut storages = Storages::new();
+        let mesh_a = storages.add_triangle();
+        let mesh_b = storages.add_triangle();
+        let shader = storages.add_shader();
+        let material_1 = storages.add_material(shader, Vec::new());
+        let material_2 = storages.add_material(shader, Vec::new());
+
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.stage_instances(&[
+            instance(mesh_a, material_2, Vec3::ZERO),
+            instance(mesh_a, material_1, Vec3::ZERO),
+            instance(mesh_b, material_1, Vec3::ZERO),
+            instance(mesh_a, material_1, Vec3::X),
+            instance(mesh_a, material_2, Vec3::Y),
+            instance(mesh_a, material_1, Vec3::Y),
+        ]);
+        storages.render(&mut renderer, Some(camera()));
+
+        let instance_counts: Vec<i32> = renderer
+            .backend()
+            .draw_calls()
+            .iter()
+            .map(|(_, _, count)| *count)
+            .collect();
+        assert_eq!(instance_counts, vec![3, 1, 2]);
+        // Each draw keeps its own instance buffer, and so its own vertex array.
+        assert_eq!(renderer.backend().vertex_arrays_created(), 3);
+        assert_eq!(renderer.frames_rendered(), 1);
+    }
+
+    #[test]
+    fn taking_the_commands_leaves_the_null_backend_log_empty() {
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        let shader = storages.add_shader();
+        let material = storages.add_material(shader, Vec::new());
+
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.stage_instances(&[instance(mesh, material, Vec3::ZERO)]);
+        storages.render(&mut renderer, Some(camera()));
+
+        let commands = renderer.backend_mut().take_commands();
+        assert!(matches!(
+            commands.first(),
+            Some(NullCommand::BeginFrame { .. })
+        ));
+        assert!(renderer.backend().commands.is_empty());
+
+        storages.render(&mut renderer, Some(camera()));
+        assert_eq!(renderer.backend().draw_calls().len(), 1);
+    }
+
+    #[test]
+    fn culls_instances_outside_the_frustum() {
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        let shader = storages.add_shader();
+        let material = storages.add_material(shader, Vec::new());
+
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.stage_instances(&[
+            instance(mesh, material, Vec3::ZERO),
+            // Behind the camera.
+            instance(mesh, material, Vec3::new(0.0, 0.0, 50.0)),
+            // Past the far plane.
+            instance(mesh, material, Vec3::new(0.0, 0.0, -500.0)),
+        ]);
+        storages.render(&mut renderer, Some(camera()));
+
+        let draw_calls = renderer.backend().draw_calls();
+        assert_eq!(draw_calls.len(), 1);
+        assert_eq!(draw_calls[0].1, 3, "one triangle's worth of indices");
+        assert_eq!(draw_calls[0].2, 1);
+
+        let uploaded = renderer
+            .backend()
+            .commands
+            .iter()
+            .find_map(|c| match c {
+                NullCommand::UpdateBufferRange { data, .. } => Some(data.clone()),
+                _ => None,
+            })
+            .expect("Instance buffer should be uploaded");
+        let instances: &[InstanceData] = bytemuck::cast_slice(&uploaded);
+        assert_eq!(
+            instances,
+            &[InstanceData {
+                model: Mat4::IDENTITY.to_cols_array(),
+                lod_fade: 1.0,
+                params: InstanceParams::default().pack(),
+            }]
+        );
+    }
+
+    #[test]
+    fn staged_groups_are_culled_by_their_bounds() {
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        let shader = storages.add_shader();
+        let material = storages.add_material(shader, Vec::new());
+        let group = |entity: u64, instances: Range<usize>, at: Option<Vec3>| RenderGroup {
+            entity: Entity::from_bits(entity),
+            instances,
+            moved_bounds: at.map(|at| Aabb {
+                min: at - Vec3::ONE,
+                max: at + Vec3::ONE,
+            }),
+        };
+
+        let mut renderer = Renderer::new(NullBackend::new());
+        let behind = Vec3::new(0.0, 0.0, 50.0);
+        let instances = [
+            instance(mesh, material, Vec3::ZERO),
+            instance(mesh, material, Vec3::X),
+            instance(mesh, material, behind),
+        ];
+        renderer.stage_instances(&instances);
+        renderer.stage_groups(&[
+            group(1, 0..2, Some(Vec3::ZERO)),
+            group(2, 2..3, Some(behind)),
+        ]);
+        storages.render(&mut renderer, Some(camera()));
+        assert_eq!(renderer.backend().draw_calls()[0].2, 2);
+
+        // Groups that didn't move keep their bounds, and the instances of a group entirely
+        // on screen aren't tested again, so the moved group's bounds alone decide.
+        renderer.backend_mut().commands.clear();
+        renderer.stage_instances(&instances);
+        renderer.stage_groups(&[group(1, 0..2, None), group(2, 2..3, Some(Vec3::ZERO))]);
+        storages.render(&mut renderer, Some(camera()));
+        assert_eq!(renderer.backend().draw_calls()[0].2, 3);
+    }
+
+    #[test]
+    fn binds_frame_and_material_uniforms() {
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        let shader = storages.add_shader();
+        let albedo = storages.textures.add_texture(Texture::new(1, 1));
+        let material = storages.add_material(
+            shader,
+            vec![
+                ("u_roughness".to_string(), UniformValue::Float(0.5)),
+                (
+                    "u_albedo".to_string(),
+                    UniformValue::Texture {
+                        handle: albedo,
+                        unit: 0,
+                    },
+                ),
+                ("u_not_in_shader".to_string(), UniformValue::Float(1.0)),
+            ],
+        );
+
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.stage_instances(&[instance(mesh, material, Vec3::ZERO)]);
+        let camera = camera();
+        let view_proj = camera.view_proj;
+        storages.render(&mut renderer, Some(camera));
+
+        let uniforms = renderer.backend().uniforms();
+        assert_eq!(
+            uniforms,
+            vec![
+                (VIEW_PROJ_LOC, &UniformValue::Mat4(view_proj)),
+                (
+                    CAMERA_POSITION_LOC,
+                    &UniformValue::Vec3(Vec3::new(0.0, 0.0, 10.0))
+                ),
+                (ROUGHNESS_LOC, &UniformValue::Float(0.5)),
+                (ALBEDO_LOC, &UniformValue::Int(0)),
+            ]
+        );
+
+        let texture_binds = renderer
+            .backend()
+            .commands
+            .iter()
+            .filter(|c| matches!(c, NullCommand::BindTexture { unit: 0, .. }))
+            .count();
+        assert_eq!(
+            texture_binds, 2,
+            "texture unit is bound, then unbound after the material"
+        );
+    }
+
+    #[test]
+    fn blended_instances_are_drawn_last_and_farthest_first() {
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        let shader = storages.add_shader();
+        let roughness = |value: f32| vec![("u_roughness".to_string(), UniformValue::Float(value))];
+        let opaque = storages.add_material(shader, roughness(0.1));
+        let glass = storages.add_material_with_blend_mode(shader, roughness(0.2), BlendMode::Blend);
+        let water = storages.add_material_with_blend_mode(shader, roughness(0.3), BlendMode::Blend);
+
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.stage_instances(&[
+            instance(mesh, glass, Vec3::new(0.0, 0.0, 2.0)),
+            instance(mesh, water, Vec3::new(0.0, 0.0, -2.0)),
+            instance(mesh, opaque, Vec3::ZERO),
+            instance(mesh, glass, Vec3::new(0.0, 0.0, -5.0)),
+        ]);
+        storages.render(&mut renderer, Some(camera()));
+
+        // (roughness of the material, instances) per draw, with `None` where blending
+        // was switched on and off.
+        let mut material = 0.0;
+        let mut draws = Vec::new();
+        for command in &renderer.backend().commands {
+            match command {
+                NullCommand::SetUniform {
+                    location: ROUGHNESS_LOC,
+                    value: UniformValue::Float(value),
+                } => material = *value,
+                NullCommand::DrawElementsInstanced { instance_count, .. } => {
+                    draws.push(Some((material, *instance_count)))
                 }
+                NullCommand::SetBlending { .. } => draws.push(None),
+                _ => {}
             }
         }
+        // The two glass instances aren't neighbours by distance, so they are drawn apart.
+        assert_eq!(
+            draws,
+            vec![
+                Some((0.1, 1)),
+                None,
+                Some((0.2, 1)),
+                Some((0.3, 1)),
+                Some((0.2, 1)),
+                None,
+            ]
+        );
+        let blending: Vec<bool> = renderer
+            .backend()
+            .commands
+            .iter()
+            .filter_map(|c| match c {
+                NullCommand::SetBlending { enabled } => Some(*enabled),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(blending, vec![true, false]);
+        assert_eq!(renderer.backend().vertex_arrays_created(), 4);
     }
 
-    /// Upload raw RGBA bytes to GPU
-    pub fn upload_texture_to_gpu(texture: &mut texture::Texture, gl: &glow::Context, data: &[u8]) {
-        unsafe {
-            let tex = gl.create_texture().expect("Failed to create texture");
-            gl.bind_texture(glow::TEXTURE_2D, Some(tex));
-            gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
+    #[test]
+    fn materials_set_their_alpha_mode() {
+        const ALPHA_MODE_LOC: u32 = 30;
+        const ALPHA_CUTOFF_LOC: u32 = 31;
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        let shader = storages.add_shader_with_uniforms(&[
+            ("u_alpha_mode", ALPHA_MODE_LOC),
+            ("u_alpha_cutoff", ALPHA_CUTOFF_LOC),
+        ]);
+        let leaves = storages.add_material_with_blend_mode(
+            shader,
+            Vec::new(),
+            BlendMode::Mask { cutoff: 0.25 },
+        );
+        let glass = storages.add_material_with_blend_mode(shader, Vec::new(), BlendMode::Blend);
+
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.stage_instances(&[
+            instance(mesh, glass, Vec3::ZERO),
+            instance(mesh, leaves, Vec3::ZERO),
+        ]);
+        storages.render(&mut renderer, Some(camera()));
+
+        let alpha_uniforms: Vec<(u32, &UniformValue)> = renderer
+            .backend()
+            .uniforms()
+            .into_iter()
+            .filter(|(location, _)| *location >= ALPHA_MODE_LOC)
+            .collect();
+        assert_eq!(
+            alpha_uniforms,
+            vec![
+                (ALPHA_MODE_LOC, &UniformValue::Int(1)),
+                (ALPHA_CUTOFF_LOC, &UniformValue::Float(0.25)),
+                (ALPHA_MODE_LOC, &UniformValue::Int(2)),
+                (ALPHA_CUTOFF_LOC, &UniformValue::Float(0.0)),
+            ]
+        );
+    }
 
-            // Set wrapping
-            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::REPEAT as i32);
-            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::REPEAT as i32);
+    #[test]
+    fn translucent_draws_keep_their_streams_when_the_depth_order_flips() {
+        let mut storages = Storages::new();
+        let mesh_a = storages.add_triangle();
+        let mesh_b = storages.add_triangle();
+        let shader = storages.add_shader();
+        let glass = storages.add_material_with_blend_mode(shader, Vec::new(), BlendMode::Blend);
+
+        let mut renderer = Renderer::new(NullBackend::new());
+        // Alternating meshes, so every draw is a run of its own.
+        renderer.stage_instances(&[
+            instance(mesh_b, glass, Vec3::new(0.0, 0.0, -4.0)),
+            instance(mesh_a, glass, Vec3::new(0.0, 0.0, -2.0)),
+            instance(mesh_b, glass, Vec3::ZERO),
+            instance(mesh_a, glass, Vec3::new(0.0, 0.0, 2.0)),
+        ]);
+        storages.render(&mut renderer, Some(camera()));
+        assert_eq!(renderer.backend().draw_calls().len(), 4);
+
+        // From the other side the runs come in the opposite order.
+        renderer.backend_mut().clear();
+        let position = Vec3::new(0.0, 0.0, -10.0);
+        let view = Mat4::look_at_rh(position, Vec3::ZERO, Vec3::Y);
+        let proj = Mat4::perspective_rh(60f32.to_radians(), 4.0 / 3.0, 0.1, 100.0);
+        storages.render(
+            &mut renderer,
+            Some(CameraRenderData {
+                view_proj: proj * view,
+                position,
+                ..camera()
+            }),
+        );
 
-            // Upload texture data
-            gl.tex_image_2d(
-                glow::TEXTURE_2D,
-                0,                 // base mip level
-                glow::RGBA as i32, // internal format
-                texture.width as i32,
-                texture.height as i32,
-                0,          // border must be 0
-                glow::RGBA, // format
-                glow::UNSIGNED_BYTE,
-                glow::PixelUnpackData::Slice(Some(data)),
-            );
+        assert_eq!(renderer.backend().draw_calls().len(), 4);
+        assert_eq!(renderer.backend().vertex_arrays_created(), 0);
+        assert!(
+            !renderer
+                .backend()
+                .commands
+                .iter()
+                .any(|c| matches!(c, NullCommand::CreateBuffer { .. }))
+        );
+    }
 
-            // Generate mipmaps
-            gl.generate_mipmap(glow::TEXTURE_2D);
+    #[test]
+    fn light_uniforms_are_set_once_per_shader() {
+        const DIRECTIONAL_COUNT_LOC: u32 = 10;
+        const DIRECTIONAL_COLOR_LOC: u32 = 11;
+        const POINT_COUNT_LOC: u32 = 12;
+        const POINT_RANGE_LOC: u32 = 13;
+
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        let uniform = |name: &str, loc: u32| (name.to_string(), NativeUniformLocation(loc));
+        let shader = storages.shaders.insert_shader(Shader {
+            program: None,
+            uniforms: vec![
+                uniform("u_directional_light_count", DIRECTIONAL_COUNT_LOC),
+                uniform("u_directional_lights[0].color", DIRECTIONAL_COLOR_LOC),
+                uniform("u_point_light_count", POINT_COUNT_LOC),
+                uniform("u_point_lights[0].range", POINT_RANGE_LOC),
+            ],
+            attributes: Vec::new(),
+        });
+        let material_1 = storages.add_material(shader, Vec::new());
+        let material_2 = storages.add_material(shader, Vec::new());
+
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.stage_instances(&[
+            instance(mesh, material_1, Vec3::ZERO),
+            instance(mesh, material_2, Vec3::ZERO),
+        ]);
+        storages.render(&mut renderer, Some(camera()));
+
+        // Without any lights the scene gets the default sun.
+        assert_eq!(
+            renderer.backend().uniforms(),
+            vec![
+                (DIRECTIONAL_COUNT_LOC, &UniformValue::Int(1)),
+                (DIRECTIONAL_COLOR_LOC, &UniformValue::Vec3(Vec3::ONE)),
+                (POINT_COUNT_LOC, &UniformValue::Int(0)),
+            ]
+        );
 
-            // Set filtering to use mipmaps
-            gl.tex_parameter_i32(
-                glow::TEXTURE_2D,
-                glow::TEXTURE_MIN_FILTER,
-                glow::LINEAR_MIPMAP_LINEAR as i32, // trilinear filtering
-            );
-            gl.tex_parameter_i32(
-                glow::TEXTURE_2D,
-                glow::TEXTURE_MAG_FILTER,
-                glow::LINEAR_MIPMAP_LINEAR as i32, // magnification
+        let point = |x: f32, range: f32| PointLightData {
+            position: Vec3::new(x, 0.0, 0.0),
+            color: Vec3::ONE,
+            range,
+            falloff: 2.0,
+        };
+        let mut lights = FrameLights::default();
+        lights.point.push(point(100.0, 1.0));
+        lights.point.push(point(0.0, 2.0));
+        renderer.stage_lights(&lights);
+        renderer.backend_mut().clear();
+        storages.render(&mut renderer, Some(camera()));
+
+        // The shader only declares one point light, so only the first is used.
+        assert_eq!(
+            renderer.backend().uniforms(),
+            vec![
+                (DIRECTIONAL_COUNT_LOC, &UniformValue::Int(0)),
+                (POINT_COUNT_LOC, &UniformValue::Int(1)),
+                (POINT_RANGE_LOC, &UniformValue::Float(1.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn shadow_cascades_are_drawn_before_the_main_pass() {
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        let shader = storages.add_shader();
+        let material = storages.add_material(shader, Vec::new());
+        let depth_shader = storages.add_shader();
+
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.set_shadow_shader(Some(depth_shader));
+        renderer.set_shadow_settings(ShadowSettings {
+            cascade_count: 2,
+            resolution: 512,
+            ..Default::default()
+        });
+        renderer.stage_instances(&[instance(mesh, material, Vec3::ZERO)]);
+        storages.render(&mut renderer, Some(camera()));
+
+        let commands = &renderer.backend().commands;
+        let shadow_commands: Vec<_> = commands
+            .iter()
+            .filter(|c| {
+                matches!(
+                    c,
+                    NullCommand::CreateShadowMap { .. }
+                        | NullCommand::BeginShadowPass { .. }
+                        | NullCommand::EndShadowPass
+                        | NullCommand::BindShadowMap { .. }
+                )
+            })
+            .cloned()
+            .collect();
+        assert_eq!(
+            shadow_commands,
+            vec![
+                NullCommand::CreateShadowMap {
+                    shadow_map: 1,
+                    resolution: 512,
+                    layers: 2
+                },
+                NullCommand::BeginShadowPass {
+                    shadow_map: 1,
+                    layer: 0
+                },
+                NullCommand::EndShadowPass,
+                NullCommand::BeginShadowPass {
+                    shadow_map: 1,
+                    layer: 1
+                },
+                NullCommand::EndShadowPass,
+                NullCommand::BindShadowMap {
+                    unit: SHADOW_MAP_TEXTURE_UNIT,
+                    shadow_map: Some(1)
+                },
+                NullCommand::BindShadowMap {
+                    unit: SHADOW_MAP_TEXTURE_UNIT,
+                    shadow_map: None
+                },
+            ]
+        );
+        // One depth draw per cascade, then the main pass.
+        assert_eq!(renderer.backend().draw_calls().len(), 3);
+
+        // The map is reused, and disabling shadows skips the pass entirely.
+        renderer.backend_mut().clear();
+        storages.render(&mut renderer, Some(camera()));
+        renderer.set_shadow_settings(ShadowSettings {
+            enabled: false,
+            ..*renderer.shadow_settings()
+        });
+        renderer.backend_mut().clear();
+        storages.render(&mut renderer, Some(camera()));
+        assert!(!renderer.backend().commands.iter().any(|c| matches!(
+            c,
+            NullCommand::CreateShadowMap { .. } | NullCommand::BeginShadowPass { .. }
+        )));
+        assert_eq!(renderer.backend().draw_calls().len(), 1);
+    }
+
+    #[test]
+    fn skinned_instances_are_drawn_one_at_a_time_with_their_joints() {
+        const JOINTS_LOC: u32 = 20;
+
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        let shader = storages.add_shader_with_uniforms(&[("u_joint_matrices[0]", JOINTS_LOC)]);
+        let material = storages.add_material(shader, Vec::new());
+        let depth_shader = storages.add_shader();
+        let skinned_depth_shader =
+            storages.add_shader_with_uniforms(&[("u_joint_matrices[0]", JOINTS_LOC)]);
+
+        let skinned = |at: Vec3, joints: Range<usize>| RenderInstance {
+            joints: Some(joints),
+            ..instance(mesh, material, at)
+        };
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.set_shadow_shader(Some(depth_shader));
+        renderer.set_skinned_shadow_shader(Some(skinned_depth_shader));
+        renderer.set_shadow_settings(ShadowSettings {
+            cascade_count: 1,
+            ..Default::default()
+        });
+        renderer.stage_instances(&[
+            skinned(Vec3::ZERO, 0..1),
+            instance(mesh, material, Vec3::ZERO),
+            skinned(Vec3::X, 1..3),
+            instance(mesh, material, Vec3::Y),
+        ]);
+        let joints = [
+            Mat4::from_translation(Vec3::Z),
+            Mat4::IDENTITY,
+            Mat4::from_scale(Vec3::splat(2.0)),
+        ];
+        renderer.stage_joint_matrices(&joints);
+        storages.render(&mut renderer, Some(camera()));
+
+        // The two rigid instances share a draw; each skinned one gets its own, in the
+        // shadow pass and in the main pass.
+        let instance_counts: Vec<i32> = renderer
+            .backend()
+            .draw_calls()
+            .iter()
+            .map(|(_, _, count)| *count)
+            .collect();
+        assert_eq!(instance_counts, vec![2, 1, 1, 2, 1, 1]);
+
+        let joint_uploads: Vec<_> = renderer
+            .backend()
+            .commands
+            .iter()
+            .filter_map(|c| match c {
+                NullCommand::SetUniformMat4Array { location, matrices } => {
+                    Some((*location, matrices.len()))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(joint_uploads.len(), 4);
+        assert!(joint_uploads.contains(&(JOINTS_LOC, 1)));
+        assert!(joint_uploads.contains(&(JOINTS_LOC, 2)));
+    }
+
+    #[test]
+    fn levels_of_detail_are_picked_by_screen_size() {
+        let mut storages = Storages::new();
+        // Two triangles in the same place, so the levels can be told apart by index count.
+        let mut add_mesh = |triangles: usize| {
+            let vertex = |x: f32, y: f32| Vertex {
+                position: [x, y, 0.0],
+                ..Vertex::zeroed()
+            };
+            let vertices = vec![vertex(-1.0, -1.0), vertex(1.0, -1.0), vertex(0.0, 1.0)];
+            let mut mesh = Mesh {
+                aabb: Aabb::from_vertices(&vertices),
+                vertices,
+                indices: [0, 1, 2].repeat(triangles),
+                ..Default::default()
+            };
+            mesh.compute_bounding_sphere();
+            storages.meshes.add_mesh(mesh)
+        };
+        let detailed = add_mesh(2);
+        let coarse = add_mesh(1);
+        let shader = storages.add_shader();
+        let material = storages.add_material(shader, Vec::new());
+
+        // The triangle's bounding sphere is about 118 pixels across 10 units from the
+        // camera, 24 at 50 units and 13 at 90.
+        let lod = Arc::new(
+            LodGroup::new()
+                .with_level(coarse, 50.0)
+                .with_cull_below(15.0),
+        );
+        let with_lod = |at: Vec3| RenderInstance {
+            lod: Some(lod.clone()),
+            ..instance(detailed, material, at)
+        };
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.stage_instances(&[
+            with_lod(Vec3::ZERO),
+            with_lod(Vec3::new(0.0, 0.0, -40.0)),
+            with_lod(Vec3::new(0.0, 0.0, -80.0)),
+        ]);
+        storages.render(&mut renderer, Some(camera()));
+
+        let mut drawn: Vec<(i32, i32)> = renderer
+            .backend()
+            .draw_calls()
+            .iter()
+            .map(|&(_, index_count, instance_count)| (index_count, instance_count))
+            .collect();
+        drawn.sort();
+        assert_eq!(drawn, vec![(3, 1), (6, 1)]);
+    }
+
+    #[test]
+    fn instance_buffers_are_only_patched_where_instances_changed() {
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        let shader = storages.add_shader();
+        let material = storages.add_material(shader, Vec::new());
+        let uploads = |renderer: &Renderer<NullBackend>| -> Vec<(usize, usize)> {
+            renderer
+                .backend()
+                .commands
+                .iter()
+                .filter_map(|c| match c {
+                    NullCommand::UpdateBufferRange { offset, data, .. } => Some((
+                        offset / size_of::<InstanceData>(),
+                        data.len() / size_of::<InstanceData>(),
+                    )),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let mut instances: Vec<RenderInstance> = (0..3)
+            .map(|i| instance(mesh, material, Vec3::new(i as f32, 0.0, 0.0)))
+            .collect();
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.stage_instances(&instances);
+        storages.render(&mut renderer, Some(camera()));
+        assert_eq!(uploads(&renderer), vec![(0, 3)]);
+
+        // Nothing changed, nothing is uploaded.
+        renderer.backend_mut().commands.clear();
+        renderer.stage_instance_changes(&instances, &[]);
+        storages.render(&mut renderer, Some(camera()));
+        assert_eq!(uploads(&renderer), vec![]);
+        assert_eq!(renderer.backend().draw_calls()[0].2, 3);
+
+        // One instance moved: only it is written, into the same buffer.
+        renderer.backend_mut().commands.clear();
+        instances[1].transform = Mat4::from_translation(Vec3::Y);
+        renderer.stage_instance_changes(&instances, slice::from_ref(&(1..2)));
+        storages.render(&mut renderer, Some(camera()));
+        assert_eq!(uploads(&renderer), vec![(1, 1)]);
+        assert_eq!(renderer.backend().vertex_arrays_created(), 0);
+
+        // Outgrowing the buffer orphans it for a larger one.
+        renderer.backend_mut().commands.clear();
+        instances.push(instance(mesh, material, Vec3::new(0.0, 1.0, 0.0)));
+        instances.push(instance(mesh, material, Vec3::new(0.0, -1.0, 0.0)));
+        renderer.stage_instance_changes(&instances, slice::from_ref(&(3..5)));
+        storages.render(&mut renderer, Some(camera()));
+        assert!(renderer.backend().commands.iter().any(|c| matches!(
+            c,
+            NullCommand::AllocateBuffer { len, .. } if *len == 8 * size_of::<InstanceData>()
+        )));
+        assert_eq!(uploads(&renderer), vec![(0, 5)]);
+    }
+
+    #[test]
+    fn instances_keep_their_slots_when_another_is_culled() {
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        let shader = storages.add_shader();
+        let material = storages.add_material(shader, Vec::new());
+        let uploads = |renderer: &Renderer<NullBackend>| -> Vec<(usize, Vec<InstanceData>)> {
+            renderer
+                .backend()
+                .commands
+                .iter()
+                .filter_map(|c| match c {
+                    NullCommand::UpdateBufferRange { offset, data, .. } => Some((
+                        offset / size_of::<InstanceData>(),
+                        bytemuck::cast_slice(data).to_vec(),
+                    )),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let mut instances: Vec<RenderInstance> = (0..5)
+            .map(|i| instance(mesh, material, Vec3::new(i as f32 - 2.0, 0.0, 0.0)))
+            .collect();
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.stage_instances(&instances);
+        storages.render(&mut renderer, Some(camera()));
+
+        // The last instance takes the culled one's slot; the rest aren't written.
+        renderer.backend_mut().clear();
+        instances[1].transform = Mat4::from_translation(Vec3::new(0.0, 0.0, 50.0));
+        renderer.stage_instance_changes(&instances, slice::from_ref(&(1..2)));
+        storages.render(&mut renderer, Some(camera()));
+        assert_eq!(
+            uploads(&renderer),
+            vec![(1, vec![InstanceData::new(&instances[4])])]
+        );
+        assert_eq!(renderer.backend().draw_calls()[0].2, 4);
+
+        // Coming back into view, it is added after the others.
+        renderer.backend_mut().clear();
+        instances[1].transform = Mat4::from_translation(Vec3::new(-1.0, 1.0, 0.0));
+        renderer.stage_instance_changes(&instances, slice::from_ref(&(1..2)));
+        storages.render(&mut renderer, Some(camera()));
+        assert_eq!(
+            uploads(&renderer),
+            vec![(4, vec![InstanceData::new(&instances[1])])]
+        );
+        assert_eq!(renderer.backend().draw_calls()[0].2, 5);
+    }
+
+    #[test]
+    fn instances_behind_occluders_are_not_drawn() {
+        let mut storages = Storages::new();
+        let vertex = |x: f32, y: f32| Vertex {
+            position: [x, y, 0.0],
+            ..Vertex::zeroed()
+        };
+        let mut add_mesh = |vertices: Vec<Vertex>, indices: Vec<u32>| {
+            let mut mesh = Mesh {
+                aabb: Aabb::from_vertices(&vertices),
+                vertices,
+                indices,
+                ..Default::default()
+            };
+            mesh.compute_bounding_sphere();
+            storages.meshes.add_mesh(mesh)
+        };
+        let square = add_mesh(
+            vec![
+                vertex(-1.0, -1.0),
+                vertex(1.0, -1.0),
+                vertex(1.0, 1.0),
+                vertex(-1.0, 1.0),
+            ],
+            vec![0, 1, 2, 0, 2, 3],
+        );
+        let triangle = add_mesh(
+            vec![vertex(-1.0, -1.0), vertex(1.0, -1.0), vertex(0.0, 1.0)],
+            vec![0, 1, 2],
+        );
+        let shader = storages.add_shader();
+        let material = storages.add_material(shader, Vec::new());
+
+        // A wall ten units wide with one triangle behind it and one beside it.
+        let wall = RenderInstance {
+            transform: Mat4::from_scale(Vec3::new(5.0, 5.0, 1.0)),
+            occluder: true,
+            ..instance(square, material, Vec3::ZERO)
+        };
+        let mut renderer = Renderer::new(NullBackend::new());
+        let drawn = |renderer: &mut Renderer<NullBackend>| {
+            renderer.stage_instances(&[
+                wall.clone(),
+                instance(triangle, material, Vec3::new(0.0, 0.0, -10.0)),
+                instance(triangle, material, Vec3::new(13.0, 0.0, -10.0)),
+            ]);
+            storages.render(renderer, Some(camera()));
+            let mut drawn: Vec<(i32, i32)> = renderer
+                .backend()
+                .draw_calls()
+                .iter()
+                .map(|&(_, index_count, instance_count)| (index_count, instance_count))
+                .collect();
+            drawn.sort();
+            renderer.backend_mut().commands.clear();
+            drawn
+        };
+        assert_eq!(drawn(&mut renderer), vec![(3, 2), (6, 1)]);
+
+        renderer.set_occlusion_settings(OcclusionSettings {
+            enabled: true,
+            ..Default::default()
+        });
+        assert_eq!(drawn(&mut renderer), vec![(3, 1), (6, 1)]);
+    }
+
+    #[test]
+    fn instance_params_reach_the_attributes_shaders_declare() {
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        // A shader reading the tint and custom values, but not the highlight.
+        let shader =
+            storages.add_shader_with(&[], &[("instance_tint", 13), ("instance_custom", 15)]);
+        let material = storages.add_material(shader, Vec::new());
+
+        let params = InstanceParams {
+            tint: Vec4::new(1.0, 0.5, 0.25, 0.75),
+            highlight: Vec4::new(0.0, 1.0, 0.0, 0.5),
+            custom: Vec4::splat(3.0),
+        };
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.stage_instances(&[RenderInstance {
+            params,
+            ..instance(mesh, material, Vec3::ZERO)
+        }]);
+        storages.render(&mut renderer, Some(camera()));
+
+        let commands = &renderer.backend().commands;
+        let bindings = commands
+            .iter()
+            .find_map(|c| match c {
+                NullCommand::CreateVertexArray { bindings, .. } => Some(bindings.clone()),
+                _ => None,
+            })
+            .unwrap();
+        let instance_offsets: Vec<(u32, i32, i32)> = bindings
+            .iter()
+            .filter(|binding| binding.location >= 13)
+            .map(|binding| (binding.location, binding.offset, binding.components))
+            .collect();
+        let params = offset_of!(InstanceData, params) as i32;
+        assert_eq!(
+            instance_offsets,
+            vec![(13, params, 4), (15, params + 32, 4)]
+        );
+
+        let uploaded = commands
+            .iter()
+            .find_map(|c| match c {
+                NullCommand::UpdateBufferRange { data, .. } => Some(data.clone()),
+                _ => None,
+            })
+            .unwrap();
+        let instances: &[InstanceData] = bytemuck::cast_slice(&uploaded);
+        assert_eq!(
+            instances[0].params,
+            [
+                1.0, 0.5, 0.25, 0.75, // tint
+                0.0, 1.0, 0.0, 0.5, // highlight
+                3.0, 3.0, 3.0, 3.0, // custom
+            ]
+        );
+    }
+
+    #[test]
+    fn vertex_array_binds_vertex_and_instance_attributes() {
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        let shader = storages.add_shader();
+        let material = storages.add_material(shader, Vec::new());
+
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.stage_instances(&[instance(mesh, material, Vec3::ZERO)]);
+        storages.render(&mut renderer, Some(camera()));
+
+        let bindings = renderer
+            .backend()
+            .commands
+            .iter()
+            .find_map(|c| match c {
+                NullCommand::CreateVertexArray { bindings, .. } => Some(bindings.clone()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(bindings.len(), 5);
+        assert_eq!(bindings[0].source, AttribSource::Vertex);
+        assert_eq!(bindings[0].components, 3);
+        assert_eq!(bindings[0].stride, Vertex::stride());
+        for (col, binding) in bindings[1..].iter().enumerate() {
+            assert_eq!(binding.location, 6 + col as u32);
+            assert_eq!(binding.source, AttribSource::Instance);
+            assert_eq!(binding.offset, col as i32 * 16);
+            assert_eq!(binding.divisor, 1);
+        }
+    }
+
+    #[test]
+    fn without_a_camera_only_the_frame_is_cleared() {
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        let shader = storages.add_shader();
+        let material = storages.add_material(shader, Vec::new());
+
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.stage_instances(&[instance(mesh, material, Vec3::ZERO)]);
+        storages.render(&mut renderer, None);
+
+        assert_eq!(
+            renderer.backend().commands,
+            vec![
+                NullCommand::BeginFrame {
+                    width: 640,
+                    height: 480
+                },
+                NullCommand::EndFrame
+            ]
+        );
+    }
+
+    #[test]
+    fn render_to_image_draws_into_a_reused_offscreen_target() {
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        let shader = storages.add_shader();
+        let material = storages.add_material(shader, Vec::new());
+
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.stage_instances(&[instance(mesh, material, Vec3::ZERO)]);
+        for _ in 0..2 {
+            let image = renderer.render_to_image(
+                RenderParams {
+                    width: 64,
+                    height: 32,
+                },
+                &storages.meshes,
+                &storages.materials,
+                &storages.textures,
+                &storages.shaders,
+                Some(camera()),
             );
+            assert_eq!(image.dimensions(), (64, 32));
+        }
+
+        let commands = &renderer.backend().commands;
+        let created: Vec<_> = commands
+            .iter()
+            .filter(|c| matches!(c, NullCommand::CreateRenderTarget { .. }))
+            .collect();
+        assert_eq!(
+            created,
+            vec![&NullCommand::CreateRenderTarget {
+                target: 1,
+                width: 64,
+                height: 32
+            }]
+        );
 
-            gl.bind_texture(glow::TEXTURE_2D, None);
-            texture.gl_tex = Some(tex);
+        // The draw lands between binding the target and reading it back.
+        let bind = commands
+            .iter()
+            .position(|c| *c == NullCommand::BindRenderTarget { target: Some(1) })
+            .unwrap();
+        let draw = commands
+            .iter()
+            .position(|c| matches!(c, NullCommand::DrawElementsInstanced { .. }))
+            .unwrap();
+        let read = commands
+            .iter()
+            .position(|c| matches!(c, NullCommand::ReadPixels { .. }))
+            .unwrap();
+        assert!(bind < draw && draw < read);
+        assert_eq!(
+            commands[read + 1],
+            NullCommand::BindRenderTarget { target: None }
+        );
+    }
+
+    /// The color targets created so far, in order.
+    fn color_targets_created(backend: &NullBackend) -> Vec<u32> {
+        backend
+            .commands
+            .iter()
+            .filter_map(|c| match c {
+                NullCommand::CreateColorTarget { target, .. } => Some(*target),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn post_processing_draws_the_scene_through_the_enabled_effects() {
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        let shader = storages.add_shader();
+        let material = storages.add_material(shader, Vec::new());
+        let post_shader = storages.add_shader_with_uniforms(&[("u_source", 20)]);
+
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.add_post_process_effect(Box::new(Bloom {
+            extract_shader: post_shader,
+            blur_shader: post_shader,
+            composite_shader: post_shader,
+        }));
+        renderer.add_post_process_effect(Box::new(ToneMapping {
+            shader: post_shader,
+        }));
+        renderer.add_post_process_effect(Box::new(ColorGrading {
+            shader: post_shader,
+        }));
+        renderer.add_post_process_effect(Box::new(Fxaa {
+            shader: post_shader,
+        }));
+        let mut settings = PostProcessSettings {
+            enabled: true,
+            ..Default::default()
+        };
+        settings.bloom.blur_passes = 1;
+        renderer.set_post_process_settings(settings);
+        renderer.stage_instances(&[instance(mesh, material, Vec3::ZERO)]);
+        storages.render(&mut renderer, Some(camera()));
+
+        // The scene is drawn into a depth-tested HDR target of the frame's size.
+        let commands = &renderer.backend().commands;
+        let targets = color_targets_created(renderer.backend());
+        let &[scene, ping, bright, blurred, pong] = &targets[..] else {
+            panic!("unexpected color targets {:?}", targets);
+        };
+        assert!(commands.contains(&NullCommand::CreateColorTarget {
+            target: scene,
+            width: 640,
+            height: 480,
+            depth: true
+        }));
+        assert!(commands.contains(&NullCommand::CreateColorTarget {
+            target: bright,
+            width: 320,
+            height: 240,
+            depth: false
+        }));
+        let bind_scene = commands
+            .iter()
+            .position(|c| {
+                *c == NullCommand::BindColorTarget {
+                    target: Some(scene),
+                }
+            })
+            .unwrap();
+        let begin = commands
+            .iter()
+            .position(|c| matches!(c, NullCommand::BeginFrame { .. }))
+            .unwrap();
+        let draw = commands
+            .iter()
+            .position(|c| matches!(c, NullCommand::DrawElementsInstanced { .. }))
+            .unwrap();
+        assert!(bind_scene < begin && begin < draw);
+
+        // Bloom extracts, blurs both ways and composites, then tone mapping and FXAA
+        // follow; without a LUT colour grading is skipped. FXAA draws into the frame.
+        let bound: Vec<_> = commands[draw..]
+            .iter()
+            .filter_map(|c| match c {
+                NullCommand::BindColorTarget { target } => Some(*target),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            bound,
+            vec![
+                Some(bright),
+                Some(blurred),
+                Some(bright),
+                Some(ping),
+                Some(pong),
+                None
+            ]
+        );
+        let fullscreen_draws = commands
+            .iter()
+            .filter(|c| **c == NullCommand::DrawFullscreenTriangle)
+            .count();
+        assert_eq!(fullscreen_draws, 6);
+        assert!(commands.contains(&NullCommand::BindColorTexture {
+            unit: 0,
+            target: Some(scene)
+        }));
+        assert_eq!(commands.last(), Some(&NullCommand::EndFrame));
+
+        // Targets are kept from frame to frame.
+        renderer.backend_mut().clear();
+        storages.render(&mut renderer, Some(camera()));
+        assert!(color_targets_created(renderer.backend()).is_empty());
+
+        // Disabling post-processing deletes them and draws straight into the frame.
+        renderer.backend_mut().clear();
+        renderer.set_post_process_settings(PostProcessSettings::default());
+        storages.render(&mut renderer, Some(camera()));
+        let commands = &renderer.backend().commands;
+        let deleted = commands
+            .iter()
+            .filter(|c| matches!(c, NullCommand::DeleteColorTarget { .. }))
+            .count();
+        assert_eq!(deleted, 5);
+        assert!(!commands.iter().any(|c| matches!(
+            c,
+            NullCommand::BindColorTarget { .. } | NullCommand::DrawFullscreenTriangle
+        )));
+    }
+
+    /// Reads the input of the pass and records where it was asked to draw.
+    struct RecordingEffect {
+        shader: ShaderHandle,
+        outputs: Rc<RefCell<Vec<Option<u32>>>>,
+    }
+
+    impl PostProcessEffect<NullBackend> for RecordingEffect {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        fn is_enabled(&self, settings: &PostProcessSettings) -> bool {
+            settings.fxaa
+        }
+
+        fn apply(&mut self, pass: &mut PostProcessPass<'_, NullBackend>) {
+            self.outputs.borrow_mut().push(pass.output());
+            let input = pass.input();
+            pass.draw(self.shader, pass.output(), &[("u_source", input)], &[]);
+        }
+    }
+
+    #[test]
+    fn debug_views_bind_their_uniforms_and_bypass_post_processing() {
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        let shader = storages.add_shader_with_uniforms(&[
+            ("u_debug_view", 30),
+            ("u_wireframe", 31),
+            ("u_wireframe_color", 32),
+        ]);
+        let material = storages.add_material(shader, Vec::new());
+        let post_shader = storages.add_shader_with_uniforms(&[("u_source", 20)]);
+
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.add_post_process_effect(Box::new(ToneMapping {
+            shader: post_shader,
+        }));
+        renderer.set_post_process_settings(PostProcessSettings {
+            enabled: true,
+            ..Default::default()
+        });
+        renderer.set_debug_settings(RenderDebugSettings {
+            view: DebugView::Overdraw,
+            wireframe: true,
+            wireframe_color: Vec3::X,
+        });
+        renderer.stage_instances(&[instance(mesh, material, Vec3::ZERO)]);
+        storages.render(&mut renderer, Some(camera()));
+
+        let uniforms = renderer.backend().uniforms();
+        assert!(uniforms.contains(&(30, &UniformValue::Int(5))));
+        assert!(uniforms.contains(&(31, &UniformValue::Int(1))));
+        assert!(uniforms.contains(&(32, &UniformValue::Vec3(Vec3::X))));
+        assert!(color_targets_created(renderer.backend()).is_empty());
+
+        // Overdraw piles the opaque surfaces up additively, then restores the depth test.
+        let commands = &renderer.backend().commands;
+        let position = |command: NullCommand| commands.iter().position(|c| *c == command);
+        let enable = position(NullCommand::SetAdditiveBlending { enabled: true }).unwrap();
+        let disable = position(NullCommand::SetAdditiveBlending { enabled: false }).unwrap();
+        let draw = commands
+            .iter()
+            .position(|c| matches!(c, NullCommand::DrawElementsInstanced { .. }))
+            .unwrap();
+        assert!(enable < draw && draw < disable);
+
+        // Back to the lit view, the frame goes through post-processing again.
+        renderer.set_debug_settings(RenderDebugSettings::default());
+        renderer.backend_mut().commands.clear();
+        renderer.stage_instances(&[instance(mesh, material, Vec3::ZERO)]);
+        storages.render(&mut renderer, Some(camera()));
+        assert!(!color_targets_created(renderer.backend()).is_empty());
+        assert!(
+            renderer
+                .backend()
+                .uniforms()
+                .contains(&(30, &UniformValue::Int(0)))
+        );
+    }
+
+    #[test]
+    fn custom_effects_join_the_stack_where_they_are_inserted() {
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        let shader = storages.add_shader();
+        let material = storages.add_material(shader, Vec::new());
+        let post_shader = storages.add_shader_with_uniforms(&[("u_source", 20)]);
+
+        let outputs = Rc::default();
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.add_post_process_effect(Box::new(ToneMapping {
+            shader: post_shader,
+        }));
+        renderer.post_process_effects_mut().insert(
+            0,
+            Box::new(RecordingEffect {
+                shader: post_shader,
+                outputs: Rc::clone(&outputs),
+            }),
+        );
+        renderer.set_post_process_settings(PostProcessSettings {
+            enabled: true,
+            ..Default::default()
+        });
+        renderer.stage_instances(&[instance(mesh, material, Vec3::ZERO)]);
+        storages.render(&mut renderer, Some(camera()));
+
+        // Ahead of tone mapping, the effect draws into an intermediate target.
+        let targets = color_targets_created(renderer.backend());
+        assert_eq!(targets.len(), 2);
+        assert_eq!(*outputs.borrow(), vec![Some(targets[1])]);
+
+        // Disabled, tone mapping alone reads the scene and draws into the frame.
+        outputs.borrow_mut().clear();
+        renderer.backend_mut().clear();
+        renderer.set_post_process_settings(PostProcessSettings {
+            enabled: true,
+            fxaa: false,
+            ..Default::default()
+        });
+        storages.render(&mut renderer, Some(camera()));
+        assert!(outputs.borrow().is_empty());
+        let commands = &renderer.backend().commands;
+        assert!(commands.contains(&NullCommand::BindColorTexture {
+            unit: 0,
+            target: Some(targets[0])
+        }));
+        let fullscreen_draws = commands
+            .iter()
+            .filter(|c| **c == NullCommand::DrawFullscreenTriangle)
+            .count();
+        assert_eq!(fullscreen_draws, 1);
+    }
+
+    /// Draws a full-screen triangle into its output, sampling its first input if it has one.
+    struct FullscreenPass {
+        name: &'static str,
+        inputs: &'static [&'static str],
+        outputs: &'static [&'static str],
+        shader: ShaderHandle,
+    }
+
+    impl RenderPass<NullBackend> for FullscreenPass {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn inputs(&self) -> &[&str] {
+            self.inputs
+        }
+
+        fn outputs(&self) -> &[&str] {
+            self.outputs
         }
+
+        fn execute(&mut self, context: &mut RenderPassContext<'_, NullBackend>) {
+            let inputs: Vec<_> = self
+                .inputs
+                .iter()
+                .filter_map(|&name| Some(("u_source", context.target(name)?)))
+                .collect();
+            context.draw_fullscreen(self.shader, &inputs, &[]);
+        }
+    }
+
+    #[test]
+    fn custom_render_passes_draw_into_their_targets_in_graph_order() {
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        let shader = storages.add_shader();
+        let material = storages.add_material(shader, Vec::new());
+        let pass_shader = storages.add_shader_with_uniforms(&[("u_source", 20)]);
+
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer
+            .add_render_pass(Box::new(FullscreenPass {
+                name: "zoning_overlay",
+                inputs: &["zoning"],
+                outputs: &[SCENE_COLOR_TARGET],
+                shader: pass_shader,
+            }))
+            .unwrap();
+        renderer
+            .add_render_pass(Box::new(FullscreenPass {
+                name: "zoning_mask",
+                inputs: &[],
+                outputs: &["zoning"],
+                shader: pass_shader,
+            }))
+            .unwrap();
+        assert_eq!(
+            renderer.render_pass_order(),
+            [
+                "shadows",
+                "forward_opaque",
+                "forward_translucent",
+                "zoning_mask",
+                "zoning_overlay",
+                "post_process",
+                "debug_lines"
+            ]
+        );
+
+        renderer.stage_instances(&[instance(mesh, material, Vec3::ZERO)]);
+        storages.render(&mut renderer, Some(camera()));
+
+        // The mask is drawn into a cleared target of its own, then the overlay samples it
+        // over the scene, which without post-processing is the frame.
+        let commands = &renderer.backend().commands;
+        let &[zoning] = &color_targets_created(renderer.backend())[..] else {
+            panic!("expected one color target");
+        };
+        let draw = commands
+            .iter()
+            .position(|c| matches!(c, NullCommand::DrawElementsInstanced { .. }))
+            .unwrap();
+        let passes: Vec<_> = commands[draw..]
+            .iter()
+            .filter(|c| {
+                matches!(
+                    c,
+                    NullCommand::BindColorTarget { .. }
+                        | NullCommand::BindColorTexture { .. }
+                        | NullCommand::Clear
+                        | NullCommand::DrawFullscreenTriangle
+                )
+            })
+            .cloned()
+            .collect();
+        assert_eq!(
+            passes,
+            vec![
+                NullCommand::BindColorTarget {
+                    target: Some(zoning)
+                },
+                NullCommand::Clear,
+                NullCommand::DrawFullscreenTriangle,
+                NullCommand::BindColorTarget { target: None },
+                NullCommand::BindColorTarget { target: None },
+                NullCommand::BindColorTexture {
+                    unit: 0,
+                    target: Some(zoning)
+                },
+                NullCommand::DrawFullscreenTriangle,
+                NullCommand::BindColorTexture {
+                    unit: 0,
+                    target: None
+                },
+                NullCommand::BindColorTarget { target: None },
+            ]
+        );
+
+        // Removing a pass lets go of the custom targets.
+        assert!(renderer.remove_render_pass("zoning_mask").is_some());
+        assert!(
+            renderer
+                .backend()
+                .commands
+                .contains(&NullCommand::DeleteColorTarget { target: zoning })
+        );
+    }
+
+    #[test]
+    fn debug_shapes_are_projected_and_drawn_over_the_frame() {
+        let mut storages = Storages::new();
+        let mesh = storages.add_triangle();
+        let shader = storages.add_shader();
+        let material = storages.add_material(shader, Vec::new());
+        let line_shader = storages.add_shader();
+
+        let mut renderer = Renderer::new(NullBackend::new());
+        renderer.set_debug_line_shader(Some(line_shader));
+        let mut debug_draw = DebugDraw::default();
+        debug_draw.line(Vec3::new(-1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::X);
+        debug_draw.text(Vec3::ZERO, "1", Vec3::Y);
+        renderer.stage_instances(&[instance(mesh, material, Vec3::ZERO)]);
+        renderer.stage_debug_draw(&debug_draw);
+        storages.render(&mut renderer, Some(camera()));
+
+        let commands = &renderer.backend().commands;
+        let last_draw = commands
+            .iter()
+            .rposition(|c| matches!(c, NullCommand::DrawElementsInstanced { .. }))
+            .unwrap();
+        let lines = commands
+            .iter()
+            .position(|c| matches!(c, NullCommand::DrawLines { .. }))
+            .unwrap();
+        assert!(lines > last_draw, "debug lines are drawn after the scene");
+        let Some(NullCommand::DrawLines {
+            vertex_array,
+            vertex_count,
+        }) = commands.get(lines)
+        else {
+            unreachable!();
+        };
+        // Two ends of the line and two strokes of the "1".
+        assert_eq!(*vertex_count, 6);
+        let Some(NullCommand::CreateVertexArray {
+            buffers, bindings, ..
+        }) = commands.iter().find(|c| {
+            matches!(c, NullCommand::CreateVertexArray { vertex_array: v, .. } if v == vertex_array)
+        })
+        else {
+            panic!("the debug line vertex array was never created");
+        };
+        assert_eq!(bindings.len(), 2);
+        let Some(NullCommand::UpdateBuffer { data, .. }) = commands.iter().rev().find(
+            |c| matches!(c, NullCommand::UpdateBuffer { buffer, .. } if *buffer == buffers.vertex),
+        ) else {
+            panic!("no debug vertices were uploaded");
+        };
+        let vertices: &[DebugVertex] = bytemuck::cast_slice(data);
+
+        let view_proj = camera().view_proj;
+        assert_eq!(
+            vertices[0].position,
+            (view_proj * Vec4::new(-1.0, 0.0, 0.0, 1.0)).to_array()
+        );
+        assert_eq!(vertices[0].color, [1.0, 0.0, 0.0]);
+        // Label strokes land at their pixel offsets from the projected position, whatever
+        // its depth.
+        let pixels: Vec<Vec2> = vertices[2..]
+            .iter()
+            .map(|v| {
+                let [x, y, _, w] = v.position;
+                Vec2::new(x / w * 320.0, y / w * 240.0)
+            })
+            .collect();
+        let expected = [
+            Vec2::new(6.0, 10.0),
+            Vec2::new(6.0, 5.0),
+            Vec2::new(6.0, 5.0),
+            Vec2::new(6.0, 0.0),
+        ];
+        for (pixel, expected) in pixels.iter().zip(expected) {
+            assert!(pixel.abs_diff_eq(expected, 1e-3), "{pixel} != {expected}");
+        }
+
+        // Nothing is drawn once the shapes are cleared.
+        debug_draw.clear();
+        renderer.stage_debug_draw(&debug_draw);
+        renderer.backend_mut().clear();
+        storages.render(&mut renderer, Some(camera()));
+        assert!(
+            !renderer
+                .backend()
+                .commands
+                .iter()
+                .any(|c| matches!(c, NullCommand::DrawLines { .. }))
+        );
     }
 }
@@ -1,33 +1,1210 @@
-// use std::{collections::HashMap, rc::Rc};
-
-// use glam::{Mat4, Vec3};
-// use glow::{Context as GlowContext, HasContext};
-
-// use crate::{MaterialHandle, MeshHandle, render_instance::RenderInstance, renderer::VaoKey};
-
-// pub trait GraphicsBackend {
-//     type Buffer;
-//     type Program;
-//     type VertexArray;
-
-// }
-// pub struct NullBackend {
-//     pub frames_rendered: u64,
-// }
-
-// pub struct GlowBackend {
-//     pub gl: Rc<GlowContext>,
-//     pub frames_rendered: u64,
-// }
-
-// impl GraphicsBackend for NullBackend {
-//     type Buffer = ();
-//     type Program = ();
-//     type VertexArray = ();
-// }
-
-// impl GraphicsBackend for GlowBackend {
-//     type Buffer = glow::Buffer;
-//     type Program = glow::Program;
-//     type VertexArray = glow::VertexArray;
-// }
+use std::rc::Rc;
+
+use glow::{Context as GlowContext, HasContext};
+
+use crate::assets::{shader::UniformValue, texture};
+
+/// The GPU operations the [`Renderer`](crate::render::renderer::Renderer) needs.
+///
+/// The renderer decides *what* to draw (culling, batching, material binding) and
+/// hands the backend a flat list of calls. `GlowBackend` turns them into OpenGL
+/// calls; `NullBackend` records them so the renderer can be tested without a GL context.
+///
+/// Shader programs, uniform locations and textures are still created through glow by
+/// the asset loaders, so each backend's handle type converts from its glow counterpart.
+pub trait GraphicsBackend {
+    type Program: Copy + From<glow::Program>;
+    type UniformLocation: Copy + From<glow::UniformLocation>;
+    type Texture: Copy + From<glow::Texture>;
+    type Buffer: Copy;
+    type VertexArray: Copy;
+    type RenderTarget: Copy;
+    type ShadowMap: Copy;
+    type ColorTarget: Copy;
+
+    /// Resets per-frame state, clears the target and sets the viewport.
+    fn begin_frame(&mut self, width: u32, height: u32);
+    /// Restores any state changed by `begin_frame`.
+    fn end_frame(&mut self);
+
+    fn create_buffer(&mut self, kind: BufferKind, data: &[u8]) -> Self::Buffer;
+    /// Replaces the contents of a buffer, resizing it if needed.
+    fn update_buffer(&mut self, buffer: Self::Buffer, data: &[u8]);
+    /// Gives a buffer `size` bytes of new, undefined storage. The old storage is orphaned,
+    /// so draws still reading it don't stall the upload.
+    fn allocate_buffer(&mut self, buffer: Self::Buffer, size: usize);
+    /// Overwrites part of a buffer's storage, starting `offset` bytes in.
+    fn update_buffer_range(&mut self, buffer: Self::Buffer, offset: usize, data: &[u8]);
+    fn delete_buffer(&mut self, buffer: Self::Buffer);
+
+    fn create_vertex_array(
+        &mut self,
+        buffers: MeshBuffers<Self::Buffer>,
+        bindings: &[VertexAttribBinding],
+    ) -> Self::VertexArray;
+    fn delete_vertex_array(&mut self, vertex_array: Self::VertexArray);
+
+    fn use_program(&mut self, program: Option<Self::Program>);
+    /// Sets a non-texture uniform. Texture uniforms are split by the renderer
+    /// into `bind_texture` and an `Int` sampler uniform.
+    fn set_uniform(&mut self, location: &Self::UniformLocation, value: &UniformValue);
+    /// Sets a `mat4` array uniform, starting at the element `location` points to.
+    fn set_uniform_mat4_array(&mut self, location: &Self::UniformLocation, matrices: &[[f32; 16]]);
+    fn bind_texture(&mut self, unit: u32, texture: Option<Self::Texture>);
+    /// While enabled, draws are blended over the target by their alpha and leave the
+    /// depth buffer as it is. Disabled by `begin_frame`.
+    fn set_blending(&mut self, enabled: bool);
+    /// While enabled, draws are added onto the target without testing or writing depth,
+    /// so every surface covering a pixel adds to it. Disabled by `begin_frame`.
+    fn set_additive_blending(&mut self, enabled: bool);
+
+    fn draw_elements_instanced(
+        &mut self,
+        vertex_array: Self::VertexArray,
+        index_count: i32,
+        instance_count: i32,
+    );
+
+    /// Creates an offscreen RGBA8 colour + depth target.
+    fn create_render_target(&mut self, width: u32, height: u32) -> Self::RenderTarget;
+    fn delete_render_target(&mut self, target: Self::RenderTarget);
+    /// Draws into `target` from now on, or into the window's framebuffer when `None`.
+    fn bind_render_target(&mut self, target: Option<Self::RenderTarget>);
+    /// Reads back the bound target as tightly packed RGBA8 rows, bottom row first.
+    fn read_pixels(&mut self, width: u32, height: u32) -> Vec<u8>;
+
+    /// Creates a depth texture array with `layers` square layers of `resolution` texels,
+    /// sampled with depth comparison (`sampler2DArrayShadow`).
+    fn create_shadow_map(&mut self, resolution: u32, layers: u32) -> Self::ShadowMap;
+    fn delete_shadow_map(&mut self, shadow_map: Self::ShadowMap);
+    /// Draws depth only into one layer of `shadow_map` from now on, clearing it first.
+    fn begin_shadow_pass(&mut self, shadow_map: Self::ShadowMap, layer: u32);
+    /// Returns to the render target and viewport that were in use before `begin_shadow_pass`.
+    fn end_shadow_pass(&mut self);
+    fn bind_shadow_map(&mut self, unit: u32, shadow_map: Option<Self::ShadowMap>);
+
+    /// Creates an RGBA16F colour texture that can be drawn into and then sampled, with a
+    /// depth buffer when `depth` is set.
+    fn create_color_target(&mut self, width: u32, height: u32, depth: bool) -> Self::ColorTarget;
+    fn delete_color_target(&mut self, target: Self::ColorTarget);
+    /// Draws into `target` from now on with a viewport covering it, or, when `None`, back
+    /// into the render target and viewport of the frame.
+    fn bind_color_target(&mut self, target: Option<Self::ColorTarget>);
+    fn bind_color_texture(&mut self, unit: u32, target: Option<Self::ColorTarget>);
+    /// Clears the colour and depth of the bound target.
+    fn clear(&mut self);
+    /// Draws one triangle covering the viewport, without depth testing. Its vertex shader
+    /// places the corners from `gl_VertexID`, as no vertex data is bound.
+    fn draw_fullscreen_triangle(&mut self);
+    /// Draws `vertex_count` vertices of `vertex_array` as separate line segments, over
+    /// whatever is in the target, without depth testing.
+    fn draw_lines(&mut self, vertex_array: Self::VertexArray, vertex_count: i32);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferKind {
+    Vertex,
+    Index,
+    Instance,
+}
+
+/// The three buffers every uploaded mesh owns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MeshBuffers<Buffer> {
+    pub vertex: Buffer,
+    pub index: Buffer,
+    pub instance: Buffer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttribSource {
+    Vertex,
+    Instance,
+}
+
+/// One `vertex_attrib_pointer` call: where attribute `location` reads from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexAttribBinding {
+    pub location: u32,
+    pub source: AttribSource,
+    /// Number of `f32` components.
+    pub components: i32,
+    pub stride: i32,
+    pub offset: i32,
+    pub divisor: u32,
+}
+
+/// A framebuffer with colour and depth renderbuffers attached.
+#[derive(Debug, Clone, Copy)]
+pub struct GlowRenderTarget {
+    framebuffer: glow::Framebuffer,
+    color: glow::Renderbuffer,
+    depth: glow::Renderbuffer,
+}
+
+/// A sampled colour texture, with an optional depth renderbuffer, and its framebuffer.
+#[derive(Debug, Clone, Copy)]
+pub struct GlowColorTarget {
+    framebuffer: glow::Framebuffer,
+    texture: glow::Texture,
+    depth: Option<glow::Renderbuffer>,
+    width: u32,
+    height: u32,
+}
+
+/// A depth texture array and the framebuffer its layers are rendered through.
+#[derive(Debug, Clone, Copy)]
+pub struct GlowShadowMap {
+    texture: glow::Texture,
+    framebuffer: glow::Framebuffer,
+    resolution: u32,
+}
+
+pub struct GlowBackend {
+    gl: Rc<GlowContext>,
+    saved_viewport: [i32; 4],
+    /// Size passed to `begin_frame`, restored after shadow passes.
+    frame_size: (u32, u32),
+    render_target: Option<GlowRenderTarget>,
+    /// Bound by `bind_color_target`, drawn into instead of `render_target`.
+    color_target: Option<GlowColorTarget>,
+    /// Bound for full-screen draws, which need a vertex array but no attributes.
+    empty_vertex_array: Option<glow::VertexArray>,
+}
+
+impl GlowBackend {
+    pub fn new(gl: Rc<GlowContext>) -> Self {
+        unsafe {
+            gl.enable(glow::DEPTH_TEST);
+            gl.depth_func(glow::LESS);
+        }
+        Self {
+            gl,
+            saved_viewport: [0; 4],
+            frame_size: (0, 0),
+            render_target: None,
+            color_target: None,
+            empty_vertex_array: None,
+        }
+    }
+
+    /// The framebuffer draws go to outside shadow passes.
+    fn target_framebuffer(&self) -> Option<glow::Framebuffer> {
+        match self.color_target {
+            Some(target) => Some(target.framebuffer),
+            None => self.render_target.map(|t| t.framebuffer),
+        }
+    }
+
+    pub fn gl(&self) -> &Rc<GlowContext> {
+        &self.gl
+    }
+
+    /// Upload raw RGBA bytes to GPU
+    pub fn upload_texture_to_gpu(texture: &mut texture::Texture, gl: &glow::Context, data: &[u8]) {
+        unsafe {
+            let tex = gl.create_texture().expect("Failed to create texture");
+            gl.bind_texture(glow::TEXTURE_2D, Some(tex));
+            gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
+
+            // Set wrapping
+            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::REPEAT as i32);
+            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::REPEAT as i32);
+
+            // Upload texture data
+            gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,                 // base mip level
+                glow::RGBA as i32, // internal format
+                texture.width as i32,
+                texture.height as i32,
+                0,          // border must be 0
+                glow::RGBA, // format
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(Some(data)),
+            );
+
+            // Generate mipmaps
+            gl.generate_mipmap(glow::TEXTURE_2D);
+
+            // Set filtering to use mipmaps
+            gl.tex_parameter_i32(
+                glow::TEXTURE_2D,
+                glow::TEXTURE_MIN_FILTER,
+                glow::LINEAR_MIPMAP_LINEAR as i32, // trilinear filtering
+            );
+            gl.tex_parameter_i32(
+                glow::TEXTURE_2D,
+                glow::TEXTURE_MAG_FILTER,
+                glow::LINEAR_MIPMAP_LINEAR as i32, // magnification
+            );
+
+            gl.bind_texture(glow::TEXTURE_2D, None);
+            texture.gl_tex = Some(tex);
+        }
+    }
+}
+
+impl GraphicsBackend for GlowBackend {
+    type Program = glow::Program;
+    type UniformLocation = glow::UniformLocation;
+    type Texture = glow::Texture;
+    type Buffer = glow::Buffer;
+    type VertexArray = glow::VertexArray;
+    type RenderTarget = GlowRenderTarget;
+    type ShadowMap = GlowShadowMap;
+    type ColorTarget = GlowColorTarget;
+
+    fn begin_frame(&mut self, width: u32, height: u32) {
+        self.frame_size = (width, height);
+        let gl = &self.gl;
+        unsafe {
+            gl.enable(glow::DEPTH_TEST);
+            gl.depth_func(glow::LEQUAL);
+            // Depth writes must be on for the clear to reach the depth buffer.
+            gl.disable(glow::BLEND);
+            gl.depth_mask(true);
+            gl.clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
+            gl.enable(glow::CULL_FACE);
+            gl.cull_face(glow::BACK);
+
+            gl.get_parameter_i32_slice(glow::VIEWPORT, &mut self.saved_viewport);
+            gl.viewport(0, 0, width as i32, height as i32);
+        }
+    }
+
+    fn end_frame(&mut self) {
+        let vp = self.saved_viewport;
+        unsafe {
+            self.gl.bind_vertex_array(None);
+            self.gl.viewport(vp[0], vp[1], vp[2], vp[3]);
+        }
+    }
+
+    fn create_buffer(&mut self, kind: BufferKind, data: &[u8]) -> glow::Buffer {
+        let gl = &self.gl;
+        let (target, usage) = match kind {
+            BufferKind::Vertex => (glow::ARRAY_BUFFER, glow::STATIC_DRAW),
+            BufferKind::Index => (glow::ELEMENT_ARRAY_BUFFER, glow::STATIC_DRAW),
+            BufferKind::Instance => (glow::ARRAY_BUFFER, glow::DYNAMIC_DRAW),
+        };
+        unsafe {
+            // Unbind any active VAO so that an EBO binding below does not
+            // corrupt a previously-created VAO's element-buffer state.
+            gl.bind_vertex_array(None);
+
+            let buffer = gl.create_buffer().unwrap();
+            gl.bind_buffer(target, Some(buffer));
+            if data.is_empty() {
+                gl.buffer_data_size(target, 0, usage);
+            } else {
+                gl.buffer_data_u8_slice(target, data, usage);
+            }
+            gl.bind_buffer(target, None);
+            buffer
+        }
+    }
+
+    fn update_buffer(&mut self, buffer: glow::Buffer, data: &[u8]) {
+        unsafe {
+            self.gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
+            self.gl
+                .buffer_data_u8_slice(glow::ARRAY_BUFFER, data, glow::DYNAMIC_DRAW);
+            self.gl.bind_buffer(glow::ARRAY_BUFFER, None);
+        }
+    }
+
+    fn allocate_buffer(&mut self, buffer: glow::Buffer, size: usize) {
+        unsafe {
+            self.gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
+            self.gl
+                .buffer_data_size(glow::ARRAY_BUFFER, size as i32, glow::DYNAMIC_DRAW);
+            self.gl.bind_buffer(glow::ARRAY_BUFFER, None);
+        }
+    }
+
+    fn update_buffer_range(&mut self, buffer: glow::Buffer, offset: usize, data: &[u8]) {
+        unsafe {
+            self.gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
+            self.gl
+                .buffer_sub_data_u8_slice(glow::ARRAY_BUFFER, offset as i32, data);
+            self.gl.bind_buffer(glow::ARRAY_BUFFER, None);
+        }
+    }
+
+    fn delete_buffer(&mut self, buffer: glow::Buffer) {
+        unsafe {
+            self.gl.delete_buffer(buffer);
+        }
+    }
+
+    fn create_vertex_array(
+        &mut self,
+        buffers: MeshBuffers<glow::Buffer>,
+        bindings: &[VertexAttribBinding],
+    ) -> glow::VertexArray {
+        let gl = &self.gl;
+        unsafe {
+            let vao = gl.create_vertex_array().unwrap();
+            gl.bind_vertex_array(Some(vao));
+            gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(buffers.index));
+
+            for binding in bindings {
+                let buffer = match binding.source {
+                    AttribSource::Vertex => buffers.vertex,
+                    AttribSource::Instance => buffers.instance,
+                };
+                gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
+                gl.enable_vertex_attrib_array(binding.location);
+                gl.vertex_attrib_pointer_f32(
+                    binding.location,
+                    binding.components,
+                    glow::FLOAT,
+                    false,
+                    binding.stride,
+                    binding.offset,
+                );
+                gl.vertex_attrib_divisor(binding.location, binding.divisor);
+            }
+
+            gl.bind_vertex_array(None);
+            vao
+        }
+    }
+
+    fn delete_vertex_array(&mut self, vertex_array: glow::VertexArray) {
+        unsafe {
+            self.gl.delete_vertex_array(vertex_array);
+        }
+    }
+
+    fn use_program(&mut self, program: Option<glow::Program>) {
+        unsafe {
+            self.gl.use_program(program);
+        }
+    }
+
+    fn set_uniform(&mut self, location: &glow::UniformLocation, value: &UniformValue) {
+        let gl = &self.gl;
+        unsafe {
+            match value {
+                UniformValue::Float(v) => gl.uniform_1_f32(Some(location), *v),
+                UniformValue::Vec3(v) => gl.uniform_3_f32(Some(location), v.x, v.y, v.z),
+                UniformValue::Mat4(m) => {
+                    gl.uniform_matrix_4_f32_slice(Some(location), false, &m.to_cols_array())
+                }
+                UniformValue::Int(i) => gl.uniform_1_i32(Some(location), *i),
+                UniformValue::Texture { unit, .. } => {
+                    gl.uniform_1_i32(Some(location), *unit as i32)
+                }
+            }
+        }
+    }
+
+    fn set_uniform_mat4_array(&mut self, location: &glow::UniformLocation, matrices: &[[f32; 16]]) {
+        unsafe {
+            self.gl.uniform_matrix_4_f32_slice(
+                Some(location),
+                false,
+                bytemuck::cast_slice(matrices),
+            );
+        }
+    }
+
+    fn bind_texture(&mut self, unit: u32, texture: Option<glow::Texture>) {
+        unsafe {
+            self.gl.active_texture(glow::TEXTURE0 + unit);
+            self.gl.bind_texture(glow::TEXTURE_2D, texture);
+        }
+    }
+
+    fn set_blending(&mut self, enabled: bool) {
+        let gl = &self.gl;
+        unsafe {
+            if enabled {
+                gl.enable(glow::BLEND);
+                gl.blend_func_separate(
+                    glow::SRC_ALPHA,
+                    glow::ONE_MINUS_SRC_ALPHA,
+                    glow::ONE,
+                    glow::ONE_MINUS_SRC_ALPHA,
+                );
+            } else {
+                gl.disable(glow::BLEND);
+            }
+            gl.depth_mask(!enabled);
+        }
+    }
+
+    fn set_additive_blending(&mut self, enabled: bool) {
+        let gl = &self.gl;
+        unsafe {
+            if enabled {
+                gl.enable(glow::BLEND);
+                gl.blend_func(glow::ONE, glow::ONE);
+                gl.disable(glow::DEPTH_TEST);
+            } else {
+                gl.disable(glow::BLEND);
+                gl.enable(glow::DEPTH_TEST);
+            }
+            gl.depth_mask(!enabled);
+        }
+    }
+
+    fn draw_elements_instanced(
+        &mut self,
+        vertex_array: glow::VertexArray,
+        index_count: i32,
+        instance_count: i32,
+    ) {
+        unsafe {
+            self.gl.bind_vertex_array(Some(vertex_array));
+            self.gl.draw_elements_instanced(
+                glow::TRIANGLES,
+                index_count,
+                glow::UNSIGNED_INT,
+                0,
+                instance_count,
+            );
+        }
+    }
+
+    fn create_render_target(&mut self, width: u32, height: u32) -> GlowRenderTarget {
+        let gl = &self.gl;
+        unsafe {
+            let framebuffer = gl.create_framebuffer().unwrap();
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+
+            let color = gl.create_renderbuffer().unwrap();
+            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(color));
+            gl.renderbuffer_storage(glow::RENDERBUFFER, glow::RGBA8, width as i32, height as i32);
+            gl.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::RENDERBUFFER,
+                Some(color),
+            );
+
+            let depth = gl.create_renderbuffer().unwrap();
+            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(depth));
+            gl.renderbuffer_storage(
+                glow::RENDERBUFFER,
+                glow::DEPTH_COMPONENT24,
+                width as i32,
+                height as i32,
+            );
+            gl.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                glow::DEPTH_ATTACHMENT,
+                glow::RENDERBUFFER,
+                Some(depth),
+            );
+
+            let status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
+            if status != glow::FRAMEBUFFER_COMPLETE {
+                log::error!(
+                    "Offscreen framebuffer is incomplete (status 0x{:x})",
+                    status
+                );
+            }
+
+            gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+
+            GlowRenderTarget {
+                framebuffer,
+                color,
+                depth,
+            }
+        }
+    }
+
+    fn delete_render_target(&mut self, target: GlowRenderTarget) {
+        unsafe {
+            self.gl.delete_framebuffer(target.framebuffer);
+            self.gl.delete_renderbuffer(target.color);
+            self.gl.delete_renderbuffer(target.depth);
+        }
+    }
+
+    fn bind_render_target(&mut self, target: Option<GlowRenderTarget>) {
+        self.render_target = target;
+        unsafe {
+            self.gl
+                .bind_framebuffer(glow::FRAMEBUFFER, target.map(|t| t.framebuffer));
+        }
+    }
+
+    fn read_pixels(&mut self, width: u32, height: u32) -> Vec<u8> {
+        let mut pixels = vec![0; width as usize * height as usize * 4];
+        unsafe {
+            self.gl.pixel_store_i32(glow::PACK_ALIGNMENT, 1);
+            self.gl.read_pixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelPackData::Slice(Some(&mut pixels)),
+            );
+        }
+        pixels
+    }
+
+    fn create_shadow_map(&mut self, resolution: u32, layers: u32) -> GlowShadowMap {
+        let gl = &self.gl;
+        unsafe {
+            let texture = gl.create_texture().unwrap();
+            gl.bind_texture(glow::TEXTURE_2D_ARRAY, Some(texture));
+            gl.tex_image_3d(
+                glow::TEXTURE_2D_ARRAY,
+                0,
+                glow::DEPTH_COMPONENT24 as i32,
+                resolution as i32,
+                resolution as i32,
+                layers as i32,
+                0,
+                glow::DEPTH_COMPONENT,
+                glow::UNSIGNED_INT,
+                glow::PixelUnpackData::Slice(None),
+            );
+            let parameters = [
+                (glow::TEXTURE_MIN_FILTER, glow::LINEAR),
+                (glow::TEXTURE_MAG_FILTER, glow::LINEAR),
+                (glow::TEXTURE_WRAP_S, glow::CLAMP_TO_BORDER),
+                (glow::TEXTURE_WRAP_T, glow::CLAMP_TO_BORDER),
+                (glow::TEXTURE_COMPARE_MODE, glow::COMPARE_REF_TO_TEXTURE),
+                (glow::TEXTURE_COMPARE_FUNC, glow::LEQUAL),
+            ];
+            for (parameter, value) in parameters {
+                gl.tex_parameter_i32(glow::TEXTURE_2D_ARRAY, parameter, value as i32);
+            }
+            // Outside the map counts as unshadowed.
+            gl.tex_parameter_f32_slice(
+                glow::TEXTURE_2D_ARRAY,
+                glow::TEXTURE_BORDER_COLOR,
+                &[1.0; 4],
+            );
+            gl.bind_texture(glow::TEXTURE_2D_ARRAY, None);
+
+            let framebuffer = gl.create_framebuffer().unwrap();
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+            gl.draw_buffer(glow::NONE);
+            gl.read_buffer(glow::NONE);
+            gl.bind_framebuffer(glow::FRAMEBUFFER, self.target_framebuffer());
+
+            GlowShadowMap {
+                texture,
+                framebuffer,
+                resolution,
+            }
+        }
+    }
+
+    fn delete_shadow_map(&mut self, shadow_map: GlowShadowMap) {
+        unsafe {
+            self.gl.delete_framebuffer(shadow_map.framebuffer);
+            self.gl.delete_texture(shadow_map.texture);
+        }
+    }
+
+    fn begin_shadow_pass(&mut self, shadow_map: GlowShadowMap, layer: u32) {
+        let gl = &self.gl;
+        let resolution = shadow_map.resolution as i32;
+        unsafe {
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(shadow_map.framebuffer));
+            gl.framebuffer_texture_layer(
+                glow::FRAMEBUFFER,
+                glow::DEPTH_ATTACHMENT,
+                Some(shadow_map.texture),
+                0,
+                layer as i32,
+            );
+            gl.viewport(0, 0, resolution, resolution);
+            gl.clear(glow::DEPTH_BUFFER_BIT);
+            // Pushes depths back a little to keep surfaces from shadowing themselves.
+            gl.enable(glow::POLYGON_OFFSET_FILL);
+            gl.polygon_offset(2.0, 4.0);
+        }
+    }
+
+    fn end_shadow_pass(&mut self) {
+        let gl = &self.gl;
+        let (width, height) = match self.color_target {
+            Some(target) => (target.width, target.height),
+            None => self.frame_size,
+        };
+        unsafe {
+            gl.disable(glow::POLYGON_OFFSET_FILL);
+            gl.bind_framebuffer(glow::FRAMEBUFFER, self.target_framebuffer());
+            gl.viewport(0, 0, width as i32, height as i32);
+        }
+    }
+
+    fn bind_shadow_map(&mut self, unit: u32, shadow_map: Option<GlowShadowMap>) {
+        unsafe {
+            self.gl.active_texture(glow::TEXTURE0 + unit);
+            self.gl
+                .bind_texture(glow::TEXTURE_2D_ARRAY, shadow_map.map(|m| m.texture));
+        }
+    }
+
+    fn create_color_target(&mut self, width: u32, height: u32, depth: bool) -> GlowColorTarget {
+        let gl = &self.gl;
+        unsafe {
+            let texture = gl.create_texture().unwrap();
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                glow::RGBA16F as i32,
+                width as i32,
+                height as i32,
+                0,
+                glow::RGBA,
+                glow::HALF_FLOAT,
+                glow::PixelUnpackData::Slice(None),
+            );
+            let parameters = [
+                (glow::TEXTURE_MIN_FILTER, glow::LINEAR),
+                (glow::TEXTURE_MAG_FILTER, glow::LINEAR),
+                (glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE),
+                (glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE),
+            ];
+            for (parameter, value) in parameters {
+                gl.tex_parameter_i32(glow::TEXTURE_2D, parameter, value as i32);
+            }
+            gl.bind_texture(glow::TEXTURE_2D, None);
+
+            let framebuffer = gl.create_framebuffer().unwrap();
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+            gl.framebuffer_texture_2d(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::TEXTURE_2D,
+                Some(texture),
+                0,
+            );
+
+            let depth = depth.then(|| {
+                let depth = gl.create_renderbuffer().unwrap();
+                gl.bind_renderbuffer(glow::RENDERBUFFER, Some(depth));
+                gl.renderbuffer_storage(
+                    glow::RENDERBUFFER,
+                    glow::DEPTH_COMPONENT24,
+                    width as i32,
+                    height as i32,
+                );
+                gl.framebuffer_renderbuffer(
+                    glow::FRAMEBUFFER,
+                    glow::DEPTH_ATTACHMENT,
+                    glow::RENDERBUFFER,
+                    Some(depth),
+                );
+                gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+                depth
+            });
+
+            let status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
+            if status != glow::FRAMEBUFFER_COMPLETE {
+                log::error!(
+                    "Colour target framebuffer is incomplete (status 0x{:x})",
+                    status
+                );
+            }
+            gl.bind_framebuffer(glow::FRAMEBUFFER, self.target_framebuffer());
+
+            GlowColorTarget {
+                framebuffer,
+                texture,
+                depth,
+                width,
+                height,
+            }
+        }
+    }
+
+    fn delete_color_target(&mut self, target: GlowColorTarget) {
+        unsafe {
+            self.gl.delete_framebuffer(target.framebuffer);
+            self.gl.delete_texture(target.texture);
+            if let Some(depth) = target.depth {
+                self.gl.delete_renderbuffer(depth);
+            }
+        }
+    }
+
+    fn bind_color_target(&mut self, target: Option<GlowColorTarget>) {
+        self.color_target = target;
+        let (width, height) = match target {
+            Some(target) => (target.width, target.height),
+            None => self.frame_size,
+        };
+        unsafe {
+            self.gl
+                .bind_framebuffer(glow::FRAMEBUFFER, self.target_framebuffer());
+            self.gl.viewport(0, 0, width as i32, height as i32);
+        }
+    }
+
+    fn bind_color_texture(&mut self, unit: u32, target: Option<GlowColorTarget>) {
+        unsafe {
+            self.gl.active_texture(glow::TEXTURE0 + unit);
+            self.gl
+                .bind_texture(glow::TEXTURE_2D, target.map(|t| t.texture));
+        }
+    }
+
+    fn clear(&mut self) {
+        unsafe {
+            self.gl.depth_mask(true);
+            self.gl
+                .clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
+        }
+    }
+
+    fn draw_fullscreen_triangle(&mut self) {
+        let gl = &self.gl;
+        unsafe {
+            let vertex_array = *self
+                .empty_vertex_array
+                .get_or_insert_with(|| gl.create_vertex_array().unwrap());
+            gl.disable(glow::DEPTH_TEST);
+            gl.bind_vertex_array(Some(vertex_array));
+            gl.draw_arrays(glow::TRIANGLES, 0, 3);
+            gl.enable(glow::DEPTH_TEST);
+        }
+    }
+
+    fn draw_lines(&mut self, vertex_array: glow::VertexArray, vertex_count: i32) {
+        let gl = &self.gl;
+        unsafe {
+            gl.disable(glow::DEPTH_TEST);
+            gl.bind_vertex_array(Some(vertex_array));
+            gl.draw_arrays(glow::LINES, 0, vertex_count);
+            gl.enable(glow::DEPTH_TEST);
+        }
+    }
+}
+
+/// Everything a [`NullBackend`] was asked to do, in call order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NullCommand {
+    BeginFrame {
+        width: u32,
+        height: u32,
+    },
+    EndFrame,
+    CreateBuffer {
+        buffer: u32,
+        kind: BufferKind,
+        len: usize,
+    },
+    UpdateBuffer {
+        buffer: u32,
+        data: Vec<u8>,
+    },
+    AllocateBuffer {
+        buffer: u32,
+        len: usize,
+    },
+    UpdateBufferRange {
+        buffer: u32,
+        offset: usize,
+        data: Vec<u8>,
+    },
+    DeleteBuffer {
+        buffer: u32,
+    },
+    CreateVertexArray {
+        vertex_array: u32,
+        buffers: MeshBuffers<u32>,
+        bindings: Vec<VertexAttribBinding>,
+    },
+    DeleteVertexArray {
+        vertex_array: u32,
+    },
+    UseProgram {
+        program: Option<u32>,
+    },
+    SetUniform {
+        location: u32,
+        value: UniformValue,
+    },
+    SetUniformMat4Array {
+        location: u32,
+        matrices: Vec<[f32; 16]>,
+    },
+    BindTexture {
+        unit: u32,
+        texture: Option<u32>,
+    },
+    SetBlending {
+        enabled: bool,
+    },
+    SetAdditiveBlending {
+        enabled: bool,
+    },
+    DrawElementsInstanced {
+        vertex_array: u32,
+        index_count: i32,
+        instance_count: i32,
+    },
+    CreateRenderTarget {
+        target: u32,
+        width: u32,
+        height: u32,
+    },
+    DeleteRenderTarget {
+        target: u32,
+    },
+    BindRenderTarget {
+        target: Option<u32>,
+    },
+    ReadPixels {
+        width: u32,
+        height: u32,
+    },
+    CreateShadowMap {
+        shadow_map: u32,
+        resolution: u32,
+        layers: u32,
+    },
+    DeleteShadowMap {
+        shadow_map: u32,
+    },
+    BeginShadowPass {
+        shadow_map: u32,
+        layer: u32,
+    },
+    EndShadowPass,
+    BindShadowMap {
+        unit: u32,
+        shadow_map: Option<u32>,
+    },
+    CreateColorTarget {
+        target: u32,
+        width: u32,
+        height: u32,
+        depth: bool,
+    },
+    DeleteColorTarget {
+        target: u32,
+    },
+    BindColorTarget {
+        target: Option<u32>,
+    },
+    BindColorTexture {
+        unit: u32,
+        target: Option<u32>,
+    },
+    Clear,
+    DrawFullscreenTriangle,
+    DrawLines {
+        vertex_array: u32,
+        vertex_count: i32,
+    },
+}
+
+/// The program, uniform location or texture id a [`NullBackend`] records in place of
+/// a GPU handle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NullHandle(pub u32);
+
+impl From<glow::NativeProgram> for NullHandle {
+    fn from(program: glow::NativeProgram) -> Self {
+        Self(program.0.get())
+    }
+}
+
+impl From<glow::NativeUniformLocation> for NullHandle {
+    fn from(location: glow::NativeUniformLocation) -> Self {
+        Self(location.0)
+    }
+}
+
+impl From<glow::NativeTexture> for NullHandle {
+    fn from(texture: glow::NativeTexture) -> Self {
+        Self(texture.0.get())
+    }
+}
+
+/// A backend with no GPU behind it. Buffers and vertex arrays are plain ids and
+/// every call is appended to `commands` until it is cleared or taken, so a long-lived
+/// one should be drained every frame.
+#[derive(Default)]
+pub struct NullBackend {
+    pub commands: Vec<NullCommand>,
+    next_id: u32,
+}
+
+impl NullBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn next_id(&mut self) -> u32 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+
+    /// Removes and returns every command recorded so far.
+    pub fn take_commands(&mut self) -> Vec<NullCommand> {
+        std::mem::take(&mut self.commands)
+    }
+
+    /// `(vertex_array, index_count, instance_count)` for every draw call.
+    pub fn draw_calls(&self) -> Vec<(u32, i32, i32)> {
+        self.commands
+            .iter()
+            .filter_map(|c| match c {
+                NullCommand::DrawElementsInstanced {
+                    vertex_array,
+                    index_count,
+                    instance_count,
+                } => Some((*vertex_array, *index_count, *instance_count)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// `(location, value)` for every uniform set.
+    pub fn uniforms(&self) -> Vec<(u32, &UniformValue)> {
+        self.commands
+            .iter()
+            .filter_map(|c| match c {
+                NullCommand::SetUniform { location, value } => Some((*location, value)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn vertex_arrays_created(&self) -> usize {
+        self.commands
+            .iter()
+            .filter(|c| matches!(c, NullCommand::CreateVertexArray { .. }))
+            .count()
+    }
+}
+
+impl GraphicsBackend for NullBackend {
+    type Program = NullHandle;
+    type UniformLocation = NullHandle;
+    type Texture = NullHandle;
+    type Buffer = u32;
+    type VertexArray = u32;
+    type RenderTarget = u32;
+    type ShadowMap = u32;
+    type ColorTarget = u32;
+
+    fn begin_frame(&mut self, width: u32, height: u32) {
+        self.commands
+            .push(NullCommand::BeginFrame { width, height });
+    }
+
+    fn end_frame(&mut self) {
+        self.commands.push(NullCommand::EndFrame);
+    }
+
+    fn create_buffer(&mut self, kind: BufferKind, data: &[u8]) -> u32 {
+        let buffer = self.next_id();
+        self.commands.push(NullCommand::CreateBuffer {
+            buffer,
+            kind,
+            len: data.len(),
+        });
+        buffer
+    }
+
+    fn update_buffer(&mut self, buffer: u32, data: &[u8]) {
+        self.commands.push(NullCommand::UpdateBuffer {
+            buffer,
+            data: data.to_vec(),
+        });
+    }
+
+    fn allocate_buffer(&mut self, buffer: u32, size: usize) {
+        self.commands
+            .push(NullCommand::AllocateBuffer { buffer, len: size });
+    }
+
+    fn update_buffer_range(&mut self, buffer: u32, offset: usize, data: &[u8]) {
+        self.commands.push(NullCommand::UpdateBufferRange {
+            buffer,
+            offset,
+            data: data.to_vec(),
+        });
+    }
+
+    fn delete_buffer(&mut self, buffer: u32) {
+        self.commands.push(NullCommand::DeleteBuffer { buffer });
+    }
+
+    fn create_vertex_array(
+        &mut self,
+        buffers: MeshBuffers<u32>,
+        bindings: &[VertexAttribBinding],
+    ) -> u32 {
+        let vertex_array = self.next_id();
+        self.commands.push(NullCommand::CreateVertexArray {
+            vertex_array,
+            buffers,
+            bindings: bindings.to_vec(),
+        });
+        vertex_array
+    }
+
+    fn delete_vertex_array(&mut self, vertex_array: u32) {
+        self.commands
+            .push(NullCommand::DeleteVertexArray { vertex_array });
+    }
+
+    fn use_program(&mut self, program: Option<NullHandle>) {
+        self.commands.push(NullCommand::UseProgram {
+            program: program.map(|p| p.0),
+        });
+    }
+
+    fn set_uniform(&mut self, location: &NullHandle, value: &UniformValue) {
+        self.commands.push(NullCommand::SetUniform {
+            location: location.0,
+            value: value.clone(),
+        });
+    }
+
+    fn set_uniform_mat4_array(&mut self, location: &NullHandle, matrices: &[[f32; 16]]) {
+        self.commands.push(NullCommand::SetUniformMat4Array {
+            location: location.0,
+            matrices: matrices.to_vec(),
+        });
+    }
+
+    fn bind_texture(&mut self, unit: u32, texture: Option<NullHandle>) {
+        self.commands.push(NullCommand::BindTexture {
+            unit,
+            texture: texture.map(|t| t.0),
+        });
+    }
+
+    fn set_blending(&mut self, enabled: bool) {
+        self.commands.push(NullCommand::SetBlending { enabled });
+    }
+
+    fn set_additive_blending(&mut self, enabled: bool) {
+        self.commands
+            .push(NullCommand::SetAdditiveBlending { enabled });
+    }
+
+    fn draw_elements_instanced(
+        &mut self,
+        vertex_array: u32,
+        index_count: i32,
+        instance_count: i32,
+    ) {
+        self.commands.push(NullCommand::DrawElementsInstanced {
+            vertex_array,
+            index_count,
+            instance_count,
+        });
+    }
+
+    fn create_render_target(&mut self, width: u32, height: u32) -> u32 {
+        let target = self.next_id();
+        self.commands.push(NullCommand::CreateRenderTarget {
+            target,
+            width,
+            height,
+        });
+        target
+    }
+
+    fn delete_render_target(&mut self, target: u32) {
+        self.commands
+            .push(NullCommand::DeleteRenderTarget { target });
+    }
+
+    fn bind_render_target(&mut self, target: Option<u32>) {
+        self.commands.push(NullCommand::BindRenderTarget { target });
+    }
+
+    /// Nothing is ever drawn, so the image is all zeroes.
+    fn read_pixels(&mut self, width: u32, height: u32) -> Vec<u8> {
+        self.commands
+            .push(NullCommand::ReadPixels { width, height });
+        vec![0; width as usize * height as usize * 4]
+    }
+
+    fn create_shadow_map(&mut self, resolution: u32, layers: u32) -> u32 {
+        let shadow_map = self.next_id();
+        self.commands.push(NullCommand::CreateShadowMap {
+            shadow_map,
+            resolution,
+            layers,
+        });
+        shadow_map
+    }
+
+    fn delete_shadow_map(&mut self, shadow_map: u32) {
+        self.commands
+            .push(NullCommand::DeleteShadowMap { shadow_map });
+    }
+
+    fn begin_shadow_pass(&mut self, shadow_map: u32, layer: u32) {
+        self.commands
+            .push(NullCommand::BeginShadowPass { shadow_map, layer });
+    }
+
+    fn end_shadow_pass(&mut self) {
+        self.commands.push(NullCommand::EndShadowPass);
+    }
+
+    fn bind_shadow_map(&mut self, unit: u32, shadow_map: Option<u32>) {
+        self.commands
+            .push(NullCommand::BindShadowMap { unit, shadow_map });
+    }
+
+    fn create_color_target(&mut self, width: u32, height: u32, depth: bool) -> u32 {
+        let target = self.next_id();
+        self.commands.push(NullCommand::CreateColorTarget {
+            target,
+            width,
+            height,
+            depth,
+        });
+        target
+    }
+
+    fn delete_color_target(&mut self, target: u32) {
+        self.commands
+            .push(NullCommand::DeleteColorTarget { target });
+    }
+
+    fn bind_color_target(&mut self, target: Option<u32>) {
+        self.commands.push(NullCommand::BindColorTarget { target });
+    }
+
+    fn bind_color_texture(&mut self, unit: u32, target: Option<u32>) {
+        self.commands
+            .push(NullCommand::BindColorTexture { unit, target });
+    }
+
+    fn clear(&mut self) {
+        self.commands.push(NullCommand::Clear);
+    }
+
+    fn draw_fullscreen_triangle(&mut self) {
+        self.commands.push(NullCommand::DrawFullscreenTriangle);
+    }
+
+    fn draw_lines(&mut self, vertex_array: u32, vertex_count: i32) {
+        self.commands.push(NullCommand::DrawLines {
+            vertex_array,
+            vertex_count,
+        });
+    }
+}
@@ -0,0 +1,264 @@
+use glam::{Mat4, Vec3, Vec3Swizzles};
+
+use crate::assets::shader::{Shader, UniformValue};
+use crate::render::renderer_backends::GraphicsBackend;
+
+/// Most cascades a shadow map can have. Must match `MAX_SHADOW_CASCADES` in `pbr.frag`.
+pub const MAX_SHADOW_CASCADES: usize = 4;
+
+/// Texture unit the shadow map is bound to during the main pass. Materials use the units
+/// from 0 upwards, so this stays clear of them.
+pub const SHADOW_MAP_TEXTURE_UNIT: u32 = 8;
+
+/// How far casters in front of a cascade (towards the light) still land in its depth map.
+const CASTER_MARGIN: f32 = 100.0;
+
+/// Cascaded shadow maps for the brightest directional light.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    /// Number of depth maps the view is split into, from 1 to `MAX_SHADOW_CASCADES`.
+    pub cascade_count: usize,
+    /// Width and height of each cascade's depth map in texels.
+    pub resolution: u32,
+    /// How the camera's depth range is split: 0 gives equal slices, 1 logarithmic ones that
+    /// spend more resolution close to the camera.
+    pub split_lambda: f32,
+    /// Shadows end this far from the camera, or at its far plane if that is closer.
+    pub max_distance: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            cascade_count: 4,
+            resolution: 2048,
+            split_lambda: 0.75,
+            max_distance: 200.0,
+        }
+    }
+}
+
+impl ShadowSettings {
+    pub(crate) fn clamped_cascade_count(&self) -> usize {
+        self.cascade_count.clamp(1, MAX_SHADOW_CASCADES)
+    }
+}
+
+/// One slice of the camera's view and the light-space projection covering it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowCascade {
+    pub light_view_proj: Mat4,
+    /// View depth at which this cascade ends and the next one starts.
+    pub split_far: f32,
+}
+
+/// View depths where each cascade ends, blending uniform and logarithmic splits of
+/// `near..far` by `lambda`.
+pub(crate) fn cascade_splits(near: f32, far: f32, count: usize, lambda: f32) -> Vec<f32> {
+    (1..=count)
+        .map(|i| {
+            let t = i as f32 / count as f32;
+            let logarithmic = near * (far / near).powf(t);
+            let uniform = near + (far - near) * t;
+            lambda * logarithmic + (1.0 - lambda) * uniform
+        })
+        .collect()
+}
+
+/// Fits an orthographic light projection around each slice of the camera frustum.
+///
+/// The camera's `view_proj` must map its near plane to NDC depth 0 and its far plane to 1,
+/// as `Mat4::perspective_rh` does. Each cascade is fitted to a bounding sphere of its slice
+/// and snapped to whole shadow-map texels, so shadows don't shimmer as the camera turns
+/// or moves.
+pub(crate) fn compute_cascades(
+    camera_view_proj: Mat4,
+    near: f32,
+    far: f32,
+    light_direction: Vec3,
+    settings: &ShadowSettings,
+    cascades: &mut Vec<ShadowCascade>,
+) {
+    cascades.clear();
+    let shadow_far = far.min(settings.max_distance);
+    if near <= 0.0 || shadow_far <= near {
+        return;
+    }
+
+    let inverse = camera_view_proj.inverse();
+    let unproject = |x: f32, y: f32, z: f32| inverse.project_point3(Vec3::new(x, y, z));
+    let corners = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)];
+    let near_corners = corners.map(|(x, y)| unproject(x, y, 0.0));
+    let far_corners = corners.map(|(x, y)| unproject(x, y, 1.0));
+
+    let direction = light_direction.normalize_or(Vec3::NEG_Z);
+    let up = if direction.z.abs() > 0.99 {
+        Vec3::Y
+    } else {
+        Vec3::Z
+    };
+
+    let count = settings.clamped_cascade_count();
+    let mut split_near = near;
+    for split_far in cascade_splits(near, shadow_far, count, settings.split_lambda) {
+        // Points along a corner ray have view depth linear in the ray parameter.
+        let slice = |depth: f32| {
+            let t = (depth - near) / (far - near);
+            near_corners
+                .iter()
+                .zip(&far_corners)
+                .map(move |(n, f)| n.lerp(*f, t))
+        };
+        let slice_corners: Vec<Vec3> = slice(split_near).chain(slice(split_far)).collect();
+
+        let center = slice_corners.iter().sum::<Vec3>() / slice_corners.len() as f32;
+        let radius = slice_corners
+            .iter()
+            .map(|corner| corner.distance(center))
+            .fold(0.0, f32::max)
+            .max(f32::EPSILON);
+
+        let eye = center - direction * (radius + CASTER_MARGIN);
+        let view = Mat4::look_at_rh(eye, center, up);
+        let mut projection = Mat4::orthographic_rh_gl(
+            -radius,
+            radius,
+            -radius,
+            radius,
+            0.0,
+            2.0 * radius + CASTER_MARGIN,
+        );
+
+        // Move the projection so the world origin lands on a texel corner.
+        let half_resolution = settings.resolution as f32 / 2.0;
+        let origin = (projection * view).project_point3(Vec3::ZERO).xy() * half_resolution;
+        let offset = (origin.round() - origin) / half_resolution;
+        projection.w_axis.x += offset.x;
+        projection.w_axis.y += offset.y;
+
+        cascades.push(ShadowCascade {
+            light_view_proj: projection * view,
+            split_far,
+        });
+        split_near = split_far;
+    }
+}
+
+/// Where one shader's shadow uniforms live. Shaders that don't sample shadows have none.
+pub(crate) struct ShadowUniformLocations {
+    map: Option<glow::UniformLocation>,
+    cascade_count: Option<glow::UniformLocation>,
+    matrices: Vec<Option<glow::UniformLocation>>,
+    splits: Vec<Option<glow::UniformLocation>>,
+}
+
+impl ShadowUniformLocations {
+    pub(crate) fn new(shader: &Shader) -> Self {
+        let array = |name: &str| {
+            (0..MAX_SHADOW_CASCADES)
+                .map(|i| shader.get_uniform(&format!("{name}[{i}]")))
+                .collect()
+        };
+        Self {
+            map: shader.get_uniform("u_shadow_map"),
+            cascade_count: shader.get_uniform("u_shadow_cascade_count"),
+            matrices: array("u_shadow_matrices"),
+            splits: array("u_shadow_splits"),
+        }
+    }
+
+    /// Sets the shadow uniforms of the program in use. With no cascades the shader
+    /// skips shadowing.
+    pub(crate) fn bind<B: GraphicsBackend>(&self, backend: &mut B, cascades: &[ShadowCascade]) {
+        let mut set = |location: &Option<glow::UniformLocation>, value: UniformValue| {
+            if let Some(location) = location {
+                backend.set_uniform(&(*location).into(), &value);
+            }
+        };
+
+        set(&self.map, UniformValue::Int(SHADOW_MAP_TEXTURE_UNIT as i32));
+        set(
+            &self.cascade_count,
+            UniformValue::Int(cascades.len() as i32),
+        );
+        for ((cascade, matrix), split) in cascades.iter().zip(&self.matrices).zip(&self.splits) {
+            set(matrix, UniformValue::Mat4(cascade.light_view_proj));
+            set(split, UniformValue::Float(cascade.split_far));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera_view_proj(near: f32, far: f32) -> Mat4 {
+        let view = Mat4::look_at_rh(Vec3::ZERO, Vec3::NEG_Y, Vec3::Z);
+        let projection = Mat4::perspective_rh(60f32.to_radians(), 16.0 / 9.0, near, far);
+        projection * view
+    }
+
+    #[test]
+    fn splits_run_from_near_to_far() {
+        let splits = cascade_splits(0.1, 100.0, 4, 0.5);
+
+        assert_eq!(splits.len(), 4);
+        assert!(splits.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!((splits[3] - 100.0).abs() < 1e-3);
+
+        let uniform = cascade_splits(1.0, 101.0, 4, 0.0);
+        assert_eq!(uniform, vec![26.0, 51.0, 76.0, 101.0]);
+    }
+
+    #[test]
+    fn each_cascade_contains_its_slice_of_the_view() {
+        let settings = ShadowSettings {
+            cascade_count: 3,
+            max_distance: 60.0,
+            ..Default::default()
+        };
+        let mut cascades = Vec::new();
+        compute_cascades(
+            camera_view_proj(0.5, 500.0),
+            0.5,
+            500.0,
+            Vec3::new(0.3, -0.2, -1.0),
+            &settings,
+            &mut cascades,
+        );
+
+        assert_eq!(cascades.len(), 3);
+        assert!((cascades[2].split_far - 60.0).abs() < 1e-3);
+
+        // A point straight ahead of the camera at the middle of each slice lands inside
+        // that cascade's light projection.
+        let mut split_near = 0.5;
+        for cascade in &cascades {
+            let depth = (split_near + cascade.split_far) / 2.0;
+            let point = Vec3::new(0.0, -depth, 0.0);
+            let ndc = cascade.light_view_proj.project_point3(point);
+            assert!(ndc.abs().max_element() < 1.0, "{ndc:?} at depth {depth}");
+            split_near = cascade.split_far;
+        }
+    }
+
+    #[test]
+    fn no_cascades_without_a_depth_range() {
+        let mut cascades = Vec::new();
+        compute_cascades(
+            camera_view_proj(0.5, 500.0),
+            0.5,
+            500.0,
+            Vec3::NEG_Z,
+            &ShadowSettings {
+                max_distance: 0.1,
+                ..Default::default()
+            },
+            &mut cascades,
+        );
+
+        assert!(cascades.is_empty());
+    }
+}
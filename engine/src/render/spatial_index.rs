@@ -0,0 +1,187 @@
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    ops::Range,
+};
+
+use bevy_ecs::entity::Entity;
+
+use crate::{
+    physics::dynamic_aabb_tree::{DynamicAabbTree, NodeId},
+    render::{frustum::Frustum, render_instance::RenderGroup},
+};
+
+/// Bounding volume tree over the render groups staged each frame, kept between frames so
+/// only groups that moved are reinserted. Culling walks the tree and skips whole
+/// subtrees outside the frustum.
+#[derive(Debug, Default)]
+pub struct RenderSpatialIndex {
+    tree: DynamicAabbTree,
+    entries: HashMap<Entity, IndexEntry>,
+    /// Staged instances that no indexed group covers; tested one by one.
+    unindexed: Vec<Range<usize>>,
+    /// Bumped by every `update`, to find groups that weren't staged again.
+    generation: u64,
+}
+
+#[derive(Debug)]
+struct IndexEntry {
+    node: NodeId,
+    instances: Range<usize>,
+    generation: u64,
+}
+
+impl RenderSpatialIndex {
+    /// Takes this frame's groups of `instance_count` staged instances. Groups with new
+    /// bounds are moved in the tree and groups missing from `groups` are removed.
+    pub fn update(&mut self, groups: &[RenderGroup], instance_count: usize) {
+        self.generation += 1;
+        self.unindexed.clear();
+
+        let mut covered = 0;
+        for group in groups {
+            if group.instances.start > covered {
+                self.unindexed.push(covered..group.instances.start);
+            }
+            covered = covered.max(group.instances.end);
+
+            let entry = match self.entries.entry(group.entity) {
+                Entry::Occupied(occupied) => {
+                    let entry = occupied.into_mut();
+                    if let Some(bounds) = group.moved_bounds {
+                        self.tree.update(entry.node, bounds);
+                    }
+                    entry
+                }
+                Entry::Vacant(vacant) => {
+                    // Never seen with bounds, e.g. staged before the renderer existed.
+                    let Some(bounds) = group.moved_bounds else {
+                        self.unindexed.push(group.instances.clone());
+                        continue;
+                    };
+                    vacant.insert(IndexEntry {
+                        node: self.tree.allocate_leaf(group.entity, bounds),
+                        instances: 0..0,
+                        generation: 0,
+                    })
+                }
+            };
+            entry.instances = group.instances.clone();
+            entry.generation = self.generation;
+        }
+        if instance_count > covered {
+            self.unindexed.push(covered..instance_count);
+        }
+
+        let (generation, tree) = (self.generation, &mut self.tree);
+        self.entries.retain(|_, entry| {
+            if entry.generation != generation {
+                tree.destroy_leaf(entry.node);
+            }
+            entry.generation == generation
+        });
+    }
+
+    /// Calls `callback` with every range of staged instances that may be inside
+    /// `frustum`, and whether it is known to be entirely inside.
+    pub fn query(&self, frustum: &Frustum, mut callback: impl FnMut(Range<usize>, bool)) {
+        for range in &self.unindexed {
+            callback(range.clone(), false);
+        }
+        self.tree.query_frustum(frustum, |entity, inside| {
+            if let Some(entry) = self.entries.get(&entity) {
+                callback(entry.instances.clone(), inside);
+            }
+        });
+    }
+
+    /// Number of groups in the tree.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Mat4, Vec3};
+
+    use super::*;
+    use crate::assets::mesh::Aabb;
+
+    fn group(entity: u64, instances: Range<usize>, at: Option<Vec3>) -> RenderGroup {
+        RenderGroup {
+            entity: Entity::from_bits(entity),
+            instances,
+            moved_bounds: at.map(|at| Aabb {
+                min: at - Vec3::ONE,
+                max: at + Vec3::ONE,
+            }),
+        }
+    }
+
+    fn visible(index: &RenderSpatialIndex) -> Vec<(Range<usize>, bool)> {
+        let view = Mat4::look_at_rh(Vec3::new(0.0, 0.0, 10.0), Vec3::ZERO, Vec3::Y);
+        let proj = Mat4::perspective_rh(60f32.to_radians(), 4.0 / 3.0, 0.1, 100.0);
+        let mut ranges = Vec::new();
+        index.query(&Frustum::from_view_proj(&(proj * view)), |range, inside| {
+            ranges.push((range, inside))
+        });
+        ranges.sort_by_key(|(range, _)| range.start);
+        ranges
+    }
+
+    #[test]
+    fn culls_groups_and_keeps_bounds_of_unmoved_ones() {
+        let mut index = RenderSpatialIndex::default();
+        index.update(
+            &[
+                group(1, 0..2, Some(Vec3::ZERO)),
+                group(2, 2..3, Some(Vec3::new(0.0, 0.0, 50.0))),
+            ],
+            3,
+        );
+        assert_eq!(visible(&index), vec![(0..2, true)]);
+
+        // Unmoved groups keep their bounds even when their instances shift.
+        index.update(
+            &[
+                group(2, 0..1, Some(Vec3::new(0.0, 0.0, -20.0))),
+                group(1, 1..3, None),
+            ],
+            3,
+        );
+        assert_eq!(visible(&index), vec![(0..1, true), (1..3, true)]);
+        assert_eq!(index.len(), 2);
+    }
+
+    #[test]
+    fn groups_no_longer_staged_are_removed() {
+        let mut index = RenderSpatialIndex::default();
+        index.update(
+            &[
+                group(1, 0..1, Some(Vec3::ZERO)),
+                group(2, 1..2, Some(Vec3::X)),
+            ],
+            2,
+        );
+        index.update(&[group(2, 0..1, None)], 1);
+
+        assert_eq!(index.len(), 1);
+        assert_eq!(visible(&index), vec![(0..1, true)]);
+    }
+
+    #[test]
+    fn instances_outside_indexed_groups_are_always_tested() {
+        let mut index = RenderSpatialIndex::default();
+        // A group never given bounds, and instances after the last group.
+        index.update(&[group(1, 1..2, None), group(2, 2..3, Some(Vec3::ZERO))], 5);
+
+        assert_eq!(
+            visible(&index),
+            vec![(0..1, false), (1..2, false), (2..3, true), (3..5, false)]
+        );
+    }
+}
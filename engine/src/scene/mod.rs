@@ -1,3 +1,5 @@
 pub mod scene;
 pub mod scene_services;
 pub mod scene_changer_resource;
+pub mod scene_file;
+pub mod transform_system;
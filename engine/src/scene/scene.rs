@@ -1,11 +1,11 @@
 use bevy_ecs::prelude::*;
 
 use crate::{
-    ActiveCamera, Gravity, TimeResource, WorldBasis,
+    ActiveCamera, ExitRequest, Gravity, TimeResource, WorldBasis,
     audio::audio_control::AudioControl,
     input::InputStateResource,
     physics::physics_resource::{CollisionFrameData, PhysicsFrameData, PhysicsResource},
-    render::render_queue::RenderQueue,
+    render::{debug_draw::DebugDraw, render_queue::RenderQueue},
     scene::{scene_changer_resource::SceneChangerResource, scene_services::SceneServices},
 };
 
@@ -30,6 +30,7 @@ impl Scene {
         world.insert_resource(services.materials.clone());
 
         world.insert_resource(RenderQueue::default());
+        world.insert_resource(DebugDraw::default());
         world.insert_resource(ActiveCamera::default());
         world.insert_resource(InputStateResource::default());
         world.insert_resource(WorldBasis::canonical());
@@ -40,6 +41,7 @@ impl Scene {
         world.insert_resource(Gravity::default());
         world.insert_resource(AudioControl::default());
         world.insert_resource(SceneChangerResource::default());
+        world.insert_resource(ExitRequest::default());
 
         let game_frame_schedule = Schedule::default();
         let game_simulation_schedule = Schedule::default();
@@ -0,0 +1,545 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    ActiveCamera, CameraComponent, Children, DirectionalLight, Engine, InstanceParams,
+    MaterialComponent, MaterialHandle, Occluder, Parent, PointLight, RenderBodyComponent,
+    RenderBodyHandle, SleepComponent, SoundHandle, SpotLight, TransformComponent,
+    VelocityComponent,
+    assets::{
+        asset_error::AssetError, asset_registry::asset_file_path,
+        material_resource::MaterialResource, sound_resource::SoundResource,
+    },
+    components::{
+        audio_source_component::AudioSourceComponent,
+        collider_component::{CollisionLayer, ConvexCollider, MeshCollider},
+        physics_component::PhysicsComponent,
+        physics_event_listener_component::PhysicsEventListenerComponent,
+        simple_on_hit_audio_component::SimpleOnHitAudioComponent,
+        single_audio_listener_component::SingleAudioListenerComponent,
+    },
+    render::render_body_resource::RenderBodyResource,
+    scene::scene::Scene,
+};
+
+#[derive(Debug, Error)]
+pub enum SceneFileError {
+    #[error("IO Error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Deserialization Error: {0}")]
+    Parse(#[from] toml::de::Error),
+
+    #[error("Serialization Error: {0}")]
+    Serialize(#[from] toml::ser::Error),
+
+    #[error(transparent)]
+    Asset(#[from] AssetError),
+}
+
+/// A scene's entities and engine components in a form that can be written to TOML.
+///
+/// Asset handles are stored as their path in the asset registries, so only assets loaded
+/// with `Engine::load_model` / `Engine::load_wav` (and the materials inside those models)
+/// survive a round trip. Components referencing assets built in code are skipped with a warning.
+/// Resources and schedules are not saved; they come from `Scene::new` and game code.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SceneFile {
+    #[serde(default)]
+    pub entities: Vec<EntityRecord>,
+}
+
+/// All saved components of one entity. Absent components are `None`/`false`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EntityRecord {
+    /// Index in `SceneFile::entities` of the entity's `Parent`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transform: Option<TransformComponent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub velocity: Option<VelocityComponent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub physics: Option<PhysicsComponent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sleep: Option<SleepComponent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub convex_collider: Option<ConvexCollider>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mesh_collider: Option<MeshColliderRecord>,
+    /// Path of the model file the render body was loaded from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub render_body: Option<String>,
+    /// Registry path of the material, e.g. `models/house.gltf#material0`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub material: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camera: Option<CameraComponent>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub active_camera: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directional_light: Option<DirectionalLight>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub point_light: Option<PointLight>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spot_light: Option<SpotLight>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub occluder: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_params: Option<InstanceParams>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_source: Option<AudioSourceRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_hit_audio: Option<OnHitAudioRecord>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub audio_listener: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub physics_event_listener: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeshColliderRecord {
+    pub render_body: String,
+    pub layer: CollisionLayer,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioSourceRecord {
+    pub sound: String,
+    pub volume: f32,
+    pub pitch: f32,
+    pub looping: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnHitAudioRecord {
+    pub sound: String,
+    pub volume: f32,
+    pub pitch: f32,
+    pub force_volume_scale: f32,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+impl EntityRecord {
+    fn is_empty(&self) -> bool {
+        self.transform.is_none()
+            && self.velocity.is_none()
+            && self.physics.is_none()
+            && self.sleep.is_none()
+            && self.convex_collider.is_none()
+            && self.mesh_collider.is_none()
+            && self.render_body.is_none()
+            && self.material.is_none()
+            && self.camera.is_none()
+            && self.directional_light.is_none()
+            && self.point_light.is_none()
+            && self.spot_light.is_none()
+            && !self.occluder
+            && self.instance_params.is_none()
+            && self.audio_source.is_none()
+            && self.on_hit_audio.is_none()
+            && !self.audio_listener
+            && !self.physics_event_listener
+    }
+}
+
+impl SceneFile {
+    /// Captures every entity in `world` that has at least one saved component or is part of
+    /// a hierarchy, in entity index order. Grouping nodes with nothing else on them are kept
+    /// so their children stay attached.
+    pub fn from_world(world: &mut World) -> Self {
+        let mut entities: Vec<Entity> = world.query::<Entity>().iter(world).collect();
+        entities.sort_by_key(|e| e.index());
+
+        let active_camera = world.get_resource::<ActiveCamera>().and_then(|c| c.get());
+        let bodies = world
+            .get_resource::<RenderBodyResource>()
+            .expect("RenderBodyResource resource not found")
+            .read();
+        let sounds = world
+            .get_resource::<SoundResource>()
+            .expect("SoundResource resource not found")
+            .read();
+        let materials = world
+            .get_resource::<MaterialResource>()
+            .expect("MaterialResource resource not found")
+            .read();
+
+        let body_path = |handle: RenderBodyHandle| {
+            let path = bodies.paths.path(handle).map(str::to_string);
+            if path.is_none() {
+                log::warn!("Render body {:?} has no source path; not saved", handle);
+            }
+            path
+        };
+        let sound_path = |handle: SoundHandle| {
+            let path = sounds.paths.path(handle).map(str::to_string);
+            if path.is_none() {
+                log::warn!("Sound {:?} has no source path; not saved", handle);
+            }
+            path
+        };
+        let material_path = |handle: MaterialHandle| {
+            let path = materials.paths.path(handle).map(str::to_string);
+            if path.is_none() {
+                log::warn!("Material {:?} has no source path; not saved", handle);
+            }
+            path
+        };
+
+        let mut saved = Vec::with_capacity(entities.len());
+        let mut records = Vec::with_capacity(entities.len());
+        for entity in entities {
+            let record = EntityRecord {
+                parent: None,
+                transform: world.get::<TransformComponent>(entity).copied(),
+                velocity: world.get::<VelocityComponent>(entity).copied(),
+                physics: world.get::<PhysicsComponent>(entity).copied(),
+                sleep: world.get::<SleepComponent>(entity).copied(),
+                convex_collider: world.get::<ConvexCollider>(entity).copied(),
+                mesh_collider: world.get::<MeshCollider>(entity).and_then(|c| {
+                    Some(MeshColliderRecord {
+                        render_body: body_path(c.render_body_id)?,
+                        layer: c.layer,
+                    })
+                }),
+                render_body: world
+                    .get::<RenderBodyComponent>(entity)
+                    .and_then(|c| body_path(c.render_body_id)),
+                material: world
+                    .get::<MaterialComponent>(entity)
+                    .and_then(|c| material_path(c.material_id)),
+                camera: world.get::<CameraComponent>(entity).copied(),
+                active_camera: active_camera == Some(entity),
+                directional_light: world.get::<DirectionalLight>(entity).copied(),
+                point_light: world.get::<PointLight>(entity).copied(),
+                spot_light: world.get::<SpotLight>(entity).copied(),
+                occluder: world.get::<Occluder>(entity).is_some(),
+                instance_params: world.get::<InstanceParams>(entity).copied(),
+                audio_source: world.get::<AudioSourceComponent>(entity).and_then(|c| {
+                    Some(AudioSourceRecord {
+                        sound: sound_path(c.sound)?,
+                        volume: c.volume,
+                        pitch: c.pitch,
+                        looping: c.looping,
+                    })
+                }),
+                on_hit_audio: world
+                    .get::<SimpleOnHitAudioComponent>(entity)
+                    .and_then(|c| {
+                        Some(OnHitAudioRecord {
+                            sound: sound_path(c.sound_handle)?,
+                            volume: c.volume,
+                            pitch: c.pitch,
+                            force_volume_scale: c.force_volume_scale,
+                        })
+                    }),
+                audio_listener: world.get::<SingleAudioListenerComponent>(entity).is_some(),
+                physics_event_listener: world
+                    .get::<PhysicsEventListenerComponent>(entity)
+                    .is_some(),
+            };
+
+            let in_hierarchy =
+                world.get::<Parent>(entity).is_some() || world.get::<Children>(entity).is_some();
+            if !record.is_empty() || in_hierarchy {
+                saved.push(entity);
+                records.push(record);
+            }
+        }
+
+        let indices: HashMap<Entity, usize> = saved
+            .iter()
+            .enumerate()
+            .map(|(index, &entity)| (entity, index))
+            .collect();
+        for (record, &entity) in records.iter_mut().zip(&saved) {
+            record.parent = world
+                .get::<Parent>(entity)
+                .and_then(|parent| indices.get(&parent.get()).copied());
+        }
+
+        Self { entities: records }
+    }
+
+    pub fn to_toml_string(&self) -> Result<String, SceneFileError> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    pub fn from_toml_str(contents: &str) -> Result<Self, SceneFileError> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    pub fn save_to_file(&self, path: impl AsRef<Path>) -> Result<(), SceneFileError> {
+        fs::write(path, self.to_toml_string()?)?;
+        Ok(())
+    }
+
+    pub fn load_from_file(path: impl AsRef<Path>) -> Result<Self, SceneFileError> {
+        Self::from_toml_str(&fs::read_to_string(path)?)
+    }
+}
+
+impl Engine {
+    /// Saves the current scene's entities to a TOML scene file.
+    pub fn save_scene(&mut self, path: impl AsRef<Path>) -> Result<(), SceneFileError> {
+        SceneFile::from_world(&mut self.scene.world).save_to_file(path)
+    }
+
+    /// Loads a TOML scene file into a new `Scene`, loading every model and sound it references.
+    /// The returned scene has empty game schedules; switch to it like any other scene.
+    pub fn load_scene(&mut self, path: impl AsRef<Path>) -> Result<Scene, SceneFileError> {
+        let file = SceneFile::load_from_file(path)?;
+        self.instantiate_scene(&file)
+    }
+
+    /// Builds a new `Scene` containing the entities of `file`.
+    /// Assets that are already loaded are reused through the asset registries.
+    pub fn instantiate_scene(&mut self, file: &SceneFile) -> Result<Scene, SceneFileError> {
+        let mut bodies: HashMap<String, RenderBodyHandle> = HashMap::new();
+        let mut sounds: HashMap<String, SoundHandle> = HashMap::new();
+        let mut materials: HashMap<String, MaterialHandle> = HashMap::new();
+
+        for record in &file.entities {
+            let body_paths = record
+                .render_body
+                .iter()
+                .chain(record.mesh_collider.iter().map(|c| &c.render_body));
+            for path in body_paths {
+                let handle = self.load_model(path)?;
+                bodies.insert(path.clone(), handle);
+            }
+
+            let sound_paths = record
+                .audio_source
+                .iter()
+                .map(|a| &a.sound)
+                .chain(record.on_hit_audio.iter().map(|a| &a.sound));
+            for path in sound_paths {
+                let handle = self.load_wav(path)?;
+                sounds.insert(path.clone(), handle);
+            }
+
+            if let Some(path) = &record.material {
+                // Materials live inside model files, so load the model to register them.
+                self.load_model(asset_file_path(path))?;
+                let handle = self
+                    .scene
+                    .world
+                    .resource::<MaterialResource>()
+                    .read()
+                    .paths
+                    .handle(path)
+                    .ok_or_else(|| AssetError::NotLoaded(path.clone()))?;
+                materials.insert(path.clone(), handle);
+            }
+        }
+
+        let mut scene = self.new_scene();
+        let world = &mut scene.world;
+        let mut spawned = Vec::with_capacity(file.entities.len());
+        for record in &file.entities {
+            let mut entity = world.spawn_empty();
+            if let Some(transform) = record.transform {
+                entity.insert(transform);
+            }
+            if let Some(velocity) = record.velocity {
+                entity.insert(velocity);
+            }
+            if let Some(physics) = record.physics {
+                entity.insert(physics);
+            }
+            if let Some(sleep) = record.sleep {
+                entity.insert(sleep);
+            }
+            if let Some(collider) = record.convex_collider {
+                entity.insert(collider);
+            }
+            if let Some(collider) = &record.mesh_collider {
+                entity.insert(MeshCollider::new(
+                    bodies[&collider.render_body],
+                    collider.layer,
+                ));
+            }
+            if let Some(path) = &record.render_body {
+                entity.insert(RenderBodyComponent {
+                    render_body_id: bodies[path],
+                });
+            }
+            if let Some(path) = &record.material {
+                entity.insert(MaterialComponent {
+                    material_id: materials[path],
+                });
+            }
+            if let Some(camera) = record.camera {
+                entity.insert(camera);
+            }
+            if let Some(light) = record.directional_light {
+                entity.insert(light);
+            }
+            if let Some(light) = record.point_light {
+                entity.insert(light);
+            }
+            if let Some(light) = record.spot_light {
+                entity.insert(light);
+            }
+            if let Some(source) = &record.audio_source {
+                entity.insert(AudioSourceComponent {
+                    sound: sounds[&source.sound],
+                    volume: source.volume,
+                    pitch: source.pitch,
+                    looping: source.looping,
+                });
+            }
+            if let Some(on_hit) = &record.on_hit_audio {
+                entity.insert(SimpleOnHitAudioComponent {
+                    sound_handle: sounds[&on_hit.sound],
+                    volume: on_hit.volume,
+                    pitch: on_hit.pitch,
+                    force_volume_scale: on_hit.force_volume_scale,
+                });
+            }
+            if record.occluder {
+                entity.insert(Occluder);
+            }
+            if let Some(params) = record.instance_params {
+                entity.insert(params);
+            }
+            if record.audio_listener {
+                entity.insert(SingleAudioListenerComponent);
+            }
+            if record.physics_event_listener {
+                entity.insert(PhysicsEventListenerComponent);
+            }
+
+            let id = entity.id();
+            if record.active_camera {
+                world.resource_mut::<ActiveCamera>().set(id);
+            }
+            spawned.push(id);
+        }
+
+        // Parents can come after their children in the file, so attach once all exist.
+        for (record, &entity) in file.entities.iter().zip(&spawned) {
+            let Some(index) = record.parent else {
+                continue;
+            };
+            match spawned.get(index) {
+                Some(&parent) if parent != entity => {
+                    world.entity_mut(entity).insert(Parent(parent));
+                }
+                _ => log::warn!("Invalid parent index {} in scene file; not attached", index),
+            }
+        }
+
+        Ok(scene)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Mat3, Quat, Vec3, Vec4};
+
+    use super::*;
+    use crate::components::{collider_component::ConvexShape, physics_component::PhysicsType};
+
+    #[test]
+    fn records_round_trip_through_toml() {
+        let file = SceneFile {
+            entities: vec![
+                EntityRecord {
+                    transform: Some(TransformComponent {
+                        position: Vec3::new(1.0, -2.5, 3.25),
+                        rotation: Quat::from_rotation_z(0.3),
+                        scale: Vec3::splat(2.0),
+                    }),
+                    physics: Some(PhysicsComponent {
+                        physics_type: PhysicsType::Dynamic,
+                        mass: 2.0,
+                        friction: 0.4,
+                        drag_coefficient: 0.1,
+                        angular_drag_coefficient: 0.2,
+                        restitution: 0.3,
+                        local_inertia: Mat3::IDENTITY,
+                    }),
+                    convex_collider: Some(ConvexCollider::sphere(0.5, CollisionLayer::Player)),
+                    render_body: Some("models/crate.gltf".to_string()),
+                    material: Some("models/crate.gltf#material1".to_string()),
+                    occluder: true,
+                    instance_params: Some(InstanceParams {
+                        tint: Vec4::new(1.0, 0.2, 0.2, 0.5),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+                EntityRecord {
+                    parent: Some(0),
+                    camera: Some(CameraComponent {
+                        fov_y_radians: 1.0,
+                        aspect_ratio: 0.0,
+                        near: 0.1,
+                        far: 500.0,
+                    }),
+                    active_camera: true,
+                    audio_listener: true,
+                    point_light: Some(PointLight {
+                        range: 25.0,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            ],
+        };
+
+        let text = file.to_toml_string().unwrap();
+        let loaded = SceneFile::from_toml_str(&text).unwrap();
+
+        assert_eq!(loaded.entities.len(), 2);
+        let first = &loaded.entities[0];
+        let transform = first.transform.unwrap();
+        assert_eq!(transform.position, Vec3::new(1.0, -2.5, 3.25));
+        assert_eq!(transform.rotation, Quat::from_rotation_z(0.3));
+        assert!(matches!(
+            first.physics.unwrap().physics_type,
+            PhysicsType::Dynamic
+        ));
+        assert!(matches!(
+            first.convex_collider.unwrap().shape,
+            ConvexShape::Sphere { radius } if radius == 0.5
+        ));
+        assert_eq!(first.render_body.as_deref(), Some("models/crate.gltf"));
+        assert_eq!(
+            first.material.as_deref(),
+            Some("models/crate.gltf#material1")
+        );
+        assert!(first.velocity.is_none());
+        assert!(first.parent.is_none());
+        assert!(!first.active_camera);
+        assert!(first.occluder);
+        let params = first.instance_params.unwrap();
+        assert_eq!(params.tint, Vec4::new(1.0, 0.2, 0.2, 0.5));
+        assert_eq!(params.highlight, Vec4::ZERO);
+
+        let second = &loaded.entities[1];
+        assert!(second.active_camera && second.audio_listener);
+        assert_eq!(second.parent, Some(0));
+        assert_eq!(second.camera.unwrap().far, 500.0);
+        assert_eq!(second.point_light.unwrap().range, 25.0);
+        assert!(second.spot_light.is_none());
+        assert!(!second.occluder);
+        assert!(second.instance_params.is_none());
+    }
+
+    #[test]
+    fn empty_file_has_no_entities() {
+        let loaded = SceneFile::from_toml_str("").unwrap();
+        assert!(loaded.entities.is_empty());
+    }
+}
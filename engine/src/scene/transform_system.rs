@@ -0,0 +1,267 @@
+use std::collections::HashSet;
+
+use bevy_ecs::prelude::*;
+use glam::Mat4;
+
+use crate::components::{
+    hierarchy_component::{Children, Parent},
+    transform_component::{GlobalTransform, TransformComponent},
+};
+
+pub struct TransformSystem {}
+
+impl TransformSystem {
+    /// Walks down from every root entity and writes each `GlobalTransform` as its parent's
+    /// world matrix times its own `TransformComponent`.
+    ///
+    /// Only entities whose transform or parent changed since the last run, or that sit
+    /// below such an entity, are written, so `Changed<GlobalTransform>` means the entity
+    /// moved in the world. Children without a `TransformComponent` end the walk down
+    /// their branch.
+    #[allow(clippy::type_complexity)]
+    pub fn propagate_transforms(
+        mut roots: Query<
+            (
+                Entity,
+                Ref<TransformComponent>,
+                &mut GlobalTransform,
+                Option<&Children>,
+            ),
+            Without<Parent>,
+        >,
+        mut descendants: Query<(
+            Ref<TransformComponent>,
+            Ref<Parent>,
+            &mut GlobalTransform,
+            Option<&Children>,
+        )>,
+        mut removed_parents: RemovedComponents<Parent>,
+        mut stack: Local<Vec<(Entity, Mat4, bool)>>,
+    ) {
+        // Entities detached from their parent are roots now, even if their transform
+        // didn't change.
+        let detached: HashSet<Entity> = removed_parents.read().collect();
+
+        for (entity, transform, mut global, children) in &mut roots {
+            let changed = transform.is_changed() || detached.contains(&entity);
+            if changed {
+                *global = GlobalTransform(transform.to_mat4());
+            }
+            if let Some(children) = children {
+                stack.extend(children.iter().map(|child| (child, global.0, changed)));
+            }
+
+            while let Some((entity, parent_matrix, parent_changed)) = stack.pop() {
+                let Ok((transform, parent, mut global, children)) = descendants.get_mut(entity)
+                else {
+                    continue;
+                };
+                let changed = parent_changed || transform.is_changed() || parent.is_changed();
+                if changed {
+                    *global = GlobalTransform(parent_matrix * transform.to_mat4());
+                }
+                if let Some(children) = children {
+                    stack.extend(children.iter().map(|child| (child, global.0, changed)));
+                }
+            }
+        }
+    }
+}
+
+/// World matrix of `entity` computed from the `TransformComponent`s up its parent chain.
+/// Unlike `GlobalTransform`, this is up to date between propagation runs.
+pub fn world_matrix(world: &World, entity: Entity) -> Mat4 {
+    let mut matrix = Mat4::IDENTITY;
+    let mut current = Some(entity);
+    while let Some(entity) = current {
+        if let Some(transform) = world.get::<TransformComponent>(entity) {
+            matrix = transform.to_mat4() * matrix;
+        }
+        current = world.get::<Parent>(entity).map(Parent::get);
+    }
+    matrix
+}
+
+/// Attaches `child` to `parent`, or detaches it with `None`, without moving it in the
+/// world: its `TransformComponent` is rewritten relative to the new parent.
+///
+/// Parenting an entity to itself or to one of its descendants would create a cycle; that
+/// is refused with a warning and nothing changes.
+pub fn reparent(world: &mut World, child: Entity, parent: Option<Entity>) {
+    if let Some(parent) = parent {
+        let mut ancestor = Some(parent);
+        while let Some(entity) = ancestor {
+            if entity == child {
+                log::warn!(
+                    "Can't parent {:?} to {:?}: it would become its own ancestor",
+                    child,
+                    parent
+                );
+                return;
+            }
+            ancestor = world.get::<Parent>(entity).map(Parent::get);
+        }
+    }
+
+    let child_world = world_matrix(world, child);
+    let parent_world = parent.map_or(Mat4::IDENTITY, |parent| world_matrix(world, parent));
+    let local = TransformComponent::from_mat4(&(parent_world.inverse() * child_world));
+
+    let mut entity = world.entity_mut(child);
+    entity.insert(local);
+    match parent {
+        Some(parent) => entity.insert(Parent(parent)),
+        None => entity.remove::<Parent>(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Quat, Vec3};
+
+    use super::*;
+
+    fn at(position: Vec3) -> TransformComponent {
+        TransformComponent {
+            position,
+            ..Default::default()
+        }
+    }
+
+    fn propagate(world: &mut World, schedule: &mut Schedule) {
+        schedule.run(world);
+        world.clear_trackers();
+    }
+
+    fn new_schedule() -> Schedule {
+        let mut schedule = Schedule::default();
+        schedule.add_systems(TransformSystem::propagate_transforms);
+        schedule
+    }
+
+    #[test]
+    fn children_follow_their_parents() {
+        let mut world = World::new();
+        let mut schedule = new_schedule();
+        let building = world
+            .spawn(TransformComponent {
+                position: Vec3::new(10.0, 0.0, 0.0),
+                rotation: Quat::from_rotation_z(std::f32::consts::FRAC_PI_2),
+                ..Default::default()
+            })
+            .id();
+        let arm = world
+            .spawn((at(Vec3::new(2.0, 0.0, 5.0)), Parent(building)))
+            .id();
+        let hook = world
+            .spawn((at(Vec3::new(1.0, 0.0, 0.0)), Parent(arm)))
+            .id();
+
+        propagate(&mut world, &mut schedule);
+
+        let hook_position = world.get::<GlobalTransform>(hook).unwrap().translation();
+        assert!(hook_position.abs_diff_eq(Vec3::new(10.0, 3.0, 5.0), 1e-5));
+
+        world
+            .get_mut::<TransformComponent>(building)
+            .unwrap()
+            .position = Vec3::ZERO;
+        propagate(&mut world, &mut schedule);
+
+        let hook_position = world.get::<GlobalTransform>(hook).unwrap().translation();
+        assert!(hook_position.abs_diff_eq(Vec3::new(0.0, 3.0, 5.0), 1e-5));
+    }
+
+    #[test]
+    fn only_moved_branches_are_written() {
+        let mut world = World::new();
+        let mut schedule = new_schedule();
+        // Never moves, so it's never written after the first run.
+        world.spawn(at(Vec3::X));
+        let moving = world.spawn(at(Vec3::Y)).id();
+        let child = world.spawn((at(Vec3::Z), Parent(moving))).id();
+        propagate(&mut world, &mut schedule);
+
+        world
+            .get_mut::<TransformComponent>(moving)
+            .unwrap()
+            .position = Vec3::NEG_Y;
+        schedule.run(&mut world);
+
+        let mut changed = world.query_filtered::<Entity, Changed<GlobalTransform>>();
+        let changed: HashSet<Entity> = changed.iter(&world).collect();
+        assert_eq!(changed, HashSet::from([moving, child]));
+    }
+
+    #[test]
+    fn reparenting_keeps_the_world_transform() {
+        let mut world = World::new();
+        let mut schedule = new_schedule();
+        let vehicle = world
+            .spawn(TransformComponent {
+                position: Vec3::new(5.0, -3.0, 1.0),
+                rotation: Quat::from_rotation_z(0.7),
+                scale: Vec3::splat(2.0),
+            })
+            .id();
+        let camera = world.spawn(at(Vec3::new(1.0, 2.0, 3.0))).id();
+
+        reparent(&mut world, camera, Some(vehicle));
+        propagate(&mut world, &mut schedule);
+
+        assert_eq!(world.get::<Parent>(camera), Some(&Parent(vehicle)));
+        assert_eq!(&**world.get::<Children>(vehicle).unwrap(), &[camera]);
+        let position = world.get::<GlobalTransform>(camera).unwrap().translation();
+        assert!(position.abs_diff_eq(Vec3::new(1.0, 2.0, 3.0), 1e-5));
+
+        world
+            .get_mut::<TransformComponent>(vehicle)
+            .unwrap()
+            .position += Vec3::X;
+        propagate(&mut world, &mut schedule);
+        reparent(&mut world, camera, None);
+        propagate(&mut world, &mut schedule);
+
+        assert!(world.get::<Parent>(camera).is_none());
+        assert!(world.get::<Children>(vehicle).is_none());
+        let transform = world.get::<TransformComponent>(camera).unwrap();
+        assert!(
+            transform
+                .position
+                .abs_diff_eq(Vec3::new(2.0, 2.0, 3.0), 1e-5)
+        );
+        assert!(transform.scale.abs_diff_eq(Vec3::ONE, 1e-5));
+    }
+
+    #[test]
+    fn reparenting_under_a_descendant_is_refused() {
+        let mut world = World::new();
+        let root = world.spawn(TransformComponent::default()).id();
+        let child = world
+            .spawn((TransformComponent::default(), Parent(root)))
+            .id();
+
+        reparent(&mut world, root, Some(child));
+        reparent(&mut world, root, Some(root));
+
+        assert!(world.get::<Parent>(root).is_none());
+        assert_eq!(world.get::<Parent>(child), Some(&Parent(root)));
+    }
+
+    #[test]
+    fn detached_children_become_roots() {
+        let mut world = World::new();
+        let mut schedule = new_schedule();
+        let parent = world.spawn(at(Vec3::X)).id();
+        let child = world.spawn((at(Vec3::Y), Parent(parent))).id();
+        propagate(&mut world, &mut schedule);
+
+        world.entity_mut(child).remove::<Parent>();
+        propagate(&mut world, &mut schedule);
+
+        assert_eq!(
+            world.get::<GlobalTransform>(child).unwrap().translation(),
+            Vec3::Y
+        );
+    }
+}
@@ -9,6 +9,8 @@ pub struct TimeResource {
     frame_count: u64,
     target_frame_duration: Duration,
     last_frame_time: Instant,
+    /// Used instead of wall-clock time by the next `update_time_resource` run.
+    frame_dt_override: Option<Duration>,
 }
 
 impl Default for TimeResource {
@@ -20,6 +22,7 @@ impl Default for TimeResource {
             frame_count: 0,
             target_frame_duration: Duration::from_secs_f32(1.0 / 60.0), // Default to 60 FPS max
             last_frame_time: Instant::now(),
+            frame_dt_override: None,
         }
     }
 }
@@ -36,6 +39,7 @@ impl TimeResource {
             target_frame_duration: target_frame_time,
             simulation_fixed_dt,
             last_frame_time: Instant::now(),
+            frame_dt_override: None,
         }
     }
 
@@ -74,9 +78,17 @@ impl TimeResource {
         self.target_frame_duration
     }
 
+    /// Makes the next `update_time_resource` run advance by `dt` instead of the elapsed wall-clock time.
+    pub(crate) fn set_frame_dt_override(&mut self, dt: Duration) {
+        self.frame_dt_override = Some(dt);
+    }
+
     pub fn update_time_resource(mut time: ResMut<TimeResource>) {
         let now = Instant::now();
-        let frame_time = now.duration_since(time.last_frame_time).as_secs_f32();
+        let frame_time = match time.frame_dt_override.take() {
+            Some(dt) => dt.as_secs_f32(),
+            None => now.duration_since(time.last_frame_time).as_secs_f32(),
+        };
         time.last_frame_time = now;
         time.update_frame_dt(frame_time);
     }
@@ -186,6 +198,26 @@ mod tests {
         assert!(time.total_time() > 0.0);
     }
 
+    #[test]
+    fn update_time_resource_system_uses_frame_dt_override_once() {
+        let mut world = World::new();
+        let mut time = TimeResource::default();
+        time.set_frame_dt_override(Duration::from_millis(40));
+        world.insert_resource(time);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(TimeResource::update_time_resource);
+        schedule.run(&mut world);
+
+        let time = world
+            .get_resource::<TimeResource>()
+            .expect("TimeResource should exist after system run");
+
+        assert_f32_close(time.frame_delta_time(), 0.040, 1e-6);
+        assert_f64_close(time.total_time(), 0.040, 1e-6);
+        assert!(time.frame_dt_override.is_none());
+    }
+
     #[test]
     fn update_time_resource_system_accumulates_over_multiple_runs() {
         let mut world = World::new();
@@ -0,0 +1,136 @@
+use engine::{
+    EngineBuilder,
+    assets::{
+        asset_error::{AssetError, ModelError, SoundError},
+        material_resource::MaterialResource,
+        shader::UniformValue,
+        texture_resource::TextureResource,
+    },
+};
+
+#[test]
+fn missing_and_unsupported_models_are_errors() {
+    let mut engine = EngineBuilder::headless().build();
+
+    assert!(matches!(
+        engine.load_model("test_resources/does_not_exist.obj"),
+        Err(AssetError::Model {
+            source: ModelError::Obj(_),
+            ..
+        })
+    ));
+    assert!(matches!(
+        engine.load_model("test_resources/does_not_exist.gltf"),
+        Err(AssetError::Model {
+            source: ModelError::Gltf(_),
+            ..
+        })
+    ));
+    assert!(matches!(
+        engine.load_model("test_resources/model.fbx"),
+        Err(AssetError::Model {
+            source: ModelError::FbxNotImplemented,
+            ..
+        })
+    ));
+    assert!(matches!(
+        engine.load_model("test_resources/model.blend"),
+        Err(AssetError::UnsupportedFormat(ext)) if ext == "blend"
+    ));
+}
+
+#[test]
+fn missing_sound_is_an_error() {
+    let mut engine = EngineBuilder::headless().build();
+
+    let result = engine.load_wav("../resources/sounds/does_not_exist.wav");
+
+    assert!(matches!(
+        result,
+        Err(AssetError::Sound {
+            source: SoundError::Wav(_),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn missing_texture_falls_back_to_the_checkerboard_on_request() {
+    let engine = EngineBuilder::headless().build();
+    let mut textures = engine.scene.world.resource::<TextureResource>().write();
+
+    assert!(matches!(
+        textures.load_from_file(None, "does_not_exist.png".as_ref()),
+        Err(AssetError::Texture { .. })
+    ));
+
+    let fallback = textures.load_from_file_or_missing(None, "does_not_exist.png".as_ref());
+    assert_eq!(fallback, textures.missing_texture(None));
+    assert_eq!(textures.get_texture(fallback).unwrap().width, 64);
+}
+
+#[test]
+fn model_with_a_missing_texture_still_loads() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("shape.mtl"),
+        "newmtl broken\nmap_Kd missing.png\n",
+    )
+    .unwrap();
+    let obj_path = dir.path().join("shape.obj");
+    std::fs::write(
+        &obj_path,
+        "mtllib shape.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl broken\nf 1 2 3\n",
+    )
+    .unwrap();
+    let mut engine = EngineBuilder::headless().build();
+
+    let obj_path = obj_path.to_str().unwrap();
+    engine.load_model(obj_path).unwrap();
+
+    let missing = engine
+        .scene
+        .world
+        .resource::<TextureResource>()
+        .write()
+        .missing_texture(None);
+    let materials = engine.scene.world.resource::<MaterialResource>().read();
+    let material = materials
+        .get_material(
+            materials
+                .paths
+                .handle(&format!("{}#material0", obj_path))
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(
+        material
+            .desc
+            .params
+            .iter()
+            .any(|(name, value)| name == "u_albedo"
+                && *value
+                    == UniformValue::Texture {
+                        handle: missing,
+                        unit: 0
+                    })
+    );
+}
+
+#[test]
+fn error_material_is_created_once() {
+    let mut engine = EngineBuilder::headless().build();
+
+    let first = engine.error_material();
+
+    assert_eq!(engine.error_material(), first);
+    assert!(
+        engine
+            .scene
+            .world
+            .resource::<MaterialResource>()
+            .read()
+            .get_material(first)
+            .is_some()
+    );
+}
@@ -0,0 +1,58 @@
+use engine::{
+    EngineBuilder,
+    assets::{mesh_resource::MeshResource, sound_resource::SoundResource},
+};
+
+#[test]
+fn loading_a_path_twice_returns_the_cached_handle() {
+    let mut engine = EngineBuilder::headless().build();
+
+    let first = engine
+        .load_model("test_resources/test_ground/test_ground.obj")
+        .unwrap();
+    let mesh_count = engine
+        .scene
+        .world
+        .resource::<MeshResource>()
+        .read()
+        .meshes
+        .len();
+    let second = engine
+        .load_model("./test_resources/test_ground/../test_ground/test_ground.obj")
+        .unwrap();
+    assert_eq!(first, second);
+    assert_eq!(
+        engine
+            .scene
+            .world
+            .resource::<MeshResource>()
+            .read()
+            .meshes
+            .len(),
+        mesh_count,
+        "no new meshes should be created for a cached model"
+    );
+
+    let pop = engine.load_wav("../resources/sounds/pop.wav").unwrap();
+    assert_eq!(engine.load_wav("../resources/sounds/pop.wav").unwrap(), pop);
+    assert_eq!(
+        engine
+            .scene
+            .world
+            .resource::<SoundResource>()
+            .read()
+            .paths
+            .path(pop),
+        Some("../resources/sounds/pop.wav")
+    );
+
+    let meshes = engine.scene.world.resource::<MeshResource>().read();
+    let mesh = meshes
+        .paths
+        .handle("test_resources/test_ground/test_ground.obj#mesh0")
+        .expect("OBJ meshes are registered as sub-assets");
+    assert_eq!(
+        meshes.paths.path(mesh),
+        Some("test_resources/test_ground/test_ground.obj#mesh0")
+    );
+}
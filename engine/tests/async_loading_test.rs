@@ -0,0 +1,110 @@
+use std::time::{Duration, Instant};
+
+use engine::{EngineBuilder, render::render_body_resource::RenderBodyResource};
+
+const GROUND: &str = "test_resources/test_ground/test_ground.obj";
+
+fn part_count(engine: &engine::Engine, handle: engine::RenderBodyHandle) -> usize {
+    engine
+        .scene
+        .world
+        .resource::<RenderBodyResource>()
+        .read()
+        .get_render_body(handle)
+        .unwrap()
+        .parts
+        .len()
+}
+
+#[test]
+fn handle_is_usable_before_the_model_finishes_loading() {
+    let mut engine = EngineBuilder::headless().build();
+
+    let ground = engine.load_model_async(GROUND);
+
+    // Nothing is committed until the main thread asks for it.
+    assert!(engine.load_state(ground).unwrap().is_loading());
+    assert_eq!(part_count(&engine, ground), 0);
+    assert_eq!(engine.load_model_async(GROUND), ground);
+    assert_eq!(engine.pending_loads(), 1);
+
+    engine.wait_for_loads();
+
+    assert!(engine.load_state(ground).unwrap().is_loaded());
+    assert_eq!(part_count(&engine, ground), 1);
+    assert_eq!(engine.load_model(GROUND).unwrap(), ground);
+    assert_eq!(engine.pending_loads(), 0);
+}
+
+#[test]
+fn frames_commit_finished_loads() {
+    let mut engine = EngineBuilder::headless().build();
+    let ground = engine.load_model_async(GROUND);
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while engine.load_state(ground).unwrap().is_loading() {
+        assert!(Instant::now() < deadline, "model never finished loading");
+        engine.step_frame(Duration::from_millis(16));
+    }
+
+    assert!(engine.load_state(ground).unwrap().is_loaded());
+    assert!(engine.aabb_from_render_body(ground).is_some());
+}
+
+#[test]
+fn failed_load_keeps_its_handle_and_can_be_retried() {
+    let mut engine = EngineBuilder::headless().build();
+
+    let missing = engine.load_model_async("test_resources/does_not_exist.obj");
+    engine.wait_for_loads();
+
+    assert!(engine.load_state(missing).unwrap().is_failed());
+    assert_eq!(part_count(&engine, missing), 0);
+
+    // Loading the path again starts a new load under the same handle.
+    assert_eq!(
+        engine.load_model_async("test_resources/does_not_exist.obj"),
+        missing
+    );
+    assert!(engine.load_state(missing).unwrap().is_loading());
+    assert_eq!(engine.pending_loads(), 1);
+    engine.wait_for_loads();
+    assert!(engine.load_state(missing).unwrap().is_failed());
+    assert!(
+        engine
+            .load_model("test_resources/does_not_exist.obj")
+            .is_err()
+    );
+}
+
+#[test]
+fn retried_load_fills_the_failed_handle_once_the_file_exists() {
+    let mut engine = EngineBuilder::headless().build();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test_ground.obj");
+    let path = path.to_str().unwrap();
+
+    let ground = engine.load_model_async(path);
+    engine.wait_for_loads();
+    assert!(engine.load_state(ground).unwrap().is_failed());
+
+    for file in ["test_ground.obj", "test_ground.mtl"] {
+        std::fs::copy(
+            format!("test_resources/test_ground/{file}"),
+            dir.path().join(file),
+        )
+        .unwrap();
+    }
+    assert_eq!(engine.load_model(path).unwrap(), ground);
+    assert!(engine.load_state(ground).unwrap().is_loaded());
+    assert_eq!(part_count(&engine, ground), 1);
+}
+
+#[test]
+fn synchronously_loaded_models_are_loaded() {
+    let mut engine = EngineBuilder::headless().build();
+
+    let ground = engine.load_model(GROUND).unwrap();
+
+    assert!(engine.load_state(ground).unwrap().is_loaded());
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use bevy_ecs::prelude::*;
+use engine::{
+    CollisionLayer, ConvexCollider, DebugDraw, Engine, EngineBuilder, TransformComponent,
+};
+use glam::Vec3;
+
+/// Lines in `DebugDraw` once the frame systems have run, before the frame is rendered.
+#[derive(Resource, Default)]
+struct PushedLines(usize);
+
+fn record_pushed_lines(debug_draw: Res<DebugDraw>, mut pushed: ResMut<PushedLines>) {
+    pushed.0 = debug_draw.lines().len();
+}
+
+fn engine_with_two_cubes() -> Engine {
+    let mut engine = EngineBuilder::headless().build();
+    engine.scene.world.insert_resource(PushedLines::default());
+    engine
+        .scene
+        .game_frame_schedule
+        .add_systems(record_pushed_lines);
+    for x in [-5.0, 5.0] {
+        engine.scene.world.spawn((
+            TransformComponent {
+                position: Vec3::new(x, 0.0, 0.0),
+                ..Default::default()
+            },
+            ConvexCollider::cube(1.0, CollisionLayer::Default),
+        ));
+    }
+    // The visualisations show what the last simulation tick left.
+    engine.step_simulation(1);
+    engine
+}
+
+fn lines_pushed_by_a_frame(engine: &mut Engine) -> usize {
+    engine.step_frame(Duration::from_millis(16));
+    engine.scene.world.resource::<PushedLines>().0
+}
+
+#[test]
+fn physics_visualisations_push_lines_while_enabled() {
+    let mut engine = engine_with_two_cubes();
+    assert_eq!(lines_pushed_by_a_frame(&mut engine), 0);
+
+    engine
+        .scene
+        .world
+        .resource_mut::<DebugDraw>()
+        .physics
+        .colliders = true;
+    // A box of twelve edges per cube.
+    assert_eq!(lines_pushed_by_a_frame(&mut engine), 24);
+
+    let mut debug_draw = engine.scene.world.resource_mut::<DebugDraw>();
+    debug_draw.physics.colliders = false;
+    debug_draw.physics.world_aabbs = true;
+    debug_draw.physics.broadphase = true;
+    // Two world boxes, then the broadphase's two leaves under their root.
+    assert_eq!(lines_pushed_by_a_frame(&mut engine), 24 + 36);
+}
+
+#[test]
+fn shapes_are_cleared_after_each_frame() {
+    let mut engine = engine_with_two_cubes();
+    let mut debug_draw = engine.scene.world.resource_mut::<DebugDraw>();
+    debug_draw.arrow(Vec3::ZERO, Vec3::Z, Vec3::ONE);
+    debug_draw.text(Vec3::Z, "up", Vec3::ONE);
+
+    // Shapes pushed outside the frame systems are drawn by the next frame.
+    assert_eq!(lines_pushed_by_a_frame(&mut engine), 5);
+    assert!(engine.scene.world.resource::<DebugDraw>().is_empty());
+    assert_eq!(lines_pushed_by_a_frame(&mut engine), 0);
+}
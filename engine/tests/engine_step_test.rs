@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use bevy_ecs::prelude::*;
+use engine::{
+    Engine, EngineBuilder, TimeResource, TransformComponent, VelocityComponent,
+    components::physics_component::{PhysicsComponent, PhysicsType},
+};
+use glam::{Mat3, Vec3};
+
+#[derive(Resource, Default)]
+struct Counters {
+    frames: u32,
+    ticks: u32,
+}
+
+fn count_frames(mut counters: ResMut<Counters>) {
+    counters.frames += 1;
+}
+
+fn count_ticks(mut counters: ResMut<Counters>) {
+    counters.ticks += 1;
+}
+
+fn counting_engine() -> Engine {
+    let mut engine = EngineBuilder::headless().build();
+    engine.scene.world.insert_resource(Counters::default());
+    engine.scene.game_frame_schedule.add_systems(count_frames);
+    engine
+        .scene
+        .game_simulation_schedule
+        .add_systems(count_ticks);
+    engine
+}
+
+fn fixed_dt(engine: &Engine) -> Duration {
+    engine
+        .scene
+        .world
+        .get_resource::<TimeResource>()
+        .unwrap()
+        .simulation_fixed_dt()
+}
+
+fn spawn_falling_body(engine: &mut Engine) -> Entity {
+    engine
+        .scene
+        .world
+        .spawn((
+            TransformComponent {
+                position: Vec3::new(0.0, 0.0, 10.0),
+                ..Default::default()
+            },
+            VelocityComponent::default(),
+            PhysicsComponent {
+                physics_type: PhysicsType::Dynamic,
+                mass: 1.0,
+                friction: 0.5,
+                drag_coefficient: 0.1,
+                angular_drag_coefficient: 0.1,
+                restitution: 0.5,
+                local_inertia: Mat3::IDENTITY,
+            },
+        ))
+        .id()
+}
+
+#[test]
+fn step_simulation_runs_exact_tick_count() {
+    let mut engine = counting_engine();
+
+    engine.step_simulation(5);
+
+    let counters = engine.scene.world.get_resource::<Counters>().unwrap();
+    assert_eq!(counters.ticks, 5);
+    assert_eq!(counters.frames, 0);
+    assert_eq!(engine.frame_count(), 0);
+}
+
+#[test]
+fn step_frame_runs_ticks_that_fit_in_dt() {
+    let mut engine = counting_engine();
+    let fixed_dt = fixed_dt(&engine);
+
+    engine.step_frame(fixed_dt * 3);
+    // Leftover time stays in the accumulator, so half a tick more gives no extra tick.
+    engine.step_frame(fixed_dt / 2);
+
+    let counters = engine.scene.world.get_resource::<Counters>().unwrap();
+    assert_eq!(counters.frames, 2);
+    assert_eq!(counters.ticks, 3);
+    assert_eq!(engine.frame_count(), 2);
+}
+
+#[test]
+fn step_frame_reports_dt_to_time_resource() {
+    let mut engine = counting_engine();
+
+    engine.step_frame(Duration::from_millis(20));
+
+    let time = engine.scene.world.get_resource::<TimeResource>().unwrap();
+    assert!((time.frame_delta_time() - 0.020).abs() < 1e-6);
+    assert_eq!(time.frame_count(), 1);
+}
+
+#[test]
+fn step_frame_and_step_simulation_agree() {
+    let mut by_frame = EngineBuilder::headless().build();
+    let mut by_tick = EngineBuilder::headless().build();
+    let body_a = spawn_falling_body(&mut by_frame);
+    let body_b = spawn_falling_body(&mut by_tick);
+
+    let fixed_dt = fixed_dt(&by_frame);
+    by_frame.step_frame(fixed_dt * 4);
+    by_tick.step_simulation(4);
+
+    let a = by_frame
+        .scene
+        .world
+        .get::<TransformComponent>(body_a)
+        .unwrap();
+    let b = by_tick
+        .scene
+        .world
+        .get::<TransformComponent>(body_b)
+        .unwrap();
+    assert!(a.position.z < 10.0, "body should fall under gravity");
+    assert_eq!(a.position, b.position);
+}
+
+#[test]
+fn step_frame_runs_every_tick_of_a_long_dt() {
+    let mut engine = counting_engine();
+    let fixed_dt = fixed_dt(&engine);
+
+    // Longer than `run` would ever feed the accumulator, and more ticks than it allows.
+    engine.step_frame(Duration::from_secs(1));
+
+    let ticks = engine.scene.world.get_resource::<Counters>().unwrap().ticks;
+    assert_eq!(
+        ticks,
+        (Duration::from_secs(1).as_nanos() / fixed_dt.as_nanos()) as u32
+    );
+    assert!(ticks > 6);
+}
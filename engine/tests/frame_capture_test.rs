@@ -0,0 +1,77 @@
+use engine::{
+    ActiveCamera, CameraComponent, CaptureError, EngineBuilder, RenderBodyComponent,
+    TransformComponent,
+};
+use glam::Vec3;
+
+#[test]
+fn capture_frame_fails_without_a_gl_context() {
+    let mut engine = EngineBuilder::headless().build();
+
+    assert!(matches!(
+        engine.capture_frame(),
+        Err(CaptureError::Headless)
+    ));
+    let dir = tempfile::tempdir().unwrap();
+    assert!(matches!(
+        engine.save_screenshot(dir.path().join("frame.png")),
+        Err(CaptureError::Headless)
+    ));
+}
+
+#[test]
+#[ignore = "needs an OpenGL 3.3 context; use LIBGL_ALWAYS_SOFTWARE=1 on machines without a GPU"]
+fn captured_frame_round_trips_through_png() {
+    let mut engine = EngineBuilder::new()
+        .hidden_window()
+        .window_size(64, 48)
+        .build();
+
+    let frame = engine.capture_frame().unwrap();
+    assert_eq!(frame.dimensions(), (64, 48));
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("frame.png");
+    engine.save_screenshot(&path).unwrap();
+    let saved = image::open(&path).unwrap().to_rgba8();
+    assert_eq!(saved, frame);
+}
+
+#[test]
+#[ignore = "needs an OpenGL 3.3 context; use LIBGL_ALWAYS_SOFTWARE=1 on machines without a GPU"]
+fn freshly_built_scene_is_captured_without_stepping_a_frame() {
+    let mut engine = EngineBuilder::new()
+        .hidden_window()
+        .window_size(64, 48)
+        .build();
+    let ground = engine
+        .load_model("test_resources/test_ground/test_ground.obj")
+        .unwrap();
+    let empty = engine.capture_frame().unwrap();
+
+    let world = &mut engine.scene.world;
+    world.spawn((
+        TransformComponent::default(),
+        RenderBodyComponent {
+            render_body_id: ground,
+        },
+    ));
+    let camera = world
+        .spawn((
+            TransformComponent {
+                position: Vec3::new(0.0, 0.0, 10.0),
+                ..Default::default()
+            },
+            CameraComponent {
+                fov_y_radians: 1.0,
+                aspect_ratio: 0.0,
+                near: 0.1,
+                far: 100.0,
+            },
+        ))
+        .id();
+    world.resource_mut::<ActiveCamera>().set(camera);
+
+    // The ground is queued by the capture itself, so it shows up straight away.
+    assert_ne!(engine.capture_frame().unwrap(), empty);
+}
@@ -0,0 +1,167 @@
+use engine::{
+    EngineBuilder,
+    assets::{material::BlendMode, material_resource::MaterialResource},
+    render::render_body_resource::RenderBodyResource,
+};
+use glam::{Mat4, Vec3};
+
+/// One triangle with positions, normals, UVs and u16 indices, stored in that order.
+fn triangle_buffer() -> Vec<u8> {
+    let positions: [f32; 9] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+    let normals: [f32; 9] = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0];
+    let uvs: [f32; 6] = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0];
+    let indices: [u16; 4] = [0, 1, 2, 0];
+
+    let mut bytes = Vec::new();
+    for value in positions.iter().chain(&normals).chain(&uvs) {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    for index in indices {
+        bytes.extend_from_slice(&index.to_le_bytes());
+    }
+    bytes
+}
+
+/// A "Car" node at z = 1 whose two "Wheel" children share one mesh.
+const CAR_GLTF: &str = r#"{
+    "asset": { "version": "2.0" },
+    "scene": 0,
+    "scenes": [{ "nodes": [0] }],
+    "nodes": [
+        { "name": "Car", "translation": [0, 0, 1], "children": [1, 2] },
+        { "name": "Wheel", "translation": [2, 0, 0], "mesh": 0 },
+        { "name": "Wheel", "translation": [-2, 0, 0], "scale": [2, 2, 2], "mesh": 0 }
+    ],
+    "meshes": [{
+        "primitives": [{
+            "attributes": { "POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2 },
+            "indices": 3
+        }]
+    }],
+    "buffers": [{ "uri": "car.bin", "byteLength": 104 }],
+    "bufferViews": [
+        { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+        { "buffer": 0, "byteOffset": 36, "byteLength": 36 },
+        { "buffer": 0, "byteOffset": 72, "byteLength": 24 },
+        { "buffer": 0, "byteOffset": 96, "byteLength": 6 }
+    ],
+    "accessors": [
+        { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+          "min": [0, 0, 0], "max": [1, 1, 0] },
+        { "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3" },
+        { "bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC2" },
+        { "bufferView": 3, "componentType": 5123, "count": 3, "type": "SCALAR" }
+    ]
+}"#;
+
+#[test]
+fn node_transforms_are_accumulated_into_parts() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("car.bin"), triangle_buffer()).unwrap();
+    let gltf_path = dir.path().join("car.gltf");
+    std::fs::write(&gltf_path, CAR_GLTF).unwrap();
+
+    let mut engine = EngineBuilder::headless().build();
+    let car = engine.load_model(gltf_path.to_str().unwrap()).unwrap();
+
+    let bodies = engine.scene.world.resource::<RenderBodyResource>().read();
+    let body = bodies.get_render_body(car).unwrap();
+    assert_eq!(body.parts.len(), 2);
+
+    // Both wheels draw the same mesh.
+    assert_eq!(body.parts[0].mesh_id, body.parts[1].mesh_id);
+
+    let wheels: Vec<_> = body.parts_named("Wheel").collect();
+    assert_eq!(wheels.len(), 2);
+    assert_eq!(
+        wheels[0].local_transform,
+        Mat4::from_translation(Vec3::new(2.0, 0.0, 1.0))
+    );
+    assert_eq!(
+        wheels[1].local_transform,
+        Mat4::from_scale_rotation_translation(
+            Vec3::splat(2.0),
+            glam::Quat::IDENTITY,
+            Vec3::new(-2.0, 0.0, 1.0)
+        )
+    );
+    assert_eq!(body.parts_named("Car").count(), 0);
+    drop(bodies);
+
+    // The body's bounds follow the placed parts, not the raw mesh at the origin.
+    let aabb = engine.aabb_from_render_body(car).unwrap();
+    assert_eq!(aabb.min, Vec3::new(-2.0, 0.0, 1.0));
+    assert_eq!(aabb.max, Vec3::new(3.0, 2.0, 1.0));
+}
+
+/// One mesh whose three primitives use an opaque, a masked and a blended material.
+const WINDOW_GLTF: &str = r#"{
+    "asset": { "version": "2.0" },
+    "scene": 0,
+    "scenes": [{ "nodes": [0] }],
+    "nodes": [{ "name": "Window", "mesh": 0 }],
+    "materials": [
+        { "name": "Frame" },
+        { "name": "Ivy", "alphaMode": "MASK", "alphaCutoff": 0.3 },
+        { "name": "Glass", "alphaMode": "BLEND" }
+    ],
+    "meshes": [{
+        "primitives": [
+            { "attributes": { "POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2 },
+              "indices": 3, "material": 0 },
+            { "attributes": { "POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2 },
+              "indices": 3, "material": 1 },
+            { "attributes": { "POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2 },
+              "indices": 3, "material": 2 }
+        ]
+    }],
+    "buffers": [{ "uri": "window.bin", "byteLength": 104 }],
+    "bufferViews": [
+        { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+        { "buffer": 0, "byteOffset": 36, "byteLength": 36 },
+        { "buffer": 0, "byteOffset": 72, "byteLength": 24 },
+        { "buffer": 0, "byteOffset": 96, "byteLength": 6 }
+    ],
+    "accessors": [
+        { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+          "min": [0, 0, 0], "max": [1, 1, 0] },
+        { "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3" },
+        { "bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC2" },
+        { "bufferView": 3, "componentType": 5123, "count": 3, "type": "SCALAR" }
+    ]
+}"#;
+
+#[test]
+fn alpha_modes_are_imported_into_blend_modes() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("window.bin"), triangle_buffer()).unwrap();
+    let gltf_path = dir.path().join("window.gltf");
+    std::fs::write(&gltf_path, WINDOW_GLTF).unwrap();
+
+    let mut engine = EngineBuilder::headless().build();
+    let window = engine.load_model(gltf_path.to_str().unwrap()).unwrap();
+
+    let bodies = engine.scene.world.resource::<RenderBodyResource>().read();
+    let materials = engine.scene.world.resource::<MaterialResource>().read();
+    let blend_modes: Vec<BlendMode> = bodies
+        .get_render_body(window)
+        .unwrap()
+        .parts
+        .iter()
+        .map(|part| {
+            materials
+                .get_material(part.material_id)
+                .unwrap()
+                .desc
+                .blend_mode
+        })
+        .collect();
+    assert_eq!(
+        blend_modes,
+        vec![
+            BlendMode::Opaque,
+            BlendMode::Mask { cutoff: 0.3 },
+            BlendMode::Blend,
+        ]
+    );
+}
@@ -0,0 +1,72 @@
+use bevy_ecs::prelude::*;
+use engine::{EngineBuilder, ExitRequest, RenderBodyComponent, TransformComponent};
+use glam::Vec3;
+
+#[test]
+fn headless_engine_builds_without_display_or_audio() {
+    let engine = EngineBuilder::headless().build();
+
+    assert!(engine.is_headless());
+}
+
+#[derive(Resource, Default)]
+struct FramesRun(u32);
+
+fn exit_after_three_frames(mut frames: ResMut<FramesRun>, mut exit: ResMut<ExitRequest>) {
+    frames.0 += 1;
+    if frames.0 == 3 {
+        exit.request();
+    }
+}
+
+#[test]
+fn headless_run_returns_once_exit_is_requested() {
+    let mut engine = EngineBuilder::headless().build();
+    engine.scene.world.insert_resource(FramesRun::default());
+    engine
+        .scene
+        .game_frame_schedule
+        .add_systems(exit_after_three_frames);
+
+    engine.run();
+
+    assert_eq!(engine.scene.world.resource::<FramesRun>().0, 3);
+}
+
+#[test]
+fn headless_engine_loads_models_and_sounds() {
+    let mut engine = EngineBuilder::headless().build();
+
+    let ground = engine
+        .load_model("test_resources/test_ground/test_ground.obj")
+        .expect("OBJ should load without a GL context");
+    let aabb = engine
+        .aabb_from_render_body(ground)
+        .expect("Loaded render body should have an AABB");
+    assert!(aabb.max.cmpge(aabb.min).all());
+
+    engine
+        .load_wav("../resources/sounds/pop.wav")
+        .expect("WAV should load without an audio device");
+
+    let entity = engine
+        .scene
+        .world
+        .spawn((
+            TransformComponent {
+                position: Vec3::new(1.0, 2.0, 3.0),
+                ..Default::default()
+            },
+            RenderBodyComponent {
+                render_body_id: ground,
+            },
+        ))
+        .id();
+    assert!(
+        engine
+            .scene
+            .world
+            .get::<TransformComponent>(entity)
+            .is_some()
+    );
+}
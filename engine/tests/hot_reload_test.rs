@@ -0,0 +1,163 @@
+use std::{
+    fs::File,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use engine::{
+    EngineBuilder,
+    assets::{
+        material_resource::MaterialResource, mesh_resource::MeshResource,
+        texture_resource::TextureResource,
+    },
+    render::render_body_resource::RenderBodyResource,
+};
+
+const MTL: &str = "newmtl red\nKd 1.0 0.0 0.0\n";
+
+const TRIANGLE: &str = "mtllib shape.mtl\no Shape\n\
+v 0 0 0\nv 1 0 0\nv 0 1 0\n\
+usemtl red\nf 1 2 3\n";
+
+const QUAD: &str = "mtllib shape.mtl\no Shape\n\
+v 0 0 0\nv 2 0 0\nv 2 2 0\nv 0 2 0\n\
+usemtl red\nf 1 2 3\nf 1 3 4\n";
+
+const TWO_MTLS: &str = "newmtl red\nKd 1.0 0.0 0.0\nnewmtl blue\nKd 0.0 0.0 1.0\n";
+
+const TWO_SHAPES: &str = "mtllib shape.mtl\n\
+o Red\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n\
+o Blue\nv 0 0 1\nv 1 0 1\nv 0 1 1\nusemtl blue\nf 4 5 6\n";
+
+/// Writes `contents` and backdates the file so the change counts as settled.
+fn write_settled(path: &Path, contents: &[u8]) {
+    std::fs::write(path, contents).unwrap();
+    File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(SystemTime::now() - Duration::from_secs(1))
+        .unwrap();
+}
+
+#[test]
+fn changed_model_is_reimported_into_the_same_handles() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("shape.mtl"), MTL).unwrap();
+    let obj_path = dir.path().join("shape.obj");
+    std::fs::write(&obj_path, TRIANGLE).unwrap();
+    let obj_path = obj_path.to_str().unwrap();
+
+    let mut engine = EngineBuilder::headless().build();
+    let body = engine.load_model(obj_path).unwrap();
+    engine.enable_hot_reload();
+
+    let meshes = engine.scene.world.resource::<MeshResource>().clone();
+    let bodies = engine.scene.world.resource::<RenderBodyResource>().clone();
+    let mesh = meshes
+        .read()
+        .paths
+        .handle(&format!("{}#mesh0", obj_path))
+        .unwrap();
+    let parts_before = bodies.read().get_render_body(body).unwrap().parts.clone();
+    assert_eq!(meshes.read().get_mesh(mesh).unwrap().vertices.len(), 3);
+
+    assert_eq!(engine.reload_changed_assets(), 0);
+    write_settled(Path::new(obj_path), QUAD.as_bytes());
+    assert_eq!(engine.reload_changed_assets(), 1);
+
+    assert_eq!(engine.load_model(obj_path).unwrap(), body);
+    assert_eq!(meshes.read().get_mesh(mesh).unwrap().vertices.len(), 4);
+    assert_eq!(meshes.read().meshes.len(), 1);
+    // The old material's colour textures were freed rather than leaked.
+    let textures = engine.scene.world.resource::<TextureResource>();
+    assert_eq!(textures.read().textures.len(), 2);
+
+    let bodies = bodies.read();
+    let parts_after = &bodies.get_render_body(body).unwrap().parts;
+    assert_eq!(parts_after.len(), 1);
+    assert_eq!(parts_after[0].mesh_id, parts_before[0].mesh_id);
+    assert_eq!(parts_after[0].material_id, parts_before[0].material_id);
+}
+
+#[test]
+fn sub_assets_missing_from_the_new_version_are_freed() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("shape.mtl"), TWO_MTLS).unwrap();
+    let obj_path = dir.path().join("shape.obj");
+    std::fs::write(&obj_path, TWO_SHAPES).unwrap();
+    let obj_path = obj_path.to_str().unwrap();
+
+    let mut engine = EngineBuilder::headless().build();
+    engine.load_model(obj_path).unwrap();
+    engine.enable_hot_reload();
+
+    let meshes = engine.scene.world.resource::<MeshResource>().clone();
+    let materials = engine.scene.world.resource::<MaterialResource>().clone();
+    let textures = engine.scene.world.resource::<TextureResource>().clone();
+    assert_eq!(meshes.read().meshes.len(), 2);
+    assert_eq!(materials.read().materials.len(), 2);
+    let textures_before = textures.read().textures.len();
+
+    std::fs::write(dir.path().join("shape.mtl"), MTL).unwrap();
+    write_settled(Path::new(obj_path), TRIANGLE.as_bytes());
+    assert_eq!(engine.reload_changed_assets(), 1);
+
+    let meshes = meshes.read();
+    assert_eq!(meshes.meshes.len(), 1);
+    assert!(
+        meshes
+            .paths
+            .handle(&format!("{}#mesh1", obj_path))
+            .is_none()
+    );
+    let materials = materials.read();
+    assert_eq!(materials.materials.len(), 1);
+    assert_eq!(materials.paths.iter().count(), 1);
+    // The dropped material's colour textures went with it.
+    assert!(textures.read().textures.len() < textures_before);
+}
+
+#[test]
+fn changed_texture_is_reloaded_into_the_same_handle() {
+    let dir = tempfile::tempdir().unwrap();
+    let png_path = dir.path().join("albedo.png");
+    image::RgbaImage::new(1, 1).save(&png_path).unwrap();
+
+    let mut engine = EngineBuilder::headless().build();
+    let textures = engine.scene.world.resource::<TextureResource>().clone();
+    let texture = textures
+        .write()
+        .load_from_file(None, png_path.as_os_str())
+        .unwrap();
+    engine.enable_hot_reload();
+
+    let mut bytes = Vec::new();
+    image::RgbaImage::new(4, 2)
+        .write_to(
+            &mut std::io::Cursor::new(&mut bytes),
+            image::ImageFormat::Png,
+        )
+        .unwrap();
+    write_settled(&png_path, &bytes);
+    assert_eq!(engine.reload_changed_assets(), 1);
+
+    let textures = textures.read();
+    let reloaded = textures.get_texture(texture).unwrap();
+    assert_eq!((reloaded.width, reloaded.height), (4, 2));
+    assert_eq!(textures.textures.len(), 1);
+}
+
+#[test]
+fn nothing_is_reloaded_unless_enabled() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("shape.mtl"), MTL).unwrap();
+    let obj_path = dir.path().join("shape.obj");
+    std::fs::write(&obj_path, TRIANGLE).unwrap();
+
+    let mut engine = EngineBuilder::headless().build();
+    engine.load_model(obj_path.to_str().unwrap()).unwrap();
+    write_settled(&obj_path, QUAD.as_bytes());
+
+    assert_eq!(engine.reload_changed_assets(), 0);
+}
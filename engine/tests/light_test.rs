@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use engine::{
+    DirectionalLight, EngineBuilder, PointLight, SpotLight, TransformComponent,
+    render::render_queue::RenderQueue,
+};
+use glam::{Quat, Vec3};
+
+#[test]
+fn light_components_are_gathered_each_frame() {
+    let mut engine = EngineBuilder::headless().build();
+    let world = &mut engine.scene.world;
+    world.spawn(DirectionalLight {
+        direction: Vec3::new(0.0, 0.0, -2.0),
+        color: Vec3::new(1.0, 0.5, 0.25),
+        intensity: 2.0,
+    });
+    let lamp = world
+        .spawn((
+            TransformComponent {
+                position: Vec3::new(3.0, 4.0, 5.0),
+                ..Default::default()
+            },
+            PointLight {
+                range: 12.0,
+                ..Default::default()
+            },
+        ))
+        .id();
+    world.spawn((
+        TransformComponent {
+            position: Vec3::new(0.0, 0.0, 6.0),
+            // Turns the -Y forward axis to point straight down.
+            rotation: Quat::from_rotation_x(std::f32::consts::FRAC_PI_2),
+            ..Default::default()
+        },
+        SpotLight::default(),
+    ));
+
+    engine.step_frame(Duration::from_millis(16));
+
+    {
+        let lights = &engine.scene.world.resource::<RenderQueue>().lights;
+        assert_eq!(lights.directional.len(), 1);
+        assert_eq!(lights.directional[0].direction, Vec3::NEG_Z);
+        assert_eq!(lights.directional[0].color, Vec3::new(2.0, 1.0, 0.5));
+
+        assert_eq!(lights.point.len(), 1);
+        assert_eq!(lights.point[0].position, Vec3::new(3.0, 4.0, 5.0));
+        assert_eq!(lights.point[0].range, 12.0);
+
+        assert_eq!(lights.spot.len(), 1);
+        assert!(lights.spot[0].direction.abs_diff_eq(Vec3::NEG_Z, 1e-6));
+        assert!(lights.spot[0].cos_inner > lights.spot[0].cos_outer);
+    }
+
+    engine.scene.world.despawn(lamp);
+    engine.step_frame(Duration::from_millis(16));
+
+    assert!(
+        engine
+            .scene
+            .world
+            .resource::<RenderQueue>()
+            .lights
+            .point
+            .is_empty(),
+        "lights are rebuilt every frame"
+    );
+}
@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use engine::{
+    EngineBuilder, MaterialHandle, RenderBodyComponent, TransformComponent,
+    assets::{
+        mesh::{Aabb, Mesh},
+        mesh_resource::MeshResource,
+    },
+    render::{
+        render_body::{RenderBody, RenderBodyPart},
+        render_body_resource::RenderBodyResource,
+        render_queue::RenderQueue,
+    },
+};
+use glam::{Mat4, Vec3};
+
+#[test]
+fn render_groups_only_carry_bounds_after_moving() {
+    let mut engine = EngineBuilder::headless().build();
+    let world = &mut engine.scene.world;
+
+    let mut mesh = Mesh {
+        aabb: Aabb {
+            min: Vec3::splat(-1.0),
+            max: Vec3::splat(1.0),
+        },
+        ..Default::default()
+    };
+    mesh.compute_bounding_sphere();
+    let mesh_id = world.resource::<MeshResource>().write().add_mesh(mesh);
+    let part = |x: f32| RenderBodyPart {
+        mesh_id,
+        material_id: MaterialHandle::default(),
+        local_transform: Mat4::from_translation(Vec3::new(x, 0.0, 0.0)),
+        name: None,
+        node: None,
+        skin: None,
+        lod: None,
+    };
+    let body = world
+        .resource::<RenderBodyResource>()
+        .write()
+        .add_render_body(RenderBody::new(vec![part(-2.0), part(2.0)]));
+    let crate_entity = world
+        .spawn((
+            TransformComponent {
+                position: Vec3::new(0.0, 10.0, 0.0),
+                ..Default::default()
+            },
+            RenderBodyComponent {
+                render_body_id: body,
+            },
+        ))
+        .id();
+
+    engine.step_frame(Duration::from_millis(16));
+
+    let groups = &engine.scene.world.resource::<RenderQueue>().groups;
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].entity, crate_entity);
+    assert_eq!(groups[0].instances, 0..2);
+    let bounds = groups[0].moved_bounds.unwrap();
+    let radius = 3f32.sqrt();
+    assert!(
+        bounds
+            .min
+            .abs_diff_eq(Vec3::new(-2.0 - radius, 10.0 - radius, -radius), 1e-5)
+    );
+    assert!(
+        bounds
+            .max
+            .abs_diff_eq(Vec3::new(2.0 + radius, 10.0 + radius, radius), 1e-5)
+    );
+
+    // The simulation tick of the first frame places the new entity once more.
+    engine.step_frame(Duration::from_millis(16));
+    engine.step_frame(Duration::from_millis(16));
+    let groups = &engine.scene.world.resource::<RenderQueue>().groups;
+    assert!(groups[0].moved_bounds.is_none());
+
+    engine
+        .scene
+        .world
+        .get_mut::<TransformComponent>(crate_entity)
+        .unwrap()
+        .position
+        .z = 4.0;
+    engine.step_frame(Duration::from_millis(16));
+    let groups = &engine.scene.world.resource::<RenderQueue>().groups;
+    let bounds = groups[0].moved_bounds.unwrap();
+    assert!((bounds.max.z - (4.0 + radius)).abs() < 1e-5);
+}
@@ -0,0 +1,168 @@
+use std::time::Duration;
+
+use bevy_ecs::prelude::*;
+use engine::{
+    EngineBuilder, InstanceParams, MaterialHandle, Occluder, RenderBodyComponent,
+    TransformComponent,
+    assets::{mesh::Mesh, mesh_resource::MeshResource},
+    render::{
+        render_body::{RenderBody, RenderBodyPart},
+        render_body_resource::RenderBodyResource,
+        render_queue::RenderQueue,
+    },
+};
+use glam::{Mat4, Vec3, Vec4};
+
+/// Spawns `count` two-part crates in a row and settles them in.
+fn spawn_crates(engine: &mut engine::Engine, count: usize) -> Vec<Entity> {
+    let world = &mut engine.scene.world;
+    let mesh_id = world
+        .resource::<MeshResource>()
+        .write()
+        .add_mesh(Mesh::default());
+    let part = |x: f32| RenderBodyPart {
+        mesh_id,
+        material_id: MaterialHandle::default(),
+        local_transform: Mat4::from_translation(Vec3::new(x, 0.0, 0.0)),
+        name: None,
+        node: None,
+        skin: None,
+        lod: None,
+    };
+    let body = world
+        .resource::<RenderBodyResource>()
+        .write()
+        .add_render_body(RenderBody::new(vec![part(-1.0), part(1.0)]));
+    let crates = (0..count)
+        .map(|i| {
+            world
+                .spawn((
+                    TransformComponent {
+                        position: Vec3::new(0.0, 0.0, i as f32 * 5.0),
+                        ..Default::default()
+                    },
+                    RenderBodyComponent {
+                        render_body_id: body,
+                    },
+                ))
+                .id()
+        })
+        .collect();
+
+    // The simulation tick of the first frame places new entities once more.
+    engine.step_frame(Duration::from_millis(16));
+    engine.step_frame(Duration::from_millis(16));
+    crates
+}
+
+fn changed_instances(engine: &engine::Engine) -> Vec<std::ops::Range<usize>> {
+    engine
+        .scene
+        .world
+        .resource::<RenderQueue>()
+        .changed_instances
+        .clone()
+}
+
+#[test]
+fn only_changed_entities_are_requeued() {
+    let mut engine = EngineBuilder::headless().build();
+    let crates = spawn_crates(&mut engine, 3);
+
+    engine.step_frame(Duration::from_millis(16));
+    assert!(changed_instances(&engine).is_empty());
+
+    let queue = engine.scene.world.resource::<RenderQueue>();
+    let group = queue
+        .groups
+        .iter()
+        .find(|group| group.entity == crates[1])
+        .unwrap()
+        .clone();
+    engine
+        .scene
+        .world
+        .get_mut::<TransformComponent>(crates[1])
+        .unwrap()
+        .position
+        .y = 3.0;
+    engine.step_frame(Duration::from_millis(16));
+
+    assert_eq!(changed_instances(&engine), vec![group.instances.clone()]);
+    let queue = engine.scene.world.resource::<RenderQueue>();
+    assert_eq!(queue.instances.len(), 6);
+    let moved = &queue.instances[group.instances.clone()];
+    assert_eq!(moved[0].transform.w_axis.y, 3.0);
+    assert_eq!(moved[1].transform.w_axis.y, 3.0);
+
+    // The simulation tick places the moved crate once more.
+    engine.step_frame(Duration::from_millis(16));
+    engine.step_frame(Duration::from_millis(16));
+    assert!(changed_instances(&engine).is_empty());
+
+    // Becoming an occluder requeues the entity too.
+    engine.scene.world.entity_mut(crates[2]).insert(Occluder);
+    engine.step_frame(Duration::from_millis(16));
+    assert_eq!(changed_instances(&engine).len(), 1);
+    assert_ne!(changed_instances(&engine)[0], group.instances);
+    let queue = engine.scene.world.resource::<RenderQueue>();
+    assert_eq!(queue.instances.iter().filter(|i| i.occluder).count(), 2);
+}
+
+#[test]
+fn instance_params_requeue_only_their_entity() {
+    let mut engine = EngineBuilder::headless().build();
+    let crates = spawn_crates(&mut engine, 3);
+
+    let tint = Vec4::new(1.0, 0.0, 0.0, 0.5);
+    engine
+        .scene
+        .world
+        .entity_mut(crates[0])
+        .insert(InstanceParams {
+            tint,
+            ..Default::default()
+        });
+    engine.step_frame(Duration::from_millis(16));
+    assert_eq!(changed_instances(&engine), vec![0..2]);
+    let queue = engine.scene.world.resource::<RenderQueue>();
+    assert!(queue.instances[..2].iter().all(|i| i.params.tint == tint));
+    assert!(
+        queue.instances[2..]
+            .iter()
+            .all(|i| i.params.tint == Vec4::ONE)
+    );
+
+    engine.step_frame(Duration::from_millis(16));
+    assert!(changed_instances(&engine).is_empty());
+
+    // Removing the params puts the defaults back.
+    engine
+        .scene
+        .world
+        .entity_mut(crates[0])
+        .remove::<InstanceParams>();
+    engine.step_frame(Duration::from_millis(16));
+    assert_eq!(changed_instances(&engine), vec![0..2]);
+    let queue = engine.scene.world.resource::<RenderQueue>();
+    assert!(
+        queue
+            .instances
+            .iter()
+            .all(|i| i.params == InstanceParams::default())
+    );
+}
+
+#[test]
+fn despawned_entities_rebuild_the_queue() {
+    let mut engine = EngineBuilder::headless().build();
+    let crates = spawn_crates(&mut engine, 3);
+
+    engine.scene.world.despawn(crates[0]);
+    engine.step_frame(Duration::from_millis(16));
+
+    assert_eq!(changed_instances(&engine), vec![0..4]);
+    let queue = engine.scene.world.resource::<RenderQueue>();
+    assert_eq!(queue.groups.len(), 2);
+    assert!(queue.groups.iter().all(|group| group.entity != crates[0]));
+}
@@ -0,0 +1,176 @@
+use std::{
+    f32::consts::FRAC_PI_2,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bevy_ecs::prelude::*;
+use engine::{
+    AnimationFinished, AnimationPlayer, AnimationTarget, EngineBuilder, GlobalTransform, Parent,
+    RenderBodyComponent, TransformComponent,
+    render::{render_body_resource::RenderBodyResource, render_queue::RenderQueue},
+};
+use glam::{Mat4, Quat, Vec3};
+
+/// One triangle, then a one-second quarter turn about Y.
+fn windmill_buffer() -> Vec<u8> {
+    let mut floats: Vec<f32> = vec![
+        0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, // positions
+        0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, // normals
+        0.0, 0.0, 1.0, 0.0, 0.0, 1.0, // UVs
+    ];
+    let mut bytes: Vec<u8> = floats.drain(..).flat_map(f32::to_le_bytes).collect();
+    for index in [0u16, 1, 2, 0] {
+        bytes.extend_from_slice(&index.to_le_bytes());
+    }
+    let quarter_turn = Quat::from_rotation_y(FRAC_PI_2);
+    let keyframes = [0.0, 1.0]
+        .into_iter()
+        .chain(Quat::IDENTITY.to_array())
+        .chain(quarter_turn.to_array());
+    bytes.extend(keyframes.flat_map(f32::to_le_bytes));
+    bytes
+}
+
+/// A mill with "Blades" five units up, both drawing the triangle.
+const WINDMILL_GLTF: &str = r#"{
+    "asset": { "version": "2.0" },
+    "scene": 0,
+    "scenes": [{ "nodes": [0] }],
+    "nodes": [
+        { "name": "Mill", "mesh": 0, "children": [1] },
+        { "name": "Blades", "translation": [0, 0, 5], "mesh": 0 }
+    ],
+    "meshes": [{
+        "primitives": [{
+            "attributes": { "POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2 },
+            "indices": 3
+        }]
+    }],
+    "animations": [{
+        "name": "spin",
+        "samplers": [{ "input": 4, "output": 5 }],
+        "channels": [{ "sampler": 0, "target": { "node": 1, "path": "rotation" } }]
+    }],
+    "buffers": [{ "uri": "windmill.bin", "byteLength": 144 }],
+    "bufferViews": [
+        { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+        { "buffer": 0, "byteOffset": 36, "byteLength": 36 },
+        { "buffer": 0, "byteOffset": 72, "byteLength": 24 },
+        { "buffer": 0, "byteOffset": 96, "byteLength": 6 },
+        { "buffer": 0, "byteOffset": 104, "byteLength": 8 },
+        { "buffer": 0, "byteOffset": 112, "byteLength": 32 }
+    ],
+    "accessors": [
+        { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+          "min": [0, 0, 0], "max": [1, 1, 0] },
+        { "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3" },
+        { "bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC2" },
+        { "bufferView": 3, "componentType": 5123, "count": 3, "type": "SCALAR" },
+        { "bufferView": 4, "componentType": 5126, "count": 2, "type": "SCALAR",
+          "min": [0], "max": [1] },
+        { "bufferView": 5, "componentType": 5126, "count": 2, "type": "VEC4" }
+    ]
+}"#;
+
+/// Loads the windmill and spawns it at x = 10 playing "spin" once.
+fn spawn_windmill(engine: &mut engine::Engine, dir: &tempfile::TempDir) -> (Entity, usize) {
+    std::fs::write(dir.path().join("windmill.bin"), windmill_buffer()).unwrap();
+    let gltf_path = dir.path().join("windmill.gltf");
+    std::fs::write(&gltf_path, WINDMILL_GLTF).unwrap();
+
+    let windmill = engine.load_model(gltf_path.to_str().unwrap()).unwrap();
+    let spin = engine
+        .scene
+        .world
+        .resource::<RenderBodyResource>()
+        .read()
+        .get_render_body(windmill)
+        .unwrap()
+        .animation_index("spin")
+        .unwrap();
+
+    let mut player = AnimationPlayer::default();
+    player.play(spin).looping = false;
+    let entity = engine
+        .scene
+        .world
+        .spawn((
+            TransformComponent {
+                position: Vec3::new(10.0, 0.0, 0.0),
+                ..Default::default()
+            },
+            RenderBodyComponent {
+                render_body_id: windmill,
+            },
+            player,
+        ))
+        .id();
+    (entity, spin)
+}
+
+#[test]
+fn animated_nodes_move_their_parts_and_target_entities() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut engine = EngineBuilder::headless().build();
+    let (windmill, _) = spawn_windmill(&mut engine, &dir);
+    let sign = engine
+        .scene
+        .world
+        .spawn((
+            TransformComponent::default(),
+            AnimationTarget("Blades".to_string()),
+            Parent(windmill),
+        ))
+        .id();
+
+    engine.step_frame(Duration::from_millis(500));
+
+    let blades = Mat4::from_translation(Vec3::new(10.0, 0.0, 5.0))
+        * Mat4::from_quat(Quat::from_rotation_y(FRAC_PI_2 / 2.0));
+    let world = &engine.scene.world;
+    let queue = world.resource::<RenderQueue>();
+    assert_eq!(queue.instances.len(), 2);
+    assert_eq!(
+        queue.instances[0].transform,
+        Mat4::from_translation(Vec3::new(10.0, 0.0, 0.0))
+    );
+    assert!(queue.instances[1].transform.abs_diff_eq(blades, 1e-5));
+
+    let sign_global = world.get::<GlobalTransform>(sign).unwrap().matrix();
+    assert!(sign_global.abs_diff_eq(blades, 1e-5));
+}
+
+#[test]
+fn finished_clips_trigger_an_event_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut engine = EngineBuilder::headless().build();
+    let (windmill, spin) = spawn_windmill(&mut engine, &dir);
+
+    let finished = Arc::new(Mutex::new(Vec::new()));
+    let observed = finished.clone();
+    engine
+        .scene
+        .world
+        .add_observer(move |event: On<AnimationFinished>| {
+            observed.lock().unwrap().push(*event.event());
+        });
+
+    engine.step_frame(Duration::from_millis(600));
+    assert!(finished.lock().unwrap().is_empty());
+
+    engine.step_frame(Duration::from_millis(600));
+    engine.step_frame(Duration::from_millis(600));
+    assert_eq!(
+        *finished.lock().unwrap(),
+        vec![AnimationFinished {
+            entity: windmill,
+            clip: spin
+        }]
+    );
+
+    // The last pose is held.
+    let player = engine.scene.world.get::<AnimationPlayer>(windmill).unwrap();
+    assert_eq!(player.layer(spin).unwrap().time, 1.0);
+    assert!(player.layer(spin).unwrap().is_finished());
+}
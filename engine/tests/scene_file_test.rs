@@ -0,0 +1,163 @@
+use bevy_ecs::entity::Entity;
+use engine::{
+    ActiveCamera, CameraComponent, Children, EngineBuilder, MaterialComponent, Parent,
+    RenderBodyComponent, TransformComponent, VelocityComponent,
+    assets::material_resource::MaterialResource,
+    components::{
+        audio_source_component::AudioSourceComponent,
+        collider_component::{CollisionLayer, ConvexCollider},
+        physics_component::{PhysicsComponent, PhysicsType},
+    },
+    render::render_body_resource::RenderBodyResource,
+    scene::scene_file::SceneFile,
+};
+use glam::{Mat3, Quat, Vec3};
+
+const GROUND: &str = "test_resources/test_ground/test_ground.obj";
+const POP: &str = "../resources/sounds/pop.wav";
+
+#[test]
+fn saved_scene_loads_into_an_equivalent_scene() {
+    let mut engine = EngineBuilder::headless().build();
+    let ground = engine.load_model(GROUND).unwrap();
+    let pop = engine.load_wav(POP).unwrap();
+    let ground_material = engine
+        .scene
+        .world
+        .resource::<MaterialResource>()
+        .read()
+        .paths
+        .handle(&format!("{}#material0", GROUND))
+        .unwrap();
+
+    let world = &mut engine.scene.world;
+    world.spawn((
+        TransformComponent {
+            position: Vec3::new(0.0, 0.0, -1.0),
+            rotation: Quat::from_rotation_x(0.5),
+            scale: Vec3::new(10.0, 10.0, 1.0),
+        },
+        RenderBodyComponent {
+            render_body_id: ground,
+        },
+        MaterialComponent {
+            material_id: ground_material,
+        },
+        ConvexCollider::cuboid(Vec3::new(10.0, 10.0, 1.0), CollisionLayer::Environment),
+    ));
+    let player = world.spawn((
+        TransformComponent {
+            position: Vec3::new(0.0, 0.0, 5.0),
+            ..Default::default()
+        },
+        VelocityComponent {
+            translational: Vec3::new(1.0, 0.0, 0.0),
+            angular: Vec3::ZERO,
+        },
+        PhysicsComponent {
+            physics_type: PhysicsType::Dynamic,
+            mass: 3.0,
+            friction: 0.5,
+            drag_coefficient: 0.1,
+            angular_drag_coefficient: 0.1,
+            restitution: 0.25,
+            local_inertia: Mat3::IDENTITY,
+        },
+        AudioSourceComponent {
+            sound: pop,
+            volume: 0.8,
+            pitch: 1.0,
+            looping: true,
+        },
+    ));
+    let player = player.id();
+    let camera = world
+        .spawn((
+            TransformComponent::default(),
+            Parent(player),
+            CameraComponent {
+                fov_y_radians: 1.2,
+                aspect_ratio: 0.0,
+                near: 0.1,
+                far: 100.0,
+            },
+        ))
+        .id();
+    world.resource_mut::<ActiveCamera>().set(camera);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("level.toml");
+    engine.save_scene(&path).unwrap();
+    let mut loaded = engine.load_scene(&path).unwrap();
+
+    let mut bodies = loaded.world.query::<(
+        &TransformComponent,
+        &RenderBodyComponent,
+        &MaterialComponent,
+    )>();
+    let (transform, body, material) = bodies.single(&loaded.world).unwrap();
+    assert_eq!(transform.rotation, Quat::from_rotation_x(0.5));
+    // Assets already loaded by this engine are reused rather than loaded again.
+    assert_eq!(body.render_body_id, ground);
+    assert_eq!(material.material_id, ground_material);
+    let render_bodies = loaded.world.resource::<RenderBodyResource>().read();
+    assert_eq!(render_bodies.paths.path(ground), Some(GROUND));
+    drop(render_bodies);
+
+    let mut dynamic = loaded.world.query::<(
+        Entity,
+        &VelocityComponent,
+        &PhysicsComponent,
+        &AudioSourceComponent,
+    )>();
+    let (player, velocity, physics, source) = dynamic.single(&loaded.world).unwrap();
+    assert_eq!(velocity.translational, Vec3::new(1.0, 0.0, 0.0));
+    assert_eq!(physics.mass, 3.0);
+    assert!(source.looping);
+
+    let active = loaded.world.resource::<ActiveCamera>().get().unwrap();
+    assert_eq!(
+        loaded
+            .world
+            .get::<CameraComponent>(active)
+            .unwrap()
+            .fov_y_radians,
+        1.2
+    );
+    assert_eq!(loaded.world.get::<Parent>(active), Some(&Parent(player)));
+
+    // Saving the loaded scene again gives the same file.
+    let resaved = SceneFile::from_world(&mut loaded.world)
+        .to_toml_string()
+        .unwrap();
+    assert_eq!(resaved, std::fs::read_to_string(&path).unwrap());
+}
+
+#[test]
+fn empty_grouping_nodes_keep_their_children_attached() {
+    let mut engine = EngineBuilder::headless().build();
+    let world = &mut engine.scene.world;
+    let group = world.spawn_empty().id();
+    let child = world
+        .spawn((
+            TransformComponent {
+                position: Vec3::new(1.0, 2.0, 3.0),
+                ..Default::default()
+            },
+            Parent(group),
+        ))
+        .id();
+    assert!(world.get::<Children>(group).is_some());
+    assert!(world.get::<TransformComponent>(child).is_some());
+
+    let file = SceneFile::from_world(&mut engine.scene.world);
+    assert_eq!(file.entities.len(), 2);
+    let mut loaded = engine.instantiate_scene(&file).unwrap();
+
+    let mut children = loaded.world.query::<(&TransformComponent, &Parent)>();
+    let (transform, parent) = children.single(&loaded.world).unwrap();
+    assert_eq!(transform.position, Vec3::new(1.0, 2.0, 3.0));
+    let group = parent.get();
+    assert!(loaded.world.get::<TransformComponent>(group).is_none());
+    assert_eq!(loaded.world.get::<Children>(group).unwrap().len(), 1);
+}
@@ -0,0 +1,221 @@
+use std::time::Duration;
+
+use engine::{
+    AnimatedPose, AnimationPlayer, EngineBuilder, RenderBodyComponent, TransformComponent,
+    assets::{
+        asset_registry::sub_asset_path, material_resource::MaterialResource,
+        mesh_resource::MeshResource,
+    },
+    render::{render_body_resource::RenderBodyResource, render_queue::RenderQueue},
+};
+use glam::{Mat4, Vec3};
+
+/// One triangle skinned to two joints, the inverse bind matrices of the joints and a
+/// one-second animation moving the second joint up, in that order.
+fn arm_buffer() -> Vec<u8> {
+    fn floats(values: &[f32], bytes: &mut Vec<u8>) {
+        for value in values {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    let mut bytes = Vec::new();
+    // Positions, normals, UVs.
+    floats(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0], &mut bytes);
+    floats(&[0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0], &mut bytes);
+    floats(&[0.0, 0.0, 1.0, 0.0, 0.0, 1.0], &mut bytes);
+    // Joints.
+    for _ in 0..3 {
+        for joint in [0u16, 1, 0, 0] {
+            bytes.extend_from_slice(&joint.to_le_bytes());
+        }
+    }
+    // Weights; the last vertex's don't add up to 1.
+    floats(
+        &[1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0],
+        &mut bytes,
+    );
+    // Indices, padded to four bytes.
+    for index in [0u16, 1, 2, 0] {
+        bytes.extend_from_slice(&index.to_le_bytes());
+    }
+    // Inverse bind matrices of joints at (0, 0, 1) and (1, 0, 1).
+    floats(
+        &Mat4::from_translation(Vec3::new(0.0, 0.0, -1.0)).to_cols_array(),
+        &mut bytes,
+    );
+    floats(
+        &Mat4::from_translation(Vec3::new(-1.0, 0.0, -1.0)).to_cols_array(),
+        &mut bytes,
+    );
+    // Keyframe times and translations.
+    floats(&[0.0, 1.0], &mut bytes);
+    floats(&[1.0, 0.0, 0.0, 1.0, 0.0, 2.0], &mut bytes);
+    bytes
+}
+
+const ARM_GLTF: &str = r#"{
+    "asset": { "version": "2.0" },
+    "scene": 0,
+    "scenes": [{ "nodes": [0] }],
+    "nodes": [
+        { "name": "Rig", "children": [1, 2] },
+        { "name": "Body", "translation": [100, 0, 0], "mesh": 0, "skin": 0 },
+        { "name": "Shoulder", "translation": [0, 0, 1], "children": [3] },
+        { "name": "Hand", "translation": [1, 0, 0] }
+    ],
+    "meshes": [{
+        "primitives": [{
+            "attributes": {
+                "POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2, "JOINTS_0": 3, "WEIGHTS_0": 4
+            },
+            "indices": 5
+        }]
+    }],
+    "skins": [{ "joints": [2, 3], "inverseBindMatrices": 6 }],
+    "animations": [{
+        "name": "wave",
+        "samplers": [{ "input": 7, "output": 8, "interpolation": "LINEAR" }],
+        "channels": [{ "sampler": 0, "target": { "node": 3, "path": "translation" } }]
+    }],
+    "buffers": [{ "uri": "arm.bin", "byteLength": 336 }],
+    "bufferViews": [
+        { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+        { "buffer": 0, "byteOffset": 36, "byteLength": 36 },
+        { "buffer": 0, "byteOffset": 72, "byteLength": 24 },
+        { "buffer": 0, "byteOffset": 96, "byteLength": 24 },
+        { "buffer": 0, "byteOffset": 120, "byteLength": 48 },
+        { "buffer": 0, "byteOffset": 168, "byteLength": 6 },
+        { "buffer": 0, "byteOffset": 176, "byteLength": 128 },
+        { "buffer": 0, "byteOffset": 304, "byteLength": 8 },
+        { "buffer": 0, "byteOffset": 312, "byteLength": 24 }
+    ],
+    "accessors": [
+        { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+          "min": [0, 0, 0], "max": [1, 1, 0] },
+        { "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3" },
+        { "bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC2" },
+        { "bufferView": 3, "componentType": 5123, "count": 3, "type": "VEC4" },
+        { "bufferView": 4, "componentType": 5126, "count": 3, "type": "VEC4" },
+        { "bufferView": 5, "componentType": 5123, "count": 3, "type": "SCALAR" },
+        { "bufferView": 6, "componentType": 5126, "count": 2, "type": "MAT4" },
+        { "bufferView": 7, "componentType": 5126, "count": 2, "type": "SCALAR",
+          "min": [0], "max": [1] },
+        { "bufferView": 8, "componentType": 5126, "count": 2, "type": "VEC3" }
+    ]
+}"#;
+
+fn write_arm(dir: &tempfile::TempDir) -> String {
+    std::fs::write(dir.path().join("arm.bin"), arm_buffer()).unwrap();
+    let gltf_path = dir.path().join("arm.gltf");
+    std::fs::write(&gltf_path, ARM_GLTF).unwrap();
+    gltf_path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn skins_and_animations_are_imported() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_arm(&dir);
+    let mut engine = EngineBuilder::headless().build();
+    let arm = engine.load_model(&path).unwrap();
+
+    let world = &engine.scene.world;
+    let bodies = world.resource::<RenderBodyResource>().read();
+    let body = bodies.get_render_body(arm).unwrap();
+
+    let hierarchy = &body.hierarchy;
+    assert_eq!(hierarchy.nodes().len(), 4);
+    let shoulder = hierarchy.node_index("Shoulder").unwrap();
+    let hand = hierarchy.node_index("Hand").unwrap();
+    assert_eq!(hierarchy.nodes()[hand].parent, Some(shoulder));
+    assert_eq!(hierarchy.skins()[0].joints, vec![shoulder, hand]);
+
+    // The skinned node's own transform is ignored; its joints place it.
+    assert_eq!(body.parts[0].skin, Some(0));
+    assert_eq!(body.parts[0].local_transform, Mat4::IDENTITY);
+
+    let wave = body.animation_index("wave").unwrap();
+    assert_eq!(body.animations[wave].duration, 1.0);
+    assert_eq!(body.animations[wave].channels[0].node, hand);
+
+    // The part is drawn with a skinning twin of the file's material.
+    let materials = world.resource::<MaterialResource>().read();
+    assert_eq!(
+        materials
+            .paths
+            .handle(&sub_asset_path(&path, "skinned_material0")),
+        Some(body.parts[0].material_id)
+    );
+
+    let meshes = world.resource::<MeshResource>().read();
+    let mesh = meshes.get_mesh(body.parts[0].mesh_id).unwrap();
+    assert_eq!(mesh.vertices[1].joints, [0.0, 1.0, 0.0, 0.0]);
+    assert_eq!(mesh.vertices[1].weights, [0.0, 1.0, 0.0, 0.0]);
+    assert_eq!(mesh.vertices[2].weights, [0.5, 0.5, 0.0, 0.0]);
+}
+
+#[test]
+fn animation_players_pose_the_joints_sent_to_the_renderer() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_arm(&dir);
+    let mut engine = EngineBuilder::headless().build();
+    let arm = engine.load_model(&path).unwrap();
+    let wave = engine
+        .scene
+        .world
+        .resource::<RenderBodyResource>()
+        .read()
+        .get_render_body(arm)
+        .unwrap()
+        .animation_index("wave")
+        .unwrap();
+
+    let mut player = AnimationPlayer::default();
+    player.play(wave);
+    let world = &mut engine.scene.world;
+    let waving = world
+        .spawn((
+            TransformComponent {
+                position: Vec3::new(5.0, 0.0, 0.0),
+                ..Default::default()
+            },
+            RenderBodyComponent {
+                render_body_id: arm,
+            },
+            player,
+        ))
+        .id();
+    let resting = world
+        .spawn(RenderBodyComponent {
+            render_body_id: arm,
+        })
+        .id();
+
+    engine.step_frame(Duration::from_millis(500));
+
+    let world = &engine.scene.world;
+    let player = world.get::<AnimationPlayer>(waving).unwrap();
+    assert_eq!(player.layer(wave).unwrap().time, 0.5);
+    let pose = world.get::<AnimatedPose>(waving).unwrap();
+    assert!(
+        pose.node_matrix(3)
+            .unwrap()
+            .abs_diff_eq(Mat4::from_translation(Vec3::new(1.0, 0.0, 2.0)), 1e-6)
+    );
+    assert!(world.get::<AnimatedPose>(resting).is_none());
+
+    // Halfway through, the hand has moved up by one unit from where it was bound.
+    let queue = world.resource::<RenderQueue>();
+    assert_eq!(queue.instances.len(), 2);
+    assert_eq!(queue.joint_matrices.len(), 4);
+    for instance in &queue.instances {
+        let joints = &queue.joint_matrices[instance.joints.clone().unwrap()];
+        let hand_offset = if instance.transform.w_axis.x == 5.0 {
+            Vec3::new(0.0, 0.0, 1.0)
+        } else {
+            Vec3::ZERO
+        };
+        assert!(joints[0].abs_diff_eq(Mat4::IDENTITY, 1e-6));
+        assert!(joints[1].abs_diff_eq(Mat4::from_translation(hand_offset), 1e-6));
+    }
+}
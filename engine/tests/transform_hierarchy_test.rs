@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use engine::{
+    Children, EngineBuilder, GlobalTransform, Parent, PointLight, TransformComponent,
+    components::collider_component::{CollisionLayer, ConvexCollider},
+    physics::physics_resource::PhysicsResource,
+    render::render_queue::RenderQueue,
+};
+use glam::{Quat, Vec3};
+
+fn at(position: Vec3) -> TransformComponent {
+    TransformComponent {
+        position,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn children_are_lit_and_collided_where_their_parent_puts_them() {
+    let mut engine = EngineBuilder::headless().build();
+    let world = &mut engine.scene.world;
+    let pole = world
+        .spawn(TransformComponent {
+            position: Vec3::new(5.0, 0.0, 0.0),
+            rotation: Quat::from_rotation_z(std::f32::consts::FRAC_PI_2),
+            ..Default::default()
+        })
+        .id();
+    let lamp = world
+        .spawn((
+            at(Vec3::new(1.0, 0.0, 4.0)),
+            PointLight::default(),
+            ConvexCollider::sphere(0.5, CollisionLayer::Environment),
+            Parent(pole),
+        ))
+        .id();
+
+    engine.step_frame(Duration::from_millis(16));
+
+    let lamp_position = Vec3::new(5.0, 1.0, 4.0);
+    let lights = &engine.scene.world.resource::<RenderQueue>().lights;
+    assert!(lights.point[0].position.abs_diff_eq(lamp_position, 1e-5));
+
+    engine.step_simulation(1);
+
+    let aabb = engine.scene.world.resource::<PhysicsResource>().world_aabbs[&lamp];
+    assert!(((aabb.min + aabb.max) / 2.0).abs_diff_eq(lamp_position, 1e-5));
+
+    engine
+        .scene
+        .world
+        .get_mut::<TransformComponent>(pole)
+        .unwrap()
+        .position
+        .z = 10.0;
+    engine.step_frame(Duration::from_millis(16));
+
+    let global = engine.scene.world.get::<GlobalTransform>(lamp).unwrap();
+    assert!(
+        global
+            .translation()
+            .abs_diff_eq(Vec3::new(5.0, 1.0, 14.0), 1e-5)
+    );
+}
+
+#[test]
+fn despawning_a_parent_despawns_its_descendants() {
+    let mut engine = EngineBuilder::headless().build();
+    let world = &mut engine.scene.world;
+    let building = world.spawn(TransformComponent::default()).id();
+    let arm = world
+        .spawn((at(Vec3::new(0.0, 0.0, 20.0)), Parent(building)))
+        .id();
+    let hook = world
+        .spawn((
+            at(Vec3::new(8.0, 0.0, 0.0)),
+            PointLight::default(),
+            Parent(arm),
+        ))
+        .id();
+    let neighbour = world.spawn(TransformComponent::default()).id();
+
+    assert_eq!(&**world.get::<Children>(building).unwrap(), &[arm]);
+
+    world.despawn(building);
+    engine.step_frame(Duration::from_millis(16));
+
+    let world = &engine.scene.world;
+    assert!(world.get_entity(arm).is_err());
+    assert!(world.get_entity(hook).is_err());
+    assert!(world.get_entity(neighbour).is_ok());
+    assert!(world.resource::<RenderQueue>().lights.point.is_empty());
+}
@@ -24,6 +24,7 @@ use crate::game_controller::{
     spatial_audio_orbit_demo,
     // spatial_audio_popping_demo,
 };
+use crate::settings::Settings;
 use bevy_ecs::schedule::IntoScheduleConfigs;
 use engine::{
     ActiveCamera, CameraComponent, CollisionLayer, ConvexCollider, Engine, RenderBodyComponent,
@@ -44,6 +45,8 @@ fn main() {
 
     println!("Welcome to the Game!");
     let mut engine = Engine::new();
+    let settings = Settings::load_user_settings();
+    settings.renderer.apply(&mut engine);
 
     // Create an ECS-driven camera entity and mark it active.
     let aspect_ratio = 1024.0 / 769.0;
@@ -356,5 +359,7 @@ fn main() {
             local_inertia: glam::Mat3::IDENTITY,
         },
     ));
+    #[cfg(debug_assertions)]
+    engine.enable_hot_reload();
     engine.run();
 }
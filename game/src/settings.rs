@@ -1,4 +1,11 @@
 use dirs_next::config_dir; // Use dirs-next for better maintenance
+use engine::{
+    Engine,
+    render::{
+        debug_view::{DebugView, RenderDebugSettings},
+        post_process::{BloomSettings, PostProcessSettings, Tonemapper},
+    },
+};
 use serde::{Deserialize, Serialize};
 use std::{
     fs,
@@ -16,8 +23,46 @@ pub struct GeneralSettings {
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct RendererSettings {
     pub render_scale: f32,
+    /// Draws triangle edges over the scene.
     pub visualize_edges: bool,
+    /// Shorthand for the `WorldNormals` debug view; `debug_view` wins when both are set.
     pub visualize_normals: bool,
+    #[serde(default)]
+    pub debug_view: DebugView,
+    /// Missing from settings files written before it existed, so it falls back to the defaults.
+    #[serde(default)]
+    pub post_processing: PostProcessingSettings,
+}
+
+/// The HDR post-process stack; each effect can be toggled on its own.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PostProcessingSettings {
+    pub enabled: bool,
+    pub exposure: f32,
+    pub tonemapper: Tonemapper,
+    pub bloom: bool,
+    pub bloom_threshold: f32,
+    pub bloom_intensity: f32,
+    /// Image file of the colour grading lookup table; no grading without one.
+    pub color_grading_lut: Option<String>,
+    pub fxaa: bool,
+}
+
+impl Default for PostProcessingSettings {
+    fn default() -> Self {
+        let bloom = BloomSettings::default();
+        Self {
+            enabled: true,
+            exposure: 1.0,
+            tonemapper: Tonemapper::default(),
+            bloom: bloom.enabled,
+            bloom_threshold: bloom.threshold,
+            bloom_intensity: bloom.intensity,
+            color_grading_lut: None,
+            fxaa: true,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
@@ -43,8 +88,11 @@ impl Default for Settings {
             },
             renderer: RendererSettings {
                 render_scale: 1.0,
-                visualize_edges: true,
+                // Draws the wireframe overlay, which a fresh install shouldn't show.
+                visualize_edges: false,
                 visualize_normals: false,
+                debug_view: DebugView::Lit,
+                post_processing: PostProcessingSettings::default(),
             },
             network: NetworkSettings {
                 timeout: 30,
@@ -54,6 +102,46 @@ impl Default for Settings {
     }
 }
 
+impl RendererSettings {
+    /// Hands the settings to the engine's renderer. A colour grading LUT that can't be
+    /// loaded is reported and left out.
+    pub fn apply(&self, engine: &mut Engine) {
+        let post = &self.post_processing;
+        let color_grading_lut = post.color_grading_lut.as_deref().and_then(|path| {
+            engine
+                .load_texture(path)
+                .inspect_err(|e| log::warn!("Failed to load colour grading LUT: {}", e))
+                .ok()
+        });
+        engine.set_post_process_settings(PostProcessSettings {
+            enabled: post.enabled,
+            exposure: post.exposure,
+            tonemapper: post.tonemapper,
+            bloom: BloomSettings {
+                enabled: post.bloom,
+                threshold: post.bloom_threshold,
+                intensity: post.bloom_intensity,
+                ..Default::default()
+            },
+            color_grading_lut,
+            fxaa: post.fxaa,
+        });
+        engine.set_render_debug_settings(self.render_debug_settings());
+    }
+
+    fn render_debug_settings(&self) -> RenderDebugSettings {
+        let view = match self.debug_view {
+            DebugView::Lit if self.visualize_normals => DebugView::WorldNormals,
+            view => view,
+        };
+        RenderDebugSettings {
+            view,
+            wireframe: self.visualize_edges,
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum SettingsError {
     #[error("IO Error: {0}")]
@@ -116,7 +204,6 @@ impl Settings {
     }
 
     /// Loads user settings, handling defaults and creating necessary files.
-    #[allow(dead_code)]
     pub fn load_user_settings() -> Settings {
         match Settings::initialize_settings() {
             Ok(settings) => settings,
@@ -450,7 +537,7 @@ mod tests {
         assert_eq!(settings.general.auto_save, true);
 
         assert_eq!(settings.renderer.render_scale, 1.0);
-        assert_eq!(settings.renderer.visualize_edges, true);
+        assert_eq!(settings.renderer.visualize_edges, false);
         assert_eq!(settings.renderer.visualize_normals, false);
 
         assert_eq!(settings.network.timeout, 30);
@@ -540,6 +627,8 @@ mod tests {
                 render_scale: 2.0,
                 visualize_edges: false,
                 visualize_normals: true,
+                debug_view: DebugView::Lit,
+                post_processing: PostProcessingSettings::default(),
             },
             network: NetworkSettings {
                 timeout: 50,
@@ -589,6 +678,8 @@ mod tests {
                 render_scale: 1.2,
                 visualize_edges: true,
                 visualize_normals: false,
+                debug_view: DebugView::Lit,
+                post_processing: PostProcessingSettings::default(),
             },
             network: NetworkSettings {
                 timeout: 40,
@@ -820,7 +911,7 @@ mod tests {
         assert_eq!(settings.general.auto_save, true);
 
         assert_eq!(settings.renderer.render_scale, 1.0);
-        assert_eq!(settings.renderer.visualize_edges, true);
+        assert_eq!(settings.renderer.visualize_edges, false);
         assert_eq!(settings.renderer.visualize_normals, false);
 
         assert_eq!(settings.network.timeout, 30);
@@ -846,6 +937,8 @@ mod tests {
                 render_scale: 3.0,
                 visualize_edges: true,
                 visualize_normals: true,
+                debug_view: DebugView::Lit,
+                post_processing: PostProcessingSettings::default(),
             },
             network: NetworkSettings {
                 timeout: 100,
@@ -864,6 +957,16 @@ auto_save = false
 render_scale = 3.0
 visualize_edges = true
 visualize_normals = true
+debug_view = "Lit"
+
+[renderer.post_processing]
+enabled = true
+exposure = 1.0
+tonemapper = "Aces"
+bloom = true
+bloom_threshold = 1.0
+bloom_intensity = 0.1
+fxaa = true
 
 [network]
 timeout = 100
@@ -927,6 +1030,61 @@ use_https = false
         assert!(result.is_err());
     }
 
+    /// Test Case 5d: Post-processing settings are optional and may be partial
+    #[test]
+    fn partial_post_processing_settings_fall_back_to_defaults() {
+        let toml_content = r#"
+            [general]
+            username = "PostUser"
+            theme = "dark"
+            auto_save = true
+
+            [renderer]
+            render_scale = 1.0
+            visualize_edges = false
+            visualize_normals = false
+
+            [renderer.post_processing]
+            tonemapper = "Reinhard"
+            fxaa = false
+            color_grading_lut = "resources/luts/warm.png"
+
+            [network]
+            timeout = 30
+            use_https = true
+        "#;
+
+        let settings: Settings = toml::from_str(toml_content).unwrap();
+        let post = &settings.renderer.post_processing;
+
+        assert_eq!(post.tonemapper, Tonemapper::Reinhard);
+        assert!(!post.fxaa);
+        assert_eq!(
+            post.color_grading_lut.as_deref(),
+            Some("resources/luts/warm.png")
+        );
+        assert!(post.enabled);
+        assert_eq!(post.exposure, 1.0);
+        assert!(post.bloom);
+    }
+
+    /// Test Case 5e: Visualisation flags become the renderer's debug settings
+    #[test]
+    fn visualisation_flags_populate_render_debug_settings() {
+        let mut renderer = Settings::default().renderer;
+        renderer.visualize_edges = false;
+        renderer.visualize_normals = true;
+        let debug = renderer.render_debug_settings();
+        assert_eq!(debug.view, DebugView::WorldNormals);
+        assert!(!debug.wireframe);
+
+        renderer.visualize_edges = true;
+        renderer.debug_view = DebugView::Overdraw;
+        let debug = renderer.render_debug_settings();
+        assert_eq!(debug.view, DebugView::Overdraw);
+        assert!(debug.wireframe);
+    }
+
     /// Test Case 6a: Correct Default Values
     #[test]
     fn correct_default_values() {
@@ -937,7 +1095,7 @@ use_https = false
         assert_eq!(default_settings.general.auto_save, true);
 
         assert_eq!(default_settings.renderer.render_scale, 1.0);
-        assert_eq!(default_settings.renderer.visualize_edges, true);
+        assert_eq!(default_settings.renderer.visualize_edges, false);
         assert_eq!(default_settings.renderer.visualize_normals, false);
 
         assert_eq!(default_settings.network.timeout, 30);
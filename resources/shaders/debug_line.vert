@@ -0,0 +1,13 @@
+#version 330 core
+
+// Debug lines arrive already in clip space: the renderer projects them, and places label
+// strokes in pixels around their projected point.
+layout(location = 0) in vec4 position;
+layout(location = 1) in vec3 color;
+
+out vec3 v_color;
+
+void main() {
+    v_color = color;
+    gl_Position = position;
+}
@@ -0,0 +1,11 @@
+#version 330 core
+
+// One triangle covering the screen, placed from the vertex index: post-process passes
+// bind no vertex data.
+out vec2 v_uv;
+
+void main() {
+    vec2 corner = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+    v_uv = corner;
+    gl_Position = vec4(corner * 2.0 - 1.0, 0.0, 1.0);
+}
@@ -1,5 +1,7 @@
 #version 330 core
 
+in vec3 v_world_position;
+in float v_view_depth;
 in vec3 v_normal;
 in vec3 v_view_dir;
 in vec3 v_barycentric;
@@ -7,15 +9,81 @@ in vec3 v_camera_position;
 in vec2 v_uv_albedo;
 in vec2 v_uv_normal;
 in mat3 v_tbn;
+flat in float v_lod_fade;
+flat in vec4 v_tint;
+flat in vec4 v_highlight;
 
 out vec4 fragColor;
 
 uniform sampler2D u_albedo;
 uniform sampler2D u_normal;
-uniform vec3 u_light_direction;
-uniform vec3 u_light_color;
 uniform float u_roughness;
 uniform vec3 u_base_reflectance;
+// The material's BlendMode: 0 opaque, 1 masked at u_alpha_cutoff, 2 blended.
+uniform int u_alpha_mode;
+uniform float u_alpha_cutoff;
+
+// -------------------- Debug views --------------------
+// The DebugView from engine/src/render/debug_view.rs: 0 lit, 1 world normals, 2 UVs,
+// 3 albedo, 4 roughness, 5 overdraw. u_wireframe draws triangle edges on top.
+
+uniform int u_debug_view;
+uniform int u_wireframe;
+uniform vec3 u_wireframe_color;
+
+// Heat each overdraw layer adds; the renderer blends the layers additively.
+const vec3 OVERDRAW_HEAT = vec3(0.2, 0.05, 0.02);
+
+// -------------------- Lights --------------------
+// The renderer fills these from the scene's light components every frame.
+// The limits must match MAX_*_LIGHTS in engine/src/render/lights.rs; when a scene
+// has more point or spot lights, the ones nearest the camera are used.
+
+#define MAX_DIRECTIONAL_LIGHTS 4
+#define MAX_POINT_LIGHTS 32
+#define MAX_SPOT_LIGHTS 16
+
+struct DirectionalLight {
+    vec3 direction; // direction the light travels
+    vec3 color;     // premultiplied by intensity
+};
+
+struct PointLight {
+    vec3 position;
+    vec3 color;
+    float range;
+    float falloff;
+};
+
+struct SpotLight {
+    vec3 position;
+    vec3 direction;
+    vec3 color;
+    float range;
+    float falloff;
+    float cos_inner;
+    float cos_outer;
+};
+
+uniform vec3 u_ambient_color;
+uniform int u_directional_light_count;
+uniform DirectionalLight u_directional_lights[MAX_DIRECTIONAL_LIGHTS];
+uniform int u_point_light_count;
+uniform PointLight u_point_lights[MAX_POINT_LIGHTS];
+uniform int u_spot_light_count;
+uniform SpotLight u_spot_lights[MAX_SPOT_LIGHTS];
+
+// -------------------- Shadows --------------------
+// Cascaded shadow maps for u_directional_lights[0], the brightest directional light.
+// MAX_SHADOW_CASCADES must match engine/src/render/shadows.rs. A cascade count of 0
+// means shadows are off.
+
+#define MAX_SHADOW_CASCADES 4
+
+uniform sampler2DArrayShadow u_shadow_map;
+uniform int u_shadow_cascade_count;
+uniform mat4 u_shadow_matrices[MAX_SHADOW_CASCADES];
+uniform float u_shadow_splits[MAX_SHADOW_CASCADES]; // view depth where each cascade ends
 
 const float PI = 3.14159265359;
 
@@ -43,27 +111,52 @@ vec3 F(vec3 F0, vec3 V, vec3 H) {
     return F0 + (vec3(1.0) - F0) * pow(1.0 - VdotH, 5.0);
 }
 
-// -------------------- Main --------------------
+// Fades from 1 at the light to 0 at `range`.
+float distance_attenuation(float light_distance, float range, float falloff) {
+    return pow(clamp(1.0 - light_distance / range, 0.0, 1.0), falloff);
+}
 
-void main() {
-    // Albedo
-    vec3 albedo = texture(u_albedo, v_uv_albedo).rgb;
+// 1 where the fragment is lit by the shadowed light, 0 where it is in shadow,
+// averaged over a 3x3 texel neighbourhood (PCF).
+float shadow_factor(vec3 N, vec3 L) {
+    int cascade = -1;
+    for (int i = 0; i < u_shadow_cascade_count; ++i) {
+        if (v_view_depth < u_shadow_splits[i]) {
+            cascade = i;
+            break;
+        }
+    }
+    if (cascade < 0) {
+        return 1.0;
+    }
 
-    // Normal mapping (tangent → world)
-    vec3 N_tangent = texture(u_normal, v_uv_normal).xyz * 2.0 - 1.0;
-    N_tangent.xy *= 2.0; // Increase normal map strength
-    vec3 N = normalize(v_tbn * N_tangent);
+    vec4 light_space = u_shadow_matrices[cascade] * vec4(v_world_position, 1.0);
+    vec3 coords = light_space.xyz / light_space.w * 0.5 + 0.5;
+    if (coords.z > 1.0) {
+        return 1.0;
+    }
 
-    vec3 V = normalize(v_view_dir);
-    vec3 L = normalize(u_light_direction);
+    // Surfaces at a grazing angle to the light need more bias to avoid acne.
+    float bias = max(0.002 * (1.0 - dot(N, L)), 0.0005);
+    vec2 texel = 1.0 / vec2(textureSize(u_shadow_map, 0).xy);
+
+    float lit = 0.0;
+    for (int x = -1; x <= 1; ++x) {
+        for (int y = -1; y <= 1; ++y) {
+            vec2 uv = coords.xy + vec2(x, y) * texel;
+            lit += texture(u_shadow_map, vec4(uv, float(cascade), coords.z - bias));
+        }
+    }
+    return lit / 9.0;
+}
+
+// Light reflected towards the viewer from one light arriving from direction L.
+vec3 shade(vec3 N, vec3 V, vec3 L, vec3 radiance, vec3 albedo, float alpha, vec3 F0) {
     vec3 H = normalize(V + L);
 
     float NdotL = max(dot(N, L), 0.0);
     float NdotV = max(dot(N, V), 0.0);
 
-    float alpha = u_roughness * u_roughness;
-    vec3 F0 = u_base_reflectance;
-
     // Specular BRDF
     vec3 F_spec = F(F0, V, H);
     float G_spec = G(alpha, N, V, L);
@@ -74,14 +167,131 @@ void main() {
     // Diffuse (energy-conserving-ish)
     vec3 diffuse = albedo * (1.0 - F_spec);
 
+    return (diffuse + specular) * radiance * NdotL;
+}
+
+// -------------------- Dithered fading --------------------
+// Two LOD levels fading into each other are drawn with complementary halves of a 4x4
+// ordered dither: a positive fade keeps the cells below it, a negative fade the rest.
+// Instances with a translucent tint are thinned out the same way, unless their material
+// is blended anyway.
+
+const float BAYER_4X4[16] = float[16](
+     0.0,  8.0,  2.0, 10.0,
+    12.0,  4.0, 14.0,  6.0,
+     3.0, 11.0,  1.0,  9.0,
+    15.0,  7.0, 13.0,  5.0
+);
+
+bool dither_discard(float fade) {
+    if (fade >= 1.0) {
+        return false;
+    }
+    ivec2 cell = ivec2(gl_FragCoord.xy) & 3;
+    float threshold = (BAYER_4X4[cell.y * 4 + cell.x] + 0.5) / 16.0;
+    return fade >= 0.0 ? threshold >= fade : threshold < -fade;
+}
+
+// 0 on a triangle's edges, 1 a pixel or so inside them.
+float edge_factor() {
+    vec3 width = fwidth(v_barycentric);
+    vec3 edge = smoothstep(vec3(0.0), width * 1.5, v_barycentric);
+    return min(min(edge.x, edge.y), edge.z);
+}
+
+vec3 debug_color(int view, vec3 albedo, vec3 N) {
+    if (view == 1) {
+        return N * 0.5 + 0.5;
+    }
+    if (view == 2) {
+        return vec3(fract(v_uv_albedo), 0.0);
+    }
+    if (view == 3) {
+        return albedo;
+    }
+    if (view == 4) {
+        return vec3(u_roughness);
+    }
+    return OVERDRAW_HEAT;
+}
+
+// -------------------- Main --------------------
+
+void main() {
+    bool blended = u_alpha_mode == 2;
+    if (dither_discard(v_lod_fade) || (!blended && dither_discard(v_tint.a))) {
+        discard;
+    }
+
+    // Albedo
+    vec4 base_color = texture(u_albedo, v_uv_albedo);
+    if (u_alpha_mode == 1 && base_color.a < u_alpha_cutoff) {
+        discard;
+    }
+    vec3 albedo = base_color.rgb * v_tint.rgb;
+
+    // Normal mapping (tangent → world)
+    vec3 N_tangent = texture(u_normal, v_uv_normal).xyz * 2.0 - 1.0;
+    N_tangent.xy *= 2.0; // Increase normal map strength
+    vec3 N = normalize(v_tbn * N_tangent);
+
+    if (u_debug_view != 0) {
+        vec3 color = debug_color(u_debug_view, albedo, N);
+        if (u_wireframe != 0 && u_debug_view != 5) {
+            color = mix(u_wireframe_color, color, edge_factor());
+        }
+        fragColor = vec4(color, 1.0);
+        return;
+    }
+
+    vec3 V = normalize(v_view_dir);
+
+    float alpha = u_roughness * u_roughness;
+    vec3 F0 = u_base_reflectance;
+
     // Direct lighting
-    vec3 direct_light =
-        (diffuse + specular) * u_light_color * NdotL;
+    vec3 direct_light = vec3(0.0);
+
+    for (int i = 0; i < u_directional_light_count; ++i) {
+        DirectionalLight light = u_directional_lights[i];
+        vec3 L = normalize(-light.direction);
+        float shadow = i == 0 ? shadow_factor(N, L) : 1.0;
+        direct_light += shade(N, V, L, light.color * shadow, albedo, alpha, F0);
+    }
+
+    for (int i = 0; i < u_point_light_count; ++i) {
+        PointLight light = u_point_lights[i];
+        vec3 to_light = light.position - v_world_position;
+        float light_distance = length(to_light);
+        float attenuation = distance_attenuation(light_distance, light.range, light.falloff);
+        if (attenuation <= 0.0) {
+            continue;
+        }
+        vec3 L = to_light / max(light_distance, 1e-4);
+        direct_light += shade(N, V, L, light.color * attenuation, albedo, alpha, F0);
+    }
+
+    for (int i = 0; i < u_spot_light_count; ++i) {
+        SpotLight light = u_spot_lights[i];
+        vec3 to_light = light.position - v_world_position;
+        float light_distance = length(to_light);
+        vec3 L = to_light / max(light_distance, 1e-4);
+        float cone = smoothstep(light.cos_outer, light.cos_inner, dot(-L, normalize(light.direction)));
+        float attenuation = distance_attenuation(light_distance, light.range, light.falloff) * cone;
+        if (attenuation <= 0.0) {
+            continue;
+        }
+        direct_light += shade(N, V, L, light.color * attenuation, albedo, alpha, F0);
+    }
 
     // ✅ Albedo-preserving ambient
-    vec3 ambient = albedo * u_light_color * 0.2;
+    vec3 ambient = albedo * u_ambient_color;
 
     vec3 color = direct_light + ambient;
+    color += v_highlight.rgb * v_highlight.a;
+    if (u_wireframe != 0) {
+        color = mix(u_wireframe_color, color, edge_factor());
+    }
 
-    fragColor = vec4(color, 1.0);
+    fragColor = vec4(color, blended ? base_color.a * v_tint.a : 1.0);
 }
\ No newline at end of file
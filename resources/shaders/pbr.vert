@@ -13,10 +13,17 @@ layout(location = 6) in vec4 instance_model_col0;
 layout(location = 7) in vec4 instance_model_col1;
 layout(location = 8) in vec4 instance_model_col2;
 layout(location = 9) in vec4 instance_model_col3;
+// Share of pixels drawn while cross-fading between LOD levels, see dither_discard.
+layout(location = 12) in float instance_lod_fade;
+// Per-instance InstanceParams: albedo tint with opacity, and highlight colour with strength.
+layout(location = 13) in vec4 instance_tint;
+layout(location = 14) in vec4 instance_highlight;
 
 uniform mat4 u_view_proj;
 uniform vec3 u_camera_position;
 
+out vec3 v_world_position;
+out float v_view_depth;
 out vec3 v_normal;
 out vec3 v_view_dir;
 out vec3 v_barycentric;
@@ -24,6 +31,9 @@ out vec3 v_camera_position;
 out vec2 v_uv_albedo;
 out vec2 v_uv_normal;
 out mat3 v_tbn;
+flat out float v_lod_fade;
+flat out vec4 v_tint;
+flat out vec4 v_highlight;
 
 void main() {
     // Construct model matrix
@@ -59,10 +69,16 @@ void main() {
     );
 
     // Other outputs
+    v_world_position = world_pos;
+    // Clip-space w of a perspective projection is the distance in front of the camera.
+    v_view_depth = gl_Position.w;
     v_normal = N;
     v_view_dir = normalize(u_camera_position - world_pos);
     v_barycentric = barycentric;
     v_camera_position = u_camera_position;
     v_uv_albedo = uv_albedo;
     v_uv_normal = uv_normal;
+    v_lod_fade = instance_lod_fade;
+    v_tint = instance_tint;
+    v_highlight = instance_highlight;
 }
\ No newline at end of file
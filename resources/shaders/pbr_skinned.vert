@@ -0,0 +1,98 @@
+#version 330 core
+
+// Vertex attributes
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in vec3 barycentric;
+layout(location = 3) in vec2 uv_albedo;
+layout(location = 4) in vec2 uv_normal;
+layout(location = 5) in vec4 tangent;
+
+// Per-instance model matrix
+layout(location = 6) in vec4 instance_model_col0;
+layout(location = 7) in vec4 instance_model_col1;
+layout(location = 8) in vec4 instance_model_col2;
+layout(location = 9) in vec4 instance_model_col3;
+// Share of pixels drawn while cross-fading between LOD levels, see dither_discard.
+layout(location = 12) in float instance_lod_fade;
+// Per-instance InstanceParams: albedo tint with opacity, and highlight colour with strength.
+layout(location = 13) in vec4 instance_tint;
+layout(location = 14) in vec4 instance_highlight;
+
+// Skinning: up to four joints per vertex. Indices are stored as floats.
+layout(location = 10) in vec4 joints;
+layout(location = 11) in vec4 weights;
+
+// Must match MAX_JOINTS in animation/skeleton.rs.
+const int MAX_JOINTS = 64;
+
+uniform mat4 u_joint_matrices[MAX_JOINTS];
+uniform mat4 u_view_proj;
+uniform vec3 u_camera_position;
+
+out vec3 v_world_position;
+out float v_view_depth;
+out vec3 v_normal;
+out vec3 v_view_dir;
+out vec3 v_barycentric;
+out vec3 v_camera_position;
+out vec2 v_uv_albedo;
+out vec2 v_uv_normal;
+out mat3 v_tbn;
+flat out float v_lod_fade;
+flat out vec4 v_tint;
+flat out vec4 v_highlight;
+
+void main() {
+    // Joint matrices place the vertex in the model's space, then the instance places the model.
+    mat4 skin =
+        weights.x * u_joint_matrices[int(joints.x)] +
+        weights.y * u_joint_matrices[int(joints.y)] +
+        weights.z * u_joint_matrices[int(joints.z)] +
+        weights.w * u_joint_matrices[int(joints.w)];
+
+    mat4 instance_model = mat4(
+        instance_model_col0,
+        instance_model_col1,
+        instance_model_col2,
+        instance_model_col3
+    ) * skin;
+
+    // World position
+    vec3 world_pos = vec3(instance_model * vec4(position, 1.0));
+    gl_Position = u_view_proj * vec4(world_pos, 1.0);
+
+    // Normal matrix
+    mat3 normal_matrix = mat3(transpose(inverse(instance_model)));
+
+    // World-space normal
+    vec3 N = normalize(normal_matrix * normal);
+
+    // World-space tangent (with Gram–Schmidt)
+    vec3 T = normalize(normal_matrix * tangent.xyz);
+    T = normalize(T - N * dot(N, T));
+
+    // Reconstructed bitangent (glTF: tangent.w = handedness)
+    vec3 B = tangent.w * normalize(cross(N, T));
+
+    // TBN matrix
+    v_tbn = mat3(
+        normalize(T),
+        normalize(B),
+        normalize(N)
+    );
+
+    // Other outputs
+    v_world_position = world_pos;
+    // Clip-space w of a perspective projection is the distance in front of the camera.
+    v_view_depth = gl_Position.w;
+    v_normal = N;
+    v_view_dir = normalize(u_camera_position - world_pos);
+    v_barycentric = barycentric;
+    v_camera_position = u_camera_position;
+    v_uv_albedo = uv_albedo;
+    v_uv_normal = uv_normal;
+    v_lod_fade = instance_lod_fade;
+    v_tint = instance_tint;
+    v_highlight = instance_highlight;
+}
\ No newline at end of file
@@ -0,0 +1,16 @@
+#version 330 core
+
+// Adds the blurred bright colour back over the scene.
+in vec2 v_uv;
+
+out vec4 fragColor;
+
+uniform sampler2D u_scene;
+uniform sampler2D u_bloom;
+uniform float u_intensity;
+
+void main() {
+    vec3 scene = texture(u_scene, v_uv).rgb;
+    vec3 bloom = texture(u_bloom, v_uv).rgb;
+    fragColor = vec4(scene + bloom * u_intensity, 1.0);
+}
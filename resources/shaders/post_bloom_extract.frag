@@ -0,0 +1,16 @@
+#version 330 core
+
+// Keeps the part of each pixel brighter than u_threshold.
+in vec2 v_uv;
+
+out vec4 fragColor;
+
+uniform sampler2D u_source;
+uniform float u_threshold;
+
+void main() {
+    vec3 color = texture(u_source, v_uv).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+    float weight = max(brightness - u_threshold, 0.0) / max(brightness, 0.0001);
+    fragColor = vec4(color * weight, 1.0);
+}
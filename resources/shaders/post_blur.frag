@@ -0,0 +1,24 @@
+#version 330 core
+
+// One direction of a separable 9-tap Gaussian blur.
+in vec2 v_uv;
+
+out vec4 fragColor;
+
+uniform sampler2D u_source;
+uniform int u_horizontal;
+
+const float WEIGHTS[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(u_source, 0));
+    vec2 direction = u_horizontal != 0 ? vec2(texel.x, 0.0) : vec2(0.0, texel.y);
+
+    vec3 sum = texture(u_source, v_uv).rgb * WEIGHTS[0];
+    for (int i = 1; i < 5; ++i) {
+        vec2 offset = direction * float(i);
+        sum += texture(u_source, v_uv + offset).rgb * WEIGHTS[i];
+        sum += texture(u_source, v_uv - offset).rgb * WEIGHTS[i];
+    }
+    fragColor = vec4(sum, 1.0);
+}
@@ -0,0 +1,26 @@
+#version 330 core
+
+// Grades colour through a lookup table stored as a strip of N slices of N×N texels:
+// red runs across each slice, green down it and blue from slice to slice.
+in vec2 v_uv;
+
+out vec4 fragColor;
+
+uniform sampler2D u_source;
+uniform sampler2D u_lut;
+
+void main() {
+    vec3 color = clamp(texture(u_source, v_uv).rgb, 0.0, 1.0);
+    float size = float(textureSize(u_lut, 0).y);
+    vec2 strip = vec2(size * size, size);
+
+    // Red and green are sampled at texel centres; blue blends the two nearest slices.
+    vec2 texel = color.rg * (size - 1.0) + 0.5;
+    float slice = color.b * (size - 1.0);
+    float lower = floor(slice);
+    float upper = min(lower + 1.0, size - 1.0);
+
+    vec3 a = textureLod(u_lut, vec2(lower * size + texel.x, texel.y) / strip, 0.0).rgb;
+    vec3 b = textureLod(u_lut, vec2(upper * size + texel.x, texel.y) / strip, 0.0).rgb;
+    fragColor = vec4(mix(a, b, slice - lower), 1.0);
+}
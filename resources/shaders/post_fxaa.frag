@@ -0,0 +1,55 @@
+#version 330 core
+
+// Fast approximate anti-aliasing: blurs along edges found by the contrast in luma
+// between a pixel's diagonal neighbours.
+in vec2 v_uv;
+
+out vec4 fragColor;
+
+uniform sampler2D u_source;
+
+#define FXAA_REDUCE_MIN (1.0 / 128.0)
+#define FXAA_REDUCE_MUL (1.0 / 8.0)
+#define FXAA_SPAN_MAX 8.0
+
+float luma(vec3 color) {
+    return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(u_source, 0));
+
+    float luma_nw = luma(texture(u_source, v_uv + vec2(-1.0, -1.0) * texel).rgb);
+    float luma_ne = luma(texture(u_source, v_uv + vec2(1.0, -1.0) * texel).rgb);
+    float luma_sw = luma(texture(u_source, v_uv + vec2(-1.0, 1.0) * texel).rgb);
+    float luma_se = luma(texture(u_source, v_uv + vec2(1.0, 1.0) * texel).rgb);
+    float luma_m = luma(texture(u_source, v_uv).rgb);
+
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // The edge runs across the steepest change in luma.
+    vec2 dir = vec2(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se)
+    );
+    float dir_reduce = max(
+        (luma_nw + luma_ne + luma_sw + luma_se) * (0.25 * FXAA_REDUCE_MUL),
+        FXAA_REDUCE_MIN
+    );
+    float rcp_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+    dir = clamp(dir * rcp_dir_min, vec2(-FXAA_SPAN_MAX), vec2(FXAA_SPAN_MAX)) * texel;
+
+    vec3 near = 0.5 * (
+        texture(u_source, v_uv + dir * (1.0 / 3.0 - 0.5)).rgb +
+        texture(u_source, v_uv + dir * (2.0 / 3.0 - 0.5)).rgb
+    );
+    vec3 far = near * 0.5 + 0.25 * (
+        texture(u_source, v_uv - dir * 0.5).rgb +
+        texture(u_source, v_uv + dir * 0.5).rgb
+    );
+    // Sampling further out crossed another edge when it left the local luma range.
+    float luma_far = luma(far);
+    vec3 color = (luma_far < luma_min || luma_far > luma_max) ? near : far;
+    fragColor = vec4(color, 1.0);
+}
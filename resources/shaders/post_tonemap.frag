@@ -0,0 +1,32 @@
+#version 330 core
+
+// Exposure and the tone curve, from HDR colour to the 0..1 range. Like the lit pass
+// without post-processing, no gamma is applied: textures are sampled as stored.
+in vec2 v_uv;
+
+out vec4 fragColor;
+
+uniform sampler2D u_source;
+uniform float u_exposure;
+// The Tonemapper: 0 ACES, 1 Reinhard.
+uniform int u_tonemapper;
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve.
+vec3 aces(vec3 x) {
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), 0.0, 1.0);
+}
+
+vec3 reinhard(vec3 x) {
+    return x / (1.0 + x);
+}
+
+void main() {
+    vec3 color = texture(u_source, v_uv).rgb * u_exposure;
+    color = u_tonemapper == 1 ? reinhard(color) : aces(color);
+    fragColor = vec4(color, 1.0);
+}
@@ -0,0 +1,5 @@
+#version 330 core
+
+// Nothing to shade: the depth buffer is the output.
+void main() {
+}
@@ -0,0 +1,23 @@
+#version 330 core
+
+// Depth-only pass for shadow maps: positions are all that matter.
+layout(location = 0) in vec3 position;
+
+// Per-instance model matrix
+layout(location = 6) in vec4 instance_model_col0;
+layout(location = 7) in vec4 instance_model_col1;
+layout(location = 8) in vec4 instance_model_col2;
+layout(location = 9) in vec4 instance_model_col3;
+
+uniform mat4 u_light_view_proj;
+
+void main() {
+    mat4 instance_model = mat4(
+        instance_model_col0,
+        instance_model_col1,
+        instance_model_col2,
+        instance_model_col3
+    );
+
+    gl_Position = u_light_view_proj * instance_model * vec4(position, 1.0);
+}
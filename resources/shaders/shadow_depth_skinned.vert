@@ -0,0 +1,36 @@
+#version 330 core
+
+// Depth-only pass for shadow maps of skinned meshes.
+layout(location = 0) in vec3 position;
+
+// Per-instance model matrix
+layout(location = 6) in vec4 instance_model_col0;
+layout(location = 7) in vec4 instance_model_col1;
+layout(location = 8) in vec4 instance_model_col2;
+layout(location = 9) in vec4 instance_model_col3;
+
+layout(location = 10) in vec4 joints;
+layout(location = 11) in vec4 weights;
+
+// Must match MAX_JOINTS in animation/skeleton.rs.
+const int MAX_JOINTS = 64;
+
+uniform mat4 u_joint_matrices[MAX_JOINTS];
+uniform mat4 u_light_view_proj;
+
+void main() {
+    mat4 skin =
+        weights.x * u_joint_matrices[int(joints.x)] +
+        weights.y * u_joint_matrices[int(joints.y)] +
+        weights.z * u_joint_matrices[int(joints.z)] +
+        weights.w * u_joint_matrices[int(joints.w)];
+
+    mat4 instance_model = mat4(
+        instance_model_col0,
+        instance_model_col1,
+        instance_model_col2,
+        instance_model_col3
+    ) * skin;
+
+    gl_Position = u_light_view_proj * instance_model * vec4(position, 1.0);
+}